@@ -13,16 +13,34 @@ pub struct TableFieldSchema {
     pub mode: Option<String>,
     pub fields: Option<Vec<TableFieldSchema>>,
     pub description: Option<String>,
-    // pub policyTags: xxx,
-    // pub policyTags.names[]: xxx,
+    #[serde(alias = "policyTags")]
+    pub policy_tags: Option<PolicyTags>,
     #[serde(alias = "maxLength")]
     pub max_length: Option<String>,
     pub precision: Option<String>,
     pub scale: Option<String>,
-    // pub roundingMode: xxx,
+    #[serde(alias = "roundingMode")]
+    pub rounding_mode: Option<String>,
     pub collation: Option<String>,
     #[serde(alias = "defaultValueExpression")]
     pub default_value_expression: Option<String>,
+    #[serde(alias = "rangeElementType")]
+    pub range_element_type: Option<RangeElementType>,
+}
+
+/// The policy tags of a field, by resource name
+/// (`projects/{project}/locations/{location}/taxonomies/{taxonomy}/policyTags/{tag}`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyTags {
+    #[serde(default)]
+    pub names: Vec<String>,
+}
+
+/// The type of the bounds of a RANGE field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeElementType {
+    #[serde(alias = "type")]
+    pub r#type: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,173 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use super::jobs::ErrorProto;
+
+/// Why a call to the BigQuery REST API did not return the expected resource.
+///
+/// Built from the Google error body (`{"error": {"code", "message", "status", "errors"}}`)
+/// when the API answered, or from the JS exception when the request itself failed.
+#[derive(Debug, Clone, Serialize)]
+pub struct BigQueryError {
+    /// HTTP status of the response, `None` when no response was received.
+    pub http_status: Option<u16>,
+    /// e.g. `NOT_FOUND`, `PERMISSION_DENIED`, `UNAUTHENTICATED`.
+    pub status: Option<String>,
+    pub message: String,
+    pub errors: Vec<ErrorProto>,
+}
+
+// https://cloud.google.com/apis/design/errors#http_mapping
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    message: Option<String>,
+    status: Option<String>,
+    errors: Option<Vec<ErrorProto>>,
+}
+
+impl BigQueryError {
+    pub(crate) fn from_js(context: &str, value: &JsValue) -> BigQueryError {
+        let detail = value
+            .dyn_ref::<js_sys::Error>()
+            .map(|e| String::from(e.message()))
+            .or_else(|| value.as_string())
+            .unwrap_or_else(|| format!("{:?}", value));
+
+        BigQueryError {
+            http_status: None,
+            status: None,
+            message: format!("{}: {}", context, detail),
+            errors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_response_body(
+        http_status: u16,
+        status_text: &str,
+        body: Option<serde_json::Value>,
+    ) -> BigQueryError {
+        match body.and_then(|b| serde_json::from_value::<ErrorResponse>(b).ok()) {
+            Some(response) => BigQueryError {
+                http_status: Some(http_status),
+                status: response.error.status,
+                message: response
+                    .error
+                    .message
+                    .unwrap_or_else(|| status_text.to_string()),
+                errors: response.error.errors.unwrap_or_default(),
+            },
+            None => BigQueryError {
+                http_status: Some(http_status),
+                status: None,
+                message: status_text.to_string(),
+                errors: Vec::new(),
+            },
+        }
+    }
+
+    /// `reason` of the first entry in `errors`, e.g. `notFound` or `accessDenied`.
+    pub fn reason(&self) -> Option<&str> {
+        self.errors.iter().find_map(|e| e.reason.as_deref())
+    }
+}
+
+impl fmt::Display for BigQueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.http_status, &self.status) {
+            (Some(code), Some(status)) => write!(f, "{} {}: {}", code, status, self.message),
+            (Some(code), None) => write!(f, "{}: {}", code, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for BigQueryError {}
+
+/// Sends `request` and deserializes a `200` response into `T`; any other outcome is a `BigQueryError`.
+pub(crate) async fn fetch<T: DeserializeOwned>(
+    request: &web_sys::Request,
+) -> Result<T, BigQueryError> {
+    let window = web_sys::window()
+        .ok_or_else(|| BigQueryError::from_js("fetch", &JsValue::from_str("no window exists")))?;
+
+    let resp_value = JsFuture::from(window.fetch_with_request(request))
+        .await
+        .map_err(|e| BigQueryError::from_js("fetch failed", &e))?;
+
+    let resp: web_sys::Response = resp_value
+        .dyn_into()
+        .map_err(|e| BigQueryError::from_js("fetch did not return a Response", &e))?;
+
+    let json = match resp.json() {
+        Ok(promise) => JsFuture::from(promise).await,
+        Err(e) => Err(e),
+    };
+
+    if resp.status() == 200 {
+        let json = json.map_err(|e| BigQueryError::from_js("invalid response body", &e))?;
+
+        serde_wasm_bindgen::from_value::<T>(json).map_err(|e| {
+            BigQueryError::from_js("unexpected response", &JsValue::from_str(&e.to_string()))
+        })
+    } else {
+        let body = json
+            .ok()
+            .and_then(|j| serde_wasm_bindgen::from_value::<serde_json::Value>(j).ok());
+
+        Err(BigQueryError::from_response_body(
+            resp.status(),
+            &resp.status_text(),
+            body,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigQueryError;
+
+    #[test]
+    fn google_error_body_is_parsed() {
+        let body = serde_json::json!({
+            "error": {
+                "code": 404,
+                "message": "Not found: Table p:d.t was not found in location US",
+                "errors": [
+                    {
+                        "message": "Not found: Table p:d.t was not found in location US",
+                        "domain": "global",
+                        "reason": "notFound"
+                    }
+                ],
+                "status": "NOT_FOUND"
+            }
+        });
+
+        let error = BigQueryError::from_response_body(404, "Not Found", Some(body));
+
+        assert_eq!(error.http_status, Some(404));
+        assert_eq!(error.status.as_deref(), Some("NOT_FOUND"));
+        assert_eq!(error.reason(), Some("notFound"));
+        assert_eq!(
+            error.to_string(),
+            "404 NOT_FOUND: Not found: Table p:d.t was not found in location US"
+        );
+    }
+
+    #[test]
+    fn non_json_body_falls_back_to_status_text() {
+        let error = BigQueryError::from_response_body(502, "Bad Gateway", None);
+
+        assert_eq!(error.message, "Bad Gateway");
+        assert_eq!(error.reason(), None);
+        assert_eq!(error.to_string(), "502: Bad Gateway");
+    }
+}
@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::JsValue;
-use wasm_bindgen_futures::JsFuture;
 use web_sys::console;
-use super::base::TableSchema;
+use super::{
+    base::TableSchema,
+    error::{BigQueryError, fetch},
+};
 
 pub struct Jobs {
     token: String,
@@ -380,14 +382,28 @@ impl Job {
     }
 }
 
-// #[derive(Debug, Serialize)]
-// pub struct QueryRequest {
-//     pub query: String,
-//     // #[serde(alias = "maxResults")]
-//     // pub max_results: Option<u64>,
-//     //incomplete
-//     // https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/query#QueryRequest
-// }
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/query#QueryRequest
+#[derive(Debug, Serialize)]
+pub struct QueryRequest {
+    pub query: String,
+    #[serde(rename = "useLegacySql")]
+    pub use_legacy_sql: bool,
+    /// Rows returned in the response, none needed when the results are read with `getQueryResults`.
+    #[serde(rename = "maxResults", skip_serializing_if = "Option::is_none")]
+    pub max_results: Option<usize>,
+    /// How long the call waits for the query to complete, 10 seconds by default.
+    #[serde(rename = "timeoutMs", skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/query#response-body
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryResponse {
+    #[serde(alias = "jobReference")]
+    pub job_reference: JobReference,
+}
 
 #[derive(Debug)]
 pub struct GetQueryResultsRequest {
@@ -399,6 +415,8 @@ pub struct GetQueryResultsRequest {
     pub location: Option<String>,
     pub start_index: Option<String>,
     pub max_results: Option<usize>,
+    /// Continues after the previous page; used instead of `start_index` when walking every page.
+    pub page_token: Option<String>,
 }
 
 #[derive(Debug)]
@@ -553,60 +571,37 @@ impl Jobs {
     //     None
     // }
 
-    // /* https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/query
-    //  */
-    // pub async fn query(
-    //     self: &Self,
-    //     project_id: &str,
-    //     request: QueryRequest,
-    // ) -> Option<QueryResponse> {
-    //     let mut opts = web_sys::RequestInit::new();
-    //     opts.method("POST");
-    //     opts.mode(web_sys::RequestMode::NoCors);
-    //     let headers = web_sys::Headers::new().unwrap();
-    //     // headers.set("Accept", "application/json").unwrap();
-    //     headers.set("Content-Type", "application/json").unwrap();
-    //     headers
-    //         .set("Authorization", &format!("Bearer {}", &self.token))
-    //         .unwrap();
-    //     opts.headers(&headers);
-    //     let body = serde_wasm_bindgen::to_value(&request).unwrap();
-    //     opts.body(Some(&body));
-
-    //     let url = format!(
-    //         "https://bigquery.googleapis.com/bigquery/v2/projects/{}/queries",
-    //         project_id
-    //     );
-
-    //     let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
-
-    //     let window = web_sys::window().unwrap();
-    //     let resp_value = JsFuture::from(window.fetch_with_request(&request))
-    //         .await
-    //         .unwrap();
-
-    //     assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-    //         &resp_value
-    //     ));
-    //     let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
+    /* https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/query
+    Runs `request` as a job of `project_id`, waiting for it up to `timeout_ms`.
+     */
+    pub async fn query(
+        self: &Self,
+        project_id: &str,
+        request: QueryRequest,
+    ) -> Result<QueryResponse, BigQueryError> {
+        let opts = web_sys::RequestInit::new();
+        opts.set_method("POST");
+        opts.set_mode(web_sys::RequestMode::Cors);
+        let headers = web_sys::Headers::new().unwrap();
+        headers.set("Content-Type", "application/json").unwrap();
+        headers
+            .set("Authorization", &format!("Bearer {}", &self.token))
+            .unwrap();
+        opts.set_headers(&headers);
+        let body = serde_json::to_string(&request)
+            .map_err(|e| BigQueryError::from_js("invalid request", &JsValue::from_str(&e.to_string())))?;
+        opts.set_body(&JsValue::from_str(&body));
 
-    //     if resp.status() == 200 {
-    //         let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-    //         // Use serde to parse the JSON into a struct.
-    //         let bq_response = serde_wasm_bindgen::from_value::<QueryResponse>(json);
+        let url = format!(
+            "https://bigquery.googleapis.com/bigquery/v2/projects/{}/queries",
+            project_id
+        );
 
-    //         if bq_response.is_err() {
-    //             console::log_1(&JsValue::from_str(&format!(
-    //                 "error: {:?}",
-    //                 bq_response.err().unwrap().to_string()
-    //             )));
-    //         } else {
-    //             return Some(bq_response.unwrap());
-    //         }
-    //     }
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-    //     None
-    // }
+        fetch::<QueryResponse>(&request).await
+    }
 
     /*
     https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/getQueryResults#http-request
@@ -616,7 +611,7 @@ impl Jobs {
     pub async fn get_query_results(
         self: &Self,
         request: GetQueryResultsRequest,
-    ) -> Option<GetQueryResultsResponse> {
+    ) -> Result<GetQueryResultsResponse, BigQueryError> {
         let opts = web_sys::RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(web_sys::RequestMode::Cors);
@@ -648,64 +643,29 @@ impl Jobs {
         if request.start_index.is_some() {
             url = format!("{}&startIndex={}", url, request.start_index.unwrap());
         }
+        if let Some(page_token) = &request.page_token {
+            url = format!(
+                "{}&pageToken={}",
+                url,
+                js_sys::encode_uri_component(page_token)
+            );
+        }
         url = format!("{}&formatOptions.useInt64Timestamp=false", url);
 
         // console::log_1(&JsValue::from_str(&url));
 
-        // if (request.pageToken) { url.searchParams.append("pageToken", request.pageToken); }
         // if (request.timeoutMs !== null) { url.searchParams.append("timeoutMs", request.timeoutMs.toString()); }
 
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-        let window = web_sys::window().unwrap();
-        let resp_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .unwrap();
-
-        assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-            &resp_value
-        ));
-
-        // console::log_1(resp_value.as_ref());
-
-        let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
-
-        if resp.status() == 200 {
-            let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-
-            // console::log_1(json.as_ref());
-            // console::log_1(&JsValue::from_str(&format!(
-            //     "json: {:?}",
-            //     json
-            // )));
-
-            // Use serde to parse the JSON into a struct.
-            let bq_response = serde_wasm_bindgen::from_value::<GetQueryResultsResponse>(json);
-
-            // console::log_1(&JsValue::from_str(&format!(
-            //     "bq_response: {:?}",
-            //     bq_response
-            // )));
-
-            if bq_response.is_err() {
-                console::log_1(&JsValue::from_str(&format!(
-                    "error: {:?}",
-                    bq_response.err().unwrap().to_string()
-                )));
-            } else {
-                let response = bq_response.unwrap();
-
-                return Some(response);
-            }
-        }
-
-        None
+        fetch::<GetQueryResultsResponse>(&request).await
     }
 
     /*
     https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/list
     */
-    pub async fn get(self: &Self, request: GetJobRequest) -> Option<Job> {
+    pub async fn get(self: &Self, request: GetJobRequest) -> Result<Job, BigQueryError> {
         let opts = web_sys::RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(web_sys::RequestMode::Cors);
@@ -728,45 +688,19 @@ impl Jobs {
 
         console::log_1(&JsValue::from_str(&url));
 
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
-
-        let window = web_sys::window().unwrap();
-        let resp_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .unwrap();
-
-        console::log_1(resp_value.as_ref());
-
-        assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-            &resp_value
-        ));
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-        let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
-
-        if resp.status() == 200 {
-            let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-
-            // console::log_1(json.as_ref());
-
-            // Use serde to parse the JSON into a struct.
-            let bq_response = serde_wasm_bindgen::from_value::<Job>(json);
-
-            if bq_response.is_err() {
-                console::log_1(&JsValue::from_str(&format!(
-                    "error: {:?}",
-                    bq_response.err().unwrap().to_string()
-                )));
-            } else {
-                return Some(bq_response.unwrap());
-            }
-        }
-        None
+        fetch::<Job>(&request).await
     }
 
     /*
     https://cloud.google.com/bigquery/docs/reference/rest/v2/jobs/list
     */
-    pub async fn get_list(self: &Self, request: GetListRequest) -> Option<GetListResponse> {
+    pub async fn get_list(
+        self: &Self,
+        request: GetListRequest,
+    ) -> Result<GetListResponse, BigQueryError> {
         let opts = web_sys::RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(web_sys::RequestMode::Cors);
@@ -807,37 +741,10 @@ impl Jobs {
 
         console::log_1(&JsValue::from_str(&url));
 
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
-
-        let window = web_sys::window().unwrap();
-        let resp_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .unwrap();
-
-        // assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-        //     &resp_value
-        // ));
-
-        let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-        if resp.status() == 200 {
-            let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-
-            // console::log_1(json.as_ref());
-
-            // Use serde to parse the JSON into a struct.
-            let bq_response = serde_wasm_bindgen::from_value::<GetListResponse>(json);
-
-            if bq_response.is_err() {
-                console::log_1(&JsValue::from_str(&format!(
-                    "error: {:?}",
-                    bq_response.err().unwrap().to_string()
-                )));
-            } else {
-                return Some(bq_response.unwrap());
-            }
-        }
-        None
+        fetch::<GetListResponse>(&request).await
     }
 }
 
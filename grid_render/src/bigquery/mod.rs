@@ -1,4 +1,5 @@
 pub mod base;
+pub mod error;
 pub mod jobs;
 pub mod table_data;
 pub mod tables;
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::JsValue;
-use wasm_bindgen_futures::JsFuture;
 use web_sys::console;
 
+use super::error::{BigQueryError, fetch};
+
 
 pub struct TableData {
     token: String,
@@ -19,8 +20,8 @@ pub struct TableDataListRequest{
     pub table_id: String,
     #[serde(alias = "maxResults")]
     pub max_results: Option<usize>,
-    // #[serde(alias = "pageToken")]
-    // pub page_token: Option<String>,
+    #[serde(alias = "pageToken")]
+    pub page_token: Option<String>,
     #[serde(alias = "startIndex")]
     pub start_index: Option<String>,
     //selectedFields
@@ -48,7 +49,7 @@ impl TableData {
     pub async fn list(
         self: &Self,
         request: TableDataListRequest,
-    ) -> Option<TableDataListResponse> {
+    ) -> Result<TableDataListResponse, BigQueryError> {
         let opts = web_sys::RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(web_sys::RequestMode::Cors);
@@ -77,41 +78,21 @@ impl TableData {
             url = format!("{}&startIndex={}", url, request.start_index.unwrap());
         }
 
+        if let Some(page_token) = &request.page_token {
+            url = format!(
+                "{}&pageToken={}",
+                url,
+                js_sys::encode_uri_component(page_token)
+            );
+        }
+
         console::log_1(&JsValue::from_str(&url));
 
-        // if (request.pageToken) { url.searchParams.append("pageToken", request.pageToken); }
         // if (request.timeoutMs !== null) { url.searchParams.append("timeoutMs", request.timeoutMs.toString()); }
 
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
-
-        let window = web_sys::window().unwrap();
-        let resp_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .unwrap();
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-        assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-            &resp_value
-        ));
-
-        let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
-
-        if resp.status() == 200 {
-            let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-
-            // web_sys::console::log_1(json.as_ref());
-
-            // Use serde to parse the JSON into a struct.
-            let bq_response = serde_wasm_bindgen::from_value::<TableDataListResponse>(json);
-
-            if bq_response.is_err() {
-                console::log_1(&JsValue::from_str(&format!(
-                    "error: {:?}",
-                    bq_response.err().unwrap().to_string()
-                )));
-            } else {
-                return Some(bq_response.unwrap());
-            }
-        }
-        None
+        fetch::<TableDataListResponse>(&request).await
     }
 }
\ No newline at end of file
@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
-use wasm_bindgen::JsValue;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::console;
 
-use super::base::{TableSchema, TableReference};
+use super::{
+    base::{TableReference, TableSchema},
+    error::{BigQueryError, fetch},
+    jobs::ErrorProto,
+};
 
 pub struct Tables {
     token: String,
@@ -18,7 +21,7 @@ impl Tables {
     pub async fn get(
         self: &Self,
         request: TableReference,
-    ) -> Option<Table> {
+    ) -> Result<Table, BigQueryError> {
         let opts = web_sys::RequestInit::new();
         opts.set_method("GET");
         opts.set_mode(web_sys::RequestMode::Cors);
@@ -52,37 +55,10 @@ impl Tables {
         // if (request.pageToken) { url.searchParams.append("pageToken", request.pageToken); }
         // if (request.timeoutMs !== null) { url.searchParams.append("timeoutMs", request.timeoutMs.toString()); }
 
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts).unwrap();
-
-        let window = web_sys::window().unwrap();
-        let resp_value = JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .unwrap();
-
-        assert!(wasm_bindgen::JsCast::is_instance_of::<web_sys::Response>(
-            &resp_value
-        ));
-
-        let resp: web_sys::Response = wasm_bindgen::JsCast::dyn_into(resp_value).unwrap();
-
-        if resp.status() == 200 {
-            let json = JsFuture::from(resp.json().unwrap()).await.unwrap();
-
-            web_sys::console::log_1(json.as_ref());
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|e| BigQueryError::from_js("invalid request", &e))?;
 
-            // Use serde to parse the JSON into a struct.
-            let bq_response = serde_wasm_bindgen::from_value::<Table>(json);
-
-            if bq_response.is_err() {
-                console::log_1(&JsValue::from_str(&format!(
-                    "error: {:?}",
-                    bq_response.err().unwrap().to_string()
-                )));
-            } else {
-                return Some(bq_response.unwrap());
-            }
-        }
-        None
+        fetch::<Table>(&request).await
     }
 }
 
@@ -99,20 +75,13 @@ pub struct Table {
     #[serde(alias = "friendlyName")]
     pub friendly_name: Option<String>,
     pub description: Option<String>,
-    // "labels": {
-    //     string: string,
-    //     ...
-    //   },
+    pub labels: Option<BTreeMap<String, String>>,
     pub schema: Option<TableSchema>,
-    // "timePartitioning": {
-    //     object (TimePartitioning)
-    //   },
-    //   "rangePartitioning": {
-    //     object (RangePartitioning)
-    //   },
-    //   "clustering": {
-    //     object (Clustering)
-    //   },
+    #[serde(alias = "timePartitioning")]
+    pub time_partitioning: Option<TimePartitioning>,
+    #[serde(alias = "rangePartitioning")]
+    pub range_partitioning: Option<RangePartitioning>,
+    pub clustering: Option<Clustering>,
     #[serde(alias = "requirePartitionFilter")]
     pub require_partition_filter: Option<bool>,
     #[serde(alias = "numBytes")]
@@ -129,35 +98,26 @@ pub struct Table {
     pub last_modified_time: Option<String>,
     pub r#type: Option<String>,
 
-    // "view": {
-    //     object (ViewDefinition)
-    //   },
-    //   "materializedView": {
-    //     object (MaterializedViewDefinition)
-    //   },
-    //   "materializedViewStatus": {
-    //     object (MaterializedViewStatus)
-    //   },
-    //   "externalDataConfiguration": {
-    //     object (ExternalDataConfiguration)
-    //   },
+    pub view: Option<ViewDefinition>,
+    #[serde(alias = "materializedView")]
+    pub materialized_view: Option<MaterializedViewDefinition>,
+    #[serde(alias = "materializedViewStatus")]
+    pub materialized_view_status: Option<MaterializedViewStatus>,
+    #[serde(alias = "externalDataConfiguration")]
+    pub external_data_configuration: Option<ExternalDataConfiguration>,
     pub location: Option<String>,
-    //   "streamingBuffer": {
-    //     object (Streamingbuffer)
-    //   },
-    //   "encryptionConfiguration": {
-    //     object (EncryptionConfiguration)
-    //   },
-    //   "snapshotDefinition": {
-    //     object (SnapshotDefinition)
-    //   },
+    #[serde(alias = "streamingBuffer")]
+    pub streaming_buffer: Option<StreamingBuffer>,
+    #[serde(alias = "encryptionConfiguration")]
+    pub encryption_configuration: Option<EncryptionConfiguration>,
+    #[serde(alias = "snapshotDefinition")]
+    pub snapshot_definition: Option<SnapshotDefinition>,
     //   "defaultCollation": string,
     #[serde(alias = "defaultCollation")]
     pub default_collation: Option<String>,
     //   "defaultRoundingMode": enum (RoundingMode),
-    //   "cloneDefinition": {
-    //     object (CloneDefinition)
-    //   },
+    #[serde(alias = "cloneDefinition")]
+    pub clone_definition: Option<CloneDefinition>,
     #[serde(alias = "maxStaleness")]
     pub max_staleness: Option<String>,
     //   "tableConstraints": {
@@ -168,3 +128,146 @@ pub struct Table {
     //     ...
     //   }
 }
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#viewdefinition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    pub query: String,
+    #[serde(alias = "useLegacySql")]
+    pub use_legacy_sql: Option<bool>,
+    #[serde(alias = "useExplicitColumnNames")]
+    pub use_explicit_column_names: Option<bool>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#materializedviewdefinition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterializedViewDefinition {
+    pub query: Option<String>,
+    /// Epoch milliseconds.
+    #[serde(alias = "lastRefreshTime")]
+    pub last_refresh_time: Option<String>,
+    #[serde(alias = "enableRefresh")]
+    pub enable_refresh: Option<bool>,
+    #[serde(alias = "refreshIntervalMs")]
+    pub refresh_interval_ms: Option<String>,
+    #[serde(alias = "allowNonIncrementalDefinition")]
+    pub allow_non_incremental_definition: Option<bool>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#materializedviewstatus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterializedViewStatus {
+    /// RFC 3339 timestamp, e.g. `2024-05-01T10:20:30.123Z`.
+    #[serde(alias = "refreshWatermark")]
+    pub refresh_watermark: Option<String>,
+    #[serde(alias = "lastRefreshStatus")]
+    pub last_refresh_status: Option<ErrorProto>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#externaldataconfiguration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalDataConfiguration {
+    #[serde(alias = "sourceUris", default)]
+    pub source_uris: Vec<String>,
+    /// `CSV`, `NEWLINE_DELIMITED_JSON`, `AVRO`, `PARQUET`, `ORC`, `GOOGLE_SHEETS`, `BIGTABLE`, ...
+    #[serde(alias = "sourceFormat")]
+    pub source_format: Option<String>,
+    pub autodetect: Option<bool>,
+    pub compression: Option<String>,
+    #[serde(alias = "ignoreUnknownValues")]
+    pub ignore_unknown_values: Option<bool>,
+    #[serde(alias = "maxBadRecords")]
+    pub max_bad_records: Option<i64>,
+    #[serde(alias = "hivePartitioningOptions")]
+    pub hive_partitioning_options: Option<HivePartitioningOptions>,
+    #[serde(alias = "connectionId")]
+    pub connection_id: Option<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#hivepartitioningoptions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HivePartitioningOptions {
+    /// `AUTO`, `STRINGS` or `CUSTOM`.
+    pub mode: Option<String>,
+    #[serde(alias = "sourceUriPrefix")]
+    pub source_uri_prefix: Option<String>,
+    #[serde(alias = "requirePartitionFilter")]
+    pub require_partition_filter: Option<bool>,
+    /// The partition keys, in the order of the path.
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#snapshotdefinition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotDefinition {
+    #[serde(alias = "baseTableReference")]
+    pub base_table_reference: TableReference,
+    /// RFC 3339 timestamp.
+    #[serde(alias = "snapshotTime")]
+    pub snapshot_time: Option<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#clonedefinition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneDefinition {
+    #[serde(alias = "baseTableReference")]
+    pub base_table_reference: TableReference,
+    /// RFC 3339 timestamp.
+    #[serde(alias = "cloneTime")]
+    pub clone_time: Option<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#timepartitioning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimePartitioning {
+    /// `HOUR`, `DAY`, `MONTH` or `YEAR`.
+    pub r#type: String,
+    #[serde(alias = "expirationMs")]
+    pub expiration_ms: Option<String>,
+    /// The partitioning column, none when partitioned by ingestion time (`_PARTITIONTIME`).
+    pub field: Option<String>,
+    /// Deprecated in favor of `Table.require_partition_filter`, still set on older tables.
+    #[serde(alias = "requirePartitionFilter")]
+    pub require_partition_filter: Option<bool>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#rangepartitioning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangePartitioning {
+    pub field: String,
+    pub range: PartitionRange,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionRange {
+    pub start: String,
+    pub end: String,
+    pub interval: String,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#clustering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Clustering {
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/tables#streamingbuffer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamingBuffer {
+    #[serde(alias = "estimatedBytes")]
+    pub estimated_bytes: Option<String>,
+    #[serde(alias = "estimatedRows")]
+    pub estimated_rows: Option<String>,
+    #[serde(alias = "oldestEntryTime")]
+    pub oldest_entry_time: Option<String>,
+}
+
+// https://cloud.google.com/bigquery/docs/reference/rest/v2/EncryptionConfiguration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfiguration {
+    /// The Cloud KMS key protecting the table, Google-managed encryption when none.
+    #[serde(alias = "kmsKeyName")]
+    pub kms_key_name: Option<String>,
+}
@@ -0,0 +1,295 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{cell_value::CellValue, decimal::Decimal, profile::is_numeric_type};
+
+/*
+Aggregates of the footer row of the grid, one per column chosen by the user, over the rows of
+the loaded page. NULLs are skipped, as by the SQL functions of the same name; the values of a
+REPEATED column (or of a field under one) are its elements.
+NUMERIC and BIGNUMERIC are added up exactly, their average rounded to their own scale like
+BigQuery's AVG, so a total can be checked against the one of a query to the last digit.
+*/
+const NUMERIC_SCALE: usize = 9;
+const BIGNUMERIC_SCALE: usize = 38;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Aggregate {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+}
+
+impl Aggregate {
+    pub(crate) fn get_all() -> Vec<Aggregate> {
+        vec![
+            Aggregate::Sum,
+            Aggregate::Avg,
+            Aggregate::Min,
+            Aggregate::Max,
+            Aggregate::Count,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Count => "count",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Aggregate::Sum => "Sum",
+            Aggregate::Avg => "Average",
+            Aggregate::Min => "Min",
+            Aggregate::Max => "Max",
+            Aggregate::Count => "Count",
+        }
+    }
+
+    /// Whether the aggregate can be computed over the values of `field`: the numeric types
+    /// only for a sum or an average, anything but a RECORD for a min or a max.
+    pub(crate) fn applies_to(&self, field: &TableFieldSchema) -> bool {
+        match self {
+            Aggregate::Sum | Aggregate::Avg => is_numeric_type(&field.r#type),
+            Aggregate::Min | Aggregate::Max => field.fields.is_none(),
+            Aggregate::Count => true,
+        }
+    }
+
+    /// Text of the aggregate of the values of `field`, `None` for a NULL (e.g. the sum of no value).
+    pub(crate) fn compute(&self, field: &TableFieldSchema, values: &[CellValue]) -> Option<String> {
+        let values: Vec<&CellValue> = values.iter().filter(|v| **v != CellValue::Null).collect();
+        if *self == Aggregate::Count {
+            return Some(values.len().to_string());
+        }
+        if values.is_empty() {
+            return None;
+        }
+
+        let decimal = matches!(field.r#type.as_str(), "NUMERIC" | "BIGNUMERIC");
+        match self {
+            Aggregate::Min | Aggregate::Max if decimal => {
+                let decimals = values.iter().filter_map(|v| numeric_value(v));
+                let extreme = match self {
+                    Aggregate::Min => decimals.min(),
+                    _ => decimals.max(),
+                };
+                extreme.map(|d| d.to_string())
+            }
+            Aggregate::Min => values.into_iter().min_by(|a, b| a.sort_cmp(b))?.to_text(),
+            Aggregate::Max => values.into_iter().max_by(|a, b| a.sort_cmp(b))?.to_text(),
+            Aggregate::Sum | Aggregate::Avg if decimal => {
+                // the values that do not parse are left out of the count as well as of the sum
+                let decimals: Vec<Decimal> =
+                    values.iter().filter_map(|v| numeric_value(v)).collect();
+                if decimals.is_empty() {
+                    return None;
+                }
+                let sum = decimals.iter().fold(Decimal::zero(), |sum, d| sum.add(d));
+                match self {
+                    Aggregate::Sum => Some(sum.to_string()),
+                    _ => {
+                        let scale = match field.r#type.as_str() {
+                            "BIGNUMERIC" => BIGNUMERIC_SCALE,
+                            _ => NUMERIC_SCALE,
+                        };
+                        Some(sum.div_round(decimals.len(), scale).to_string())
+                    }
+                }
+            }
+            Aggregate::Sum if values.iter().all(|v| matches!(v, CellValue::Int(_))) => {
+                let sum: i128 = values
+                    .iter()
+                    .map(|v| match v {
+                        CellValue::Int(i) => *i as i128,
+                        _ => 0,
+                    })
+                    .sum();
+                Some(sum.to_string())
+            }
+            Aggregate::Sum | Aggregate::Avg => {
+                let floats: Vec<f64> = values.iter().filter_map(|v| float_value(v)).collect();
+                if floats.is_empty() {
+                    return None;
+                }
+                let sum: f64 = floats.iter().sum();
+                match self {
+                    Aggregate::Sum => Some(sum.to_string()),
+                    _ => Some((sum / floats.len() as f64).to_string()),
+                }
+            }
+            Aggregate::Count => None,
+        }
+    }
+}
+
+impl FromStr for Aggregate {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Aggregate::get_all()
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or(())
+    }
+}
+
+fn numeric_value(value: &CellValue) -> Option<Decimal> {
+    match value {
+        CellValue::Numeric(s) => Decimal::parse(s),
+        CellValue::Int(i) => Decimal::parse(&i.to_string()),
+        _ => None,
+    }
+}
+
+fn float_value(value: &CellValue) -> Option<f64> {
+    match value {
+        CellValue::Int(i) => Some(*i as f64),
+        CellValue::Float(f) => Some(*f),
+        CellValue::Numeric(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Text of the footer cell of a column aggregated by `aggregate`, e.g. `sum: 12.5`.
+pub(crate) fn footer_text(aggregate: Aggregate, value: Option<String>) -> String {
+    format!(
+        "{}: {}",
+        aggregate.name(),
+        value.unwrap_or_else(|| "null".to_string())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aggregate;
+    use crate::bigquery::base::TableFieldSchema;
+    use crate::custom_elements::cell_value::CellValue;
+
+    fn field(r#type: &str) -> TableFieldSchema {
+        serde_json::from_value(serde_json::json!({"name": "v", "type": r#type})).unwrap()
+    }
+
+    fn numerics(values: &[&str]) -> Vec<CellValue> {
+        values
+            .iter()
+            .map(|v| CellValue::Numeric(v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn numeric_sums_are_exact() {
+        let numeric = field("NUMERIC");
+        let values = numerics(&["0.1", "0.2", "1234567890123456789.123456789"]);
+        assert_eq!(
+            Aggregate::Sum.compute(&numeric, &values).as_deref(),
+            Some("1234567890123456789.423456789")
+        );
+        assert_eq!(
+            Aggregate::Avg
+                .compute(&numeric, &numerics(&["1", "1", "0"]))
+                .as_deref(),
+            Some("0.666666667")
+        );
+        assert_eq!(
+            Aggregate::Max
+                .compute(
+                    &field("BIGNUMERIC"),
+                    &numerics(&["-0.5", "0.00000000000000000001", "-5"])
+                )
+                .as_deref(),
+            Some("0.00000000000000000001")
+        );
+    }
+
+    #[test]
+    fn nulls_are_skipped() {
+        let integer = field("INTEGER");
+        let values = vec![
+            CellValue::Int(i64::MAX),
+            CellValue::Null,
+            CellValue::Int(i64::MAX),
+        ];
+        assert_eq!(
+            Aggregate::Sum.compute(&integer, &values).as_deref(),
+            Some("18446744073709551614")
+        );
+        assert_eq!(
+            Aggregate::Count.compute(&integer, &values).as_deref(),
+            Some("2")
+        );
+        assert_eq!(Aggregate::Sum.compute(&integer, &[CellValue::Null]), None);
+        assert_eq!(
+            Aggregate::Count.compute(&integer, &[]).as_deref(),
+            Some("0")
+        );
+
+        let float = field("FLOAT");
+        let values = vec![
+            CellValue::Float(1.5),
+            CellValue::Null,
+            CellValue::Float(2.5),
+        ];
+        assert_eq!(
+            Aggregate::Avg.compute(&float, &values).as_deref(),
+            Some("2")
+        );
+    }
+
+    #[test]
+    fn averages_count_the_values_summed() {
+        let numeric = field("NUMERIC");
+        assert_eq!(
+            Aggregate::Avg
+                .compute(&numeric, &numerics(&["1", "1", "0", "not a number"]))
+                .as_deref(),
+            Some("0.666666667")
+        );
+        assert_eq!(
+            Aggregate::Avg.compute(&numeric, &numerics(&["not a number"])),
+            None
+        );
+
+        let float = field("FLOAT");
+        let values = vec![CellValue::Float(3.0), CellValue::Text("x".to_string())];
+        assert_eq!(
+            Aggregate::Avg.compute(&float, &values).as_deref(),
+            Some("3")
+        );
+        assert_eq!(
+            Aggregate::Avg.compute(&float, &[CellValue::Text("x".to_string())]),
+            None
+        );
+    }
+
+    #[test]
+    fn min_and_max_of_any_scalar() {
+        let string = field("STRING");
+        let values = vec![
+            CellValue::Text("pear".to_string()),
+            CellValue::Text("apple".to_string()),
+        ];
+        assert_eq!(
+            Aggregate::Min.compute(&string, &values).as_deref(),
+            Some("apple")
+        );
+        assert_eq!(
+            Aggregate::Max.compute(&string, &values).as_deref(),
+            Some("pear")
+        );
+        assert!(!Aggregate::Sum.applies_to(&string));
+        assert!(Aggregate::Count.applies_to(&string));
+        assert_eq!("avg".parse::<Aggregate>(), Ok(Aggregate::Avg));
+    }
+}
@@ -0,0 +1,523 @@
+use std::{cell::RefCell, collections::HashMap, str::FromStr};
+
+use super::{
+    base_element_trait::BaseElementTrait,
+    bq_common_custom_element::{
+        get_opt_attribute, get_opt_num_attribute, handle_declarative_render, set_attribute,
+        set_optional_attribute,
+    },
+    chart::{ChartKind, ChartRoles, chart_columns, chart_data, chart_html},
+    custom_element_definition::CustomElementDefinition,
+    grid_events::dispatch_load_error,
+    loaded_results::{self, LoadedResults, ResultsSource},
+    profile::is_numeric_type,
+    value_tree::escape_html,
+};
+use crate::{bigquery::jobs::GetQueryResultsRequest, custom_elements::base_element::BaseElement};
+use wasm_bindgen::{JsCast, prelude::Closure};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+/*
+`bq-chart`: the rows of a page of the results of a query as a bar, line, scatter or area chart
+(see `chart.rs`), the columns of the X axis, the Y series and the group by picked in its
+controls. The page is taken from the results already loaded for the same job (the grid of a
+`bq-query` switched to a chart) and only fetched when no element has it.
+*/
+
+const TAG_NAME: &str = "bq-chart";
+const PAGE_START_INDEX_ATT: &str = "page_start_index";
+const PAGE_SIZE_ATT: &str = "page_size";
+/// The roles a chart written in the html starts with: `chart_type="line" x="day" y="a,b"`.
+const CHART_TYPE_ATT: &str = "chart_type";
+const X_ATT: &str = "x";
+const Y_ATT: &str = "y";
+const GROUP_BY_ATT: &str = "group_by";
+/// `be_id` of the grid showing the same results, shown again by the "Grid" button.
+const SOURCE_ATT: &str = "source";
+const CHART_CONTROLS: &str = "chart_controls";
+const CHART_AREA: &str = "chart_area";
+pub(crate) const RENDER_CHART_EVENT_NAME: &str = "render_chart";
+
+thread_local! {
+    // keyed by the `be_id` of the element, the roles picked in its controls
+    static CHART_ROLES: RefCell<HashMap<String, ChartRoles>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) struct BigqueryChartCustomElement {
+    element_id: String,
+    job_id: String,
+    project_id: String,
+    location: String,
+    token: String,
+    page_start_index: usize,
+    page_size: usize,
+    source: Option<String>,
+}
+
+impl BigqueryChartCustomElement {
+    pub(crate) fn from_element(element: &Element) -> Option<BigqueryChartCustomElement> {
+        Some(BigqueryChartCustomElement {
+            element_id: element.get_attribute("be_id")?,
+            job_id: element.get_attribute("job_id")?,
+            project_id: element.get_attribute("project_id")?,
+            location: element.get_attribute("location")?,
+            token: element.get_attribute("token")?,
+            page_start_index: get_opt_num_attribute(element, PAGE_START_INDEX_ATT).unwrap_or(0),
+            page_size: get_opt_num_attribute(element, PAGE_SIZE_ATT).unwrap_or(50),
+            source: get_opt_attribute(element, SOURCE_ATT),
+        })
+    }
+
+    /// The chart of the page the `bq-query` `grid` is showing.
+    fn from_grid(grid: &Element) -> Option<BigqueryChartCustomElement> {
+        let grid_id = grid.get_attribute("be_id")?;
+        Some(BigqueryChartCustomElement {
+            element_id: format!("{}_chart", grid_id),
+            job_id: grid.get_attribute("job_id")?,
+            project_id: grid.get_attribute("project_id")?,
+            location: grid.get_attribute("location")?,
+            token: grid.get_attribute("token")?,
+            page_start_index: get_opt_num_attribute(grid, PAGE_START_INDEX_ATT).unwrap_or(0),
+            page_size: get_opt_num_attribute(grid, PAGE_SIZE_ATT).unwrap_or(50),
+            source: Some(grid_id),
+        })
+    }
+
+    fn as_query_results_request(&self) -> GetQueryResultsRequest {
+        GetQueryResultsRequest {
+            project_id: self.project_id.clone(),
+            job_id: self.job_id.clone(),
+            location: Some(self.location.clone()),
+            start_index: Some(self.page_start_index.to_string()),
+            max_results: Some(self.page_size),
+            page_token: None,
+        }
+    }
+
+    /// The page of the results the chart is drawn from, if this or another element loaded it.
+    fn loaded_page(&self) -> Option<LoadedResults> {
+        let is_page = |results: &LoadedResults| {
+            results.page_start_index == self.page_start_index
+                && matches!(&results.source, Some(ResultsSource::Query(j)) if j.job_id == self.job_id && j.project_id == self.project_id)
+        };
+        loaded_results::get(&self.element_id)
+            .filter(is_page)
+            .or_else(|| loaded_results::find(&is_page))
+    }
+
+    fn on_render_chart(event: &web_sys::Event) {
+        let element = match event
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+        {
+            Some(e) => e,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_chart: event target is not an element",
+                ));
+                return;
+            }
+        };
+        if element.has_attribute("loaded") {
+            return;
+        }
+        set_attribute(&element, "loaded", "1");
+
+        let bq_chart = match BigqueryChartCustomElement::from_element(&element) {
+            Some(c) => c,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_chart: element is missing required attributes",
+                ));
+                return;
+            }
+        };
+        let parent_node = match element.parent_element() {
+            Some(p) => p,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_chart: bq-chart element has no parent element",
+                ));
+                return;
+            }
+        };
+        bq_chart.render(&parent_node);
+
+        // switching from the grid of the same job does not fetch the rows again
+        if let Some(results) = bq_chart.loaded_page() {
+            loaded_results::store(&bq_chart.element_id, results);
+            refresh(&element);
+            return;
+        }
+
+        let jobs = crate::bigquery::jobs::Jobs::new(&bq_chart.token);
+        let request = bq_chart.as_query_results_request();
+        spawn_local(async move {
+            match jobs.get_query_results(request).await {
+                Ok(response) => {
+                    loaded_results::store(
+                        &bq_chart.element_id,
+                        response.to_loaded_results(bq_chart.page_start_index),
+                    );
+                    refresh(&element);
+                }
+                Err(e) => dispatch_load_error(&element, "jobs.getQueryResults", &e),
+            }
+        });
+    }
+}
+
+impl CustomElementDefinition for BigqueryChartCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec!["job_id", "project_id", "location", "token", PAGE_SIZE_ATT]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["job_id", "project_id", "location", "token"],
+            &[(PAGE_SIZE_ATT, "50"), (PAGE_START_INDEX_ATT, "0")],
+            RENDER_CHART_EVENT_NAME,
+        );
+    }
+
+    fn define(_document: &web_sys::Document, element: &web_sys::Element) {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(BigqueryChartCustomElement::on_render_chart)
+                as Box<dyn Fn(&web_sys::Event)>);
+
+        if let Err(e) = element.add_event_listener_with_callback(
+            RENDER_CHART_EVENT_NAME,
+            on_event_type_closure.as_ref().unchecked_ref(),
+        ) {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-chart define: failed to add '{}' listener: {:?}",
+                RENDER_CHART_EVENT_NAME, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+impl BaseElementTrait for BigqueryChartCustomElement {
+    fn get_element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    fn render(&self, parent_node: &web_sys::Node) -> BaseElement {
+        let bq_chart = BaseElement::new_and_append(parent_node, TAG_NAME, &self.element_id)
+            .apply_fn(&set_attributes, self);
+
+        let css_content = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/grid.css"));
+        bq_chart
+            .append_shadow()
+            .append_child_style(css_content, "style1")
+            .append_sibling_fn("div", CHART_CONTROLS, &init_controls, self)
+            .append_sibling_fn("div", CHART_AREA, &init_area, self);
+
+        bq_chart
+    }
+}
+
+fn set_attributes(base_element: &BaseElement, bq_chart: &BigqueryChartCustomElement) {
+    let element = base_element.element();
+    element.set_id(&bq_chart.element_id);
+
+    set_attribute(&element, "job_id", &bq_chart.job_id);
+    set_attribute(&element, "project_id", &bq_chart.project_id);
+    set_attribute(&element, "location", &bq_chart.location);
+    set_attribute(&element, "token", &bq_chart.token);
+    set_optional_attribute(
+        &element,
+        PAGE_START_INDEX_ATT,
+        &Some(bq_chart.page_start_index),
+    );
+    set_attribute(&element, PAGE_SIZE_ATT, &bq_chart.page_size.to_string());
+    if let Some(source) = &bq_chart.source {
+        set_attribute(&element, SOURCE_ATT, source);
+    }
+}
+
+fn init_controls(base_element: &BaseElement, _bq_chart: &BigqueryChartCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    let _ = element.set_attribute("role", "toolbar");
+    let _ = element.set_attribute("aria-label", "Chart");
+
+    let on_change = Closure::wrap(Box::new(on_role_change) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+    on_change.forget();
+    let on_click = Closure::wrap(Box::new(on_controls_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref());
+    on_click.forget();
+
+    let _ = element.set_attribute("bee", "1");
+}
+
+fn init_area(base_element: &BaseElement, bq_chart: &BigqueryChartCustomElement) {
+    let element = &base_element.element();
+    if bq_chart.loaded_page().is_none() {
+        element.set_inner_html(r#"<div class="loading-indicator">Rendering…</div>"#);
+    }
+}
+
+/// Shows the page the `bq-query` `grid` is showing as a chart, in a `bq-chart` next to it
+/// drawn from the rows the grid loaded.
+pub(crate) fn show_chart(grid: &Element) {
+    let (bq_chart, parent_node) = match (
+        BigqueryChartCustomElement::from_grid(grid),
+        grid.parent_node(),
+    ) {
+        (Some(c), Some(p)) => (c, p),
+        _ => {
+            web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                "show_chart: the grid is missing required attributes",
+            ));
+            return;
+        }
+    };
+    let chart = bq_chart.render(&parent_node).element();
+    let _ = parent_node.insert_before(&chart, grid.next_sibling().as_ref());
+
+    let _ = grid.set_attribute("hidden", "");
+    let _ = chart.remove_attribute("hidden");
+    let _ = chart.remove_attribute("loaded");
+    if let Ok(event) = web_sys::Event::new(RENDER_CHART_EVENT_NAME) {
+        let _ = chart.dispatch_event(&event);
+    }
+}
+
+/// Back to the grid the chart `host` was switched to from.
+fn show_grid(host: &Element) {
+    let grid = match (host.get_attribute(SOURCE_ATT), host.parent_element()) {
+        (Some(source), Some(parent)) => parent
+            .query_selector(&format!(":scope > [be_id='{}']", source))
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    if let Some(grid) = grid {
+        let _ = grid.remove_attribute("hidden");
+        let _ = host.set_attribute("hidden", "");
+    }
+}
+
+/// The roles of the chart `host`: the ones picked in its controls, its attributes before that.
+fn roles(host: &Element, fields: &[crate::bigquery::base::TableFieldSchema]) -> ChartRoles {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let picked = CHART_ROLES.with(|r| r.borrow().get(&element_id).cloned());
+    let roles = picked.unwrap_or_else(|| {
+        let defaults = ChartRoles::default_for(fields);
+        let y: Vec<String> = get_opt_attribute(host, Y_ATT)
+            .map(|y| {
+                y.split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        ChartRoles {
+            kind: get_opt_attribute(host, CHART_TYPE_ATT)
+                .and_then(|k| ChartKind::from_str(&k).ok())
+                .unwrap_or(defaults.kind),
+            x: get_opt_attribute(host, X_ATT).or(defaults.x),
+            y: if y.is_empty() { defaults.y } else { y },
+            group_by: get_opt_attribute(host, GROUP_BY_ATT).filter(|g| !g.is_empty()),
+        }
+    });
+    roles.valid_for(fields)
+}
+
+/// Draws the chart `host` from its loaded page, with the controls of its roles.
+fn refresh(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (results, shadow_root) = match (loaded_results::get(&element_id), host.shadow_root()) {
+        (Some(r), Some(s)) => (r, s),
+        _ => return,
+    };
+    let find = |id: &str| {
+        shadow_root
+            .query_selector(&format!("[be_id='{}']", id))
+            .ok()
+            .flatten()
+    };
+    let fields = &results.schema.fields;
+    let roles = roles(host, fields);
+
+    if let Some(controls) = find(CHART_CONTROLS) {
+        controls.set_inner_html(&controls_html(host, &results, &roles));
+    }
+    if let Some(area) = find(CHART_AREA) {
+        let html = match chart_data(fields, &results.rows, &roles) {
+            Some(data) => chart_html(&data),
+            None => r#"<p class="chart-empty">Choose a numeric column for Y.</p>"#.to_string(),
+        };
+        area.set_inner_html(&html);
+    }
+}
+
+fn controls_html(host: &Element, results: &LoadedResults, roles: &ChartRoles) -> String {
+    let columns = chart_columns(&results.schema.fields);
+    let option = |value: &str, label: &str, selected: bool| {
+        format!(
+            r#"<option value="{}"{}>{}</option>"#,
+            escape_html(value),
+            if selected { " selected" } else { "" },
+            escape_html(label)
+        )
+    };
+
+    let kinds: Vec<String> = ChartKind::get_all()
+        .iter()
+        .map(|k| option(k.name(), k.label(), *k == roles.kind))
+        .collect();
+    let x: Vec<String> = columns
+        .iter()
+        .map(|(p, _)| option(p, p, roles.x.as_ref() == Some(p)))
+        .collect();
+    let y: Vec<String> = columns
+        .iter()
+        .filter(|(_, f)| is_numeric_type(&f.r#type))
+        .map(|(p, _)| {
+            format!(
+                r#"<label><input type="checkbox" data-chart-role="y" value="{}"{}>{}</label>"#,
+                escape_html(p),
+                if roles.y.contains(p) { " checked" } else { "" },
+                escape_html(p)
+            )
+        })
+        .collect();
+    let group_by: Vec<String> = std::iter::once(option("", "None", roles.group_by.is_none()))
+        .chain(
+            columns
+                .iter()
+                .map(|(p, _)| option(p, p, roles.group_by.as_ref() == Some(p))),
+        )
+        .collect();
+    let grid_button = match host.has_attribute(SOURCE_ATT) {
+        true => {
+            r#"<button type="button" data-chart-action="grid" title="Show the rows as a grid">Grid</button>"#
+        }
+        false => "",
+    };
+
+    format!(
+        concat!(
+            r#"{}<label>Chart <select data-chart-role="kind">{}</select></label>"#,
+            r#"<label>X <select data-chart-role="x">{}</select></label>"#,
+            r#"<span class="chart-y" role="group" aria-label="Y">Y {}</span>"#,
+            r#"<label>Group by <select data-chart-role="group_by">{}</select></label>"#,
+            r#"<span class="chart-scope" role="status">Rows {} - {}</span>"#
+        ),
+        grid_button,
+        kinds.join(""),
+        x.join(""),
+        y.join(""),
+        group_by.join(""),
+        results.page_start_index + 1,
+        results.page_start_index + results.rows.len()
+    )
+}
+
+/// A role changed in the controls: the roles read back from them, the chart drawn again.
+fn on_role_change(event: &web_sys::Event) {
+    let controls = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(c) => c,
+        None => return,
+    };
+    let host = match controls
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let select = |role: &str| {
+        controls
+            .query_selector(&format!("select[data-chart-role='{}']", role))
+            .ok()
+            .flatten()
+            .and_then(|s| s.dyn_into::<web_sys::HtmlSelectElement>().ok())
+            .map(|s| s.value())
+            .filter(|v| !v.is_empty())
+    };
+    let mut y = Vec::new();
+    if let Ok(checked) = controls.query_selector_all("input[data-chart-role='y']:checked") {
+        for i in 0..checked.length() {
+            if let Some(input) = checked
+                .item(i)
+                .and_then(|n| n.dyn_into::<web_sys::HtmlInputElement>().ok())
+            {
+                y.push(input.value());
+            }
+        }
+    }
+
+    let roles = ChartRoles {
+        kind: select("kind")
+            .and_then(|k| ChartKind::from_str(&k).ok())
+            .unwrap_or_default(),
+        x: select("x"),
+        y,
+        group_by: select("group_by"),
+    };
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    CHART_ROLES.with(|r| r.borrow_mut().insert(element_id, roles));
+
+    // the controls are drawn again, the focus goes back to the one changed
+    let changed = event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| {
+            let role = t.get_attribute("data-chart-role")?;
+            let value = t
+                .get_attribute("value")
+                .map(|v| format!("[value='{}']", v))
+                .unwrap_or_default();
+            Some(format!(
+                "{}[data-chart-role='{}']{}",
+                t.tag_name().to_lowercase(),
+                role,
+                value
+            ))
+        });
+    refresh(&host);
+    if let Some(changed) = changed
+        .and_then(|c| controls.query_selector(&c).ok().flatten())
+        .and_then(|c| c.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = changed.focus();
+    }
+}
+
+fn on_controls_click(event: &web_sys::Event) {
+    let target = match event.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+        Some(t) => t,
+        None => return,
+    };
+    if target
+        .closest("button[data-chart-action='grid']")
+        .ok()
+        .flatten()
+        .is_none()
+    {
+        return;
+    }
+    if let Some(host) = target
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+    {
+        show_grid(&host);
+    }
+}
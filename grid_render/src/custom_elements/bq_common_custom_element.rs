@@ -3,6 +3,11 @@ use web_sys::Element;
 
 use crate::parse_to_usize;
 
+use super::grid_events::dispatch_page_changed;
+
+/// Not observed: a new render never starts from it.
+const RENDER_GENERATION_ATT: &str = "render_generation";
+
 pub(crate) fn get_attribute(element: &Element, attribute_name: &str) -> String {
     element.get_attribute(attribute_name).unwrap_or_else(|| {
         web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
@@ -50,6 +55,19 @@ pub(crate) fn get_opt_num_attribute(element: &Element, attribute_name: &str) ->
     parse_to_usize(element.get_attribute(attribute_name))
 }
 
+/// Stamps a new render of `element` and returns its generation: the fetch of an earlier render
+/// may still be pending, `is_current_render` tells its response to drop itself.
+pub(crate) fn next_render_generation(element: &Element) -> usize {
+    let generation = get_opt_num_attribute(element, RENDER_GENERATION_ATT).unwrap_or(0) + 1;
+    set_attribute(element, RENDER_GENERATION_ATT, &generation.to_string());
+    generation
+}
+
+/// Whether `generation` is still the last render started on `element`.
+pub(crate) fn is_current_render(element: &Element, generation: usize) -> bool {
+    get_opt_num_attribute(element, RENDER_GENERATION_ATT) == Some(generation)
+}
+
 /// Shared handler for pagination button events (first / previous / next / last page).
 ///
 /// `page_op` receives the custom element's DOM `Element`, applies the page change,
@@ -84,8 +102,62 @@ pub(crate) fn handle_page_nav_event(
 
     let changed = page_op(&element);
     if changed {
+        dispatch_page_changed(&element);
         if let Ok(event) = web_sys::Event::new(render_event_name) {
             let _ = element.dispatch_event(&event);
         }
     }
 }
+
+/// The `bq-table` / `bq-query` / `bq-script` whose shadow root holds `element`.
+pub(crate) fn host_of(element: &Element) -> Option<Element> {
+    element
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+}
+
+/// The element `be_id` of the shadow root of `host`.
+pub(crate) fn find_in_shadow(host: &Element, be_id: &str) -> Option<Element> {
+    host.shadow_root()?
+        .query_selector(&format!("[be_id='{}']", be_id))
+        .ok()
+        .flatten()
+}
+
+/// Shared render for elements written directly in the html (`<bq-table project_id=...>`).
+///
+/// Fills in the attributes `from_element` expects but a page author may leave out, then
+/// dispatches `render_event_name` once every attribute in `required` is present.
+pub(crate) fn handle_declarative_render(
+    element: &Element,
+    tag_name: &str,
+    required: &[&str],
+    defaults: &[(&str, &str)],
+    render_event_name: &str,
+) {
+    for (attribute_name, value) in defaults {
+        if !element.has_attribute(attribute_name) {
+            set_attribute(element, attribute_name, value);
+        }
+    }
+
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|a| !element.has_attribute(a))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&format!(
+            "handle_declarative_render: {} is missing attributes {:?}",
+            tag_name, missing
+        )));
+        return;
+    }
+
+    remove_attribute(element, "loaded");
+    if let Ok(event) = web_sys::Event::new(render_event_name) {
+        let _ = element.dispatch_event(&event);
+    }
+}
@@ -0,0 +1,563 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{
+    base_element_trait::BaseElementTrait,
+    bq_common_custom_element::{
+        get_opt_attribute, get_opt_num_attribute, handle_declarative_render, set_attribute,
+    },
+    custom_element_definition::CustomElementDefinition,
+    diff::{RowDiff, common_columns, diff_rows, diff_table, schema_changes},
+    export_all::fetch_page,
+    grid_events::dispatch_load_error,
+    grid_selection::grid_rows,
+    loaded_results::ResultsSource,
+    page_walk::{PageStep, PageWalk},
+    value_tree::escape_html,
+};
+use crate::{
+    bigquery::{
+        base::{TableFieldSchema, TableReference},
+        error::BigQueryError,
+        jobs::JobReference,
+        tables::Tables,
+    },
+    custom_elements::base_element::BaseElement,
+};
+use wasm_bindgen::{JsCast, prelude::Closure};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+use website_component_table::HtmlNodeRender;
+
+/*
+`bq-diff`: the difference between two results, each the one of a job (`left_job_id`,
+`left_project_id`, `left_location`) or of a table (`left_project_id`, `left_dataset_id`,
+`left_table_id`), and the same with `right_`. Every page of both is fetched (up to `max_rows`
+rows a side), then compared by `diff.rs`: the schema changes, the counts, and the differing
+rows as a grid, aligned by the key columns checked in its controls (`keys="id,day"` to start
+with) or by position.
+*/
+
+const TAG_NAME: &str = "bq-diff";
+const LEFT: &str = "left";
+const RIGHT: &str = "right";
+const KEYS_ATT: &str = "keys";
+const MAX_ROWS_ATT: &str = "max_rows";
+const DIFF_SUMMARY: &str = "diff_summary";
+const DIFF_CONTROLS: &str = "diff_controls";
+const DIFF_AREA: &str = "diff_area";
+pub(crate) const RENDER_DIFF_EVENT_NAME: &str = "render_diff";
+/// Rows of the difference shown in the grid, the counts cover them all.
+const MAX_DIFF_ROWS_SHOWN: usize = 1000;
+
+/// The rows of one side, as fetched.
+#[derive(Debug, Clone)]
+struct DiffSide {
+    label: String,
+    fields: Vec<TableFieldSchema>,
+    rows: Vec<serde_json::Value>,
+    /// More rows than `max_rows` were there, the rest is not compared.
+    truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+struct DiffState {
+    left: DiffSide,
+    right: DiffSide,
+    keys: Vec<String>,
+}
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static DIFFS: RefCell<HashMap<String, DiffState>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) struct BigqueryDiffCustomElement {
+    element_id: String,
+    token: String,
+    left: ResultsSource,
+    right: ResultsSource,
+    keys: Vec<String>,
+    max_rows: usize,
+}
+
+impl BigqueryDiffCustomElement {
+    pub(crate) fn from_element(element: &Element) -> Option<BigqueryDiffCustomElement> {
+        Some(BigqueryDiffCustomElement {
+            element_id: element.get_attribute("be_id")?,
+            token: element.get_attribute("token")?,
+            left: side_source(element, LEFT)?,
+            right: side_source(element, RIGHT)?,
+            keys: get_opt_attribute(element, KEYS_ATT)
+                .map(|k| {
+                    k.split(',')
+                        .map(|p| p.trim().to_string())
+                        .filter(|p| !p.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            max_rows: get_opt_num_attribute(element, MAX_ROWS_ATT).unwrap_or(50_000),
+        })
+    }
+
+    fn on_render_diff(event: &web_sys::Event) {
+        let element = match event
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+        {
+            Some(e) => e,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_diff: event target is not an element",
+                ));
+                return;
+            }
+        };
+        if element.has_attribute("loaded") {
+            return;
+        }
+        set_attribute(&element, "loaded", "1");
+
+        let bq_diff = match BigqueryDiffCustomElement::from_element(&element) {
+            Some(d) => d,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_diff: element is missing required attributes (token, and a job or a table a side)",
+                ));
+                return;
+            }
+        };
+        let parent_node = match element.parent_element() {
+            Some(p) => p,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_diff: bq-diff element has no parent element",
+                ));
+                return;
+            }
+        };
+        bq_diff.render(&parent_node);
+
+        spawn_local(async move {
+            let sides = match load_side(&bq_diff.left, &bq_diff.token, bq_diff.max_rows).await {
+                Ok(left) => load_side(&bq_diff.right, &bq_diff.token, bq_diff.max_rows)
+                    .await
+                    .map(|right| (left, right)),
+                Err(e) => Err(e),
+            };
+            match sides {
+                Ok((left, right)) => {
+                    let state = DiffState {
+                        left,
+                        right,
+                        keys: bq_diff.keys,
+                    };
+                    DIFFS.with(|d| d.borrow_mut().insert(bq_diff.element_id, state));
+                    refresh(&element);
+                }
+                Err((operation, e)) => dispatch_load_error(&element, operation, &e),
+            }
+        });
+    }
+}
+
+/// The job or the table of the side `prefix` (`left` or `right`) of `element`.
+fn side_source(element: &Element, prefix: &str) -> Option<ResultsSource> {
+    let attribute = |name: &str| element.get_attribute(&format!("{}_{}", prefix, name));
+    let project_id = attribute("project_id")?;
+    match (attribute("job_id"), attribute("table_id")) {
+        (Some(job_id), _) => Some(ResultsSource::Query(JobReference {
+            project_id,
+            job_id,
+            location: attribute("location")?,
+        })),
+        (None, Some(table_id)) => Some(ResultsSource::Table(TableReference {
+            project_id,
+            dataset_id: attribute("dataset_id")?,
+            table_id,
+        })),
+        (None, None) => None,
+    }
+}
+
+/// Every page of `source`, up to `max_rows` rows, with its schema.
+async fn load_side(
+    source: &ResultsSource,
+    token: &str,
+    max_rows: usize,
+) -> Result<DiffSide, (&'static str, BigQueryError)> {
+    let (label, mut fields) = match source {
+        ResultsSource::Query(job) => (
+            format!("job {}:{}.{}", job.project_id, job.location, job.job_id),
+            None,
+        ),
+        ResultsSource::Table(table) => {
+            let schema = Tables::new(token)
+                .get(table.clone())
+                .await
+                .map_err(|e| ("tables.get", e))?
+                .schema;
+            (
+                format!(
+                    "{}.{}.{}",
+                    table.project_id, table.dataset_id, table.table_id
+                ),
+                schema.map(|s| s.fields),
+            )
+        }
+    };
+
+    let mut rows: Vec<serde_json::Value> = vec![];
+    let mut walk = PageWalk::default();
+    let mut page_token: Option<String> = None;
+    let mut truncated = false;
+    loop {
+        let page = fetch_page(source, token, page_token.take()).await?;
+        if fields.is_none() {
+            fields = page.schema.map(|s| s.fields);
+        }
+        match walk.step(page.rows.len(), page.rows_total, page.page_token, false) {
+            PageStep::Write(next) => {
+                rows.extend(page.rows);
+                if rows.len() >= max_rows {
+                    truncated = rows.len() > max_rows || next.is_some();
+                    rows.truncate(max_rows);
+                    break;
+                }
+                match next {
+                    Some(t) => page_token = Some(t),
+                    None => break,
+                }
+            }
+            // the rows after are out of reach: compared as the first ones, not as all of them
+            PageStep::Stalled | PageStep::Cancelled => {
+                truncated = true;
+                break;
+            }
+        }
+    }
+
+    Ok(DiffSide {
+        label,
+        fields: fields.unwrap_or_default(),
+        rows,
+        truncated,
+    })
+}
+
+impl CustomElementDefinition for BigqueryDiffCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec![
+            "token",
+            "left_project_id",
+            "left_job_id",
+            "left_location",
+            "left_dataset_id",
+            "left_table_id",
+            "right_project_id",
+            "right_job_id",
+            "right_location",
+            "right_dataset_id",
+            "right_table_id",
+            KEYS_ATT,
+            MAX_ROWS_ATT,
+        ]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["token", "left_project_id", "right_project_id"],
+            &[],
+            RENDER_DIFF_EVENT_NAME,
+        );
+    }
+
+    fn define(_document: &web_sys::Document, element: &web_sys::Element) {
+        let on_event_type_closure = Closure::wrap(Box::new(
+            BigqueryDiffCustomElement::on_render_diff,
+        ) as Box<dyn Fn(&web_sys::Event)>);
+
+        if let Err(e) = element.add_event_listener_with_callback(
+            RENDER_DIFF_EVENT_NAME,
+            on_event_type_closure.as_ref().unchecked_ref(),
+        ) {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-diff define: failed to add '{}' listener: {:?}",
+                RENDER_DIFF_EVENT_NAME, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+impl BaseElementTrait for BigqueryDiffCustomElement {
+    fn get_element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    fn render(&self, parent_node: &web_sys::Node) -> BaseElement {
+        let bq_diff = BaseElement::new_and_append(parent_node, TAG_NAME, &self.element_id)
+            .apply_fn(&set_attributes, self);
+
+        let css_content = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/grid.css"));
+        bq_diff
+            .append_shadow()
+            .append_child_style(css_content, "style1")
+            .append_sibling_fn("div", DIFF_SUMMARY, &init_summary, self)
+            .append_sibling_fn("div", DIFF_CONTROLS, &init_controls, self)
+            .append_sibling_fn("div", DIFF_AREA, &init_area, self);
+
+        bq_diff
+    }
+}
+
+fn set_attributes(base_element: &BaseElement, bq_diff: &BigqueryDiffCustomElement) {
+    base_element.element().set_id(&bq_diff.element_id);
+}
+
+fn init_summary(base_element: &BaseElement, _bq_diff: &BigqueryDiffCustomElement) {
+    let element = &base_element.element();
+    let _ = element.set_attribute("role", "status");
+    let _ = element.set_attribute("aria-live", "polite");
+}
+
+fn init_controls(base_element: &BaseElement, _bq_diff: &BigqueryDiffCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    let _ = element.set_attribute("role", "group");
+    let _ = element.set_attribute("aria-label", "Align rows by");
+
+    let on_change = Closure::wrap(Box::new(on_keys_change) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref());
+    on_change.forget();
+
+    let _ = element.set_attribute("bee", "1");
+}
+
+fn init_area(base_element: &BaseElement, _bq_diff: &BigqueryDiffCustomElement) {
+    base_element
+        .element()
+        .set_inner_html(r#"<div class="loading-indicator">Fetching both results…</div>"#);
+}
+
+/// Compares the two sides of `host` by its keys and shows the difference.
+fn refresh(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (state, shadow_root) = match (
+        DIFFS.with(|d| d.borrow().get(&element_id).cloned()),
+        host.shadow_root(),
+    ) {
+        (Some(s), Some(r)) => (s, r),
+        _ => return,
+    };
+    let find = |id: &str| {
+        shadow_root
+            .query_selector(&format!("[be_id='{}']", id))
+            .ok()
+            .flatten()
+    };
+    let (left, right) = (&state.left, &state.right);
+    let changes = schema_changes(&left.fields, &right.fields);
+    let diff = diff_rows(
+        &left.fields,
+        &left.rows,
+        &right.fields,
+        &right.rows,
+        &state.keys,
+    );
+
+    if let Some(controls) = find(DIFF_CONTROLS) {
+        controls.set_inner_html(&controls_html(
+            &common_columns(&left.fields, &right.fields),
+            &state.keys,
+        ));
+    }
+
+    let mut summary = vec![format!(
+        r#"<p><span class="diff-side">− {}</span> {} rows · <span class="diff-side">+ {}</span> {} rows</p>"#,
+        escape_html(&left.label),
+        rows_text(left),
+        escape_html(&right.label),
+        rows_text(right)
+    )];
+    match &diff {
+        Ok(diff) => {
+            summary.push(format!(
+                r#"<p class="diff-counts">{} added · {} removed · {} changed · {} unchanged{}</p>"#,
+                diff.count("added"),
+                diff.count("removed"),
+                diff.count("changed"),
+                diff.unchanged,
+                match diff.duplicate_keys {
+                    0 => String::new(),
+                    n => format!(" · {} rows with a duplicate key, aligned in order", n),
+                }
+            ));
+        }
+        Err(message) => summary.push(format!(
+            r#"<p class="diff-error" role="alert">{}</p>"#,
+            escape_html(message)
+        )),
+    }
+    if changes.is_empty() {
+        summary.push(r#"<p>Same schema.</p>"#.to_string());
+    } else {
+        let items: Vec<String> = changes
+            .iter()
+            .map(|c| format!("<li>{}</li>", escape_html(&c.description())))
+            .collect();
+        summary.push(format!(
+            r#"<details open><summary>Schema: {} difference{}</summary><ul class="diff-schema">{}</ul></details>"#,
+            changes.len(),
+            if changes.len() == 1 { "" } else { "s" },
+            items.join("")
+        ));
+    }
+    if let Some(element) = find(DIFF_SUMMARY) {
+        element.set_inner_html(&summary.join(""));
+    }
+
+    let area = match find(DIFF_AREA) {
+        Some(a) => a,
+        None => return,
+    };
+    area.set_inner_html("");
+    let diff = match diff {
+        Ok(d) if !d.rows.is_empty() => d,
+        Ok(_) => {
+            area.set_inner_html(r#"<p class="diff-empty">The rows are the same.</p>"#);
+            return;
+        }
+        Err(_) => return,
+    };
+    let table_builder = diff_table(
+        &diff,
+        (&left.fields, &left.rows),
+        (&right.fields, &right.rows),
+        MAX_DIFF_ROWS_SHOWN,
+    );
+    match table_builder.render() {
+        Ok(nodes) => {
+            for node in nodes.iter().filter_map(|n| n.to_element_node().ok()) {
+                let _ = area.append_child(&node);
+            }
+        }
+        Err(e) => {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-diff: table render failed: {:?}",
+                e
+            )));
+            return;
+        }
+    }
+    if diff.rows.len() > MAX_DIFF_ROWS_SHOWN {
+        let note = format!(
+            r#"<p class="diff-more">The first {} of {} differing rows are shown.</p>"#,
+            MAX_DIFF_ROWS_SHOWN,
+            diff.rows.len()
+        );
+        let _ = area.insert_adjacent_html("beforeend", &note);
+    }
+    mark_rows(&area, &diff.rows);
+}
+
+fn rows_text(side: &DiffSide) -> String {
+    match side.truncated {
+        true => format!("first {}", side.rows.len()),
+        false => side.rows.len().to_string(),
+    }
+}
+
+/// The rows of the grid in `area` marked as added, removed or changed, the changed values too.
+fn mark_rows(area: &Element, rows: &[RowDiff]) {
+    let table = match area.query_selector("bstruct-table").ok().flatten() {
+        Some(t) => t,
+        None => return,
+    };
+    let (_, data_rows) = grid_rows(&table);
+
+    for (row, row_diff) in data_rows.iter().zip(rows) {
+        let class = match row_diff {
+            RowDiff::Added { .. } => "diff-added",
+            RowDiff::Removed { .. } => "diff-removed",
+            RowDiff::Changed { .. } => "diff-changed",
+        };
+        let _ = row.class_list().add_1(class);
+        if let RowDiff::Changed { columns, .. } = row_diff {
+            for column in columns {
+                // the `+` / `−` / `~` cell first
+                if let Some(cell) = row.children().item(*column as u32 + 1) {
+                    let _ = cell.class_list().add_1("diff-cell");
+                }
+            }
+        }
+    }
+}
+
+fn controls_html(columns: &[String], keys: &[String]) -> String {
+    let checkboxes: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            format!(
+                r#"<label><input type="checkbox" data-diff-key value="{0}"{1}>{0}</label>"#,
+                escape_html(c),
+                if keys.contains(c) { " checked" } else { "" }
+            )
+        })
+        .collect();
+    format!(
+        r#"<span>Align rows by</span>{}<span class="diff-hint">{}</span>"#,
+        checkboxes.join(""),
+        if keys.is_empty() {
+            "position, no key checked"
+        } else {
+            ""
+        }
+    )
+}
+
+/// A key checked or unchecked: the rows aligned again, in the order the keys were checked.
+fn on_keys_change(event: &web_sys::Event) {
+    let input = match event
+        .target()
+        .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+    {
+        Some(i) if i.has_attribute("data-diff-key") => i,
+        _ => return,
+    };
+    let host = match input
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let key = input.value();
+    DIFFS.with(|d| {
+        if let Some(state) = d.borrow_mut().get_mut(&element_id) {
+            state.keys.retain(|k| *k != key);
+            if input.checked() {
+                state.keys.push(key.clone());
+            }
+        }
+    });
+
+    refresh(&host);
+    if let Some(checkbox) = host
+        .shadow_root()
+        .and_then(|s| {
+            s.query_selector(&format!("input[data-diff-key][value='{}']", key))
+                .ok()
+                .flatten()
+        })
+        .and_then(|c| c.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = checkbox.focus();
+    }
+}
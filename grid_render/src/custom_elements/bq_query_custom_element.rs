@@ -1,14 +1,21 @@
 use super::{
     base_element_trait::BaseElementTrait,
     bq_common_custom_element::{
-        get_opt_attribute, get_opt_num_attribute,
-        handle_page_nav_event, set_attribute, set_optional_attribute,
+        get_opt_attribute, get_opt_num_attribute, handle_declarative_render,
+        handle_page_nav_event, is_current_render, next_render_generation, set_attribute,
+        set_optional_attribute,
     },
     custom_element_definition::CustomElementDefinition,
+    cell_inspector, column_widths, grid_columns, grid_find, grid_navigation, grid_selection, loaded_results,
+    grid_events::{
+        EVENT_LOAD_COMPLETE, LoadCompleteDetail, dispatch_grid_event, dispatch_load_error,
+        on_grid_click,
+    },
     data_table_controls_element::{
         DataTableControls, EVENT_GO_TO_FIRST_PAGE, EVENT_GO_TO_LAST_PAGE, EVENT_GO_TO_NEXT_PAGE,
         EVENT_GO_TO_PREVIOUS_PAGE,
     },
+    to_table_builder::footer_index,
 };
 use crate::{
     bigquery::jobs::{GetJobRequest, GetQueryResultsRequest, JobReference},
@@ -163,6 +170,7 @@ impl BigqueryQueryCustomElement {
             location: Some(self.location.clone()),
             start_index: Some(self.page_start_index.clone().to_string()),
             max_results: Some(self.page_size),
+            page_token: None,
         }
     }
 
@@ -219,12 +227,18 @@ impl BigqueryQueryCustomElement {
                 }
             };
 
+            let generation = next_render_generation(&element);
             if is_dml_statement || is_ddl_statement {
                 let request = bq_query_element.as_job_request();
 
                 spawn_local(async move {
-                    match jobs.get(request).await {
-                        Some(response) => {
+                    let response = jobs.get(request).await;
+                    // an attribute changed meanwhile: the render started for it shows the job
+                    if !is_current_render(&element, generation) {
+                        return;
+                    }
+                    match response {
+                        Ok(response) => {
                             if response.has_error() {
                                 render_standalone(&response.to_error_table(), &parent_node);
                             } else if is_ddl_statement {
@@ -233,28 +247,55 @@ impl BigqueryQueryCustomElement {
                                 render_standalone(&response.to_dml_table(), &parent_node);
                             }
                         }
-                        None => {
-                            web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
-                                "on_render_query: job get returned None",
-                            ));
-                        }
+                        Err(e) => dispatch_load_error(&element, "jobs.get", &e),
                     }
                 });
             } else {
                 let request = bq_query_element.as_query_results_request();
 
                 spawn_local(async move {
-                    match jobs.get_query_results(request).await {
-                        Some(response) => {
-                            response.to_bq_query(&bq_query_element).render(&parent_node);
+                    let response = jobs.get_query_results(request).await;
+                    if !is_current_render(&element, generation) {
+                        return;
+                    }
+                    match response {
+                        Ok(response) => {
+                            let bq_query = response.to_bq_query(&bq_query_element);
+                            bq_query.render(&parent_node);
+                            grid_navigation::apply_aria(
+                                &element,
+                                bq_query.table_builder.as_ref().and_then(footer_index),
+                            );
+
+                            loaded_results::store(
+                                &bq_query_element.element_id,
+                                response.to_loaded_results(bq_query_element.page_start_index),
+                            );
+                            grid_columns::apply(&element);
+                            grid_find::refresh(&element);
+                            grid_selection::clear(&bq_query_element.element_id);
+
+                            dispatch_grid_event(
+                                &element,
+                                EVENT_LOAD_COMPLETE,
+                                &bq_query.to_load_complete_detail(),
+                            );
                         }
-                        None => {}
+                        Err(e) => dispatch_load_error(&element, "jobs.getQueryResults", &e),
                     }
                 });
             }
         }
     }
 
+    pub(crate) fn to_load_complete_detail(&self) -> LoadCompleteDetail {
+        LoadCompleteDetail {
+            page_start_index: self.page_start_index,
+            rows_in_page: self.rows_in_page.unwrap_or(0),
+            rows_total: self.rows_total,
+        }
+    }
+
     pub(crate) fn get_page_start_index(&self) -> usize {
         self.page_start_index
     }
@@ -405,6 +446,20 @@ impl BigqueryQueryCustomElement {
 }
 
 impl CustomElementDefinition for BigqueryQueryCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec!["job_id", "project_id", "location", "token", PAGE_SIZE_ATT]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["job_id", "project_id", "location", "token"],
+            &[(PAGE_SIZE_ATT, "50"), (PAGE_START_INDEX_ATT, "0")],
+            RENDER_QUERY_EVENT_NAME,
+        );
+    }
+
     fn define(_document: &web_sys::Document, element: &web_sys::Element) {
         let on_event_type_closure =
             Closure::wrap(Box::new(BigqueryQueryCustomElement::on_render_query)
@@ -421,6 +476,32 @@ impl CustomElementDefinition for BigqueryQueryCustomElement {
         }
         on_event_type_closure.forget();
 
+        //click on a cell or row of the grid
+        let on_event_type_closure =
+            Closure::wrap(Box::new(on_grid_click) as Box<dyn Fn(&web_sys::Event)>);
+        if let Err(e) =
+            element.add_event_listener_with_callback("click", on_event_type_closure.as_ref().unchecked_ref())
+        {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-query define: failed to add 'click' listener: {:?}",
+                e
+            )));
+        }
+        on_event_type_closure.forget();
+
+        //select cells, rows and columns of the grid and copy them
+        grid_selection::add_event_listeners(element, TAG_NAME);
+        //arrow keys between cells, Alt+PageUp / Alt+PageDown between pages
+        grid_navigation::add_event_listeners(element, TAG_NAME);
+        //double click (or Enter) on a cell shows its whole value
+        cell_inspector::add_event_listeners(element, TAG_NAME);
+        //drag a header onto another to move the column
+        grid_columns::add_event_listeners(element, TAG_NAME);
+        //drag the border of a header to resize the column, double click it to fit the values
+        column_widths::add_event_listeners(element, TAG_NAME);
+        //Ctrl+F finds in the values of the page
+        grid_find::add_event_listeners(element, TAG_NAME);
+
         //EVENT_GO_TO_FIRST_PAGE
         let on_event_type_closure =
             Closure::wrap(Box::new(first_page) as Box<dyn Fn(&web_sys::Event)>);
@@ -591,6 +672,7 @@ mod tests {
     use crate::custom_elements::{
         base_element_trait::BaseElementTrait,
         bq_query_custom_element::{PAGE_START_INDEX_ATT, TAG_NAME},
+        layout::{ColumnLayout, GridLayout},
     };
     wasm_bindgen_test_configure!(run_in_browser);
 
@@ -696,7 +778,7 @@ mod tests {
             .parse::<usize>()
             .unwrap_or(0);
         let rows_in_page = complex_object_array_test.rows.iter().len();
-        let table_builder = complex_object_array_test.to_table_builder(1);
+        let table_builder = complex_object_array_test.to_table_builder(1, &GridLayout::default(), &ColumnLayout::default());
 
         let bq_table =
             bq_table.with_table_info(Some(rows_in_page), Some(rows_total), Some(table_builder));
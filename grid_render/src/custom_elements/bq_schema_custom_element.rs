@@ -0,0 +1,339 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{
+    base_element_trait::BaseElementTrait,
+    bq_common_custom_element::{
+        get_opt_attribute, handle_declarative_render, host_of, set_attribute,
+    },
+    custom_element_definition::CustomElementDefinition,
+    grid_events::dispatch_load_error,
+    loaded_results::ResultsSource,
+    schema_tree::{field_count, schema_html},
+    value_tree::escape_html,
+};
+use crate::{
+    bigquery::{
+        base::{TableFieldSchema, TableReference},
+        error::BigQueryError,
+        jobs::{GetQueryResultsRequest, JobReference, Jobs},
+        tables::Tables,
+    },
+    custom_elements::base_element::BaseElement,
+};
+use wasm_bindgen::{JsCast, prelude::Closure};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+/*
+`bq-schema`: the whole schema of a table (`project_id`, `dataset_id`, `table_id`) or of the
+results of a job (`project_id`, `job_id`, `location`) as a tree drawn by `schema_tree`, the
+RECORDs expandable, each field with its type, mode, description, policy tags and other
+attributes. The box above the tree filters the fields by name (`filter="..."` to start with).
+Query results have no policy tags nor descriptions, BigQuery does not return them.
+*/
+
+const TAG_NAME: &str = "bq-schema";
+const FILTER_ATT: &str = "filter";
+const SCHEMA_CONTROLS: &str = "schema_controls";
+const SCHEMA_AREA: &str = "schema_area";
+pub(crate) const RENDER_SCHEMA_EVENT_NAME: &str = "render_schema";
+
+thread_local! {
+    // keyed by the `be_id` of the element: the fields, and what they are the schema of
+    static SCHEMAS: RefCell<HashMap<String, (String, Vec<TableFieldSchema>)>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) struct BigquerySchemaCustomElement {
+    element_id: String,
+    token: String,
+    source: ResultsSource,
+    filter: String,
+}
+
+impl BigquerySchemaCustomElement {
+    pub(crate) fn from_element(element: &Element) -> Option<BigquerySchemaCustomElement> {
+        let project_id = element.get_attribute("project_id")?;
+        let source = match (
+            element.get_attribute("job_id"),
+            element.get_attribute("table_id"),
+        ) {
+            (Some(job_id), _) => ResultsSource::Query(JobReference {
+                project_id,
+                job_id,
+                location: element.get_attribute("location")?,
+            }),
+            (None, Some(table_id)) => ResultsSource::Table(TableReference {
+                project_id,
+                dataset_id: element.get_attribute("dataset_id")?,
+                table_id,
+            }),
+            (None, None) => return None,
+        };
+        Some(BigquerySchemaCustomElement {
+            element_id: element.get_attribute("be_id")?,
+            token: element.get_attribute("token")?,
+            source,
+            filter: get_opt_attribute(element, FILTER_ATT).unwrap_or_default(),
+        })
+    }
+
+    fn on_render_schema(event: &web_sys::Event) {
+        let element = match event
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+        {
+            Some(e) => e,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_schema: event target is not an element",
+                ));
+                return;
+            }
+        };
+        if element.has_attribute("loaded") {
+            return;
+        }
+        set_attribute(&element, "loaded", "1");
+
+        let bq_schema = match BigquerySchemaCustomElement::from_element(&element) {
+            Some(s) => s,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_schema: element is missing required attributes (token, and a job or a table)",
+                ));
+                return;
+            }
+        };
+        let parent_node = match element.parent_element() {
+            Some(p) => p,
+            None => {
+                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                    "on_render_schema: bq-schema element has no parent element",
+                ));
+                return;
+            }
+        };
+        bq_schema.render(&parent_node);
+
+        spawn_local(async move {
+            match load_schema(&bq_schema.source, &bq_schema.token).await {
+                Ok(schema) => {
+                    SCHEMAS.with(|s| s.borrow_mut().insert(bq_schema.element_id, schema));
+                    refresh(&element);
+                }
+                Err((operation, e)) => dispatch_load_error(&element, operation, &e),
+            }
+        });
+    }
+}
+
+/// The fields of `source`, with a label of it.
+async fn load_schema(
+    source: &ResultsSource,
+    token: &str,
+) -> Result<(String, Vec<TableFieldSchema>), (&'static str, BigQueryError)> {
+    match source {
+        ResultsSource::Query(job) => {
+            // the schema only, no row
+            let request = GetQueryResultsRequest {
+                project_id: job.project_id.clone(),
+                job_id: job.job_id.clone(),
+                location: Some(job.location.clone()),
+                start_index: None,
+                max_results: Some(0),
+                page_token: None,
+            };
+            let response = Jobs::new(token)
+                .get_query_results(request)
+                .await
+                .map_err(|e| ("jobs.getQueryResults", e))?;
+            Ok((
+                format!(
+                    "Results of job {}:{}.{}",
+                    job.project_id, job.location, job.job_id
+                ),
+                response.schema.map(|s| s.fields).unwrap_or_default(),
+            ))
+        }
+        ResultsSource::Table(table) => {
+            let schema = Tables::new(token)
+                .get(table.clone())
+                .await
+                .map_err(|e| ("tables.get", e))?
+                .schema;
+            Ok((
+                format!(
+                    "{}.{}.{}",
+                    table.project_id, table.dataset_id, table.table_id
+                ),
+                schema.map(|s| s.fields).unwrap_or_default(),
+            ))
+        }
+    }
+}
+
+impl CustomElementDefinition for BigquerySchemaCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec![
+            "token",
+            "project_id",
+            "job_id",
+            "location",
+            "dataset_id",
+            "table_id",
+            FILTER_ATT,
+        ]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["token", "project_id"],
+            &[],
+            RENDER_SCHEMA_EVENT_NAME,
+        );
+    }
+
+    fn define(_document: &web_sys::Document, element: &web_sys::Element) {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(BigquerySchemaCustomElement::on_render_schema)
+                as Box<dyn Fn(&web_sys::Event)>);
+
+        if let Err(e) = element.add_event_listener_with_callback(
+            RENDER_SCHEMA_EVENT_NAME,
+            on_event_type_closure.as_ref().unchecked_ref(),
+        ) {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-schema define: failed to add '{}' listener: {:?}",
+                RENDER_SCHEMA_EVENT_NAME, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+impl BaseElementTrait for BigquerySchemaCustomElement {
+    fn get_element_id(&self) -> &str {
+        &self.element_id
+    }
+
+    fn render(&self, parent_node: &web_sys::Node) -> BaseElement {
+        let bq_schema = BaseElement::new_and_append(parent_node, TAG_NAME, &self.element_id)
+            .apply_fn(&set_attributes, self);
+
+        let css_content = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/grid.css"));
+        bq_schema
+            .append_shadow()
+            .append_child_style(css_content, "style1")
+            .append_sibling_fn("div", SCHEMA_CONTROLS, &init_controls, self)
+            .append_sibling_fn("div", SCHEMA_AREA, &init_area, self);
+
+        bq_schema
+    }
+}
+
+fn set_attributes(base_element: &BaseElement, bq_schema: &BigquerySchemaCustomElement) {
+    base_element.element().set_id(&bq_schema.element_id);
+}
+
+fn init_controls(base_element: &BaseElement, bq_schema: &BigquerySchemaCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    element.set_inner_html(&format!(
+        concat!(
+            r#"<span class="schema-source"></span>"#,
+            r#"<input type="search" data-schema-filter placeholder="Filter fields" aria-label="Filter the fields by name" value="{}">"#,
+            r#"<button type="button" data-schema-expand="1">Expand all</button>"#,
+            r#"<button type="button" data-schema-expand="0">Collapse all</button>"#,
+            r#"<span class="schema-count" role="status" aria-live="polite"></span>"#
+        ),
+        escape_html(&bq_schema.filter)
+    ));
+
+    let on_input = Closure::wrap(Box::new(on_filter_input) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("input", on_input.as_ref().unchecked_ref());
+    on_input.forget();
+    let on_click = Closure::wrap(Box::new(on_expand_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref());
+    on_click.forget();
+
+    let _ = element.set_attribute("bee", "1");
+}
+
+fn init_area(base_element: &BaseElement, _bq_schema: &BigquerySchemaCustomElement) {
+    base_element
+        .element()
+        .set_inner_html(r#"<div class="loading-indicator">Fetching the schema…</div>"#);
+}
+
+/// Shows the fields of `host` matching its filter.
+fn refresh(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let ((label, fields), shadow_root) = match (
+        SCHEMAS.with(|s| s.borrow().get(&element_id).cloned()),
+        host.shadow_root(),
+    ) {
+        (Some(s), Some(r)) => (s, r),
+        _ => return,
+    };
+    let find = |selector: &str| shadow_root.query_selector(selector).ok().flatten();
+    let filter = find("input[data-schema-filter]")
+        .and_then(|i| i.dyn_into::<web_sys::HtmlInputElement>().ok())
+        .map(|i| i.value())
+        .unwrap_or_default();
+
+    let (html, shown) = schema_html(&fields, &filter);
+    if let Some(area) = find(&format!("[be_id='{}']", SCHEMA_AREA)) {
+        area.set_inner_html(&html);
+    }
+    if let Some(source) = find(".schema-source") {
+        source.set_text_content(Some(&label));
+    }
+    let total = field_count(&fields);
+    if let Some(count) = find(".schema-count") {
+        count.set_text_content(Some(&match filter.trim().is_empty() {
+            true => format!("{} field{}", total, if total == 1 { "" } else { "s" }),
+            false => format!("{} of {} fields", shown, total),
+        }));
+    }
+}
+
+fn on_filter_input(event: &web_sys::Event) {
+    if let Some(input) = event.target().and_then(|t| t.dyn_into::<Element>().ok())
+        && input.has_attribute("data-schema-filter")
+        && let Some(host) = host_of(&input)
+    {
+        refresh(&host);
+    }
+}
+
+/// Opens or closes every RECORD of the tree.
+fn on_expand_click(event: &web_sys::Event) {
+    let button = match event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("button[data-schema-expand]").ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    let open = button.get_attribute("data-schema-expand").as_deref() == Some("1");
+    let details = match host_of(&button)
+        .and_then(|h| h.shadow_root())
+        .and_then(|s| s.query_selector_all("ul.schema-tree details").ok())
+    {
+        Some(d) => d,
+        None => return,
+    };
+    for i in 0..details.length() {
+        if let Some(d) = details.item(i).and_then(|d| d.dyn_into::<Element>().ok()) {
+            let _ = match open {
+                true => d.set_attribute("open", ""),
+                false => d.remove_attribute("open"),
+            };
+        }
+    }
+}
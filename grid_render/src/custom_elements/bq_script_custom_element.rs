@@ -11,9 +11,15 @@ use crate::{
 use super::{
     base_element::BaseElement,
     base_element_trait::BaseElementTrait,
-    bq_common_custom_element::{get_attribute, get_opt_attribute, remove_attribute, set_attribute},
+    bq_common_custom_element::{
+        get_attribute, get_opt_attribute, handle_declarative_render, remove_attribute,
+        set_attribute,
+    },
     bq_query_custom_element::{BigqueryQueryCustomElement, RENDER_QUERY_EVENT_NAME},
     custom_element_definition::CustomElementDefinition,
+    grid_events::{
+        EVENT_LOAD_COMPLETE, ScriptLoadCompleteDetail, dispatch_grid_event, dispatch_load_error,
+    },
 };
 
 const TAG_NAME: &'static str = "bq-script";
@@ -461,6 +467,20 @@ fn resolve_job_title(element: &BaseElement, (job_name, job_status): &(String, Op
 }
 
 impl CustomElementDefinition for BigqueryScriptCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec!["job_id", "project_id", "location", "token"]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["job_id", "project_id", "location", "token"],
+            &[],
+            RENDER_SCRIPT_EVENT_NAME,
+        );
+    }
+
     fn define(_document: &web_sys::Document, element: &web_sys::Element) {
         let on_event_type_closure =
             Closure::wrap(Box::new(on_render) as Box<dyn Fn(&web_sys::Event)>);
@@ -511,39 +531,58 @@ fn on_render(event: &web_sys::Event) {
             let get_request = bq_script_element.as_job_request();
             let get_job_response = jobs.get(get_request).await;
 
-            if let Some(job) = get_job_response {
-                //TODO: confirm what is the information when one of the jobs is in error
-                if job.is_dml_statement() || job.is_query_select() || job.is_unsupported_type() {
-                    if job.is_complete() {
-                        let _ = element.set_attribute("loaded", "1");
-                    }
-
-                    bq_script_element
-                        .with_job_info(&job, &[job.clone()].to_vec())
-                        .render(&parent_node);
-                } else {
-                    let get_list_request = bq_script_element.as_job_list_request();
-                    let get_list_response = jobs.get_list(get_list_request).await;
-
-                    if let Some(list) = get_list_response {
-                        if let Some(jobs) = list.jobs {
-                            let all_jobs_done = jobs.iter().all(|j| j.is_complete());
-
-                            if let Some(statistics) = &job.statistics {
-                                if statistics.num_child_jobs.is_some() && all_jobs_done {
-                                    let _ = element.set_attribute("loaded", "1");
+            match get_job_response {
+                Ok(job) => {
+                    //TODO: confirm what is the information when one of the jobs is in error
+                    if job.is_dml_statement() || job.is_query_select() || job.is_unsupported_type()
+                    {
+                        bq_script_element
+                            .with_job_info(&job, &[job.clone()].to_vec())
+                            .render(&parent_node);
+
+                        if job.is_complete() {
+                            let _ = element.set_attribute("loaded", "1");
+                            dispatch_grid_event(
+                                &element,
+                                EVENT_LOAD_COMPLETE,
+                                &ScriptLoadCompleteDetail { job_count: 1 },
+                            );
+                        }
+                    } else {
+                        let get_list_request = bq_script_element.as_job_list_request();
+
+                        match jobs.get_list(get_list_request).await {
+                            Ok(list) => {
+                                if let Some(jobs) = list.jobs {
+                                    let all_jobs_done = jobs.iter().all(|j| j.is_complete());
+
+                                    bq_script_element
+                                        .with_job_info(&job, &jobs)
+                                        .render(&parent_node);
+
+                                    if let Some(statistics) = &job.statistics {
+                                        if statistics.num_child_jobs.is_some() && all_jobs_done {
+                                            let _ = element.set_attribute("loaded", "1");
+                                            dispatch_grid_event(
+                                                &element,
+                                                EVENT_LOAD_COMPLETE,
+                                                &ScriptLoadCompleteDetail {
+                                                    job_count: jobs.len(),
+                                                },
+                                            );
+                                        }
+                                    }
                                 }
                             }
-
-                            bq_script_element
-                                .with_job_info(&job, &jobs)
-                                .render(&parent_node);
+                            Err(e) => dispatch_load_error(&element, "jobs.list", &e),
                         }
                     }
                 }
-            } else {
-                let _ = element.set_attribute("loaded", "1");
-                element.set_inner_html(&format!("unexpected response: {:?}", get_job_response));
+                Err(e) => {
+                    let _ = element.set_attribute("loaded", "1");
+                    element.set_inner_html(&format!("unexpected response: {}", e));
+                    dispatch_load_error(&element, "jobs.get", &e);
+                }
             }
         });
     }
@@ -1,14 +1,21 @@
 use super::{
     base_element_trait::BaseElementTrait,
     bq_common_custom_element::{
-        get_opt_num_attribute, handle_page_nav_event,
-        set_attribute, set_optional_attribute,
+        get_opt_num_attribute, handle_declarative_render, handle_page_nav_event,
+        is_current_render, next_render_generation, set_attribute, set_optional_attribute,
     },
     custom_element_definition::CustomElementDefinition,
+    cell_inspector, column_widths, grid_columns, grid_find, grid_navigation, grid_selection, loaded_results,
+    table_details, table_tabs::{self, TABLE_DETAILS, TABLE_NOTICE, TABLE_TABS},
+    grid_events::{
+        EVENT_LOAD_COMPLETE, LoadCompleteDetail, dispatch_grid_event, dispatch_load_error,
+        on_grid_click,
+    },
     data_table_controls_element::{
         DataTableControls, EVENT_GO_TO_FIRST_PAGE, EVENT_GO_TO_LAST_PAGE, EVENT_GO_TO_NEXT_PAGE,
         EVENT_GO_TO_PREVIOUS_PAGE,
     },
+    to_table_builder::footer_index,
 };
 use crate::custom_elements::base_element::BaseElement;
 use wasm_bindgen::{JsCast, prelude::Closure};
@@ -133,6 +140,7 @@ impl BigqueryTableCustomElement {
             table_id: self.table_id.clone(),
             start_index: Some(self.page_start_index.to_string()),
             max_results: Some(self.page_size),
+            page_token: None,
         }
     }
 
@@ -173,25 +181,75 @@ impl BigqueryTableCustomElement {
             }
         };
 
+        let generation = next_render_generation(&element);
         spawn_local(async move {
             let tables = crate::bigquery::tables::Tables::new(&bq_table_element.token);
             let table_data = crate::bigquery::table_data::TableData::new(&bq_table_element.token);
 
             let response = tables.get(request).await;
-            let response_rows = table_data.list(table_data_list_request).await;
-
-            if let Some(table) = response {
-                table
-                    .to_bq_table(&bq_table_element, &response_rows)
-                    .render(&parent_node);
-            } else {
-                web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
-                    "on_render_table: tables.get returned None",
-                ));
+            // the rows of a table that could not be read are not listed: one failure, one error;
+            // a view or an external table has no rows to list, its preview is a query
+            let rows_result = match &response {
+                Ok(table) if table_details::rows_listable(table) => {
+                    Some(table_data.list(table_data_list_request).await)
+                }
+                _ => None,
+            };
+            // an attribute changed meanwhile: the render started for it shows the table
+            if !is_current_render(&element, generation) {
+                return;
+            }
+            let response_rows = match rows_result {
+                Some(Ok(r)) => Some(r),
+                Some(Err(e)) => {
+                    dispatch_load_error(&element, "tabledata.list", &e);
+                    None
+                }
+                None => None,
+            };
+
+            match response {
+                Ok(table) => {
+                    let bq_table = table.to_bq_table(&bq_table_element, &response_rows);
+                    bq_table.render(&parent_node);
+                    grid_navigation::apply_aria(
+                        &element,
+                        bq_table.table_builder.as_ref().and_then(footer_index),
+                    );
+                    table_tabs::show_details(&element, &table);
+                    table_tabs::show_notice(&element, &table, response_rows.is_some());
+
+                    if let Some(response_rows) = &response_rows {
+                        loaded_results::store(
+                            &bq_table_element.element_id,
+                            table.to_loaded_results(
+                                response_rows,
+                                bq_table_element.page_start_index,
+                            ),
+                        );
+                        grid_columns::apply(&element);
+                        grid_find::refresh(&element);
+                        grid_selection::clear(&bq_table_element.element_id);
+                        dispatch_grid_event(
+                            &element,
+                            EVENT_LOAD_COMPLETE,
+                            &bq_table.to_load_complete_detail(),
+                        );
+                    }
+                }
+                Err(e) => dispatch_load_error(&element, "tables.get", &e),
             }
         });
     }
 
+    pub(crate) fn to_load_complete_detail(&self) -> LoadCompleteDetail {
+        LoadCompleteDetail {
+            page_start_index: self.page_start_index,
+            rows_in_page: self.rows_in_page.unwrap_or(0),
+            rows_total: self.rows_total,
+        }
+    }
+
     pub(crate) fn get_page_start_index(&self) -> usize {
         self.page_start_index
     }
@@ -312,6 +370,20 @@ impl BigqueryTableCustomElement {
 }
 
 impl CustomElementDefinition for BigqueryTableCustomElement {
+    fn observed_attributes() -> Vec<&'static str> {
+        vec!["project_id", "dataset_id", "table_id", "token", PAGE_SIZE_ATT]
+    }
+
+    fn render_declarative(element: &web_sys::Element) {
+        handle_declarative_render(
+            element,
+            TAG_NAME,
+            &["project_id", "dataset_id", "table_id", "token"],
+            &[(PAGE_SIZE_ATT, "50"), (PAGE_START_INDEX_ATT, "0")],
+            RENDER_TABLE_EVENT_NAME,
+        );
+    }
+
     fn define(_document: &web_sys::Document, element: &web_sys::Element) {
         //RENDER_TABLE_EVENT_NAME
         let on_event_type_closure =
@@ -329,6 +401,32 @@ impl CustomElementDefinition for BigqueryTableCustomElement {
         }
         on_event_type_closure.forget();
 
+        //click on a cell or row of the grid
+        let on_event_type_closure =
+            Closure::wrap(Box::new(on_grid_click) as Box<dyn Fn(&web_sys::Event)>);
+        if let Err(e) =
+            element.add_event_listener_with_callback("click", on_event_type_closure.as_ref().unchecked_ref())
+        {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "bq-table define: failed to add 'click' listener: {:?}",
+                e
+            )));
+        }
+        on_event_type_closure.forget();
+
+        //select cells, rows and columns of the grid and copy them
+        grid_selection::add_event_listeners(element, TAG_NAME);
+        //arrow keys between cells, Alt+PageUp / Alt+PageDown between pages
+        grid_navigation::add_event_listeners(element, TAG_NAME);
+        //double click (or Enter) on a cell shows its whole value
+        cell_inspector::add_event_listeners(element, TAG_NAME);
+        //drag a header onto another to move the column
+        grid_columns::add_event_listeners(element, TAG_NAME);
+        //drag the border of a header to resize the column, double click it to fit the values
+        column_widths::add_event_listeners(element, TAG_NAME);
+        //Ctrl+F finds in the values of the page
+        grid_find::add_event_listeners(element, TAG_NAME);
+
         //EVENT_GO_TO_FIRST_PAGE
         let on_event_type_closure =
             Closure::wrap(Box::new(first_page) as Box<dyn Fn(&web_sys::Event)>);
@@ -434,8 +532,14 @@ impl BaseElementTrait for BigqueryTableCustomElement {
             break;
         }
 
-        shadow.append_child_style(css_content, "style1");
-        shadow.append_base_child(&self.to_data_table_controls());
+        // the tabs, the controls, the details and the notice, the table last
+        shadow
+            .append_child_style(css_content, "style1")
+            .append_sibling_fn("div", TABLE_TABS, &table_tabs::init_tabs, self);
+        shadow
+            .append_base_child(&self.to_data_table_controls())
+            .append_sibling_fn("div", TABLE_DETAILS, &table_tabs::init_details, self)
+            .append_sibling_fn("div", TABLE_NOTICE, &table_tabs::init_notice, self);
 
         if let Some(table_builder) = &self.table_builder {
             if let Ok(render_result) = table_builder.render() {
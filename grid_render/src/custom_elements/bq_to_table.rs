@@ -4,11 +4,14 @@ use website_component_table::{
 
 use super::{
     bq_query_custom_element::BigqueryQueryCustomElement,
+    loaded_results::{LoadedResults, ResultsSource},
     bq_table_custom_element::BigqueryTableCustomElement,
+    grid_columns, grid_layout,
     to_table_builder::patch_all_column_widths,
 };
 use crate::{
     bigquery::{
+        base::TableSchema,
         jobs::{GetQueryResultsResponse, Job},
         table_data::TableDataListResponse,
         tables::Table,
@@ -24,11 +27,29 @@ impl GetQueryResultsResponse {
         let page_start_index = bq_query_requested.get_page_start_index();
         let rows_in_page = self.rows.as_ref().map_or(0, |r| r.len());
         let rows_total = self.get_rows_total();
-        let table_builder = self.to_table_builder(page_start_index + 1);
+        let column_layout = grid_columns::get(&grid_columns::layout_key(
+            Some(&ResultsSource::Query(self.job_reference.clone())),
+            self.schema.as_ref().map_or(&[], |s| s.fields.as_slice()),
+        ));
+        let table_builder =
+            self.to_table_builder(page_start_index + 1, &grid_layout::get(), &column_layout);
 
         bq_query_requested.with_table_info(Some(rows_in_page), rows_total, Some(table_builder))
     }
 
+    pub(crate) fn to_loaded_results(&self, page_start_index: usize) -> LoadedResults {
+        LoadedResults {
+            name: self.job_reference.job_id.clone(),
+            schema: self
+                .schema
+                .clone()
+                .unwrap_or(TableSchema { fields: vec![] }),
+            rows: self.rows.clone().unwrap_or_default(),
+            page_start_index,
+            source: Some(ResultsSource::Query(self.job_reference.clone())),
+        }
+    }
+
     fn get_rows_total(&self) -> Option<usize> {
         match &self.total_rows {
             Some(v) => Some(parse_to_usize(Some(v.clone())).unwrap_or(0)),
@@ -51,12 +72,37 @@ impl Table {
         };
 
         let row_index = bq_table_element.get_page_start_index() + 1;
-        let table_builder = self.to_table_builder(rows, row_index);
+        let column_layout = grid_columns::get(&grid_columns::layout_key(
+            self.table_reference.clone().map(ResultsSource::Table).as_ref(),
+            self.schema.as_ref().map_or(&[], |s| s.fields.as_slice()),
+        ));
+        let table_builder = self.to_table_builder(rows, row_index, &grid_layout::get(), &column_layout);
         let rows_in_page = rows.as_ref().map_or(0, |r| r.len());
         
         bq_table_element.with_table_info(Some(rows_in_page), Some(rows_total), Some(table_builder))
     }
 
+    pub(crate) fn to_loaded_results(
+        &self,
+        response_rows: &TableDataListResponse,
+        page_start_index: usize,
+    ) -> LoadedResults {
+        LoadedResults {
+            name: self
+                .table_reference
+                .as_ref()
+                .map(|r| r.table_id.clone())
+                .unwrap_or_else(|| "table".to_string()),
+            schema: self
+                .schema
+                .clone()
+                .unwrap_or(TableSchema { fields: vec![] }),
+            rows: response_rows.rows.clone().unwrap_or_default(),
+            page_start_index,
+            source: self.table_reference.clone().map(ResultsSource::Table),
+        }
+    }
+
     fn get_rows_total(&self) -> usize {
         parse_to_usize(Some(self.num_rows.clone().unwrap_or(String::from("0")))).unwrap_or(0)
     }
@@ -0,0 +1,144 @@
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    bq_common_custom_element::get_opt_num_attribute,
+    export::{to_grid_columns, to_grid_records},
+    grid_columns, grid_layout,
+    grid_selection::{grid_rows, grid_table},
+    overlay_panel, record_view, value_tree,
+};
+
+/*
+Inspector of one cell of the grid of `bq-table` and `bq-query`: the whole value in the overlay panel,
+wrapped, as a tree for STRUCT, ARRAY and JSON. Opened with Enter or a double click on a cell;
+on the index cell they open the record view of the row instead.
+The value comes from `grid_columns::visible_results`, cells as laid out by `to_grid_columns` for the grid layout.
+*/
+
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    let on_event_type_closure =
+        Closure::wrap(Box::new(on_double_click) as Box<dyn Fn(&web_sys::Event)>);
+    if let Err(e) = element.add_event_listener_with_callback(
+        "dblclick",
+        on_event_type_closure.as_ref().unchecked_ref(),
+    ) {
+        web_sys::console::error_1(&JsValue::from_str(&format!(
+            "{} define: failed to add 'dblclick' listener: {:?}",
+            tag_name, e
+        )));
+    }
+    on_event_type_closure.forget();
+}
+
+/// Opens the inspector on the top level cell `cell_index` of the data row `row`,
+/// the index cell (0) opens the record view of the row.
+pub(crate) fn open(host: &Element, row: usize, cell_index: usize) {
+    let column = match cell_index.checked_sub(1) {
+        Some(c) => c,
+        None => {
+            record_view::open(host, row);
+            return;
+        }
+    };
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match grid_columns::visible_results(&element_id) {
+        Some(r) => r,
+        None => return,
+    };
+
+    let fields = &loaded_results.schema.fields;
+    let layout = grid_layout::get();
+    let columns = to_grid_columns(fields, &layout);
+    let row_values = match loaded_results.rows.get(row) {
+        Some(r) => std::slice::from_ref(r),
+        None => return,
+    };
+    let (_, records) = to_grid_records(fields, row_values, &layout);
+    let (value, (name, field)) = match (
+        records.first().and_then(|r| r.get(column)),
+        columns.get(column),
+    ) {
+        (Some(v), Some(c)) => (v, c),
+        _ => return,
+    };
+
+    let row_number = get_opt_num_attribute(host, "page_start_index").unwrap_or(0) + row + 1;
+    let title = format!("{} · row {}", name, row_number);
+    let copy_actions = vec![
+        ("Copy raw", value.to_text().unwrap_or_default()),
+        ("Copy pretty", value_tree::pretty_text(value, Some(field))),
+    ];
+
+    if let Some(body) = overlay_panel::open(
+        host,
+        &title,
+        &value_tree::value_summary(value, Some(field)),
+        copy_actions,
+    ) {
+        let drill_down = if field.mode.as_deref() == Some("REPEATED") {
+            format!(
+                r#"<div class="inspector-actions"><button type="button" data-drill-field="{}" data-drill-row="{}" title="Open the elements as a grid (Shift+Enter)">Open as grid</button></div>"#,
+                value_tree::escape_html(name),
+                row
+            )
+        } else {
+            String::new()
+        };
+        body.set_inner_html(&format!(
+            "{}{}",
+            drill_down,
+            value_tree::value_html(value, Some(field))
+        ));
+    }
+}
+
+fn on_double_click(event: &web_sys::Event) {
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let target = match event.composed_path().get(0).dyn_into::<Element>().ok() {
+        Some(t) => t,
+        None => return,
+    };
+    let table = match grid_table(&host) {
+        Some(t) => t,
+        None => return,
+    };
+
+    // the outermost cell, a double click in a nested table inspects the cell holding it
+    let mut cell = match target.closest("td").ok().flatten() {
+        Some(c) => c,
+        None => return,
+    };
+    while let Some(outer) = cell
+        .parent_element()
+        .and_then(|p| p.closest("td").ok().flatten())
+    {
+        cell = outer;
+    }
+    if !table.contains(Some(&cell)) {
+        return;
+    }
+
+    let (_, data_rows) = grid_rows(&table);
+    let row = match cell
+        .parent_element()
+        .and_then(|r| data_rows.iter().position(|d| *d == r))
+    {
+        Some(r) => r,
+        None => return,
+    };
+    let mut cell_index = 0;
+    let mut previous = cell.previous_element_sibling();
+    while let Some(p) = previous {
+        cell_index += 1;
+        previous = p.previous_element_sibling();
+    }
+
+    open(&host, row, cell_index);
+}
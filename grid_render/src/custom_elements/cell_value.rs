@@ -0,0 +1,682 @@
+use std::cmp::Ordering;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::range_interval::{self, Range};
+
+/// A BigQuery cell decoded from the REST `{"f": [{"v": ...}]}` shape using its field schema.
+///
+/// Unlike `TableValue` it keeps REPEATED and RECORD values as values (not as inner tables)
+/// and keeps the field order of RECORDs, so it can be written back out (export, copy, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CellValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// NUMERIC / BIGNUMERIC, kept as the exact decimal text.
+    Numeric(String),
+    /// Every other scalar type (STRING, BYTES, DATE, TIMESTAMP as ISO 8601, JSON, ...).
+    Text(String),
+    Array(Vec<CellValue>),
+    Struct(Vec<(String, CellValue)>),
+}
+
+impl CellValue {
+    /// `value` is the content of `"v"`.
+    pub(crate) fn from_bq(value: &serde_json::Value, field: &TableFieldSchema) -> CellValue {
+        if field.mode.as_deref() == Some("REPEATED") {
+            return match value {
+                serde_json::Value::Array(items) => CellValue::Array(
+                    items
+                        .iter()
+                        .map(|item| {
+                            let v = item.pointer("/v").unwrap_or(&serde_json::Value::Null);
+                            CellValue::from_bq_item(v, field)
+                        })
+                        .collect(),
+                ),
+                serde_json::Value::Null => CellValue::Array(vec![]),
+                other => CellValue::Text(other.to_string()),
+            };
+        }
+
+        CellValue::from_bq_item(value, field)
+    }
+
+    fn from_bq_item(value: &serde_json::Value, field: &TableFieldSchema) -> CellValue {
+        if value.is_null() {
+            return CellValue::Null;
+        }
+
+        if let Some(sub_fields) = &field.fields {
+            let f = value.pointer("/f").and_then(|f| f.as_array());
+            return match f {
+                Some(f) => CellValue::Struct(
+                    sub_fields
+                        .iter()
+                        .enumerate()
+                        .map(|(i, sub_field)| {
+                            let v = f
+                                .get(i)
+                                .and_then(|c| c.pointer("/v"))
+                                .unwrap_or(&serde_json::Value::Null);
+                            (sub_field.name.clone(), CellValue::from_bq(v, sub_field))
+                        })
+                        .collect(),
+                ),
+                None => CellValue::Text(value.to_string()),
+            };
+        }
+
+        let text = match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Bool(b) => return CellValue::Bool(*b),
+            other => other.to_string(),
+        };
+
+        match field.r#type.as_str() {
+            "BOOLEAN" | "BOOL" => match text.as_str() {
+                "true" => CellValue::Bool(true),
+                "false" => CellValue::Bool(false),
+                _ => CellValue::Text(text),
+            },
+            "INTEGER" | "INT64" => match text.parse::<i64>() {
+                Ok(i) => CellValue::Int(i),
+                Err(_) => CellValue::Text(text),
+            },
+            "FLOAT" | "FLOAT64" => match text.parse::<f64>() {
+                Ok(f) => CellValue::Float(f),
+                Err(_) => CellValue::Text(text),
+            },
+            "NUMERIC" | "BIGNUMERIC" => CellValue::Numeric(text),
+            "TIMESTAMP" => CellValue::Text(timestamp_text(&text).unwrap_or(text)),
+            _ => CellValue::Text(text),
+        }
+    }
+
+    /// All the cells of one row, in schema order.
+    pub(crate) fn row_from_bq(
+        row: &serde_json::Value,
+        fields: &[TableFieldSchema],
+    ) -> Vec<CellValue> {
+        let f = row.pointer("/f").and_then(|f| f.as_array());
+
+        fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let v = f
+                    .and_then(|f| f.get(i))
+                    .and_then(|c| c.pointer("/v"))
+                    .unwrap_or(&serde_json::Value::Null);
+                CellValue::from_bq(v, field)
+            })
+            .collect()
+    }
+
+    /// JSON text of the value, RECORD fields in schema order.
+    /// INT64, NUMERIC and non finite floats are written as strings, as BigQuery exports them, so
+    /// no precision is lost: a JS number keeps 53 bits, less than most INT64 ids.
+    pub(crate) fn to_json(&self) -> String {
+        self.json(true)
+    }
+
+    fn json(&self, int_strings: bool) -> String {
+        match self {
+            CellValue::Null => "null".to_string(),
+            CellValue::Bool(b) => b.to_string(),
+            CellValue::Int(i) if int_strings => json_string(&i.to_string()),
+            CellValue::Int(i) => i.to_string(),
+            CellValue::Float(f) if f.is_finite() => serde_json::Value::from(*f).to_string(),
+            CellValue::Float(f) => json_string(&f.to_string()),
+            CellValue::Numeric(s) | CellValue::Text(s) => json_string(s),
+            CellValue::Array(items) => format!(
+                "[{}]",
+                items
+                    .iter()
+                    .map(|i| i.json(int_strings))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            CellValue::Struct(entries) => format!(
+                "{{{}}}",
+                entries
+                    .iter()
+                    .map(|(k, v)| format!("{}:{}", json_string(k), v.json(int_strings)))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        }
+    }
+
+    /// Indented JSON text of the value, `to_json` laid out over several lines.
+    pub(crate) fn to_json_pretty(&self) -> String {
+        let mut output = String::new();
+        self.write_json_pretty(&mut output, 0, true);
+        output
+    }
+
+    /// Indented JSON text of the parsed text of a JSON column (`from_json_text`): its integers
+    /// were numbers in the text, they are written back as numbers.
+    pub(crate) fn to_json_document_pretty(&self) -> String {
+        let mut output = String::new();
+        self.write_json_pretty(&mut output, 0, false);
+        output
+    }
+
+    fn write_json_pretty(&self, output: &mut String, depth: usize, int_strings: bool) {
+        let indent = |output: &mut String, depth: usize| {
+            output.push('\n');
+            output.push_str(&"  ".repeat(depth));
+        };
+
+        match self {
+            CellValue::Array(items) if !items.is_empty() => {
+                output.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    indent(output, depth + 1);
+                    item.write_json_pretty(output, depth + 1, int_strings);
+                }
+                indent(output, depth);
+                output.push(']');
+            }
+            CellValue::Struct(entries) if !entries.is_empty() => {
+                output.push('{');
+                for (i, (name, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    indent(output, depth + 1);
+                    output.push_str(&json_string(name));
+                    output.push_str(": ");
+                    value.write_json_pretty(output, depth + 1, int_strings);
+                }
+                indent(output, depth);
+                output.push('}');
+            }
+            _ => output.push_str(&self.json(int_strings)),
+        }
+    }
+
+    /// Parses the text of a JSON column, objects as `Struct` with their keys in document order.
+    pub(crate) fn from_json_text(text: &str) -> Option<CellValue> {
+        serde_json::from_str::<JsonCellValue>(text)
+            .ok()
+            .map(|v| v.0)
+    }
+
+    /// Text of the value in a single cell: scalars as displayed, ARRAY and STRUCT as JSON.
+    /// `None` for NULL.
+    pub(crate) fn to_text(&self) -> Option<String> {
+        match self {
+            CellValue::Null => None,
+            CellValue::Bool(b) => Some(b.to_string()),
+            CellValue::Int(i) => Some(i.to_string()),
+            CellValue::Float(f) => Some(f.to_string()),
+            CellValue::Numeric(s) | CellValue::Text(s) => Some(s.clone()),
+            CellValue::Array(_) | CellValue::Struct(_) => Some(self.to_json()),
+        }
+    }
+
+    /// Order used to sort a column: NULL first, then numbers by value, booleans, and the rest
+    /// by their text (ISO dates and timestamps sort as such, INTERVALs by length and RANGEs by
+    /// their bounds).
+    pub(crate) fn sort_cmp(&self, other: &CellValue) -> Ordering {
+        match (self.sort_number(), other.sort_number()) {
+            (Some(a), Some(b)) => return a.total_cmp(&b),
+            (Some(_), None) | (None, Some(_)) => return self.sort_rank().cmp(&other.sort_rank()),
+            (None, None) => {}
+        }
+
+        if let (CellValue::Text(a), CellValue::Text(b)) = (self, other)
+            && let Some(order) = range_interval::sort_cmp(a, b)
+        {
+            return order;
+        }
+        match (self, other) {
+            (CellValue::Bool(a), CellValue::Bool(b)) => a.cmp(b),
+            _ => self
+                .sort_rank()
+                .cmp(&other.sort_rank())
+                .then_with(|| self.to_text().cmp(&other.to_text())),
+        }
+    }
+
+    fn sort_number(&self) -> Option<f64> {
+        match self {
+            CellValue::Int(i) => Some(*i as f64),
+            CellValue::Float(f) => Some(*f),
+            CellValue::Numeric(s) => s.parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+
+    fn sort_rank(&self) -> u8 {
+        match self {
+            CellValue::Null => 0,
+            CellValue::Int(_) | CellValue::Float(_) | CellValue::Numeric(_) => 1,
+            CellValue::Bool(_) => 2,
+            CellValue::Text(_) => 3,
+            CellValue::Array(_) | CellValue::Struct(_) => 4,
+        }
+    }
+
+    /// GoogleSQL literal of the value, typed by `field` (e.g. `DATE '2024-01-31'`, `[1, 2]`,
+    /// `STRUCT('a' AS name)`), so it can be pasted back into a query.
+    pub(crate) fn to_sql_literal(&self, field: &TableFieldSchema) -> String {
+        match self {
+            CellValue::Null => "NULL".to_string(),
+            CellValue::Bool(true) => "TRUE".to_string(),
+            CellValue::Bool(false) => "FALSE".to_string(),
+            CellValue::Int(i) => i.to_string(),
+            CellValue::Float(f) if f.is_finite() => {
+                let text = f.to_string();
+                if text.contains(['.', 'e', 'E']) {
+                    text
+                } else {
+                    format!("{}.0", text)
+                }
+            }
+            CellValue::Float(f) => format!("CAST({} AS FLOAT64)", sql_string(&f.to_string())),
+            CellValue::Numeric(s) => format!("{} {}", field.r#type, sql_string(s)),
+            CellValue::Text(s) => match field.r#type.as_str() {
+                "DATE" | "DATETIME" | "TIME" | "TIMESTAMP" | "JSON" => {
+                    format!("{} {}", field.r#type, sql_string(s))
+                }
+                "BYTES" => format!("FROM_BASE64({})", sql_string(s)),
+                "GEOGRAPHY" => format!("ST_GEOGFROMTEXT({})", sql_string(s)),
+                "INTERVAL" => format!("CAST({} AS INTERVAL)", sql_string(s)),
+                "RANGE" => match (&field.range_element_type, Range::parse(s)) {
+                    (Some(element), Some(range)) => {
+                        format!(
+                            "RANGE<{}> {}",
+                            element.r#type,
+                            sql_string(&range.sql_text())
+                        )
+                    }
+                    _ => sql_string(s),
+                },
+                _ => sql_string(s),
+            },
+            CellValue::Array(items) => {
+                let items: Vec<String> = items.iter().map(|i| i.to_sql_literal(field)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            CellValue::Struct(entries) => {
+                let sub_fields = field.fields.as_deref().unwrap_or(&[]);
+                let entries: Vec<String> = entries
+                    .iter()
+                    .zip(sub_fields)
+                    .map(|((name, value), sub_field)| {
+                        format!(
+                            "{} AS {}",
+                            value.to_sql_literal(sub_field),
+                            sql_identifier(name)
+                        )
+                    })
+                    .collect();
+                format!("STRUCT({})", entries.join(", "))
+            }
+        }
+    }
+}
+
+/// `CellValue` read from JSON text; a visitor (not `serde_json::Value`) so object keys keep their order.
+struct JsonCellValue(CellValue);
+
+impl<'de> serde::Deserialize<'de> for JsonCellValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonCellValueVisitor)
+    }
+}
+
+struct JsonCellValueVisitor;
+
+impl<'de> serde::de::Visitor<'de> for JsonCellValueVisitor {
+    type Value = JsonCellValue;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(CellValue::Null))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(CellValue::Bool(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(CellValue::Int(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(match i64::try_from(v) {
+            Ok(i) => CellValue::Int(i),
+            Err(_) => CellValue::Numeric(v.to_string()),
+        }))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(CellValue::Float(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(JsonCellValue(CellValue::Text(v.to_string())))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::new();
+        while let Some(JsonCellValue(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonCellValue(CellValue::Array(items)))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some((name, JsonCellValue(value))) = map.next_entry::<String, JsonCellValue>()? {
+            entries.push((name, value));
+        }
+        Ok(JsonCellValue(CellValue::Struct(entries)))
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    serde_json::Value::from(s).to_string()
+}
+
+const MICROS_PER_DAY: i128 = 86_400_000_000;
+
+/// ISO 8601 text of a TIMESTAMP as the API returns it, seconds since the epoch in decimal
+/// (e.g. `1.700000000123456E9`), to the microsecond: a JS `Date` would keep the milliseconds.
+pub(crate) fn timestamp_text(seconds: &str) -> Option<String> {
+    let micros = decimal_micros(seconds)?;
+    let (year, month, day) = civil_from_days(micros.div_euclid(MICROS_PER_DAY));
+    let time = micros.rem_euclid(MICROS_PER_DAY);
+    let (seconds, micros) = (time / 1_000_000, time % 1_000_000);
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        micros
+    ))
+}
+
+/// `text`, a decimal number possibly in scientific notation, in millionths, the digits
+/// below dropped; without going through f64.
+fn decimal_micros(text: &str) -> Option<i128> {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((m, e)) => (m, e.parse::<i32>().ok()?),
+        None => (text, 0),
+    };
+    let (negative, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => (true, m),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    // a TIMESTAMP has at most 18 significant digits (seconds and microseconds)
+    if digits.is_empty() || digits.len() > 30 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let value: i128 = digits.parse().ok()?;
+    let shift = exponent
+        .checked_add(6)?
+        .checked_sub(fraction.len() as i32)?;
+    let micros = if shift >= 0 {
+        value.checked_mul(10_i128.checked_pow(shift as u32)?)?
+    } else {
+        value
+            / 10_i128
+                .checked_pow(shift.unsigned_abs())
+                .unwrap_or(i128::MAX)
+    };
+    Some(if negative { -micros } else { micros })
+}
+
+/// Year, month and day of the `days`-th day since 1970-01-01 (proleptic Gregorian calendar).
+fn civil_from_days(days: i128) -> (i128, i128, i128) {
+    // H. Hinnant, `civil_from_days`: eras of 400 years starting on March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Single quoted GoogleSQL string literal.
+pub(crate) fn sql_string(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('\'');
+    for c in s.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\'' => literal.push_str("\\'"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+/// `name` as a column alias, quoted with backticks unless it is a plain identifier.
+pub(crate) fn sql_identifier(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellValue, timestamp_text};
+    use crate::bigquery::jobs::GetQueryResultsResponse;
+
+    #[test]
+    fn struct_keeps_schema_order() {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(include_str!(
+            "test_resources/struct_json_test.json"
+        ))
+        .unwrap();
+        let fields = &response.schema.as_ref().unwrap().fields;
+        let row = &response.rows.as_ref().unwrap()[0];
+
+        let cells = CellValue::row_from_bq(row, fields);
+
+        assert_eq!(
+            cells[0],
+            CellValue::Struct(vec![
+                (
+                    "row_number".to_string(),
+                    CellValue::Text("733704".to_string())
+                ),
+                (
+                    "data_type".to_string(),
+                    CellValue::Text("njiaukk".to_string())
+                ),
+            ])
+        );
+        assert_eq!(
+            cells[0].to_json(),
+            r#"{"row_number":"733704","data_type":"njiaukk"}"#
+        );
+    }
+
+    #[test]
+    fn scalars_are_typed() {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(include_str!(
+            "test_resources/all_types_test.json"
+        ))
+        .unwrap();
+        let fields = &response.schema.as_ref().unwrap().fields;
+
+        for row in response.rows.as_ref().unwrap() {
+            for (cell, field) in CellValue::row_from_bq(row, fields).iter().zip(fields) {
+                let expected_kind = match (field.mode.as_deref(), field.r#type.as_str()) {
+                    (Some("REPEATED"), _) => "array",
+                    (_, "INTEGER" | "INT64") => "int",
+                    (_, "FLOAT" | "FLOAT64") => "float",
+                    (_, "BOOLEAN" | "BOOL") => "bool",
+                    _ => continue,
+                };
+                let kind = match cell {
+                    CellValue::Null => continue,
+                    CellValue::Array(_) => "array",
+                    CellValue::Int(_) => "int",
+                    CellValue::Float(_) => "float",
+                    CellValue::Bool(_) => "bool",
+                    _ => "other",
+                };
+                assert_eq!(kind, expected_kind, "field {}", field.name);
+            }
+        }
+    }
+
+    #[test]
+    fn timestamps_keep_their_microseconds() {
+        assert_eq!(
+            timestamp_text("1.700000000123456E9").as_deref(),
+            Some("2023-11-14T22:13:20.123456Z")
+        );
+        assert_eq!(
+            timestamp_text("0").as_deref(),
+            Some("1970-01-01T00:00:00.000000Z")
+        );
+        assert_eq!(
+            timestamp_text("-1.5").as_deref(),
+            Some("1969-12-31T23:59:58.500000Z")
+        );
+        assert_eq!(
+            timestamp_text("2.53402300799999999E11").as_deref(),
+            Some("9999-12-31T23:59:59.999999Z")
+        );
+        assert_eq!(
+            timestamp_text("-6.2135596800E10").as_deref(),
+            Some("0001-01-01T00:00:00.000000Z")
+        );
+        assert_eq!(timestamp_text("soon"), None);
+    }
+
+    #[test]
+    fn int64_is_a_json_string() {
+        // above 2^53, a JS number would round it
+        let value = CellValue::Struct(vec![
+            ("id".to_string(), CellValue::Int(9_007_199_254_740_993)),
+            ("n".to_string(), CellValue::Float(1.5)),
+        ]);
+        assert_eq!(value.to_json(), r#"{"id":"9007199254740993","n":1.5}"#);
+        assert_eq!(
+            value.to_json_pretty(),
+            "{\n  \"id\": \"9007199254740993\",\n  \"n\": 1.5\n}"
+        );
+    }
+
+    #[test]
+    fn sql_literals_are_typed() {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(include_str!(
+            "test_resources/struct_json_test.json"
+        ))
+        .unwrap();
+        let fields = &response.schema.as_ref().unwrap().fields;
+        let row = &response.rows.as_ref().unwrap()[0];
+
+        let cells = CellValue::row_from_bq(row, fields);
+
+        assert_eq!(
+            cells[0].to_sql_literal(&fields[0]),
+            "STRUCT('733704' AS row_number, 'njiaukk' AS data_type)"
+        );
+
+        let date =
+            serde_json::from_str(r#"{"name": "d", "type": "DATE", "mode": "REPEATED"}"#).unwrap();
+        let dates = CellValue::Array(vec![
+            CellValue::Text("2024-01-31".to_string()),
+            CellValue::Null,
+        ]);
+        assert_eq!(dates.to_sql_literal(&date), "[DATE '2024-01-31', NULL]");
+
+        let string = serde_json::from_str(r#"{"name": "s", "type": "STRING"}"#).unwrap();
+        assert_eq!(
+            CellValue::Text("it's\na \\ test".to_string()).to_sql_literal(&string),
+            r"'it\'s\na \\ test'"
+        );
+        assert_eq!(CellValue::Float(2.0).to_sql_literal(&string), "2.0");
+
+        let range = serde_json::from_str(
+            r#"{"name": "r", "type": "RANGE", "rangeElementType": {"type": "DATE"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            CellValue::Text("[2024-01-01, UNBOUNDED)".to_string()).to_sql_literal(&range),
+            "RANGE<DATE> '[2024-01-01, UNBOUNDED)'"
+        );
+    }
+
+    #[test]
+    fn json_text_keeps_key_order() {
+        let value =
+            CellValue::from_json_text(r#"{"b": [1, 2.5, null], "a": {"z": true}}"#).unwrap();
+
+        assert_eq!(value.json(false), r#"{"b":[1,2.5,null],"a":{"z":true}}"#);
+        assert_eq!(
+            value.to_json_document_pretty(),
+            "{\n  \"b\": [\n    1,\n    2.5,\n    null\n  ],\n  \"a\": {\n    \"z\": true\n  }\n}"
+        );
+        assert_eq!(CellValue::from_json_text("not json"), None);
+    }
+
+    #[test]
+    fn sorts_nulls_first_and_numbers_by_value() {
+        let mut values = vec![
+            CellValue::Numeric("10.5".to_string()),
+            CellValue::Text("b".to_string()),
+            CellValue::Int(9),
+            CellValue::Null,
+            CellValue::Float(-1.0),
+            CellValue::Text("a".to_string()),
+        ];
+        values.sort_by(|a, b| a.sort_cmp(b));
+
+        assert_eq!(
+            values,
+            vec![
+                CellValue::Null,
+                CellValue::Float(-1.0),
+                CellValue::Int(9),
+                CellValue::Numeric("10.5".to_string()),
+                CellValue::Text("a".to_string()),
+                CellValue::Text("b".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,743 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::CellValue,
+    conditional_format::{ThemeColor, number},
+    export::{NestedMode, to_columns, to_records},
+    profile::{is_numeric_type, number_text},
+    value_tree::escape_html,
+};
+
+/*
+Charts of `bq-chart`: the rows of the loaded page (REST `{"f": [...]}` rows and their schema,
+RECORDs flattened as in the grid) turned into series by the column roles (X, the Y columns and
+an optional group by), then drawn as SVG. A text or date X is an axis of categories, the values
+of a series at the same category summed; a numeric X is a continuous axis (except for bars).
+The colors are the chart colors of the VS Code theme.
+*/
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 320.0;
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 12.0;
+const MARGIN_BOTTOM: f64 = 44.0;
+/// Series over this (a group by on an id, ...) are left out.
+const MAX_SERIES: usize = 12;
+/// Longest label of a category on the X axis, the whole one in its tooltip.
+const MAX_LABEL_CHARS: usize = 14;
+pub(crate) const SERIES_COLORS: [ThemeColor; 6] = [
+    ThemeColor::Blue,
+    ThemeColor::Orange,
+    ThemeColor::Green,
+    ThemeColor::Purple,
+    ThemeColor::Red,
+    ThemeColor::Yellow,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ChartKind {
+    #[default]
+    Bar,
+    Line,
+    Scatter,
+    Area,
+}
+
+impl ChartKind {
+    pub(crate) fn get_all() -> Vec<ChartKind> {
+        vec![
+            ChartKind::Bar,
+            ChartKind::Line,
+            ChartKind::Scatter,
+            ChartKind::Area,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ChartKind::Bar => "bar",
+            ChartKind::Line => "line",
+            ChartKind::Scatter => "scatter",
+            ChartKind::Area => "area",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            ChartKind::Bar => "Bar",
+            ChartKind::Line => "Line",
+            ChartKind::Scatter => "Scatter",
+            ChartKind::Area => "Area",
+        }
+    }
+}
+
+impl FromStr for ChartKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChartKind::get_all()
+            .into_iter()
+            .find(|k| k.name() == s)
+            .ok_or(())
+    }
+}
+
+/// Which columns a chart is drawn from, by their path (`a.b` for the field of a RECORD).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct ChartRoles {
+    pub kind: ChartKind,
+    pub x: Option<String>,
+    /// Numeric columns, a series each (only the first one with `group_by`).
+    pub y: Vec<String>,
+    /// Column whose values split the first Y column into series.
+    pub group_by: Option<String>,
+}
+
+impl ChartRoles {
+    /// The first column that is not a number as X (the first column if they all are), the
+    /// first number that is not X as Y.
+    pub(crate) fn default_for(fields: &[TableFieldSchema]) -> ChartRoles {
+        let columns = chart_columns(fields);
+        let x = columns
+            .iter()
+            .find(|(_, f)| !is_numeric_type(&f.r#type))
+            .or(columns.first())
+            .map(|(p, _)| p.clone());
+        let y = columns
+            .iter()
+            .filter(|(p, f)| is_numeric_type(&f.r#type) && Some(p) != x.as_ref())
+            .map(|(p, _)| p.clone())
+            .take(1)
+            .collect();
+
+        ChartRoles {
+            kind: ChartKind::default(),
+            x,
+            y,
+            group_by: None,
+        }
+    }
+
+    /// The roles for the columns of `fields` (the query may have changed since they were
+    /// chosen): the columns it does not have left out, the defaults instead of a missing X or Y.
+    pub(crate) fn valid_for(&self, fields: &[TableFieldSchema]) -> ChartRoles {
+        let columns = chart_columns(fields);
+        let has = |path: &String| columns.iter().any(|(p, _)| p == path);
+        let defaults = ChartRoles::default_for(fields);
+
+        let y: Vec<String> = self
+            .y
+            .iter()
+            .filter(|path| {
+                columns
+                    .iter()
+                    .any(|(p, f)| p == *path && is_numeric_type(&f.r#type))
+            })
+            .cloned()
+            .collect();
+
+        ChartRoles {
+            kind: self.kind,
+            x: self.x.clone().filter(has).or(defaults.x),
+            // an empty Y chosen on purpose stays empty
+            y: if y.is_empty() && !self.y.is_empty() {
+                defaults.y
+            } else {
+                y
+            },
+            group_by: self.group_by.clone().filter(has),
+        }
+    }
+}
+
+/// Path and field of the columns of `fields` a chart can use: the scalar ones, RECORDs
+/// flattened, REPEATED ones left out.
+pub(crate) fn chart_columns(fields: &[TableFieldSchema]) -> Vec<(String, &TableFieldSchema)> {
+    to_columns(fields, NestedMode::Flatten)
+        .into_iter()
+        .filter(|(_, f)| f.fields.is_none() && f.mode.as_deref() != Some("REPEATED"))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Series {
+    pub name: String,
+    /// `(x, y)`: on an axis of categories `x` is the index of the category.
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ChartData {
+    pub kind: ChartKind,
+    pub x_label: String,
+    pub y_label: String,
+    /// Labels of an axis of categories, `None` for a numeric X.
+    pub categories: Option<Vec<String>>,
+    pub series: Vec<Series>,
+    /// Series left out over `MAX_SERIES`.
+    pub hidden_series: usize,
+}
+
+/// Whether the values of a column of `field_type` have an order of their own (numbers, dates
+/// and times), used to sort the categories instead of keeping the order of the rows.
+fn is_ordered_type(field_type: &str) -> bool {
+    is_numeric_type(field_type) || matches!(field_type, "DATE" | "DATETIME" | "TIME" | "TIMESTAMP")
+}
+
+fn category_text(value: &CellValue) -> String {
+    value.to_text().unwrap_or_else(|| "NULL".to_string())
+}
+
+/// The series of `rows` by `roles`, `None` without an X or a numeric Y column.
+pub(crate) fn chart_data(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    roles: &ChartRoles,
+) -> Option<ChartData> {
+    let columns = to_columns(fields, NestedMode::Flatten);
+    let position = |path: &String| columns.iter().position(|(p, _)| p == path);
+    let x = roles.x.as_ref().and_then(position)?;
+    let mut y: Vec<usize> = roles
+        .y
+        .iter()
+        .filter_map(position)
+        .filter(|i| is_numeric_type(&columns[*i].1.r#type))
+        .collect();
+    let group_by = roles.group_by.as_ref().and_then(position);
+    if group_by.is_some() {
+        y.truncate(1);
+    }
+    if y.is_empty() {
+        return None;
+    }
+
+    let (_, records) = to_records(fields, rows, NestedMode::Flatten);
+    let x_type = &columns[x].1.r#type;
+
+    let categories = match roles.kind != ChartKind::Bar && is_numeric_type(x_type) {
+        true => None,
+        false => {
+            let mut seen = HashSet::new();
+            let mut values: Vec<&CellValue> = records
+                .iter()
+                .filter_map(|r| r.get(x))
+                .filter(|v| seen.insert(category_text(v)))
+                .collect();
+            if is_ordered_type(x_type) {
+                values.sort_by(|a, b| a.sort_cmp(b));
+            }
+            Some(
+                values
+                    .into_iter()
+                    .map(category_text)
+                    .collect::<Vec<String>>(),
+            )
+        }
+    };
+    let category_index: HashMap<&str, usize> = categories
+        .iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+
+    // a series per Y column, or per value of the group by as they come
+    let mut series: Vec<Series> = match group_by {
+        Some(_) => vec![],
+        None => y
+            .iter()
+            .take(MAX_SERIES)
+            .map(|i| Series {
+                name: columns[*i].0.clone(),
+                points: vec![],
+            })
+            .collect(),
+    };
+    let mut hidden = HashSet::new();
+    for record in &records {
+        let x_value = match (&categories, record.get(x)) {
+            (_, None) => continue,
+            (None, Some(v)) => match number(v) {
+                Some(n) => n,
+                None => continue,
+            },
+            (Some(_), Some(v)) => category_index[category_text(v).as_str()] as f64,
+        };
+        for column in &y {
+            let y_value = match record.get(*column).and_then(number) {
+                Some(v) => v,
+                None => continue,
+            };
+            let name = match group_by.and_then(|g| record.get(g)) {
+                Some(g) => category_text(g),
+                None => columns[*column].0.clone(),
+            };
+            let index = match series.iter().position(|s| s.name == name) {
+                Some(i) => i,
+                None if group_by.is_some() && series.len() < MAX_SERIES => {
+                    series.push(Series {
+                        name,
+                        points: vec![],
+                    });
+                    series.len() - 1
+                }
+                None => {
+                    hidden.insert(name);
+                    continue;
+                }
+            };
+            series[index].points.push((x_value, y_value));
+        }
+    }
+
+    for s in series.iter_mut() {
+        match (&categories, roles.kind) {
+            (_, ChartKind::Scatter) => {}
+            (Some(_), _) => {
+                // the values at the same category summed, in the order of the categories
+                let mut sums: BTreeMap<usize, f64> = BTreeMap::new();
+                for (category, value) in &s.points {
+                    *sums.entry(*category as usize).or_insert(0.0) += value;
+                }
+                s.points = sums.into_iter().map(|(x, y)| (x as f64, y)).collect();
+            }
+            (None, _) => s.points.sort_by(|a, b| a.0.total_cmp(&b.0)),
+        }
+    }
+
+    let y_label = match y.as_slice() {
+        [column] => columns[*column].0.clone(),
+        _ => String::new(),
+    };
+
+    Some(ChartData {
+        kind: roles.kind,
+        x_label: columns[x].0.clone(),
+        y_label,
+        categories,
+        series,
+        hidden_series: hidden.len(),
+    })
+}
+
+/// About 5 round steps (1, 2 or 5 times a power of ten) from below `min` to above `max`.
+pub(crate) fn nice_ticks(min: f64, max: f64) -> Vec<f64> {
+    let (min, max) = match min == max {
+        true => (min - 1.0, max + 1.0),
+        false => (min, max),
+    };
+    let rough = (max - min) / 5.0;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|s| *s >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let start = (min / step).floor() * step;
+    let count = ((max / step).ceil() * step - start) / step;
+
+    (0..=count.round() as usize)
+        .map(|i| start + step * i as f64)
+        .collect()
+}
+
+fn range(values: impl Iterator<Item = f64>) -> Option<(f64, f64)> {
+    values.fold(None, |range: Option<(f64, f64)>, v| {
+        Some(range.map_or((v, v), |(min, max)| (min.min(v), max.max(v))))
+    })
+}
+
+fn short_label(label: &str) -> String {
+    match label.chars().count() > MAX_LABEL_CHARS {
+        true => format!(
+            "{}…",
+            label.chars().take(MAX_LABEL_CHARS - 1).collect::<String>()
+        ),
+        false => label.to_string(),
+    }
+}
+
+/// The chart as an SVG figure with its legend, a short note when there is nothing to draw.
+pub(crate) fn chart_html(data: &ChartData) -> String {
+    let (y_min, y_max) = match range(
+        data.series
+            .iter()
+            .flat_map(|s| s.points.iter().map(|p| p.1)),
+    ) {
+        Some(r) => r,
+        None => {
+            return r#"<p class="chart-empty">No numbers to chart in the loaded rows.</p>"#
+                .to_string();
+        }
+    };
+    let (left, right) = (MARGIN_LEFT, WIDTH - MARGIN_RIGHT);
+    let (top, bottom) = (MARGIN_TOP, HEIGHT - MARGIN_BOTTOM);
+
+    // bars and areas stand on zero
+    let (y_min, y_max) = match data.kind {
+        ChartKind::Bar | ChartKind::Area => (y_min.min(0.0), y_max.max(0.0)),
+        _ => (y_min, y_max),
+    };
+    let y_ticks = nice_ticks(y_min, y_max);
+    let (y0, y1) = (y_ticks[0], y_ticks[y_ticks.len() - 1]);
+    let scale_y = |v: f64| bottom - (v - y0) / (y1 - y0) * (bottom - top);
+
+    let x_ticks = match &data.categories {
+        Some(_) => vec![],
+        None => {
+            let (x_min, x_max) = range(
+                data.series
+                    .iter()
+                    .flat_map(|s| s.points.iter().map(|p| p.0)),
+            )
+            .unwrap_or((0.0, 1.0));
+            nice_ticks(x_min, x_max)
+        }
+    };
+    let band = data
+        .categories
+        .as_ref()
+        .map(|c| (right - left) / c.len().max(1) as f64)
+        .unwrap_or(0.0);
+    let scale_x = |v: f64| match &data.categories {
+        Some(_) => left + (v + 0.5) * band,
+        None => {
+            let (x0, x1) = (x_ticks[0], x_ticks[x_ticks.len() - 1]);
+            left + (v - x0) / (x1 - x0) * (right - left)
+        }
+    };
+    let x_text = |v: f64| match &data.categories {
+        Some(c) => c.get(v as usize).cloned().unwrap_or_default(),
+        None => number_text(v),
+    };
+
+    let mut svg = Vec::new();
+    for tick in &y_ticks {
+        let y = scale_y(*tick);
+        svg.push(format!(
+            r#"<line class="grid-line" x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/><text class="tick" x="{:.1}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+            left, y, right, y, left - 6.0, y, number_text(*tick)
+        ));
+    }
+    match &data.categories {
+        Some(categories) => {
+            // one label in `every`, so they do not overlap
+            let every = ((categories.len() as f64 * 64.0) / (right - left))
+                .ceil()
+                .max(1.0) as usize;
+            for (i, category) in categories.iter().enumerate().step_by(every) {
+                svg.push(format!(
+                    r#"<text class="tick" x="{:.1}" y="{:.1}" text-anchor="middle"><title>{}</title>{}</text>"#,
+                    scale_x(i as f64),
+                    bottom + 16.0,
+                    escape_html(category),
+                    escape_html(&short_label(category))
+                ));
+            }
+        }
+        None => {
+            for tick in &x_ticks {
+                svg.push(format!(
+                    r#"<text class="tick" x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                    scale_x(*tick),
+                    bottom + 16.0,
+                    number_text(*tick)
+                ));
+            }
+        }
+    }
+    svg.push(format!(
+        r#"<line class="axis" x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}"/>"#,
+        left,
+        scale_y(0.0_f64.clamp(y0, y1)),
+        right,
+        scale_y(0.0_f64.clamp(y0, y1))
+    ));
+    svg.push(format!(
+        r#"<text class="axis-title" x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+        (left + right) / 2.0,
+        HEIGHT - 6.0,
+        escape_html(&data.x_label)
+    ));
+    if !data.y_label.is_empty() {
+        svg.push(format!(
+            r#"<text class="axis-title" transform="translate(12 {:.1}) rotate(-90)" text-anchor="middle">{}</text>"#,
+            (top + bottom) / 2.0,
+            escape_html(&data.y_label)
+        ));
+    }
+
+    let baseline = scale_y(0.0_f64.clamp(y0, y1));
+    for (s, series) in data.series.iter().enumerate() {
+        let color = SERIES_COLORS[s % SERIES_COLORS.len()].css_var();
+        let title = |(x, y): (f64, f64)| {
+            format!(
+                "<title>{}: {}, {}</title>",
+                escape_html(&series.name),
+                escape_html(&x_text(x)),
+                number_text(y)
+            )
+        };
+        let mut marks = Vec::new();
+        match data.kind {
+            ChartKind::Bar => {
+                let width = band * 0.8 / data.series.len() as f64;
+                for (x, y) in &series.points {
+                    let bar_x = left + x * band + band * 0.1 + s as f64 * width;
+                    let bar_y = scale_y(*y);
+                    marks.push(format!(
+                        r#"<rect class="bar" x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}">{}</rect>"#,
+                        bar_x,
+                        bar_y.min(baseline),
+                        (width - 1.0).max(1.0),
+                        (bar_y - baseline).abs(),
+                        title((*x, *y))
+                    ));
+                }
+            }
+            ChartKind::Line | ChartKind::Area => {
+                let points: Vec<String> = series
+                    .points
+                    .iter()
+                    .map(|(x, y)| format!("{:.1},{:.1}", scale_x(*x), scale_y(*y)))
+                    .collect();
+                if data.kind == ChartKind::Area && !points.is_empty() {
+                    let first = series.points[0].0;
+                    let last = series.points[series.points.len() - 1].0;
+                    marks.push(format!(
+                        r#"<path class="area" d="M{:.1},{:.1} L{} L{:.1},{:.1} Z"/>"#,
+                        scale_x(first),
+                        baseline,
+                        points.join(" L"),
+                        scale_x(last),
+                        baseline
+                    ));
+                }
+                marks.push(format!(
+                    r#"<polyline class="line" points="{}"/>"#,
+                    points.join(" ")
+                ));
+                for (x, y) in &series.points {
+                    marks.push(format!(
+                        r#"<circle class="point" cx="{:.1}" cy="{:.1}" r="2.5">{}</circle>"#,
+                        scale_x(*x),
+                        scale_y(*y),
+                        title((*x, *y))
+                    ));
+                }
+            }
+            ChartKind::Scatter => {
+                for (x, y) in &series.points {
+                    marks.push(format!(
+                        r#"<circle class="dot" cx="{:.1}" cy="{:.1}" r="3.5">{}</circle>"#,
+                        scale_x(*x),
+                        scale_y(*y),
+                        title((*x, *y))
+                    ));
+                }
+            }
+        }
+        svg.push(format!(
+            r#"<g class="series" style="color: {}">{}</g>"#,
+            color,
+            marks.join("")
+        ));
+    }
+
+    let names: Vec<&str> = data.series.iter().map(|s| s.name.as_str()).collect();
+    let description = format!(
+        "{} chart of {} by {}",
+        data.kind.label(),
+        if data.y_label.is_empty() {
+            names.join(", ")
+        } else {
+            data.y_label.clone()
+        },
+        data.x_label
+    );
+    let legend: Vec<String> = data
+        .series
+        .iter()
+        .enumerate()
+        .map(|(s, series)| {
+            format!(
+                r#"<li><span class="swatch" style="background-color: {}"></span>{}</li>"#,
+                SERIES_COLORS[s % SERIES_COLORS.len()].css_var(),
+                escape_html(&series.name)
+            )
+        })
+        .collect();
+    let hidden = match data.hidden_series {
+        0 => String::new(),
+        n => format!(r#"<li class="chart-note">{} more series not shown</li>"#, n),
+    };
+
+    format!(
+        concat!(
+            r#"<figure class="chart"><svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" role="img" aria-label="{}">{}</svg>"#,
+            r#"<figcaption><ul class="chart-legend">{}{}</ul></figcaption></figure>"#
+        ),
+        WIDTH,
+        HEIGHT,
+        escape_html(&description),
+        svg.join(""),
+        legend.join(""),
+        hidden
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        ChartData, ChartKind, ChartRoles, Series, chart_columns, chart_data, chart_html, nice_ticks,
+    };
+    use crate::bigquery::base::TableFieldSchema;
+
+    fn fields() -> Vec<TableFieldSchema> {
+        serde_json::from_value(json!([
+            {"name": "region", "type": "STRING"},
+            {"name": "day", "type": "DATE"},
+            {"name": "sales", "type": "INTEGER"},
+            {"name": "stats", "type": "RECORD", "fields": [{"name": "margin", "type": "FLOAT"}]},
+            {"name": "tags", "type": "STRING", "mode": "REPEATED"}
+        ]))
+        .unwrap()
+    }
+
+    fn row(region: &str, day: &str, sales: &str, margin: &str) -> serde_json::Value {
+        json!({"f": [
+            {"v": region},
+            {"v": day},
+            {"v": sales},
+            {"v": {"f": [{"v": margin}]}},
+            {"v": []}
+        ]})
+    }
+
+    #[test]
+    fn default_roles_and_columns() {
+        let fields = fields();
+        let paths: Vec<String> = chart_columns(&fields).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, vec!["region", "day", "sales", "stats.margin"]);
+
+        let roles = ChartRoles::default_for(&fields);
+        assert_eq!(roles.x.as_deref(), Some("region"));
+        assert_eq!(roles.y, vec!["sales".to_string()]);
+
+        let stale = ChartRoles {
+            kind: ChartKind::Line,
+            x: Some("gone".to_string()),
+            y: vec!["region".to_string(), "stats.margin".to_string()],
+            group_by: Some("gone".to_string()),
+        };
+        let valid = stale.valid_for(&fields);
+        assert_eq!(valid.kind, ChartKind::Line);
+        assert_eq!(valid.x.as_deref(), Some("region"));
+        assert_eq!(valid.y, vec!["stats.margin".to_string()]);
+        assert_eq!(valid.group_by, None);
+    }
+
+    #[test]
+    fn series_grouped_and_summed_by_category() {
+        let rows = vec![
+            row("north", "2024-01-02", "10", "0.5"),
+            row("south", "2024-01-01", "7", "0.1"),
+            row("north", "2024-01-01", "3", "0.2"),
+            row("north", "2024-01-02", "5", "0.3"),
+        ];
+        let roles = ChartRoles {
+            kind: ChartKind::Line,
+            x: Some("day".to_string()),
+            y: vec!["sales".to_string(), "stats.margin".to_string()],
+            group_by: Some("region".to_string()),
+        };
+        let data = chart_data(&fields(), &rows, &roles).unwrap();
+        // dates in their order, only the first Y with a group by
+        assert_eq!(
+            data.categories,
+            Some(vec!["2024-01-01".to_string(), "2024-01-02".to_string()])
+        );
+        assert_eq!(data.y_label, "sales");
+        assert_eq!(
+            data.series,
+            vec![
+                Series {
+                    name: "north".to_string(),
+                    points: vec![(0.0, 3.0), (1.0, 15.0)],
+                },
+                Series {
+                    name: "south".to_string(),
+                    points: vec![(0.0, 7.0)],
+                },
+            ]
+        );
+
+        // a numeric X is continuous, the points of a line in its order
+        let roles = ChartRoles {
+            kind: ChartKind::Line,
+            x: Some("stats.margin".to_string()),
+            y: vec!["sales".to_string()],
+            group_by: None,
+        };
+        let data = chart_data(&fields(), &rows, &roles).unwrap();
+        assert_eq!(data.categories, None);
+        assert_eq!(
+            data.series[0].points,
+            vec![(0.1, 7.0), (0.2, 3.0), (0.3, 5.0), (0.5, 10.0)]
+        );
+
+        let no_y = ChartRoles {
+            y: vec!["region".to_string()],
+            ..roles
+        };
+        assert_eq!(chart_data(&fields(), &rows, &no_y), None);
+    }
+
+    #[test]
+    fn svg_of_the_series() {
+        assert_eq!(
+            nice_ticks(0.0, 95.0),
+            vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(nice_ticks(3.0, 3.0), vec![2.0, 2.5, 3.0, 3.5, 4.0]);
+
+        let data = ChartData {
+            kind: ChartKind::Bar,
+            x_label: "region".to_string(),
+            y_label: String::new(),
+            categories: Some(vec!["north".to_string(), "<south>".to_string()]),
+            series: vec![
+                Series {
+                    name: "sales".to_string(),
+                    points: vec![(0.0, 10.0), (1.0, -4.0)],
+                },
+                Series {
+                    name: "returns".to_string(),
+                    points: vec![(1.0, 2.0)],
+                },
+            ],
+            hidden_series: 3,
+        };
+        let html = chart_html(&data);
+        assert_eq!(html.matches(r#"<rect class="bar""#).count(), 3);
+        assert!(html.contains(r#"aria-label="Bar chart of sales, returns by region""#));
+        assert!(html.contains("<title>sales: &lt;south&gt;, -4</title>"));
+        assert!(html.contains("3 more series not shown"));
+
+        let empty = ChartData {
+            series: vec![],
+            hidden_series: 0,
+            ..data
+        };
+        assert!(chart_html(&empty).contains("chart-empty"));
+    }
+}
@@ -0,0 +1,228 @@
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+use super::{
+    conditional_format::{
+        Comparison, FormatRule, ThemeColor, cell_styles, is_boolean_type, is_formattable,
+    },
+    export::{to_grid_columns, to_grid_records},
+    grid_columns, grid_layout,
+    layout::ColumnLayout,
+    loaded_results::LoadedResults,
+    overlay_panel,
+    profile::field_at,
+    value_tree::escape_html,
+};
+
+/*
+Conditional formatting of the grid of `bq-table` and `bq-query`: the rules of a column are
+edited in the overlay panel (opened from the context menu of its header), the styles computed
+by `conditional_format` set on the cells after each render.
+*/
+
+/// Sets the style of the formatted cells of `data_rows`, the rows of `visible` as rendered.
+pub(crate) fn apply(visible: &LoadedResults, column_layout: &ColumnLayout, data_rows: &[Element]) {
+    if column_layout.formats.is_empty() {
+        return;
+    }
+    let layout = grid_layout::get();
+    let fields = &visible.schema.fields;
+    let columns = to_grid_columns(fields, &layout);
+    let (_, records) = to_grid_records(fields, &visible.rows, &layout);
+
+    for (column, (path, field)) in columns.iter().enumerate() {
+        // rules kept for a column whose type changed since (the query edited) are left out
+        let rules: Vec<FormatRule> = match column_layout.formats.get(path) {
+            Some(r) => r.iter().filter(|r| r.applies_to(field)).cloned().collect(),
+            None => continue,
+        };
+        let values: Vec<_> = records
+            .iter()
+            .filter_map(|r| r.get(column).cloned())
+            .collect();
+        for (row, style) in data_rows.iter().zip(cell_styles(&rules, &values)) {
+            // the `#` cell first
+            if let (Some(style), Some(cell)) = (style, row.children().item(column as u32 + 1)) {
+                let current = cell.get_attribute("style").unwrap_or_default();
+                let separator = if current.is_empty() || current.trim_end().ends_with(';') {
+                    ""
+                } else {
+                    ";"
+                };
+                let _ = cell.set_attribute("style", &format!("{}{} {}", current, separator, style));
+            }
+        }
+    }
+}
+
+/// Whether the leaf column at `path` of the results loaded in `host` can be formatted.
+pub(crate) fn can_format(host: &Element, path: &str) -> bool {
+    host.get_attribute("be_id")
+        .and_then(|id| grid_columns::visible_results(&id))
+        .and_then(|v| {
+            field_at(&v.schema.fields, path).map(|(f, repeated)| is_formattable(f, repeated))
+        })
+        .unwrap_or(false)
+}
+
+/// Opens the rules of the leaf column at `path` in the overlay panel.
+pub(crate) fn open(host: &Element, path: &str) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (visible, column_layout) = match (
+        grid_columns::visible_results(&element_id),
+        grid_columns::column_layout(&element_id),
+    ) {
+        (Some(v), Some(c)) => (v, c),
+        _ => return,
+    };
+    let field = match field_at(&visible.schema.fields, path) {
+        Some((f, repeated)) if is_formattable(f, repeated) => f,
+        _ => return,
+    };
+    let rules = column_layout.formats.get(path).cloned().unwrap_or_default();
+
+    let title = format!("Conditional formatting of {}", path);
+    let body = match overlay_panel::open(host, &title, &field.r#type, vec![]) {
+        Some(b) => b,
+        None => return,
+    };
+
+    let list = if rules.is_empty() {
+        r#"<p class="format-empty">No rule, the column is not formatted.</p>"#.to_string()
+    } else {
+        let items: Vec<String> = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                format!(
+                    r#"<li><span>{}</span><button type="button" data-format-action="remove" data-format-index="{}" aria-label="Remove the rule {}">Remove</button></li>"#,
+                    escape_html(&rule.description()),
+                    i,
+                    escape_html(&rule.description())
+                )
+            })
+            .collect();
+        format!(r#"<ol class="format-rule-list">{}</ol>"#, items.join(""))
+    };
+
+    let new_rule = if is_boolean_type(&field.r#type) {
+        r#"<button type="button" data-format-action="add" data-format-kind="boolean">Add: green for TRUE, red for FALSE</button>"#.to_string()
+    } else {
+        let comparisons: Vec<String> = Comparison::get_all()
+            .iter()
+            .map(|c| format!(r#"<option value="{}">{}</option>"#, c.name(), c.symbol()))
+            .collect();
+        let colors: Vec<String> = ThemeColor::get_all()
+            .iter()
+            .map(|c| format!(r#"<option value="{}">{}</option>"#, c.name(), c.label()))
+            .collect();
+        format!(
+            concat!(
+                r#"<label>Rule <select data-format-field="kind">"#,
+                r#"<option value="threshold">Threshold</option><option value="color_scale">Color scale</option><option value="data_bar">Data bars</option>"#,
+                r#"</select></label>"#,
+                r#"<label title="Thresholds only">Where value <select data-format-field="comparison">{}</select></label>"#,
+                r#"<input type="number" step="any" data-format-field="value" aria-label="Threshold value" placeholder="value">"#,
+                r#"<label>Color <select data-format-field="color">{}</select></label>"#,
+                r#"<button type="button" data-format-action="add">Add rule</button>"#
+            ),
+            comparisons.join(""),
+            colors.join("")
+        )
+    };
+
+    body.set_inner_html(&format!(
+        concat!(
+            r#"<div class="format-rules" data-format-path="{}">{}"#,
+            r#"<div class="format-new" role="group" aria-label="New rule">{}</div>"#,
+            r#"<button type="button" data-format-action="clear"{}>Clear all</button></div>"#
+        ),
+        escape_html(path),
+        list,
+        new_rule,
+        if rules.is_empty() { " disabled" } else { "" }
+    ));
+}
+
+/// A `data-format-action` button of the panel: adds, removes or clears rules.
+pub(crate) fn on_click(host: &Element, button: &Element) {
+    let (rules_element, action) = match (
+        button.closest("[data-format-path]").ok().flatten(),
+        button.get_attribute("data-format-action"),
+    ) {
+        (Some(r), Some(a)) => (r, a),
+        _ => return,
+    };
+    let path = rules_element
+        .get_attribute("data-format-path")
+        .unwrap_or_default();
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let mut rules = grid_columns::column_layout(&element_id)
+        .and_then(|c| c.formats.get(&path).cloned())
+        .unwrap_or_default();
+
+    match action.as_str() {
+        "add" => match new_rule(&rules_element, button) {
+            Some(rule) => rules.push(rule),
+            None => return,
+        },
+        "remove" => {
+            if let Some(index) = button
+                .get_attribute("data-format-index")
+                .and_then(|i| i.parse::<usize>().ok())
+                .filter(|i| *i < rules.len())
+            {
+                rules.remove(index);
+            }
+        }
+        "clear" => rules.clear(),
+        _ => return,
+    }
+
+    grid_columns::set_formats(host, &path, rules);
+    open(host, &path);
+}
+
+/// The rule of the "new rule" fields, `None` (the value marked invalid) for a threshold
+/// without a number.
+fn new_rule(rules_element: &Element, button: &Element) -> Option<FormatRule> {
+    if button.get_attribute("data-format-kind").as_deref() == Some("boolean") {
+        return Some(FormatRule::Boolean);
+    }
+    let field = |name: &str| {
+        rules_element
+            .query_selector(&format!("[data-format-field='{}']", name))
+            .ok()
+            .flatten()
+    };
+    let select_value = |name: &str| {
+        field(name)
+            .and_then(|s| s.dyn_into::<web_sys::HtmlSelectElement>().ok())
+            .map(|s| s.value())
+            .unwrap_or_default()
+    };
+    let color = select_value("color").parse::<ThemeColor>().ok()?;
+
+    match select_value("kind").as_str() {
+        "color_scale" => Some(FormatRule::ColorScale { color }),
+        "data_bar" => Some(FormatRule::DataBar { color }),
+        _ => {
+            let input = field("value")?
+                .dyn_into::<web_sys::HtmlInputElement>()
+                .ok()?;
+            let value = match input.value().trim().parse::<f64>() {
+                Ok(v) if v.is_finite() => v,
+                _ => {
+                    let _ = input.set_attribute("aria-invalid", "true");
+                    let _ = input.focus();
+                    return None;
+                }
+            };
+            Some(FormatRule::Threshold {
+                comparison: select_value("comparison").parse::<Comparison>().ok()?,
+                value,
+                color,
+            })
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+use super::{
+    export_all::{dispatch_stalled, fetch_page},
+    grid_events::dispatch_load_error,
+    loaded_results::{self, LoadedResults},
+    overlay_panel,
+    page_walk::{PageStep, PageWalk},
+    profile::{ColumnProfile, ProfileBuilder, profile_html},
+    value_tree::escape_html,
+};
+
+/*
+Profile of a column of the grid of `bq-table` and `bq-query` in the overlay panel, opened from
+the context menu of a leaf header. Computed over the loaded page, or over every page on request
+(fetched again page by page, one page held at a time, into the bounded counts of
+`ProfileBuilder`) while the panel shows the profile.
+*/
+
+/// Opens the profile of the field at `path` over the loaded page.
+pub(crate) fn open(host: &Element, path: &str) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) => r,
+        None => return,
+    };
+    let mut builder = match ProfileBuilder::new(&loaded_results.schema.fields, path) {
+        Some(b) => b,
+        None => return,
+    };
+    builder.add_rows(&loaded_results.schema.fields, &loaded_results.rows);
+
+    let scope = format!("Over the {} rows of this page", loaded_results.rows.len());
+    show(host, &loaded_results, &builder.build(), &scope, true);
+}
+
+fn show(
+    host: &Element,
+    loaded_results: &LoadedResults,
+    profile: &ColumnProfile,
+    scope: &str,
+    can_profile_all: bool,
+) {
+    let title = format!("Profile of {}", profile.path);
+    let meta = if profile.repeated {
+        format!("{} · elements of a REPEATED field", profile.r#type)
+    } else {
+        profile.r#type.clone()
+    };
+    let body = match overlay_panel::open(host, &title, &meta, vec![]) {
+        Some(b) => b,
+        None => return,
+    };
+
+    let all_pages = if can_profile_all && loaded_results.source.is_some() {
+        r#"<button type="button" data-profile-all title="Fetch every page of the results to profile them all">Profile all pages</button>"#
+    } else {
+        ""
+    };
+    body.set_inner_html(&format!(
+        r#"<div class="profile" data-profile-path="{}"><div class="profile-scope"><span role="status">{}</span>{}</div>{}</div>"#,
+        escape_html(&profile.path),
+        escape_html(scope),
+        all_pages,
+        profile_html(profile)
+    ));
+}
+
+/// The "Profile all pages" button of the panel: fetches every page and shows the profile of them all.
+pub(crate) fn on_profile_all(host: &Element, button: &Element) {
+    let path = match button
+        .closest("[data-profile-path]")
+        .ok()
+        .flatten()
+        .and_then(|p| p.get_attribute("data-profile-path"))
+    {
+        Some(p) => p,
+        None => return,
+    };
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) => r,
+        None => return,
+    };
+    let (source, mut builder) = match (
+        loaded_results.source.clone(),
+        ProfileBuilder::new(&loaded_results.schema.fields, &path),
+    ) {
+        (Some(s), Some(b)) => (s, b),
+        _ => return,
+    };
+    let _ = button.set_attribute("disabled", "");
+    let status = button.previous_element_sibling();
+    let token = host.get_attribute("token").unwrap_or_default();
+    let host = host.clone();
+    let button = button.clone();
+
+    spawn_local(async move {
+        let fields = &loaded_results.schema.fields;
+        let mut walk = PageWalk::default();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let page = match fetch_page(&source, &token, page_token.take()).await {
+                Ok(p) => p,
+                Err((operation, e)) => {
+                    dispatch_load_error(&host, operation, &e);
+                    let _ = button.remove_attribute("disabled");
+                    return;
+                }
+            };
+
+            // the panel was closed, or shows something else
+            let cancelled = !button.is_connected();
+            let next_page_token =
+                match walk.step(page.rows.len(), page.rows_total, page.page_token, cancelled) {
+                    PageStep::Cancelled => return,
+                    PageStep::Write(t) => t,
+                    // a profile of part of the rows is not shown as the one of them all
+                    PageStep::Stalled => {
+                        dispatch_stalled(&host, &source, walk.rows_done);
+                        let _ = button.remove_attribute("disabled");
+                        return;
+                    }
+                };
+
+            builder.add_rows(fields, &page.rows);
+            if let Some(status) = &status {
+                status.set_text_content(Some(&match walk.rows_total {
+                    Some(total) => format!("Profiling… {} of {} rows", walk.rows_done, total),
+                    None => format!("Profiling… {} rows", walk.rows_done),
+                }));
+            }
+
+            match next_page_token {
+                Some(t) => page_token = Some(t),
+                None => break,
+            }
+        }
+
+        let scope = format!("Over all the {} rows", walk.rows_done);
+        show(&host, &loaded_results, &builder.build(), &scope, false);
+    });
+}
@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    grid_columns, grid_layout,
+    grid_selection::{grid_rows, grid_table},
+    layout::header_cells,
+    loaded_results::LoadedResults,
+    to_table_builder::fit_column_width,
+};
+
+/*
+Column widths of the grid of `bq-table` and `bq-query` set by the user: the border of the header
+of a leaf column is dragged to resize it, a double click on it fits the column to its values.
+The widths are kept in the column layout (`grid_columns`), by field path.
+*/
+const RESIZER_CLASS: &str = "column-resizer";
+const MIN_WIDTH_PX: i32 = 40;
+
+#[derive(Debug, Clone)]
+struct Resize {
+    element_id: String,
+    path: String,
+    header: Element,
+    start_x: i32,
+    start_width: i32,
+}
+
+thread_local! {
+    static RESIZING: RefCell<Option<Resize>> = const { RefCell::new(None) };
+}
+
+/// Adds a border to drag to the header of each leaf column of the grid of `host`
+/// (the `#` column left out), the header marked with the path of its field.
+pub(crate) fn add_handles(host: &Element, visible: &LoadedResults) {
+    let (table, document) = match (
+        grid_table(host),
+        web_sys::window().and_then(|w| w.document()),
+    ) {
+        (Some(t), Some(d)) => (t, d),
+        _ => return,
+    };
+    let layout = grid_layout::get();
+    let (header_rows, _) = grid_rows(&table);
+
+    for (depth, (cells, row)) in header_cells(&visible.schema.fields, &layout)
+        .iter()
+        .zip(header_rows.iter())
+        .enumerate()
+    {
+        let mut header = row.first_element_child();
+        // the `#` header of the index column starts the first row
+        if depth == 0 {
+            header = header.and_then(|h| h.next_element_sibling());
+        }
+        for (path, field) in cells {
+            let h = match header {
+                Some(h) => h,
+                None => break,
+            };
+            header = h.next_element_sibling();
+            if field.fields.is_some() && !layout.is_collapsed(path, field) {
+                continue;
+            }
+
+            let _ = h.set_attribute("data-column-path", path);
+            if let Ok(resizer) = document.create_element("span") {
+                let _ = resizer.set_attribute("class", RESIZER_CLASS);
+                let _ = resizer.set_attribute("aria-hidden", "true");
+                let _ = resizer.set_attribute("title", "Drag to resize, double-click to fit");
+                let _ = h.append_child(&resizer);
+            }
+        }
+    }
+}
+
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    // captured, so a press on a border does not select the column too
+    let listeners = [
+        ("mousedown", on_mouse_down as fn(&web_sys::Event), true),
+        ("dblclick", on_double_click, true),
+        ("mousemove", on_mouse_move, false),
+        ("mouseup", on_mouse_up, false),
+    ];
+    for (event_type, listener, capture) in listeners {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(listener) as Box<dyn Fn(&web_sys::Event)>);
+        if let Err(e) = element.add_event_listener_with_callback_and_bool(
+            event_type,
+            on_event_type_closure.as_ref().unchecked_ref(),
+            capture,
+        ) {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "{} define: failed to add '{}' listener: {:?}",
+                tag_name, event_type, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+/// The host and the header of the border under the pointer, with the path of its field.
+fn resizer_hit(event: &web_sys::Event) -> Option<(Element, Element, String)> {
+    let host = event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())?;
+    let target = event.composed_path().get(0).dyn_into::<Element>().ok()?;
+    if !target.class_list().contains(RESIZER_CLASS) {
+        return None;
+    }
+    let header = target.closest("th[data-column-path]").ok().flatten()?;
+    let path = header.get_attribute("data-column-path")?;
+    Some((host, header, path))
+}
+
+fn on_mouse_down(event: &web_sys::Event) {
+    let (mouse_event, (host, header, path)) =
+        match (event.dyn_ref::<web_sys::MouseEvent>(), resizer_hit(event)) {
+            (Some(m), Some(h)) => (m, h),
+            _ => return,
+        };
+    if mouse_event.button() != 0 {
+        return;
+    }
+    event.prevent_default();
+    event.stop_immediate_propagation();
+
+    let start_width = header
+        .dyn_ref::<web_sys::HtmlElement>()
+        .map(|h| h.offset_width())
+        .unwrap_or(0);
+    RESIZING.with(|r| {
+        *r.borrow_mut() = Some(Resize {
+            element_id: host.get_attribute("be_id").unwrap_or_default(),
+            path,
+            header,
+            start_x: mouse_event.client_x(),
+            start_width,
+        })
+    });
+}
+
+/// Width of the column being resized with the pointer at `client_x`.
+fn dragged_width(resize: &Resize, client_x: i32) -> i32 {
+    (resize.start_width + client_x - resize.start_x).max(MIN_WIDTH_PX)
+}
+
+fn on_mouse_move(event: &web_sys::Event) {
+    let mouse_event = match event.dyn_ref::<web_sys::MouseEvent>() {
+        Some(m) => m,
+        None => return,
+    };
+    let resize = match RESIZING.with(|r| r.borrow().clone()) {
+        Some(r) => r,
+        None => return,
+    };
+    // released outside of the grid
+    if mouse_event.buttons() & 1 == 0 {
+        finish(event, &resize, mouse_event.client_x());
+        return;
+    }
+
+    // only the header follows the pointer, the grid is rendered with the width once released
+    if let Some(header) = resize.header.dyn_ref::<web_sys::HtmlElement>() {
+        let width = format!("{}px", dragged_width(&resize, mouse_event.client_x()));
+        let style = header.style();
+        let _ = style.set_property("width", &width);
+        let _ = style.set_property("min-width", &width);
+        let _ = style.set_property("max-width", &width);
+    }
+}
+
+fn on_mouse_up(event: &web_sys::Event) {
+    if let (Some(mouse_event), Some(resize)) = (
+        event.dyn_ref::<web_sys::MouseEvent>(),
+        RESIZING.with(|r| r.borrow().clone()),
+    ) {
+        finish(event, &resize, mouse_event.client_x());
+    }
+}
+
+fn finish(event: &web_sys::Event, resize: &Resize, client_x: i32) {
+    RESIZING.with(|r| *r.borrow_mut() = None);
+    // a click, e.g. the first of a double click
+    if client_x == resize.start_x {
+        return;
+    }
+
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .filter(|h| h.get_attribute("be_id").as_deref() == Some(resize.element_id.as_str()))
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let width = dragged_width(resize, client_x) as usize;
+    grid_columns::set_width(&host, &resize.path, width);
+}
+
+fn on_double_click(event: &web_sys::Event) {
+    let (host, _, path) = match resizer_hit(event) {
+        Some(h) => h,
+        None => return,
+    };
+    event.prevent_default();
+    event.stop_immediate_propagation();
+
+    let visible = match host
+        .get_attribute("be_id")
+        .and_then(|id| grid_columns::visible_results(&id))
+    {
+        Some(v) => v,
+        None => return,
+    };
+    let rows: Vec<(usize, serde_json::Value)> = visible
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (visible.page_start_index + 1 + i, row.clone()))
+        .collect();
+    if let Some(width) = fit_column_width(&visible.schema.fields, &rows, &grid_layout::get(), &path)
+    {
+        grid_columns::set_width(&host, &path, width);
+    }
+}
@@ -0,0 +1,389 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{cell_value::CellValue, profile::is_numeric_type};
+
+/*
+Conditional formatting of the columns of the grid: rules set by the user per column (kept in
+the column layout), evaluated on the typed values of the loaded page into the inline style of
+each cell. Color scales and data bars are relative to the smallest and largest number of the
+page. The colors are the chart colors of the VS Code theme, mixed with the editor background so
+they stay readable in the light, dark and high contrast themes (and opaque in pinned cells).
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ThemeColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ThemeColor {
+    pub(crate) fn get_all() -> Vec<ThemeColor> {
+        vec![
+            ThemeColor::Red,
+            ThemeColor::Orange,
+            ThemeColor::Yellow,
+            ThemeColor::Green,
+            ThemeColor::Blue,
+            ThemeColor::Purple,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ThemeColor::Red => "red",
+            ThemeColor::Orange => "orange",
+            ThemeColor::Yellow => "yellow",
+            ThemeColor::Green => "green",
+            ThemeColor::Blue => "blue",
+            ThemeColor::Purple => "purple",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            ThemeColor::Red => "Red",
+            ThemeColor::Orange => "Orange",
+            ThemeColor::Yellow => "Yellow",
+            ThemeColor::Green => "Green",
+            ThemeColor::Blue => "Blue",
+            ThemeColor::Purple => "Purple",
+        }
+    }
+
+    /// The `charts.*` color of the theme, with the default of the dark theme.
+    pub(crate) fn css_var(&self) -> &'static str {
+        match self {
+            ThemeColor::Red => "var(--vscode-charts-red, #f14c4c)",
+            ThemeColor::Orange => "var(--vscode-charts-orange, #d18616)",
+            ThemeColor::Yellow => "var(--vscode-charts-yellow, #cca700)",
+            ThemeColor::Green => "var(--vscode-charts-green, #89d185)",
+            ThemeColor::Blue => "var(--vscode-charts-blue, #3794ff)",
+            ThemeColor::Purple => "var(--vscode-charts-purple, #b180d7)",
+        }
+    }
+
+    /// The color at `percent` over the editor background.
+    fn mixed(&self, percent: usize) -> String {
+        format!(
+            "color-mix(in srgb, {} {}%, var(--vscode-editor-background, #1e1e1e))",
+            self.css_var(),
+            percent
+        )
+    }
+}
+
+impl FromStr for ThemeColor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ThemeColor::get_all()
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    pub(crate) fn get_all() -> Vec<Comparison> {
+        vec![
+            Comparison::Lt,
+            Comparison::Le,
+            Comparison::Gt,
+            Comparison::Ge,
+            Comparison::Eq,
+            Comparison::Ne,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "lt",
+            Comparison::Le => "le",
+            Comparison::Gt => "gt",
+            Comparison::Ge => "ge",
+            Comparison::Eq => "eq",
+            Comparison::Ne => "ne",
+        }
+    }
+
+    pub(crate) fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "≤",
+            Comparison::Gt => ">",
+            Comparison::Ge => "≥",
+            Comparison::Eq => "=",
+            Comparison::Ne => "≠",
+        }
+    }
+
+    fn holds(&self, number: f64, value: f64) -> bool {
+        match self {
+            Comparison::Lt => number < value,
+            Comparison::Le => number <= value,
+            Comparison::Gt => number > value,
+            Comparison::Ge => number >= value,
+            Comparison::Eq => number == value,
+            Comparison::Ne => number != value,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Comparison::get_all()
+            .into_iter()
+            .find(|c| c.name() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(crate) enum FormatRule {
+    /// The background of the numbers comparing to `value` in `color`; the first matching
+    /// threshold of a column wins.
+    Threshold {
+        comparison: Comparison,
+        value: f64,
+        color: ThemeColor,
+    },
+    /// The background from faint at the smallest number of the page to `color` at the largest.
+    ColorScale { color: ThemeColor },
+    /// A bar as long as the number relative to the largest (absolute) one of the page.
+    DataBar { color: ThemeColor },
+    /// TRUE on green, FALSE on red.
+    Boolean,
+}
+
+impl FormatRule {
+    /// Whether the rule can format the values of `field`.
+    pub(crate) fn applies_to(&self, field: &TableFieldSchema) -> bool {
+        match self {
+            FormatRule::Boolean => is_boolean_type(&field.r#type),
+            _ => is_numeric_type(&field.r#type),
+        }
+    }
+
+    /// Short description, for the list of the rules of a column.
+    pub(crate) fn description(&self) -> String {
+        match self {
+            FormatRule::Threshold {
+                comparison,
+                value,
+                color,
+            } => format!(
+                "{} where value {} {}",
+                color.label(),
+                comparison.symbol(),
+                value
+            ),
+            FormatRule::ColorScale { color } => format!("{} color scale", color.label()),
+            FormatRule::DataBar { color } => format!("{} data bars", color.label()),
+            FormatRule::Boolean => "Green for TRUE, red for FALSE".to_string(),
+        }
+    }
+}
+
+pub(crate) fn is_boolean_type(field_type: &str) -> bool {
+    matches!(field_type, "BOOLEAN" | "BOOL")
+}
+
+/// Whether the values of `field` can be formatted, a column of the grid of its own.
+pub(crate) fn is_formattable(field: &TableFieldSchema, repeated: bool) -> bool {
+    !repeated && (is_numeric_type(&field.r#type) || is_boolean_type(&field.r#type))
+}
+
+/// The number of a numeric cell, `None` for the others and the non finite floats.
+pub(crate) fn number(value: &CellValue) -> Option<f64> {
+    match value {
+        CellValue::Int(i) => Some(*i as f64),
+        CellValue::Float(f) if f.is_finite() => Some(*f),
+        CellValue::Numeric(s) => s.parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+/// The inline style of each of `values` (a column of the page) by `rules`, `None` for the
+/// cells left alone.
+pub(crate) fn cell_styles(rules: &[FormatRule], values: &[CellValue]) -> Vec<Option<String>> {
+    let numbers: Vec<Option<f64>> = values.iter().map(number).collect();
+    let (min, max) = numbers
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), n| {
+            (min.min(*n), max.max(*n))
+        });
+    let largest = min.abs().max(max.abs());
+
+    values
+        .iter()
+        .zip(numbers)
+        .map(|(value, number)| {
+            let mut background = None;
+            let mut bar = None;
+            for rule in rules {
+                match (rule, number) {
+                    (
+                        FormatRule::Threshold {
+                            comparison,
+                            value,
+                            color,
+                        },
+                        Some(n),
+                    ) if background.is_none() && comparison.holds(n, *value) => {
+                        background = Some(color.mixed(35));
+                    }
+                    (FormatRule::ColorScale { color }, Some(n)) if background.is_none() => {
+                        let position = if max > min {
+                            (n - min) / (max - min)
+                        } else {
+                            1.0
+                        };
+                        background = Some(color.mixed(10 + (position * 50.0).round() as usize));
+                    }
+                    (FormatRule::DataBar { color }, Some(n)) if bar.is_none() && largest > 0.0 => {
+                        let width = (n.abs() / largest * 100.0).round() as usize;
+                        bar = Some(format!(
+                            "linear-gradient(90deg, {0} {1}%, transparent {1}%)",
+                            color.mixed(45),
+                            width
+                        ));
+                    }
+                    (FormatRule::Boolean, _) if background.is_none() => {
+                        background = match value {
+                            CellValue::Bool(true) => Some(ThemeColor::Green.mixed(30)),
+                            CellValue::Bool(false) => Some(ThemeColor::Red.mixed(30)),
+                            _ => None,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+
+            let style: String = [
+                background.map(|b| format!("background-color: {};", b)),
+                bar.map(|b| format!("background-image: {};", b)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<String>>()
+            .join(" ");
+            (!style.is_empty()).then_some(style)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Comparison, FormatRule, ThemeColor, cell_styles};
+    use crate::custom_elements::cell_value::CellValue;
+
+    #[test]
+    fn first_matching_threshold_wins() {
+        let rules = vec![
+            FormatRule::Threshold {
+                comparison: Comparison::Gt,
+                value: 100.0,
+                color: ThemeColor::Red,
+            },
+            FormatRule::Threshold {
+                comparison: Comparison::Gt,
+                value: 10.0,
+                color: ThemeColor::Yellow,
+            },
+        ];
+        let values = vec![
+            CellValue::Int(500),
+            CellValue::Numeric("50.5".to_string()),
+            CellValue::Float(1.0),
+            CellValue::Null,
+        ];
+        let styles = cell_styles(&rules, &values);
+        assert!(
+            styles[0]
+                .as_deref()
+                .unwrap()
+                .contains("--vscode-charts-red")
+        );
+        assert!(
+            styles[1]
+                .as_deref()
+                .unwrap()
+                .contains("--vscode-charts-yellow")
+        );
+        assert_eq!(styles[2], None);
+        assert_eq!(styles[3], None);
+    }
+
+    #[test]
+    fn scales_and_bars_relative_to_the_page() {
+        let rules = vec![
+            FormatRule::ColorScale {
+                color: ThemeColor::Green,
+            },
+            FormatRule::DataBar {
+                color: ThemeColor::Blue,
+            },
+        ];
+        let values = vec![CellValue::Int(0), CellValue::Int(5), CellValue::Int(-10)];
+        let styles: Vec<String> = cell_styles(&rules, &values).into_iter().flatten().collect();
+        assert!(styles[0].contains("--vscode-charts-green, #89d185) 43%"));
+        assert!(styles[0].contains("transparent 0%"));
+        assert!(styles[1].contains("#89d185) 60%"));
+        assert!(styles[1].contains("transparent 50%"));
+        assert!(styles[2].contains("#89d185) 10%"));
+        assert!(styles[2].contains("transparent 100%"));
+    }
+
+    #[test]
+    fn booleans_green_and_red() {
+        let values = vec![
+            CellValue::Bool(true),
+            CellValue::Bool(false),
+            CellValue::Null,
+        ];
+        let styles = cell_styles(&[FormatRule::Boolean], &values);
+        assert!(
+            styles[0]
+                .as_deref()
+                .unwrap()
+                .contains("--vscode-charts-green")
+        );
+        assert!(
+            styles[1]
+                .as_deref()
+                .unwrap()
+                .contains("--vscode-charts-red")
+        );
+        assert_eq!(styles[2], None);
+
+        let rule = serde_json::to_string(&FormatRule::DataBar {
+            color: ThemeColor::Blue,
+        })
+        .unwrap();
+        assert_eq!(rule, r#"{"kind":"data_bar","color":"blue"}"#);
+    }
+}
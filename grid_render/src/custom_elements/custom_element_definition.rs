@@ -3,4 +3,15 @@ pub trait CustomElementDefinition {
     Function that will run when the custom component appears in the html
     */
     fn define(document: &web_sys::Document, element: &web_sys::Element);
+
+    /**
+    Attributes that trigger a new render when changed on an element written directly in the html
+    */
+    fn observed_attributes() -> Vec<&'static str>;
+
+    /**
+    Function that will run when an element written directly in the html is connected,
+    or when one of its observed attributes changes
+    */
+    fn render_declarative(element: &web_sys::Element);
 }
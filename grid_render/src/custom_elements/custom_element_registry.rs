@@ -0,0 +1,169 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use wasm_bindgen::JsValue;
+use web_sys::Element;
+
+use super::{
+    CustomElement,
+    bq_common_custom_element::{remove_attribute, set_attribute},
+};
+
+/// Marks an element written directly in the html (not created by `BaseElement`),
+/// which renders itself from its own attributes.
+pub(crate) const DECLARATIVE_ATT: &str = "declarative";
+/// Same marker the controls use to avoid binding event listeners twice.
+const LISTENERS_BOUND_ATT: &str = "bee";
+/// Shared by `bq-table` and `bq-query`: a new source always starts on the first page.
+const PAGE_START_INDEX_ATT: &str = "page_start_index";
+
+static NEXT_ELEMENT_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    // `attributeChangedCallback` runs synchronously inside `setAttribute`, so the defaults
+    // written by `render_declarative` would otherwise start a second, nested render.
+    static RENDERING: Cell<bool> = const { Cell::new(false) };
+}
+
+/*
+A class extending `HTMLElement` cannot be declared from Rust, and building one from a string of
+JS (`new Function`) breaks under a content security policy without `'unsafe-eval'`. The host
+page (`grid.js`, `bqnbRenderer.ts`) declares it instead, for every tag of
+`get_web_components_list`, and forwards its lifecycle callbacks to `connected` and
+`attribute_changed`:
+
+    customElements.define(tag, class extends HTMLElement {
+        static get observedAttributes() { return get_observed_attributes(tag); }
+        connectedCallback() { on_custom_element_connected(tag, this); }
+        attributeChangedCallback(name, oldValue, newValue) {
+            on_custom_element_attribute_changed(tag, this, name, oldValue, newValue);
+        }
+    });
+*/
+
+/// The attributes of `tag_name` whose changes render the element again, `None` when it is not
+/// the tag of a `CustomElement`.
+pub(crate) fn observed_attributes(tag_name: &str) -> Option<Vec<&'static str>> {
+    CustomElement::from_str(tag_name).map(|c| c.observed_attributes())
+}
+
+/// `connectedCallback` of the element `tag_name`.
+pub(crate) fn connected(tag_name: &str, element: &Element) {
+    match CustomElement::from_str(tag_name) {
+        Some(custom_element) => on_connected(custom_element, element),
+        None => unknown_tag("connected", tag_name),
+    }
+}
+
+/// `attributeChangedCallback` of the element `tag_name`.
+pub(crate) fn attribute_changed(
+    tag_name: &str,
+    element: &Element,
+    name: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) {
+    match CustomElement::from_str(tag_name) {
+        Some(custom_element) => {
+            on_attribute_changed(custom_element, element, name, old_value, new_value)
+        }
+        None => unknown_tag("attribute_changed", tag_name),
+    }
+}
+
+fn unknown_tag(callback: &str, tag_name: &str) {
+    web_sys::console::error_1(&JsValue::from_str(&format!(
+        "{}: unknown custom element '{}'",
+        callback, tag_name
+    )));
+}
+
+fn on_connected(custom_element: CustomElement, element: &Element) {
+    // Elements created through `BaseElement` already carry a `be_id` when they are appended
+    // and are rendered by their owner; only markup without one renders itself.
+    if !element.has_attribute("be_id") {
+        let element_id = if element.id().is_empty() {
+            format!(
+                "{}_{}",
+                custom_element,
+                NEXT_ELEMENT_ID.fetch_add(1, Ordering::Relaxed)
+            )
+        } else {
+            element.id()
+        };
+        set_attribute(element, "be_id", &element_id);
+        set_attribute(element, DECLARATIVE_ATT, "1");
+    }
+
+    if !element.has_attribute(LISTENERS_BOUND_ATT) {
+        if let Err(e) = custom_element.define_custom_component(element) {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "on_connected: failed to configure component '{}': {:?}",
+                custom_element, e
+            )));
+            return;
+        }
+        set_attribute(element, LISTENERS_BOUND_ATT, "1");
+    }
+
+    if element.has_attribute(DECLARATIVE_ATT) {
+        render_declarative(custom_element, element);
+    }
+}
+
+fn on_attribute_changed(
+    custom_element: CustomElement,
+    element: &Element,
+    _name: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) {
+    // The render itself writes every attribute back with the same value: ignore those,
+    // and anything arriving before `connectedCallback` (the parser sets attributes first).
+    if old_value == new_value
+        || RENDERING.with(|r| r.get())
+        || !element.has_attribute(DECLARATIVE_ATT)
+        || !element.is_connected()
+    {
+        return;
+    }
+
+    remove_attribute(element, PAGE_START_INDEX_ATT);
+    render_declarative(custom_element, element);
+}
+
+fn render_declarative(custom_element: CustomElement, element: &Element) {
+    RENDERING.with(|r| r.set(true));
+    custom_element.render_declarative(element);
+    RENDERING.with(|r| r.set(false));
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use crate::custom_elements::CustomElement;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn every_tag_has_its_observed_attributes() {
+        for custom_element in CustomElement::get_all() {
+            assert!(super::observed_attributes(&custom_element.to_string()).is_some());
+        }
+        assert!(super::observed_attributes("bq-unknown").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn created_elements_are_not_declarative() {
+        let parent_node = &crate::createElement("div");
+        let element = &crate::createElement("bq-table");
+        element.set_attribute("be_id", "bq_table_1").unwrap();
+        parent_node.append_child(element).unwrap();
+        super::connected("bq-table", element);
+
+        assert!(!element.has_attribute(super::DECLARATIVE_ATT));
+    }
+}
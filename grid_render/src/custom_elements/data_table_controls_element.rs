@@ -1,10 +1,25 @@
 use crate::bigquery::{base::TableReference, jobs::JobReference};
 
-use super::{base_element::BaseElement, base_element_trait::BaseElementTrait};
+use super::{
+    base_element::BaseElement,
+    base_element_trait::BaseElementTrait,
+    bq_chart_custom_element,
+    export::{ExportFormat, NestedMode, export},
+    export_all,
+    grid_columns::{self, COLUMN_CHOOSER},
+    grid_find::{self, FIND_BAR},
+    grid_pivot,
+    grid_selection::{self, SELECTION_MENU},
+    loaded_results,
+    overlay_panel::{self, OVERLAY_PANEL},
+};
+use crate::utils::download_text;
+use std::str::FromStr;
 use serde_json::json;
 use wasm_bindgen::{JsCast, closure::Closure};
 use web_sys::Element;
 
+const CONTROLS: &str = "controls";
 const PAGING: &str = "paging";
 const BTN_FIRST_PAGE: &str = "btn_first_page";
 const BTN_PREVIOUS_PAGE: &str = "btn_prev_page";
@@ -13,6 +28,15 @@ const BTN_LAST_PAGE: &str = "btn_last_page";
 const BTN_DOWNLOAD_CSV: &str = "btn_download_csv";
 const BTN_DOWNLOAD_JSONL: &str = "btn_download_json";
 const BTN_SEND_PUBSUB: &str = "btn_send_pubsub";
+const BTN_CHART: &str = "btn_chart";
+const BTN_PIVOT: &str = "btn_pivot";
+const BTN_COLUMNS: &str = "btn_columns";
+const SELECT_EXPORT_FORMAT: &str = "export_format";
+const SELECT_EXPORT_NESTED: &str = "export_nested";
+const BTN_EXPORT: &str = "btn_export";
+const BTN_EXPORT_ALL: &str = "btn_export_all";
+const EXPORT_PROGRESS: &str = "export_progress";
+const BTN_EXPORT_CANCEL: &str = "btn_export_cancel";
 
 pub(crate) const EVENT_GO_TO_FIRST_PAGE: &str = "go_to_first_page";
 pub(crate) const EVENT_GO_TO_PREVIOUS_PAGE: &str = "go_to_previous_page";
@@ -55,7 +79,7 @@ impl BaseElementTrait for DataTableControls {
 
     fn render(&self, parent_node: &web_sys::Node) -> BaseElement {
         BaseElement::new_and_append(parent_node, "div", &self.get_element_id())
-            .append_child("div", "controls")
+            .append_child_fn("div", CONTROLS, &modify_controls, self)
             .append_child_fn("span", PAGING, &modify_controls, self)
             .append_sibling_fn("button", BTN_FIRST_PAGE, &modify_controls, self)
             .append_sibling_fn("button", BTN_PREVIOUS_PAGE, &modify_controls, self)
@@ -63,7 +87,20 @@ impl BaseElementTrait for DataTableControls {
             .append_sibling_fn("button", BTN_LAST_PAGE, &modify_controls, self)
             .append_sibling_fn("button", BTN_DOWNLOAD_CSV, &modify_controls, self)
             .append_sibling_fn("button", BTN_DOWNLOAD_JSONL, &modify_controls, self)
+            .append_sibling_fn("select", SELECT_EXPORT_FORMAT, &modify_controls, self)
+            .append_sibling_fn("select", SELECT_EXPORT_NESTED, &modify_controls, self)
+            .append_sibling_fn("button", BTN_EXPORT, &modify_controls, self)
+            .append_sibling_fn("button", BTN_EXPORT_ALL, &modify_controls, self)
+            .append_sibling_fn("span", EXPORT_PROGRESS, &modify_controls, self)
+            .append_sibling_fn("button", BTN_EXPORT_CANCEL, &modify_controls, self)
             .append_sibling_fn("button", BTN_SEND_PUBSUB, &modify_controls, self)
+            .append_sibling_fn("button", BTN_CHART, &modify_controls, self)
+            .append_sibling_fn("button", BTN_PIVOT, &modify_controls, self)
+            .append_sibling_fn("div", FIND_BAR, &modify_controls, self)
+            .append_sibling_fn("button", BTN_COLUMNS, &modify_controls, self)
+            .append_sibling_fn("div", COLUMN_CHOOSER, &modify_controls, self)
+            .append_sibling_fn("div", SELECTION_MENU, &modify_controls, self)
+            .append_sibling_fn("div", OVERLAY_PANEL, &modify_controls, self)
     }
 }
 
@@ -73,7 +110,14 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         None => return,
     };
     match id {
+        CONTROLS => {
+            let element = &base_element.element();
+            let _ = element.set_attribute("role", "toolbar");
+            let _ = element.set_attribute("aria-label", "Results");
+        }
         PAGING => {
+            let _ = base_element.element().set_attribute("role", "status");
+            let _ = base_element.element().set_attribute("aria-live", "polite");
             if settings.rows_in_page.is_some()
                 && settings.rows_total.is_some()
                 && settings.page_start_index.is_some()
@@ -101,8 +145,10 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         BTN_FIRST_PAGE => {
             let element = &base_element.element();
             add_event_listener(element, EVENT_GO_TO_FIRST_PAGE);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M2 2h1.5v12H2V2zm4.5 6L13 2v12L6.5 8z"/></svg> First page"#);
-            let _ = element.set_attribute("title", "First page");
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M2 2h1.5v12H2V2zm4.5 6L13 2v12L6.5 8z"/></svg> First page"#);
+            let _ = element.set_attribute("title", "First page (Alt+Home)");
+            let _ = element.set_attribute("aria-label", "First page");
+            let _ = element.set_attribute("aria-keyshortcuts", "Alt+Home");
             if settings.page_start_index.unwrap_or(0) == 0 {
                 let _ = element.set_attribute("disabled", "disabled");
             } else {
@@ -112,8 +158,10 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         BTN_PREVIOUS_PAGE => {
             let element = &base_element.element();
             add_event_listener(element, EVENT_GO_TO_PREVIOUS_PAGE);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M10.5 2L4 8l6.5 6V2z"/></svg> Previous page"#);
-            let _ = element.set_attribute("title", "Previous page");
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M10.5 2L4 8l6.5 6V2z"/></svg> Previous page"#);
+            let _ = element.set_attribute("title", "Previous page (Alt+PageUp)");
+            let _ = element.set_attribute("aria-label", "Previous page");
+            let _ = element.set_attribute("aria-keyshortcuts", "Alt+PageUp");
             if settings.page_start_index.unwrap_or(0) == 0 {
                 let _ = element.set_attribute("disabled", "disabled");
             } else {
@@ -123,8 +171,10 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         BTN_NEXT_PAGE => {
             let element = &base_element.element();
             add_event_listener(element, EVENT_GO_TO_NEXT_PAGE);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M5.5 2L12 8l-6.5 6V2z"/></svg> Next page"#);
-            let _ = element.set_attribute("title", "Next page");
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M5.5 2L12 8l-6.5 6V2z"/></svg> Next page"#);
+            let _ = element.set_attribute("title", "Next page (Alt+PageDown)");
+            let _ = element.set_attribute("aria-label", "Next page");
+            let _ = element.set_attribute("aria-keyshortcuts", "Alt+PageDown");
 
             let start_index = settings.page_start_index.unwrap_or(0);
             let page_size = settings.rows_in_page.unwrap_or(0);
@@ -139,8 +189,10 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         BTN_LAST_PAGE => {
             let element = &base_element.element();
             add_event_listener(element, EVENT_GO_TO_LAST_PAGE);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M12.5 2h1.5v12h-1.5V2zM3 2l6.5 6L3 14V2z"/></svg> Last page"#);
-            let _ = element.set_attribute("title", "Last page");
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="14" height="14" viewBox="0 0 16 16" fill="currentColor"><path d="M12.5 2h1.5v12h-1.5V2zM3 2l6.5 6L3 14V2z"/></svg> Last page"#);
+            let _ = element.set_attribute("title", "Last page (Alt+End)");
+            let _ = element.set_attribute("aria-label", "Last page");
+            let _ = element.set_attribute("aria-keyshortcuts", "Alt+End");
 
             let start_index = settings.page_start_index.unwrap_or(0);
             let page_size = settings.rows_in_page.unwrap_or(0);
@@ -155,22 +207,109 @@ fn modify_controls(base_element: &BaseElement, settings: &DataTableControls) {
         BTN_DOWNLOAD_CSV => {
             let element = &base_element.element();
             add_event_listener_command(element, BTN_DOWNLOAD_CSV, settings);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> CSV"#);
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> CSV"#);
+            let _ = element.set_attribute("title", "Download all rows as CSV");
+            let _ = element.set_attribute("aria-label", "Download all rows as CSV");
         }
         BTN_DOWNLOAD_JSONL => {
             let element = &base_element.element();
             add_event_listener_command(element, BTN_DOWNLOAD_JSONL, settings);
-            element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> JSONL"#);
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> JSONL"#);
+            let _ = element.set_attribute("title", "Download all rows as JSONL");
+            let _ = element.set_attribute("aria-label", "Download all rows as JSONL");
+        }
+        SELECT_EXPORT_FORMAT => {
+            let element = &base_element.element();
+            // options only once, so the selection survives a page change
+            if element.child_element_count() == 0 {
+                let options: Vec<String> = ExportFormat::get_all()
+                    .iter()
+                    .map(|f| format!(r#"<option value="{}">{}</option>"#, f.name(), f.label()))
+                    .collect();
+                element.set_inner_html(&options.join(""));
+            }
+            let _ = element.set_attribute("title", "Export format");
+            let _ = element.set_attribute("aria-label", "Export format");
+        }
+        SELECT_EXPORT_NESTED => {
+            let element = &base_element.element();
+            if element.child_element_count() == 0 {
+                element.set_inner_html(
+                    r#"<option value="json">Nested as JSON</option><option value="flatten">Flatten records</option>"#,
+                );
+            }
+            let _ = element.set_attribute("title", "How RECORD and REPEATED fields are exported");
+            let _ = element.set_attribute("aria-label", "Nested fields");
+        }
+        BTN_EXPORT => {
+            let element = &base_element.element();
+            add_event_listener_export(element);
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> Export page"#);
+            let _ = element.set_attribute("title", "Export the rows of this page");
+            let _ = element.set_attribute("aria-label", "Export the rows of this page");
+        }
+        BTN_EXPORT_ALL => {
+            let element = &base_element.element();
+            add_event_listener_export(element);
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M7.25 1v8.19L4.28 6.22 3.22 7.28 8 12.06l4.78-4.78-1.06-1.06-2.97 2.97V1h-1.5zM2 14h12v1.5H2V14z"/></svg> Export all"#);
+            let _ = element.set_attribute("title", "Export every row, fetching all the pages");
+            let _ = element.set_attribute("aria-label", "Export all rows");
+        }
+        EXPORT_PROGRESS => {
+            let element = &base_element.element();
+            // left alone on a page change, an export of all the pages may be running
+            if element.get_attribute("title").is_none() {
+                let _ = element.set_attribute("title", "Rows exported");
+                let _ = element.set_attribute("role", "status");
+                let _ = element.set_attribute("aria-live", "polite");
+                let _ = element.set_attribute("style", "display: none;");
+            }
+        }
+        BTN_EXPORT_CANCEL => {
+            let element = &base_element.element();
+            if element.get_attribute("bee").is_none() {
+                add_event_listener_export(element);
+                let _ = element.set_attribute("style", "display: none;");
+            }
+            element.set_inner_html("Cancel");
+            let _ = element.set_attribute("title", "Stop the export");
+            let _ = element.set_attribute("aria-label", "Cancel the export");
         }
         BTN_SEND_PUBSUB => {
             let element = &base_element.element();
             if settings.job_reference.is_some() {
                 add_event_listener_command(element, BTN_SEND_PUBSUB, settings);
-                element.set_inner_html(r#"<svg xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M1 1l14 7-14 7V9.5l10-1.5-10-1.5V1z"/></svg> Pub/Sub"#);
+                element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M1 1l14 7-14 7V9.5l10-1.5-10-1.5V1z"/></svg> Pub/Sub"#);
+                let _ = element.set_attribute("title", "Send the rows to Pub/Sub");
+                let _ = element.set_attribute("aria-label", "Send the rows to Pub/Sub");
             } else {
                 let _ = element.set_attribute("style", "display: none;");
             }
         }
+        BTN_CHART => {
+            let element = &base_element.element();
+            // charts are drawn from the results of a query
+            if settings.job_reference.is_some() {
+                add_event_listener_chart(element);
+                element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M1 14h14v1.5H1V14zm1-5h2.5v4H2V9zm4-4h2.5v8H6V5zm4 2h2.5v6H10V7z"/></svg> Chart"#);
+                let _ = element.set_attribute("title", "Show the rows of this page as a chart");
+                let _ = element.set_attribute("aria-label", "Show the rows of this page as a chart");
+            } else {
+                let _ = element.set_attribute("style", "display: none;");
+            }
+        }
+        BTN_PIVOT => {
+            let element = &base_element.element();
+            add_event_listener_pivot(element);
+            element.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M1 1h14v14H1V1zm1.5 1.5v2.5h2.5V2.5H2.5zm4 0v2.5h7V2.5h-7zm-4 4v7h2.5v-7H2.5zm4 0v7h7v-7h-7z"/></svg> Pivot"#);
+            let _ = element.set_attribute("title", "Cross-tabulate the rows of this page");
+            let _ = element.set_attribute("aria-label", "Pivot the rows of this page");
+        }
+        FIND_BAR => grid_find::init_bar(&base_element.element()),
+        BTN_COLUMNS => grid_columns::init_button(&base_element.element()),
+        COLUMN_CHOOSER => grid_columns::init_chooser(&base_element.element()),
+        SELECTION_MENU => grid_selection::init_menu(&base_element.element()),
+        OVERLAY_PANEL => overlay_panel::init(&base_element.element()),
         _ => {}
     }
 }
@@ -191,6 +330,205 @@ fn add_event_listener(element: &Element, _event_type: &str) {
     }
 }
 
+fn add_event_listener_export(element: &Element) {
+    if element.get_attribute("bee").is_none() {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(on_export_click) as Box<dyn Fn(&web_sys::Event)>);
+
+        let _ = element.add_event_listener_with_callback(
+            "click",
+            on_event_type_closure.as_ref().unchecked_ref(),
+        );
+
+        let _ = element.set_attribute("bee", "1");
+
+        on_event_type_closure.forget();
+    }
+}
+
+fn add_event_listener_chart(element: &Element) {
+    if element.get_attribute("bee").is_none() {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(on_chart_click) as Box<dyn Fn(&web_sys::Event)>);
+
+        let _ = element.add_event_listener_with_callback(
+            "click",
+            on_event_type_closure.as_ref().unchecked_ref(),
+        );
+
+        let _ = element.set_attribute("bee", "1");
+
+        on_event_type_closure.forget();
+    }
+}
+
+fn add_event_listener_pivot(element: &Element) {
+    if element.get_attribute("bee").is_none() {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(on_pivot_click) as Box<dyn Fn(&web_sys::Event)>);
+
+        let _ = element.add_event_listener_with_callback(
+            "click",
+            on_event_type_closure.as_ref().unchecked_ref(),
+        );
+
+        let _ = element.set_attribute("bee", "1");
+
+        on_event_type_closure.forget();
+    }
+}
+
+fn on_pivot_click(event: &web_sys::Event) {
+    if let Some(host) = event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|b| b.get_root_node().dyn_into::<web_sys::ShadowRoot>().ok())
+        .map(|s| s.host())
+    {
+        grid_pivot::open(&host);
+    }
+}
+
+fn on_chart_click(event: &web_sys::Event) {
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|b| b.get_root_node().dyn_into::<web_sys::ShadowRoot>().ok())
+        .map(|s| s.host())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    bq_chart_custom_element::show_chart(&host);
+}
+
+fn on_export_click(event: &web_sys::Event) {
+    let button = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    let shadow_root = match button.get_root_node().dyn_into::<web_sys::ShadowRoot>() {
+        Ok(s) => s,
+        Err(_) => {
+            web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
+                "on_export_click: button is not inside a shadow root",
+            ));
+            return;
+        }
+    };
+
+    match BaseElement::from_element(&button).id().as_deref() {
+        Some(BTN_EXPORT_ALL) => {
+            let (format, nested) = selected_export_options(&shadow_root);
+            export_all::start(shadow_root.host(), format, nested);
+            return;
+        }
+        Some(BTN_EXPORT_CANCEL) => {
+            export_all::cancel(&shadow_root.host());
+            return;
+        }
+        _ => {}
+    }
+
+    let (format, nested) = selected_export_options(&shadow_root);
+    let element_id = shadow_root.host().get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) => r,
+        None => {
+            web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "on_export_click: no rows loaded for '{}'",
+                element_id
+            )));
+            return;
+        }
+    };
+
+    let content = export(
+        &loaded_results.schema.fields,
+        &loaded_results.rows,
+        format,
+        nested,
+    );
+    let file_name = format!(
+        "{}_{}-{}.{}",
+        loaded_results.name,
+        loaded_results.page_start_index + 1,
+        loaded_results.page_start_index + loaded_results.rows.len(),
+        format.name()
+    );
+
+    if let Err(e) = download_text(&file_name, &content, format.mime_type()) {
+        web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+            "on_export_click: download failed: {:?}",
+            e
+        )));
+    }
+}
+
+fn selected_export_options(shadow_root: &web_sys::ShadowRoot) -> (ExportFormat, NestedMode) {
+    let selected = |select_id: &str| {
+        shadow_root
+            .query_selector(&format!("[be_id='{}']", select_id))
+            .ok()
+            .flatten()
+            .and_then(|e| e.dyn_into::<web_sys::HtmlSelectElement>().ok())
+            .map(|s| s.value())
+            .unwrap_or_default()
+    };
+    let format = ExportFormat::from_str(&selected(SELECT_EXPORT_FORMAT)).unwrap_or(ExportFormat::Csv);
+    let nested = NestedMode::from_str(&selected(SELECT_EXPORT_NESTED)).unwrap_or(NestedMode::Json);
+
+    (format, nested)
+}
+
+/// Shows `rows done / rows total` and the cancel button of an export of all the pages,
+/// `None` hides them once the export is over.
+pub(crate) fn show_export_progress(host: &Element, progress: Option<(usize, Option<usize>)>) {
+    let shadow_root = match host.shadow_root() {
+        Some(s) => s,
+        None => return,
+    };
+    let find = |id: &str| {
+        shadow_root
+            .query_selector(&format!("[be_id='{}']", id))
+            .ok()
+            .flatten()
+    };
+
+    if let Some(element) = find(EXPORT_PROGRESS) {
+        match progress {
+            Some((rows_done, rows_total)) => {
+                let rows_total = rows_total
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "?".to_string());
+                element.set_text_content(Some(&format!("{} / {} rows", rows_done, rows_total)));
+                let _ = element.remove_attribute("style");
+            }
+            None => {
+                element.set_text_content(None);
+                let _ = element.set_attribute("style", "display: none;");
+            }
+        }
+    }
+    if let Some(element) = find(BTN_EXPORT_CANCEL) {
+        if progress.is_some() {
+            let _ = element.remove_attribute("style");
+        } else {
+            let _ = element.set_attribute("style", "display: none;");
+        }
+    }
+    if let Some(element) = find(BTN_EXPORT_ALL) {
+        if progress.is_some() {
+            let _ = element.set_attribute("disabled", "disabled");
+        } else {
+            let _ = element.remove_attribute("disabled");
+        }
+    }
+}
+
 fn add_event_listener_command(
     element: &Element,
     button_name: &str,
@@ -266,12 +604,41 @@ fn on_click(event: &web_sys::Event) {
         }
     };
 
+    dispatch_page_event(&element);
+}
+
+/// Keyboard paging (`Alt+PageDown`, ...): same as a click on the paging button of `event_type`,
+/// nothing if that button is disabled.
+pub(crate) fn go_to_page(host: &Element, event_type: &str) {
+    let button_id = match event_type {
+        EVENT_GO_TO_FIRST_PAGE => BTN_FIRST_PAGE,
+        EVENT_GO_TO_PREVIOUS_PAGE => BTN_PREVIOUS_PAGE,
+        EVENT_GO_TO_NEXT_PAGE => BTN_NEXT_PAGE,
+        EVENT_GO_TO_LAST_PAGE => BTN_LAST_PAGE,
+        _ => return,
+    };
+    let button = match host
+        .shadow_root()
+        .and_then(|s| s.query_selector(&format!("[be_id='{}']", button_id)).ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    if button.has_attribute("disabled") {
+        return;
+    }
+
+    dispatch_page_event(&button);
+}
+
+/// Replaces the grid with a loading placeholder and dispatches the `go_to_*` event of a paging button.
+fn dispatch_page_event(element: &Element) {
     let custom_event_init = web_sys::CustomEventInit::new();
     custom_event_init.set_bubbles(true);
     custom_event_init.set_cancelable(true);
     custom_event_init.set_composed(true);
 
-    let base_element = BaseElement::from_element(&element);
+    let base_element = BaseElement::from_element(element);
     let type_ = match base_element.id().as_deref() {
         Some(BTN_FIRST_PAGE) => EVENT_GO_TO_FIRST_PAGE,
         Some(BTN_PREVIOUS_PAGE) => EVENT_GO_TO_PREVIOUS_PAGE,
@@ -279,7 +646,7 @@ fn on_click(event: &web_sys::Event) {
         Some(BTN_LAST_PAGE) => EVENT_GO_TO_LAST_PAGE,
         other => {
             web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&format!(
-                "on_click: unknown button id '{:?}'",
+                "dispatch_page_event: unknown button id '{:?}'",
                 other
             )));
             return;
@@ -288,7 +655,10 @@ fn on_click(event: &web_sys::Event) {
 
     if let Ok(Some(controls)) = element.closest(":host > [be_id=\"controls-background\"]") {
         if let Some(shadow) = controls.parent_node() {
-            if let Some(bstruct_table) = shadow.last_child() {
+            // the table, not a base element such as the details of `bq-table`
+            if let Some(bstruct_table) = shadow.last_child().filter(|c| {
+                c.dyn_ref::<Element>().is_some_and(|e| !e.has_attribute("be_id"))
+            }) {
                 let _ = shadow.remove_child(&bstruct_table);
 
                 let loading_div = &crate::createElement("div");
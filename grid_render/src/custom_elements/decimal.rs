@@ -0,0 +1,264 @@
+use std::cmp::Ordering;
+
+/*
+Exact decimal numbers for the NUMERIC and BIGNUMERIC values (up to 77 digits, 38 of them after
+the point) the REST API returns as text: added up, compared and divided by a count without the
+rounding of `f64`.
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Decimal {
+    negative: bool,
+    /// Digits of the unscaled value, least significant first, no leading zero (none for 0).
+    digits: Vec<u8>,
+    /// Digits after the decimal point.
+    scale: usize,
+}
+
+impl Decimal {
+    pub(crate) fn zero() -> Decimal {
+        Decimal {
+            negative: false,
+            digits: vec![],
+            scale: 0,
+        }
+    }
+
+    /// `-12.340`, `+5`, `.5`; `None` for any other text (e.g. an exponent).
+    pub(crate) fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim();
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if (integer.is_empty() && fraction.is_empty())
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+
+        let digits = integer
+            .bytes()
+            .chain(fraction.bytes())
+            .rev()
+            .map(|b| b - b'0')
+            .collect();
+        Some(
+            Decimal {
+                negative,
+                digits,
+                scale: fraction.len(),
+            }
+            .normalized(),
+        )
+    }
+
+    fn normalized(mut self) -> Decimal {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        if self.digits.is_empty() {
+            self.negative = false;
+        }
+        self
+    }
+
+    /// Digits of the unscaled value with `scale` (not below the own one) digits after the point.
+    fn digits_at(&self, scale: usize) -> Vec<u8> {
+        let mut digits = vec![0; scale - self.scale];
+        digits.extend(&self.digits);
+        digits
+    }
+
+    pub(crate) fn add(&self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        let (a, b) = (self.digits_at(scale), other.digits_at(scale));
+        let (negative, digits) = if self.negative == other.negative {
+            (self.negative, add_digits(&a, &b))
+        } else {
+            match cmp_digits(&a, &b) {
+                Ordering::Greater => (self.negative, sub_digits(&a, &b)),
+                Ordering::Less => (other.negative, sub_digits(&b, &a)),
+                Ordering::Equal => (false, vec![]),
+            }
+        };
+        Decimal {
+            negative,
+            digits,
+            scale,
+        }
+        .normalized()
+    }
+
+    /// Divided by `divisor` (not 0), rounded half away from zero to `scale` digits after the
+    /// point (at least the own ones), like BigQuery does.
+    pub(crate) fn div_round(&self, divisor: usize, scale: usize) -> Decimal {
+        let scale = scale.max(self.scale);
+        // one more digit to round on
+        let dividend = self.digits_at(scale + 1);
+
+        let mut quotient = Vec::with_capacity(dividend.len());
+        let mut remainder: u128 = 0;
+        for digit in dividend.iter().rev() {
+            remainder = remainder * 10 + *digit as u128;
+            quotient.push((remainder / divisor as u128) as u8);
+            remainder %= divisor as u128;
+        }
+        quotient.reverse();
+
+        let round_up = quotient.first().is_some_and(|d| *d >= 5);
+        let mut digits: Vec<u8> = quotient.into_iter().skip(1).collect();
+        if round_up {
+            digits = add_digits(&digits, &[1]);
+        }
+        Decimal {
+            negative: self.negative,
+            digits,
+            scale,
+        }
+        .normalized()
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (negative, _) => {
+                let scale = self.scale.max(other.scale);
+                let ordering = cmp_digits(&self.digits_at(scale), &other.digits_at(scale));
+                if negative {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The number without the trailing zeros of its fraction, e.g. `-0.5`, `12`.
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut digits: Vec<char> = self
+            .digits
+            .iter()
+            .rev()
+            .map(|d| (b'0' + d) as char)
+            .collect();
+        if digits.len() <= self.scale {
+            let mut padded = vec!['0'; self.scale + 1 - digits.len()];
+            padded.extend(digits);
+            digits = padded;
+        }
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale);
+        let integer: String = integer.iter().collect();
+        let fraction: String = fraction.iter().collect();
+        let fraction = fraction.trim_end_matches('0');
+
+        let sign = if self.negative { "-" } else { "" };
+        match fraction.is_empty() {
+            true => write!(f, "{}{}", sign, integer),
+            false => write!(f, "{}{}.{}", sign, integer, fraction),
+        }
+    }
+}
+
+fn add_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut digits = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = a.get(i).copied().unwrap_or(0) + b.get(i).copied().unwrap_or(0) + carry;
+        digits.push(sum % 10);
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        digits.push(carry);
+    }
+    digits
+}
+
+/// `a - b`, `a` not below `b`.
+fn sub_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut digits = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, digit) in a.iter().enumerate() {
+        let subtracted = b.get(i).copied().unwrap_or(0) + borrow;
+        if *digit >= subtracted {
+            digits.push(digit - subtracted);
+            borrow = 0;
+        } else {
+            digits.push(digit + 10 - subtracted);
+            borrow = 1;
+        }
+    }
+    digits
+}
+
+fn cmp_digits(a: &[u8], b: &[u8]) -> Ordering {
+    let significant = |d: &[u8]| d.iter().rposition(|digit| *digit != 0).map_or(0, |p| p + 1);
+    let (a, b) = (&a[..significant(a)], &b[..significant(b)]);
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimal;
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn sums_are_exact() {
+        // 0.1 + 0.2 is 0.30000000000000004 in f64
+        assert_eq!(decimal("0.1").add(&decimal("0.2")).to_string(), "0.3");
+        assert_eq!(decimal("-12.5").add(&decimal("2.25")).to_string(), "-10.25");
+        assert_eq!(decimal("1.10").add(&decimal("-1.1")).to_string(), "0");
+
+        let big = "99999999999999999999999999999999999999.99999999999999999999999999999999999999";
+        assert_eq!(
+            decimal(big)
+                .add(&decimal("0.00000000000000000000000000000000000001"))
+                .to_string(),
+            "100000000000000000000000000000000000000"
+        );
+        assert!(Decimal::parse("1e5").is_none());
+        assert!(Decimal::parse("").is_none());
+    }
+
+    #[test]
+    fn averages_round_half_away_from_zero() {
+        assert_eq!(decimal("2").div_round(3, 9).to_string(), "0.666666667");
+        assert_eq!(decimal("-2").div_round(3, 9).to_string(), "-0.666666667");
+        assert_eq!(decimal("10").div_round(4, 0).to_string(), "3");
+        assert_eq!(
+            decimal("0.000000001").div_round(2, 9).to_string(),
+            "0.000000001"
+        );
+    }
+
+    #[test]
+    fn compares() {
+        assert!(decimal("-3") < decimal("-2.5"));
+        assert!(decimal("10.01") > decimal("10.001"));
+        assert_eq!(
+            decimal("1.50").cmp(&decimal("1.5")),
+            std::cmp::Ordering::Equal
+        );
+        assert_eq!(Decimal::zero().to_string(), "0");
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::{HashMap, VecDeque};
+
+use website_component_table::{
+    TableBuilder, TableColumn, TableColumnDefinition, TableRow, TableValue,
+};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::CellValue,
+    export::{NestedMode, to_columns, to_records},
+    to_table_builder::{patch_column_widths, vscode_main_style},
+};
+
+/*
+Difference between two results (e.g. of a query before and after a refactoring): the columns
+added, removed or retyped between the two schemas, and the rows added, removed and changed
+between the two sets of rows. The rows are aligned by the values of key columns, or by position
+without any key; the values are compared column by column over the columns both sides have,
+as the grid shows them (non repeated RECORDs expanded), so a column retyped from INTEGER to
+NUMERIC does not change every row.
+*/
+
+/// A difference between the leaf columns of two schemas.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SchemaChange {
+    Added {
+        column: String,
+        r#type: String,
+    },
+    Removed {
+        column: String,
+        r#type: String,
+    },
+    /// Type or mode, as `INTEGER` or `STRING REPEATED`.
+    Changed {
+        column: String,
+        left: String,
+        right: String,
+    },
+}
+
+impl SchemaChange {
+    pub(crate) fn description(&self) -> String {
+        match self {
+            SchemaChange::Added { column, r#type } => format!("+ {} {}", column, r#type),
+            SchemaChange::Removed { column, r#type } => format!("− {} {}", column, r#type),
+            SchemaChange::Changed {
+                column,
+                left,
+                right,
+            } => format!("~ {} {} → {}", column, left, right),
+        }
+    }
+}
+
+/// A row of the difference, as indexes into the rows of each side.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RowDiff {
+    Added {
+        right: usize,
+    },
+    Removed {
+        left: usize,
+    },
+    /// `columns` are the indexes (into `ResultDiff::columns`) of the values that differ.
+    Changed {
+        left: usize,
+        right: usize,
+        columns: Vec<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ResultDiff {
+    /// The columns both sides have, in the order of the left one.
+    pub columns: Vec<String>,
+    /// Removed and changed rows in the order of the left side, then the added ones.
+    pub rows: Vec<RowDiff>,
+    pub unchanged: usize,
+    /// Rows of either side with the key of a row above them, aligned in order of appearance.
+    pub duplicate_keys: usize,
+}
+
+impl ResultDiff {
+    pub(crate) fn count(&self, kind: &str) -> usize {
+        self.rows
+            .iter()
+            .filter(|r| {
+                matches!(
+                    (kind, r),
+                    ("added", RowDiff::Added { .. })
+                        | ("removed", RowDiff::Removed { .. })
+                        | ("changed", RowDiff::Changed { .. })
+                )
+            })
+            .count()
+    }
+}
+
+/// The type of a leaf column with its mode, legacy and standard SQL names made the same.
+fn column_type(field: &TableFieldSchema) -> String {
+    let r#type = match field.r#type.as_str() {
+        "INT64" => "INTEGER",
+        "FLOAT64" => "FLOAT",
+        "BOOL" => "BOOLEAN",
+        "STRUCT" => "RECORD",
+        other => other,
+    };
+    match field.mode.as_deref() {
+        Some("REPEATED") => format!("{} REPEATED", r#type),
+        _ => r#type.to_string(),
+    }
+}
+
+/// The columns added, removed or changed from `left` to `right`, in the order of the left
+/// schema then of the columns only the right one has.
+pub(crate) fn schema_changes(
+    left: &[TableFieldSchema],
+    right: &[TableFieldSchema],
+) -> Vec<SchemaChange> {
+    let left = to_columns(left, NestedMode::Flatten);
+    let right = to_columns(right, NestedMode::Flatten);
+    let mut changes = vec![];
+
+    for (column, field) in &left {
+        match right.iter().find(|(c, _)| c == column) {
+            None => changes.push(SchemaChange::Removed {
+                column: column.clone(),
+                r#type: column_type(field),
+            }),
+            Some((_, other)) if column_type(field) != column_type(other) => {
+                changes.push(SchemaChange::Changed {
+                    column: column.clone(),
+                    left: column_type(field),
+                    right: column_type(other),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for (column, field) in &right {
+        if !left.iter().any(|(c, _)| c == column) {
+            changes.push(SchemaChange::Added {
+                column: column.clone(),
+                r#type: column_type(field),
+            });
+        }
+    }
+    changes
+}
+
+/// The columns of `left` the rows can be compared on: the ones `right` has too.
+pub(crate) fn common_columns(left: &[TableFieldSchema], right: &[TableFieldSchema]) -> Vec<String> {
+    let right: Vec<String> = to_columns(right, NestedMode::Flatten)
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect();
+    to_columns(left, NestedMode::Flatten)
+        .into_iter()
+        .map(|(c, _)| c)
+        .filter(|c| right.contains(c))
+        .collect()
+}
+
+fn same_value(a: &CellValue, b: &CellValue) -> bool {
+    // across types (INTEGER and NUMERIC, ...) the values are the same when they read the same
+    a == b || a.to_text() == b.to_text()
+}
+
+/// Difference of the rows, aligned by the values of the `keys` columns or by position without.
+pub(crate) fn diff_rows(
+    left_fields: &[TableFieldSchema],
+    left_rows: &[serde_json::Value],
+    right_fields: &[TableFieldSchema],
+    right_rows: &[serde_json::Value],
+    keys: &[String],
+) -> Result<ResultDiff, String> {
+    let columns = common_columns(left_fields, right_fields);
+    if let Some(key) = keys.iter().find(|k| !columns.contains(k)) {
+        return Err(format!("The key column {} is not on both sides.", key));
+    }
+
+    // the common columns of each record, in the order of `columns`
+    let project =
+        |fields: &[TableFieldSchema], rows: &[serde_json::Value]| -> Vec<Vec<CellValue>> {
+            let (names, records) = to_records(fields, rows, NestedMode::Flatten);
+            let indexes: Vec<Option<usize>> = columns
+                .iter()
+                .map(|c| names.iter().position(|n| n == c))
+                .collect();
+            records
+                .into_iter()
+                .map(|record| {
+                    indexes
+                        .iter()
+                        .map(|i| {
+                            i.and_then(|i| record.get(i).cloned())
+                                .unwrap_or(CellValue::Null)
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+    let left = project(left_fields, left_rows);
+    let right = project(right_fields, right_rows);
+
+    let key_indexes: Vec<usize> = keys
+        .iter()
+        .filter_map(|k| columns.iter().position(|c| c == k))
+        .collect();
+    let key_of = |record: &Vec<CellValue>| -> Vec<Option<String>> {
+        key_indexes.iter().map(|i| record[*i].to_text()).collect()
+    };
+
+    // the right row of each left one
+    let mut duplicate_keys = 0;
+    let pairs: Vec<Option<usize>> = if key_indexes.is_empty() {
+        (0..left.len())
+            .map(|i| (i < right.len()).then_some(i))
+            .collect()
+    } else {
+        let mut by_key: HashMap<Vec<Option<String>>, VecDeque<usize>> = HashMap::new();
+        for (i, record) in right.iter().enumerate() {
+            let rows = by_key.entry(key_of(record)).or_default();
+            if !rows.is_empty() {
+                duplicate_keys += 1;
+            }
+            rows.push_back(i);
+        }
+        let mut seen: HashMap<Vec<Option<String>>, usize> = HashMap::new();
+        left.iter()
+            .map(|record| {
+                let key = key_of(record);
+                let count = seen.entry(key.clone()).or_default();
+                if *count > 0 {
+                    duplicate_keys += 1;
+                }
+                *count += 1;
+                by_key.get_mut(&key).and_then(|rows| rows.pop_front())
+            })
+            .collect()
+    };
+
+    let mut rows = vec![];
+    let mut unchanged = 0;
+    let mut matched = vec![false; right.len()];
+    for (l, pair) in pairs.iter().enumerate() {
+        match pair {
+            Some(r) => {
+                matched[*r] = true;
+                let changed: Vec<usize> = (0..columns.len())
+                    .filter(|c| !same_value(&left[l][*c], &right[*r][*c]))
+                    .collect();
+                if changed.is_empty() {
+                    unchanged += 1;
+                } else {
+                    rows.push(RowDiff::Changed {
+                        left: l,
+                        right: *r,
+                        columns: changed,
+                    });
+                }
+            }
+            None => rows.push(RowDiff::Removed { left: l }),
+        }
+    }
+    rows.extend(
+        matched
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| !**m)
+            .map(|(r, _)| RowDiff::Added { right: r }),
+    );
+
+    Ok(ResultDiff {
+        columns,
+        rows,
+        unchanged,
+        duplicate_keys,
+    })
+}
+
+/// The first `limit` rows of `diff` as a grid: a `+`, `−` or `~` column, then the compared
+/// columns with the value of the side the row is on, `old → new` where a value changed.
+pub(crate) fn diff_table(
+    diff: &ResultDiff,
+    left: (&[TableFieldSchema], &[serde_json::Value]),
+    right: (&[TableFieldSchema], &[serde_json::Value]),
+    limit: usize,
+) -> TableBuilder {
+    let values = |(fields, rows): (&[TableFieldSchema], &[serde_json::Value])| {
+        let (names, records) = to_records(fields, rows, NestedMode::Flatten);
+        let indexes: Vec<Option<usize>> = diff
+            .columns
+            .iter()
+            .map(|c| names.iter().position(|n| n == c))
+            .collect();
+        (indexes, records)
+    };
+    let (left_indexes, left_records) = values(left);
+    let (right_indexes, right_records) = values(right);
+    let cell =
+        |indexes: &[Option<usize>], records: &[Vec<CellValue>], row: usize, column: usize| {
+            indexes[column]
+                .and_then(|i| records.get(row)?.get(i).cloned())
+                .unwrap_or(CellValue::Null)
+        };
+    let text = |value: &CellValue| value.to_text().unwrap_or_else(|| "NULL".to_string());
+    let table_value = |value: CellValue| match value.to_text() {
+        None => TableValue::Null,
+        Some(t) => TableValue::String(t),
+    };
+
+    let mut columns = vec![TableColumnDefinition::Column(TableColumn {
+        name: "diff".to_string(),
+        text: String::new(),
+        width_px: 0,
+    })];
+    columns.extend(diff.columns.iter().map(|c| {
+        TableColumnDefinition::Column(TableColumn {
+            name: c.clone(),
+            text: c.clone(),
+            width_px: 0,
+        })
+    }));
+
+    let rows: Vec<TableRow> = diff
+        .rows
+        .iter()
+        .take(limit)
+        .map(|row_diff| {
+            let (sign, cells): (&str, Vec<TableValue>) = match row_diff {
+                RowDiff::Added { right } => (
+                    "+",
+                    (0..diff.columns.len())
+                        .map(|c| table_value(cell(&right_indexes, &right_records, *right, c)))
+                        .collect(),
+                ),
+                RowDiff::Removed { left } => (
+                    "−",
+                    (0..diff.columns.len())
+                        .map(|c| table_value(cell(&left_indexes, &left_records, *left, c)))
+                        .collect(),
+                ),
+                RowDiff::Changed {
+                    left,
+                    right,
+                    columns,
+                } => (
+                    "~",
+                    (0..diff.columns.len())
+                        .map(|c| {
+                            let old = cell(&left_indexes, &left_records, *left, c);
+                            if columns.contains(&c) {
+                                let new = cell(&right_indexes, &right_records, *right, c);
+                                TableValue::String(format!("{} → {}", text(&old), text(&new)))
+                            } else {
+                                table_value(old)
+                            }
+                        })
+                        .collect(),
+                ),
+            };
+            let mut row = vec![TableValue::String(sign.to_string())];
+            row.extend(cells);
+            TableRow { cells: row }
+        })
+        .collect();
+
+    let mut table_builder = TableBuilder {
+        style: vscode_main_style(),
+        dynamic_table_render: false,
+        columns,
+        rows,
+    };
+    patch_column_widths(
+        &mut table_builder.columns,
+        &table_builder.rows,
+        &Default::default(),
+    );
+    table_builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RowDiff, SchemaChange, diff_rows, schema_changes};
+    use crate::bigquery::base::TableFieldSchema;
+
+    fn fields(json: &str) -> Vec<TableFieldSchema> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn rows(values: &[(&str, &str)]) -> Vec<serde_json::Value> {
+        values
+            .iter()
+            .map(|(id, name)| serde_json::json!({"f": [{"v": id}, {"v": name}]}))
+            .collect()
+    }
+
+    #[test]
+    fn schema_changes_of_the_leaf_columns() {
+        let left = fields(
+            r#"[{"name": "id", "type": "INTEGER"}, {"name": "s", "type": "RECORD", "fields": [
+                {"name": "a", "type": "STRING"}, {"name": "b", "type": "FLOAT"}]}]"#,
+        );
+        let right = fields(
+            r#"[{"name": "id", "type": "INT64"}, {"name": "s", "type": "STRUCT", "fields": [
+                {"name": "a", "type": "STRING", "mode": "REPEATED"}, {"name": "c", "type": "DATE"}]}]"#,
+        );
+        assert_eq!(
+            schema_changes(&left, &right),
+            vec![
+                SchemaChange::Changed {
+                    column: "s.a".to_string(),
+                    left: "STRING".to_string(),
+                    right: "STRING REPEATED".to_string()
+                },
+                SchemaChange::Removed {
+                    column: "s.b".to_string(),
+                    r#type: "FLOAT".to_string()
+                },
+                SchemaChange::Added {
+                    column: "s.c".to_string(),
+                    r#type: "DATE".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_aligned_by_key() {
+        let left_fields =
+            fields(r#"[{"name": "id", "type": "INTEGER"}, {"name": "name", "type": "STRING"}]"#);
+        let right_fields =
+            fields(r#"[{"name": "id", "type": "NUMERIC"}, {"name": "name", "type": "STRING"}]"#);
+        let left = rows(&[("1", "a"), ("2", "b"), ("3", "c")]);
+        let right = rows(&[("3", "c"), ("1", "A"), ("4", "d")]);
+
+        let diff = diff_rows(
+            &left_fields,
+            &left,
+            &right_fields,
+            &right,
+            &["id".to_string()],
+        )
+        .unwrap();
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(
+            diff.rows,
+            vec![
+                RowDiff::Changed {
+                    left: 0,
+                    right: 1,
+                    columns: vec![1]
+                },
+                RowDiff::Removed { left: 1 },
+                RowDiff::Added { right: 2 },
+            ]
+        );
+
+        // by position every row differs
+        let diff = diff_rows(&left_fields, &left, &right_fields, &right, &[]).unwrap();
+        assert_eq!(diff.count("changed"), 3);
+        assert_eq!(diff.unchanged, 0);
+
+        assert!(
+            diff_rows(
+                &left_fields,
+                &left,
+                &right_fields,
+                &right,
+                &["x".to_string()]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_in_order() {
+        let f = fields(r#"[{"name": "id", "type": "STRING"}, {"name": "name", "type": "STRING"}]"#);
+        let left = rows(&[("1", "a"), ("1", "b")]);
+        let right = rows(&[("1", "a"), ("1", "c"), ("1", "d")]);
+
+        let diff = diff_rows(&f, &left, &f, &right, &["id".to_string()]).unwrap();
+        assert_eq!(diff.duplicate_keys, 3);
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.count("changed"), 1);
+        assert_eq!(diff.count("added"), 1);
+    }
+}
@@ -0,0 +1,661 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use serde_json::json;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use website_component_table::HtmlNodeRender;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::CellValue,
+    export::{NestedMode, to_columns, to_grid_columns, to_records},
+    grid_columns, grid_layout,
+    grid_selection::grid_rows,
+    layout::{ColumnLayout, GridLayout},
+    loaded_results, overlay_panel,
+    to_table_builder::rows_to_table_builder,
+    value_tree::escape_html,
+};
+
+/*
+Drill-down into a REPEATED field of the grid of `bq-table` and `bq-query`: the elements of the array
+as a grid of their own in the overlay panel, paged and sorted apart from the results, with a breadcrumb
+back up the nesting path (`row 17 › items › attributes`). An ARRAY cell of that grid drills one level deeper.
+*/
+const DRILL_PAGE_SIZE: usize = 50;
+
+/// One level down: the REPEATED field `field` (dotted through non repeated RECORDs)
+/// of the record `parent` of the level above, a data row of the page for the first step.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DrillStep {
+    pub parent: usize,
+    pub field: String,
+}
+
+/// The elements of the array a path of steps leads to, as the rows of a grid.
+#[derive(Debug)]
+pub(crate) struct DrillLevel {
+    /// The REPEATED field.
+    pub field: TableFieldSchema,
+    /// Columns of an element: the sub-fields of an ARRAY<STRUCT>, the field itself otherwise.
+    pub fields: Vec<TableFieldSchema>,
+    /// One `{"f": [...]}` per element.
+    pub rows: Vec<serde_json::Value>,
+    /// The `"v"` of the array, as received.
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+struct DrillState {
+    steps: Vec<DrillStep>,
+    page: usize,
+    /// (column as laid out by `to_columns(.., NestedMode::Flatten)`, descending)
+    sort: Option<(usize, bool)>,
+    /// The page of results the steps start from.
+    page_start_index: usize,
+}
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static DRILLS: RefCell<HashMap<String, DrillState>> = RefCell::new(HashMap::new());
+}
+
+/// Follows `steps` from the page `rows` of `fields`. `None` if a step does not lead to an array.
+pub(crate) fn drill_level(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    steps: &[DrillStep],
+) -> Option<DrillLevel> {
+    let mut level: Option<DrillLevel> = None;
+
+    for step in steps {
+        let (level_fields, level_rows) = match &level {
+            Some(l) => (l.fields.as_slice(), l.rows.as_slice()),
+            None => (fields, rows),
+        };
+        let record = level_rows.get(step.parent)?;
+        let (field, value) = find_repeated(level_fields, record, &step.field)?;
+
+        let items = value.as_array().map(Vec::as_slice).unwrap_or(&[]);
+        let (element_fields, element_rows) = match &field.fields {
+            Some(sub_fields) => {
+                let null_record = json!({ "f": vec![json!({ "v": null }); sub_fields.len()] });
+                let element_rows = items
+                    .iter()
+                    .map(|item| match item.get("v") {
+                        Some(v) if !v.is_null() => v.clone(),
+                        _ => null_record.clone(),
+                    })
+                    .collect();
+                (sub_fields.clone(), element_rows)
+            }
+            None => {
+                let element_field = TableFieldSchema {
+                    mode: None,
+                    ..field.clone()
+                };
+                let element_rows = items
+                    .iter()
+                    .map(|item| json!({ "f": [{ "v": item.get("v") }] }))
+                    .collect();
+                (vec![element_field], element_rows)
+            }
+        };
+
+        level = Some(DrillLevel {
+            field,
+            fields: element_fields,
+            rows: element_rows,
+            value,
+        });
+    }
+
+    level
+}
+
+/// The REPEATED field at the dotted `path` of `record` and its `"v"`.
+fn find_repeated(
+    fields: &[TableFieldSchema],
+    record: &serde_json::Value,
+    path: &str,
+) -> Option<(TableFieldSchema, serde_json::Value)> {
+    let mut fields = fields;
+    let mut record = record;
+    let mut names = path.split('.').peekable();
+
+    while let Some(name) = names.next() {
+        let index = fields.iter().position(|f| f.name == name)?;
+        let field = &fields[index];
+        let v = record
+            .pointer(&format!("/f/{}/v", index))
+            .unwrap_or(&serde_json::Value::Null);
+        let repeated = field.mode.as_deref() == Some("REPEATED");
+
+        if names.peek().is_none() {
+            return repeated.then(|| (field.clone(), v.clone()));
+        }
+        if repeated {
+            return None;
+        }
+        fields = field.fields.as_deref()?;
+        record = v;
+    }
+
+    None
+}
+
+/// Indexes of `rows` in display order, the array order when not sorted.
+pub(crate) fn sorted_order(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    sort: Option<(usize, bool)>,
+) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    if let Some((column, descending)) = sort {
+        let (_, records) = to_records(fields, rows, NestedMode::Flatten);
+        order.sort_by(|a, b| {
+            let ordering = match (records[*a].get(column), records[*b].get(column)) {
+                (Some(x), Some(y)) => x.sort_cmp(y),
+                _ => std::cmp::Ordering::Equal,
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+    order
+}
+
+/// Where the level is, the parent element of each step included: `row 17 › items[3] › attributes`.
+pub(crate) fn level_path(row_number: usize, steps: &[DrillStep]) -> String {
+    let mut parts = vec![format!("row {}", row_number)];
+    for (i, step) in steps.iter().enumerate() {
+        match steps.get(i + 1) {
+            Some(next) => parts.push(format!("{}[{}]", step.field, next.parent)),
+            None => parts.push(step.field.clone()),
+        }
+    }
+    parts.join(" › ")
+}
+
+/// Opens the drill-down on the REPEATED column `field` (its name in the grid) of the data row `row`.
+pub(crate) fn open(host: &Element, row: usize, field: &str) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let page_start_index = match loaded_results::get(&element_id) {
+        Some(r) => r.page_start_index,
+        None => return,
+    };
+    let state = DrillState {
+        steps: vec![DrillStep {
+            parent: row,
+            field: field.to_string(),
+        }],
+        page: 0,
+        sort: None,
+        page_start_index,
+    };
+    DRILLS.with(|d| d.borrow_mut().insert(element_id, state));
+
+    render(host);
+}
+
+/// Drill-down from the top level cell `cell_index` of the data row `row`,
+/// `false` if the cell is not an ARRAY.
+pub(crate) fn open_cell(host: &Element, row: usize, cell_index: usize) -> bool {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (column, loaded_results) = match (
+        cell_index.checked_sub(1),
+        grid_columns::visible_results(&element_id),
+    ) {
+        (Some(c), Some(r)) => (c, r),
+        _ => return false,
+    };
+    let columns = to_grid_columns(&loaded_results.schema.fields, &grid_layout::get());
+    let name = match columns.get(column) {
+        Some((name, field)) if field.mode.as_deref() == Some("REPEATED") => name.clone(),
+        _ => return false,
+    };
+
+    open(host, row, &name);
+    true
+}
+
+/// Click on a button of the overlay panel, the ones of the drill-down are handled.
+pub(crate) fn on_click(host: &Element, button: &Element) {
+    if let (Some(field), Some(row)) = (
+        button.get_attribute("data-drill-field"),
+        button
+            .get_attribute("data-drill-row")
+            .and_then(|r| r.parse::<usize>().ok()),
+    ) {
+        open(host, row, &field);
+        return;
+    }
+
+    if let Some(crumb) = button
+        .get_attribute("data-drill-crumb")
+        .and_then(|c| c.parse::<usize>().ok())
+    {
+        if crumb == 0 {
+            overlay_panel::close(host);
+        } else {
+            update(host, |state| {
+                state.steps.truncate(crumb);
+                state.page = 0;
+                state.sort = None;
+            });
+            render(host);
+        }
+        return;
+    }
+
+    if let Some(page) = button.get_attribute("data-drill-page") {
+        update(host, |state| {
+            state.page = match page.as_str() {
+                "first" => 0,
+                "previous" => state.page.saturating_sub(1),
+                "next" => state.page + 1,
+                // clamped by `render`
+                _ => usize::MAX,
+            }
+        });
+        render(host);
+        refocus(
+            host,
+            &format!("[data-drill-page='{}']:not([disabled])", page),
+        );
+        return;
+    }
+
+    if button.has_attribute("data-drill-sort-dir") {
+        update(host, |state| {
+            if let Some((column, descending)) = state.sort {
+                state.sort = Some((column, !descending));
+                state.page = 0;
+            }
+        });
+        render(host);
+        refocus(host, "[data-drill-sort-dir]");
+    }
+}
+
+/// Change of the sort column.
+pub(crate) fn on_change(host: &Element, select: &Element) {
+    if !select.has_attribute("data-drill-sort") {
+        return;
+    }
+    let column = select
+        .dyn_ref::<web_sys::HtmlSelectElement>()
+        .and_then(|s| s.value().parse::<usize>().ok());
+
+    update(host, |state| {
+        let descending = state.sort.map(|(_, d)| d).unwrap_or(false);
+        state.sort = column.map(|c| (c, descending));
+        state.page = 0;
+    });
+    render(host);
+    refocus(host, "[data-drill-sort]");
+}
+
+/// Double click or Enter on an ARRAY cell of the drill-down grid: one level deeper.
+pub(crate) fn on_activate(host: &Element, target: &Element) {
+    let cell = match target.closest(".drillable").ok().flatten() {
+        Some(c) => c,
+        None => return,
+    };
+    let (field, parent) = match (
+        cell.get_attribute("data-drill-field"),
+        cell.get_attribute("data-drill-parent")
+            .and_then(|p| p.parse::<usize>().ok()),
+    ) {
+        (Some(f), Some(p)) => (f, p),
+        _ => return,
+    };
+
+    update(host, |state| {
+        state.steps.push(DrillStep { parent, field });
+        state.page = 0;
+        state.sort = None;
+    });
+    render(host);
+}
+
+fn update(host: &Element, f: impl FnOnce(&mut DrillState)) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    DRILLS.with(|d| {
+        if let Some(state) = d.borrow_mut().get_mut(&element_id) {
+            f(state);
+        }
+    });
+}
+
+fn render(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let state = match DRILLS.with(|d| d.borrow().get(&element_id).cloned()) {
+        Some(s) => s,
+        None => return,
+    };
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) if r.page_start_index == state.page_start_index => r,
+        // another page of results is shown, the path does not apply any more
+        _ => {
+            DRILLS.with(|d| d.borrow_mut().remove(&element_id));
+            overlay_panel::close(host);
+            return;
+        }
+    };
+    let level = match drill_level(
+        &loaded_results.schema.fields,
+        &loaded_results.rows,
+        &state.steps,
+    ) {
+        Some(l) => l,
+        None => return,
+    };
+
+    let order = sorted_order(&level.fields, &level.rows, state.sort);
+    let page_count = order.len().div_ceil(DRILL_PAGE_SIZE).max(1);
+    let page = state.page.min(page_count - 1);
+    if page != state.page {
+        update(host, |s| s.page = page);
+    }
+    let page_rows: Vec<(usize, serde_json::Value)> = order
+        .iter()
+        .skip(page * DRILL_PAGE_SIZE)
+        .take(DRILL_PAGE_SIZE)
+        .map(|i| (*i, level.rows[*i].clone()))
+        .collect();
+
+    let row_number = loaded_results.page_start_index + state.steps[0].parent + 1;
+    let meta = format!(
+        "{} · {} element{}",
+        level_path(row_number, &state.steps),
+        level.rows.len(),
+        if level.rows.len() == 1 { "" } else { "s" }
+    );
+    let copy_json = CellValue::from_bq(&level.value, &level.field).to_json_pretty();
+    let body =
+        match overlay_panel::open(host, "Drill-down", &meta, vec![("Copy as JSON", copy_json)]) {
+            Some(b) => b,
+            None => return,
+        };
+    if let Some(panel) = body.parent_element() {
+        let _ = panel.class_list().add_1("wide");
+    }
+
+    let columns = to_columns(&level.fields, NestedMode::Flatten);
+    body.set_inner_html(&format!(
+        r#"{}{}<div class="drill-grid"></div>"#,
+        breadcrumb_html(row_number, &state.steps),
+        toolbar_html(&columns, state.sort, page, page_count, order.len())
+    ));
+
+    let grid = match body.query_selector(".drill-grid").ok().flatten() {
+        Some(g) => g,
+        None => return,
+    };
+    let table_builder = rows_to_table_builder(
+        &level.fields,
+        &page_rows,
+        &GridLayout::default(),
+        &ColumnLayout::default(),
+    );
+    match table_builder.render() {
+        Ok(nodes) => {
+            for node in nodes.iter().filter_map(|n| n.to_element_node().ok()) {
+                let _ = grid.append_child(&node);
+            }
+        }
+        Err(e) => {
+            web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                "drill_down: table render failed: {:?}",
+                e
+            )));
+            return;
+        }
+    }
+
+    mark_drillable_cells(&grid, &columns, &page_rows);
+}
+
+fn breadcrumb_html(row_number: usize, steps: &[DrillStep]) -> String {
+    let mut crumbs = vec![format!(
+        r#"<button type="button" data-drill-crumb="0" title="Back to the results">row {}</button>"#,
+        row_number
+    )];
+    for (i, step) in steps.iter().enumerate() {
+        if i + 1 == steps.len() {
+            crumbs.push(format!(
+                r#"<span aria-current="location">{}</span>"#,
+                escape_html(&step.field)
+            ));
+        } else {
+            crumbs.push(format!(
+                r#"<button type="button" data-drill-crumb="{}">{}</button>"#,
+                i + 1,
+                escape_html(&step.field)
+            ));
+        }
+    }
+
+    format!(
+        r#"<nav class="drill-breadcrumb" aria-label="Nesting path">{}</nav>"#,
+        crumbs.join(" › ")
+    )
+}
+
+fn toolbar_html(
+    columns: &[(String, &TableFieldSchema)],
+    sort: Option<(usize, bool)>,
+    page: usize,
+    page_count: usize,
+    length: usize,
+) -> String {
+    let options: String = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| {
+            let selected = if sort.map(|(c, _)| c) == Some(i) {
+                " selected"
+            } else {
+                ""
+            };
+            format!(
+                r#"<option value="{}"{}>{}</option>"#,
+                i,
+                selected,
+                escape_html(name)
+            )
+        })
+        .collect();
+    let descending = sort.map(|(_, d)| d).unwrap_or(false);
+    let (direction, direction_label) = if descending {
+        ("↓", "Sorted descending, sort ascending")
+    } else {
+        ("↑", "Sorted ascending, sort descending")
+    };
+    let direction_disabled = if sort.is_none() { " disabled" } else { "" };
+
+    let range = if length == 0 {
+        "no elements".to_string()
+    } else {
+        format!(
+            "{}–{} of {}",
+            page * DRILL_PAGE_SIZE + 1,
+            ((page + 1) * DRILL_PAGE_SIZE).min(length),
+            length
+        )
+    };
+    let at_start = if page == 0 { " disabled" } else { "" };
+    let at_end = if page + 1 >= page_count {
+        " disabled"
+    } else {
+        ""
+    };
+
+    format!(
+        concat!(
+            r#"<div class="drill-toolbar">"#,
+            r#"<label>Sort by <select data-drill-sort><option value="">array order</option>{}</select></label>"#,
+            r#"<button type="button" data-drill-sort-dir aria-label="{}" title="{}"{}>{}</button>"#,
+            r#"<span class="drill-paging" role="status" aria-live="polite">{}</span>"#,
+            r#"<button type="button" data-drill-page="first" aria-label="First elements" title="First elements"{}>«</button>"#,
+            r#"<button type="button" data-drill-page="previous" aria-label="Previous elements" title="Previous elements"{}>‹</button>"#,
+            r#"<button type="button" data-drill-page="next" aria-label="Next elements" title="Next elements"{}>›</button>"#,
+            r#"<button type="button" data-drill-page="last" aria-label="Last elements" title="Last elements"{}>»</button>"#,
+            r#"</div>"#
+        ),
+        options,
+        direction_label,
+        direction_label,
+        direction_disabled,
+        direction,
+        range,
+        at_start,
+        at_start,
+        at_end,
+        at_end
+    )
+}
+
+/// ARRAY cells of the drill-down grid: focusable, with what to drill into.
+fn mark_drillable_cells(
+    grid: &Element,
+    columns: &[(String, &TableFieldSchema)],
+    page_rows: &[(usize, serde_json::Value)],
+) {
+    let table = match grid.query_selector("bstruct-table").ok().flatten() {
+        Some(t) => t,
+        None => return,
+    };
+    let (_, data_rows) = grid_rows(&table);
+
+    for (row, (parent, _)) in data_rows.iter().zip(page_rows) {
+        for (column, (name, field)) in columns.iter().enumerate() {
+            if field.mode.as_deref() != Some("REPEATED") {
+                continue;
+            }
+            // the index cell comes first
+            if let Some(cell) = row.children().item(column as u32 + 1) {
+                let _ = cell.class_list().add_1("drillable");
+                let _ = cell.set_attribute("tabindex", "0");
+                let _ = cell.set_attribute("data-drill-field", name);
+                let _ = cell.set_attribute("data-drill-parent", &parent.to_string());
+                let _ = cell.set_attribute("title", "Double-click or Enter to drill down");
+            }
+        }
+    }
+}
+
+fn refocus(host: &Element, selector: &str) {
+    if let Some(element) = host
+        .shadow_root()
+        .and_then(|s| s.query_selector(selector).ok().flatten())
+        .and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = element.focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrillStep, drill_level, level_path, sorted_order};
+    use crate::bigquery::{base::TableFieldSchema, jobs::GetQueryResultsResponse};
+
+    fn load_query_results(contents: &str) -> (Vec<TableFieldSchema>, Vec<serde_json::Value>) {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(contents).unwrap();
+        (
+            response.schema.unwrap().fields,
+            response.rows.unwrap_or_default(),
+        )
+    }
+
+    fn fields(json: &str) -> Vec<TableFieldSchema> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn drills_through_records_and_arrays() {
+        let fields = fields(
+            r#"[{"name": "order", "type": "RECORD", "fields": [
+                {"name": "items", "type": "RECORD", "mode": "REPEATED", "fields": [
+                    {"name": "sku", "type": "STRING"},
+                    {"name": "tags", "type": "STRING", "mode": "REPEATED"}
+                ]}
+            ]}]"#,
+        );
+        let rows = vec![serde_json::json!({"f": [{"v": {"f": [{"v": [
+            {"v": {"f": [{"v": "b"}, {"v": [{"v": "x"}]}]}},
+            {"v": {"f": [{"v": "a"}, {"v": [{"v": "y"}, {"v": "z"}]}]}}
+        ]}]}}]})];
+
+        let items_step = DrillStep {
+            parent: 0,
+            field: "order.items".to_string(),
+        };
+        let items = drill_level(&fields, &rows, std::slice::from_ref(&items_step)).unwrap();
+        assert_eq!(items.field.name, "items");
+        assert_eq!(items.fields.len(), 2);
+        assert_eq!(items.rows.len(), 2);
+        assert_eq!(
+            sorted_order(&items.fields, &items.rows, Some((0, false))),
+            vec![1, 0]
+        );
+
+        let steps = vec![
+            items_step,
+            DrillStep {
+                parent: 1,
+                field: "tags".to_string(),
+            },
+        ];
+        let tags = drill_level(&fields, &rows, &steps).unwrap();
+        assert_eq!(tags.fields[0].mode, None);
+        assert_eq!(
+            tags.rows,
+            vec![
+                serde_json::json!({"f": [{"v": "y"}]}),
+                serde_json::json!({"f": [{"v": "z"}]})
+            ]
+        );
+        assert_eq!(level_path(17, &steps), "row 17 › order.items[1] › tags");
+
+        // not an array
+        let sku = vec![
+            DrillStep {
+                parent: 0,
+                field: "order.items".to_string(),
+            },
+            DrillStep {
+                parent: 0,
+                field: "sku".to_string(),
+            },
+        ];
+        assert!(drill_level(&fields, &rows, &sku).is_none());
+    }
+
+    #[test]
+    fn drills_into_a_response() {
+        let (fields, rows) = load_query_results(include_str!("test_resources/simple_array.json"));
+
+        let level = drill_level(
+            &fields,
+            &rows,
+            &[DrillStep {
+                parent: 0,
+                field: "cat".to_string(),
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(level.rows.len(), 3);
+        assert_eq!(level.rows[0], serde_json::json!({"f": [{"v": "a"}]}));
+        assert_eq!(
+            sorted_order(&level.fields, &level.rows, Some((0, true))),
+            vec![2, 1, 0]
+        );
+    }
+}
@@ -0,0 +1,557 @@
+use std::str::FromStr;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::{CellValue, json_string},
+    layout::{GridLayout, field_path},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ExportFormat {
+    Csv,
+    Tsv,
+    Json,
+    Jsonl,
+    Markdown,
+}
+
+impl ExportFormat {
+    pub(crate) fn get_all() -> Vec<ExportFormat> {
+        vec![
+            ExportFormat::Csv,
+            ExportFormat::Tsv,
+            ExportFormat::Json,
+            ExportFormat::Jsonl,
+            ExportFormat::Markdown,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Jsonl => "JSONL",
+            ExportFormat::Markdown => "Markdown",
+        }
+    }
+
+    pub(crate) fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Tsv => "text/tab-separated-values",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Jsonl => "application/jsonl",
+            ExportFormat::Markdown => "text/markdown",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ExportFormat, Self::Err> {
+        ExportFormat::get_all()
+            .into_iter()
+            .find(|f| f.name() == input)
+            .ok_or(())
+    }
+}
+
+/// How REPEATED and RECORD fields end up in the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NestedMode {
+    /// One column per top level field; ARRAY and STRUCT values written as JSON
+    /// (nested objects in the JSON formats).
+    Json,
+    /// Non repeated RECORDs expanded into one `parent.child` column per leaf field.
+    /// REPEATED fields cannot be expanded without multiplying rows, they stay JSON.
+    Flatten,
+}
+
+impl FromStr for NestedMode {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<NestedMode, Self::Err> {
+        match input {
+            "json" => Ok(NestedMode::Json),
+            "flatten" => Ok(NestedMode::Flatten),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Column names and cells of `rows` (REST `{"f": [...]}` rows) laid out for `nested`.
+pub(crate) fn to_records(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    nested: NestedMode,
+) -> (Vec<String>, Vec<Vec<CellValue>>) {
+    layout_records(fields, rows, nested, &GridLayout::default())
+}
+
+/// Column names and cells of `rows` as the data cells of a grid row laid out by `layout`.
+pub(crate) fn to_grid_records(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    layout: &GridLayout,
+) -> (Vec<String>, Vec<Vec<CellValue>>) {
+    layout_records(fields, rows, NestedMode::Flatten, layout)
+}
+
+fn layout_records(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    nested: NestedMode,
+    layout: &GridLayout,
+) -> (Vec<String>, Vec<Vec<CellValue>>) {
+    let columns: Vec<String> = layout_columns(fields, nested, layout)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    let records = rows
+        .iter()
+        .map(|row| {
+            let cells = CellValue::row_from_bq(row, fields);
+            match nested {
+                NestedMode::Json => cells,
+                NestedMode::Flatten => {
+                    let mut flat = Vec::with_capacity(columns.len());
+                    for (cell, field) in cells.into_iter().zip(fields) {
+                        push_flattened(&mut flat, cell, &field.name, field, layout);
+                    }
+                    flat
+                }
+            }
+        })
+        .collect();
+
+    (columns, records)
+}
+
+/// Name and field of each column laid out for `nested`, flattened RECORDs as `parent.child`.
+/// With `NestedMode::Flatten` they match the data cells of a grid row.
+pub(crate) fn to_columns(
+    fields: &[TableFieldSchema],
+    nested: NestedMode,
+) -> Vec<(String, &TableFieldSchema)> {
+    layout_columns(fields, nested, &GridLayout::default())
+}
+
+/// Name and field of each data column of the grid, RECORDs `layout` shows in a single cell
+/// as one column.
+pub(crate) fn to_grid_columns<'a>(
+    fields: &'a [TableFieldSchema],
+    layout: &GridLayout,
+) -> Vec<(String, &'a TableFieldSchema)> {
+    layout_columns(fields, NestedMode::Flatten, layout)
+}
+
+fn layout_columns<'a>(
+    fields: &'a [TableFieldSchema],
+    nested: NestedMode,
+    layout: &GridLayout,
+) -> Vec<(String, &'a TableFieldSchema)> {
+    let mut columns = Vec::new();
+    for field in fields {
+        push_columns(&mut columns, field.name.clone(), field, nested, layout);
+    }
+    columns
+}
+
+fn is_flattened(field: &TableFieldSchema, nested: NestedMode) -> bool {
+    nested == NestedMode::Flatten
+        && field.fields.is_some()
+        && field.mode.as_deref() != Some("REPEATED")
+}
+
+fn push_columns<'a>(
+    columns: &mut Vec<(String, &'a TableFieldSchema)>,
+    name: String,
+    field: &'a TableFieldSchema,
+    nested: NestedMode,
+    layout: &GridLayout,
+) {
+    match &field.fields {
+        Some(sub_fields) if is_flattened(field, nested) && !layout.is_collapsed(&name, field) => {
+            for sub_field in sub_fields {
+                push_columns(
+                    columns,
+                    field_path(&name, &sub_field.name),
+                    sub_field,
+                    nested,
+                    layout,
+                );
+            }
+        }
+        _ => columns.push((name, field)),
+    }
+}
+
+fn push_flattened(
+    flat: &mut Vec<CellValue>,
+    cell: CellValue,
+    path: &str,
+    field: &TableFieldSchema,
+    layout: &GridLayout,
+) {
+    match &field.fields {
+        Some(sub_fields)
+            if is_flattened(field, NestedMode::Flatten) && !layout.is_collapsed(path, field) =>
+        {
+            match cell {
+                CellValue::Struct(entries) => {
+                    for ((_, value), sub_field) in entries.into_iter().zip(sub_fields) {
+                        let sub_path = field_path(path, &sub_field.name);
+                        push_flattened(flat, value, &sub_path, sub_field, layout);
+                    }
+                }
+                _ => {
+                    for sub_field in sub_fields {
+                        let sub_path = field_path(path, &sub_field.name);
+                        push_flattened(flat, CellValue::Null, &sub_path, sub_field, layout);
+                    }
+                }
+            }
+        }
+        _ => flat.push(cell),
+    }
+}
+
+pub(crate) fn export(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    format: ExportFormat,
+    nested: NestedMode,
+) -> String {
+    let (columns, records) = to_records(fields, rows, nested);
+    write_records(&columns, &records, format)
+}
+
+pub(crate) fn write_records(
+    columns: &[String],
+    records: &[Vec<CellValue>],
+    format: ExportFormat,
+) -> String {
+    let mut output = String::new();
+    write_header(&mut output, columns, format);
+    for (record_index, record) in records.iter().enumerate() {
+        write_record(&mut output, columns, record, format, record_index);
+    }
+    write_footer(&mut output, format, records.len());
+    output
+}
+
+/// Header line(s) of `format`, the opening bracket for JSON.
+/// With `write_record` and `write_footer` a long export can be written page by page
+/// (`record_index` counts from the first record of the whole export).
+pub(crate) fn write_header(output: &mut String, columns: &[String], format: ExportFormat) {
+    match format {
+        ExportFormat::Csv => {
+            let line: Vec<String> = columns.iter().map(|c| csv_field(c)).collect();
+            output.push_str(&line.join(","));
+            output.push_str("\r\n");
+        }
+        ExportFormat::Tsv => {
+            let line: Vec<String> = columns.iter().map(|c| tsv_field(c)).collect();
+            output.push_str(&line.join("\t"));
+            output.push('\n');
+        }
+        ExportFormat::Markdown => {
+            let line: Vec<String> = columns.iter().map(|c| markdown_field(c)).collect();
+            output.push_str(&format!("| {} |\n", line.join(" | ")));
+            output.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+        }
+        ExportFormat::Json => output.push('['),
+        ExportFormat::Jsonl => {}
+    }
+}
+
+pub(crate) fn write_record(
+    output: &mut String,
+    columns: &[String],
+    record: &[CellValue],
+    format: ExportFormat,
+    record_index: usize,
+) {
+    match format {
+        ExportFormat::Csv => {
+            let line: Vec<String> = record
+                .iter()
+                .map(|c| c.to_text().map(|t| csv_field(&t)).unwrap_or_default())
+                .collect();
+            output.push_str(&line.join(","));
+            output.push_str("\r\n");
+        }
+        ExportFormat::Tsv => {
+            let line: Vec<String> = record
+                .iter()
+                .map(|c| c.to_text().map(|t| tsv_field(&t)).unwrap_or_default())
+                .collect();
+            output.push_str(&line.join("\t"));
+            output.push('\n');
+        }
+        ExportFormat::Markdown => {
+            let line: Vec<String> = record
+                .iter()
+                .map(|c| {
+                    c.to_text()
+                        .map(|t| markdown_field(&t))
+                        .unwrap_or_else(|| "NULL".to_string())
+                })
+                .collect();
+            output.push_str(&format!("| {} |\n", line.join(" | ")));
+        }
+        ExportFormat::Json => {
+            if record_index > 0 {
+                output.push(',');
+            }
+            output.push_str("\n  ");
+            output.push_str(&json_object(columns, record));
+        }
+        ExportFormat::Jsonl => {
+            output.push_str(&json_object(columns, record));
+            output.push('\n');
+        }
+    }
+}
+
+pub(crate) fn write_footer(output: &mut String, format: ExportFormat, record_count: usize) {
+    if format == ExportFormat::Json {
+        if record_count > 0 {
+            output.push('\n');
+        }
+        output.push_str("]\n");
+    }
+}
+
+fn json_object(columns: &[String], record: &[CellValue]) -> String {
+    let entries: Vec<String> = columns
+        .iter()
+        .zip(record)
+        .map(|(column, cell)| format!("{}:{}", json_string(column), cell.to_json()))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// RFC 4180: quoted when it contains a comma, a quote or a line break; quotes doubled.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// TSV has no quoting: tabs, line breaks and backslashes are escaped.
+fn tsv_field(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+/// GitHub tables: `|` escaped and line breaks as `<br>`, a cell must stay on one line.
+fn markdown_field(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\r', '\n'], "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExportFormat, NestedMode, export, write_footer, write_header, write_record};
+    use crate::bigquery::{base::TableFieldSchema, jobs::GetQueryResultsResponse};
+    use crate::custom_elements::cell_value::CellValue;
+    use crate::custom_elements::layout::{GridLayout, StructPresentation};
+
+    fn load_query_results(contents: &str) -> (Vec<TableFieldSchema>, Vec<serde_json::Value>) {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(contents).unwrap();
+        (
+            response.schema.unwrap().fields,
+            response.rows.unwrap_or_default(),
+        )
+    }
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn csv_quotes_per_rfc_4180() {
+        let mut output = String::new();
+        let columns = columns(&["a", "b,c"]);
+        write_header(&mut output, &columns, ExportFormat::Csv);
+        write_record(
+            &mut output,
+            &columns,
+            &[
+                CellValue::Text("say \"hi\"".to_string()),
+                CellValue::Text("line1\nline2".to_string()),
+            ],
+            ExportFormat::Csv,
+            0,
+        );
+        write_record(
+            &mut output,
+            &columns,
+            &[CellValue::Null, CellValue::Int(3)],
+            ExportFormat::Csv,
+            1,
+        );
+
+        assert_eq!(
+            output,
+            "a,\"b,c\"\r\n\"say \"\"hi\"\"\",\"line1\nline2\"\r\n,3\r\n"
+        );
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_and_line_breaks() {
+        let mut output = String::new();
+        let columns = columns(&["a", "b"]);
+        write_record(
+            &mut output,
+            &columns,
+            &[
+                CellValue::Text("x\ty".to_string()),
+                CellValue::Text("1\n2\\3".to_string()),
+            ],
+            ExportFormat::Tsv,
+            0,
+        );
+
+        assert_eq!(output, "x\\ty\t1\\n2\\\\3\n");
+    }
+
+    #[test]
+    fn markdown_escapes_pipes() {
+        let mut output = String::new();
+        let columns = columns(&["a", "b"]);
+        write_header(&mut output, &columns, ExportFormat::Markdown);
+        write_record(
+            &mut output,
+            &columns,
+            &[CellValue::Text("x|y".to_string()), CellValue::Null],
+            ExportFormat::Markdown,
+            0,
+        );
+
+        assert_eq!(output, "| a | b |\n| --- | --- |\n| x\\|y | NULL |\n");
+    }
+
+    #[test]
+    fn json_array_without_rows() {
+        let mut output = String::new();
+        write_header(&mut output, &[], ExportFormat::Json);
+        write_footer(&mut output, ExportFormat::Json, 0);
+
+        assert_eq!(output, "[]\n");
+    }
+
+    #[test]
+    fn json_written_in_two_parts() {
+        let columns = columns(&["a"]);
+        let mut first = String::new();
+        write_header(&mut first, &columns, ExportFormat::Json);
+        write_record(
+            &mut first,
+            &columns,
+            &[CellValue::Int(1)],
+            ExportFormat::Json,
+            0,
+        );
+        let mut second = String::new();
+        write_record(
+            &mut second,
+            &columns,
+            &[CellValue::Null],
+            ExportFormat::Json,
+            1,
+        );
+        write_footer(&mut second, ExportFormat::Json, 2);
+
+        assert_eq!(
+            format!("{}{}", first, second),
+            "[\n  {\"a\":\"1\"},\n  {\"a\":null}\n]\n"
+        );
+    }
+
+    #[test]
+    fn struct_nested_as_json() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let output = export(&fields, &rows[0..1], ExportFormat::Jsonl, NestedMode::Json);
+
+        assert!(
+            output.starts_with(
+                r#"{"attributes":{"row_number":"733704","data_type":"njiaukk"},"data":"#
+            )
+        );
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn struct_flattened_to_dotted_columns() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let output = export(&fields, &rows[0..1], ExportFormat::Csv, NestedMode::Flatten);
+
+        let mut lines = output.split("\r\n");
+        assert_eq!(
+            lines.next(),
+            Some("attributes.row_number,attributes.data_type,data")
+        );
+        assert!(lines.next().unwrap().starts_with("733704,njiaukk,"));
+    }
+
+    #[test]
+    fn repeated_fields_stay_json_when_flattened() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/complex_nested_test.json"));
+        let (flat_columns, flat_records) = super::to_records(&fields, &rows, NestedMode::Flatten);
+
+        assert!(
+            flat_columns
+                .contains(&"nested_struct_3_levels.headquarters.coordinates.latitude".to_string())
+        );
+        assert!(flat_columns.contains(&"array_of_structs".to_string()));
+        assert!(!flat_columns.contains(&"array_of_structs.product_id".to_string()));
+        for record in flat_records {
+            assert_eq!(record.len(), flat_columns.len());
+        }
+    }
+
+    #[test]
+    fn grid_columns_follow_the_layout() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/complex_nested_test.json"));
+        let mut layout = GridLayout::default();
+        layout.set_struct_presentation(
+            "nested_struct_3_levels.headquarters",
+            StructPresentation::Json,
+        );
+        let columns = super::to_grid_columns(&fields, &layout);
+        let (_, records) = super::to_grid_records(&fields, &rows, &layout);
+
+        let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+        assert!(names.contains(&"nested_struct_3_levels.headquarters"));
+        assert!(!names.contains(&"nested_struct_3_levels.headquarters.coordinates.latitude"));
+        for record in records {
+            assert_eq!(record.len(), columns.len());
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+use crate::{
+    bigquery::{
+        base::TableSchema,
+        error::BigQueryError,
+        jobs::{GetQueryResultsRequest, Jobs},
+        table_data::{TableData, TableDataListRequest},
+    },
+    parse_to_usize,
+    utils::download_blob,
+};
+
+use super::{
+    data_table_controls_element::show_export_progress,
+    export::{ExportFormat, NestedMode, to_records, write_footer, write_header, write_record},
+    grid_events::dispatch_load_error,
+    loaded_results::{self, ResultsSource},
+    page_walk::{PageStep, PageWalk},
+};
+
+/// Set on the host while an export of every page runs; `cancelled` stops it after the current page.
+pub(crate) const EXPORT_STATE_ATT: &str = "export_state";
+const EXPORT_STATE_RUNNING: &str = "running";
+const EXPORT_STATE_CANCELLED: &str = "cancelled";
+
+// rows asked per request, the API caps the response size (~10 MB) anyway
+const EXPORT_PAGE_SIZE: usize = 10_000;
+
+/// One page of the results, as fetched again from the source.
+pub(crate) struct ExportPage {
+    /// Given by `jobs.getQueryResults` only, `tables.get` has the one of a table.
+    pub schema: Option<TableSchema>,
+    pub rows: Vec<serde_json::Value>,
+    pub rows_total: Option<usize>,
+    pub page_token: Option<String>,
+}
+
+pub(crate) fn is_running(host: &Element) -> bool {
+    host.get_attribute(EXPORT_STATE_ATT).is_some()
+}
+
+pub(crate) fn cancel(host: &Element) {
+    if is_running(host) {
+        let _ = host.set_attribute(EXPORT_STATE_ATT, EXPORT_STATE_CANCELLED);
+    }
+}
+
+/// Exports every row of the results shown by `host` (`bq-table` / `bq-query`), page by page.
+///
+/// Each page is written to its own Blob as soon as it arrives and only the Blob is kept,
+/// so the wasm memory holds one page at a time; the browser keeps (or spills to disk) the parts.
+pub(crate) fn start(host: Element, format: ExportFormat, nested: NestedMode) {
+    if is_running(&host) {
+        return;
+    }
+
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) => r,
+        None => {
+            web_sys::console::log_1(&JsValue::from_str(&format!(
+                "export_all: no rows loaded for '{}'",
+                element_id
+            )));
+            return;
+        }
+    };
+    let source = match loaded_results.source {
+        Some(s) => s,
+        None => {
+            web_sys::console::log_1(&JsValue::from_str(&format!(
+                "export_all: the source of '{}' is unknown",
+                element_id
+            )));
+            return;
+        }
+    };
+    let token = host.get_attribute("token").unwrap_or_default();
+    let fields = loaded_results.schema.fields;
+    let file_name = format!("{}.{}", loaded_results.name, format.name());
+
+    let _ = host.set_attribute(EXPORT_STATE_ATT, EXPORT_STATE_RUNNING);
+    show_export_progress(&host, Some((0, None)));
+
+    spawn_local(async move {
+        let parts = js_sys::Array::new();
+        let (columns, _) = to_records(&fields, &[], nested);
+        let mut header = String::new();
+        write_header(&mut header, &columns, format);
+        let mut walk = PageWalk::default();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let page = match fetch_page(&source, &token, page_token.take()).await {
+                Ok(p) => p,
+                Err((operation, e)) => {
+                    dispatch_load_error(&host, operation, &e);
+                    finish(&host);
+                    return;
+                }
+            };
+
+            let cancelled =
+                host.get_attribute(EXPORT_STATE_ATT).as_deref() == Some(EXPORT_STATE_CANCELLED);
+            let first_row = walk.rows_done;
+            let next_page_token =
+                match walk.step(page.rows.len(), page.rows_total, page.page_token, cancelled) {
+                    PageStep::Cancelled => {
+                        finish(&host);
+                        return;
+                    }
+                    PageStep::Write(t) => t,
+                    PageStep::Stalled => {
+                        // the rows written so far are not all of them: no file
+                        dispatch_stalled(&host, &source, walk.rows_done);
+                        finish(&host);
+                        return;
+                    }
+                };
+
+            let (_, records) = to_records(&fields, &page.rows, nested);
+            let mut chunk = std::mem::take(&mut header);
+            for (i, record) in records.iter().enumerate() {
+                write_record(&mut chunk, &columns, record, format, first_row + i);
+            }
+            if let Err(e) = push_part(&parts, &chunk) {
+                web_sys::console::error_1(&JsValue::from_str(&format!(
+                    "export_all: failed to buffer a page: {:?}",
+                    e
+                )));
+                finish(&host);
+                return;
+            }
+
+            show_export_progress(&host, Some((walk.rows_done, walk.rows_total)));
+
+            match next_page_token {
+                Some(t) => page_token = Some(t),
+                None => break,
+            }
+        }
+
+        let mut footer = std::mem::take(&mut header);
+        write_footer(&mut footer, format, walk.rows_done);
+
+        let result = push_part(&parts, &footer).and_then(|_| {
+            let options = web_sys::BlobPropertyBag::new();
+            options.set_type(format.mime_type());
+            web_sys::Blob::new_with_blob_sequence_and_options(&parts, &options)
+        });
+        match result.and_then(|blob| download_blob(&file_name, &blob)) {
+            Ok(_) => {}
+            Err(e) => web_sys::console::error_1(&JsValue::from_str(&format!(
+                "export_all: download failed: {:?}",
+                e
+            ))),
+        }
+
+        finish(&host);
+    });
+}
+
+fn finish(host: &Element) {
+    let _ = host.remove_attribute(EXPORT_STATE_ATT);
+    show_export_progress(host, None);
+}
+
+fn push_part(parts: &js_sys::Array, text: &str) -> Result<(), JsValue> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(text)))?;
+    parts.push(&blob);
+    Ok(())
+}
+
+/// `bq-load-error` of a walk through the pages of `source` ended by `PageStep::Stalled`, after
+/// `rows_done` rows.
+pub(crate) fn dispatch_stalled(host: &Element, source: &ResultsSource, rows_done: usize) {
+    let error = BigQueryError {
+        http_status: None,
+        status: None,
+        message: format!(
+            "the page after row {} came back with the token it was fetched with",
+            rows_done
+        ),
+        errors: Vec::new(),
+    };
+    dispatch_load_error(host, operation(source), &error);
+}
+
+fn operation(source: &ResultsSource) -> &'static str {
+    match source {
+        ResultsSource::Query(_) => "jobs.getQueryResults",
+        ResultsSource::Table(_) => "tabledata.list",
+    }
+}
+
+/// Fetches the page after `page_token` (the first one for `None`) of the results of `source`.
+pub(crate) async fn fetch_page(
+    source: &ResultsSource,
+    token: &str,
+    page_token: Option<String>,
+) -> Result<ExportPage, (&'static str, BigQueryError)> {
+    match source {
+        ResultsSource::Query(job_reference) => {
+            let request = GetQueryResultsRequest {
+                project_id: job_reference.project_id.clone(),
+                job_id: job_reference.job_id.clone(),
+                location: Some(job_reference.location.clone()),
+                start_index: None,
+                max_results: Some(EXPORT_PAGE_SIZE),
+                page_token,
+            };
+            let response = Jobs::new(token)
+                .get_query_results(request)
+                .await
+                .map_err(|e| (operation(source), e))?;
+
+            Ok(ExportPage {
+                schema: response.schema,
+                rows: response.rows.unwrap_or_default(),
+                rows_total: parse_to_usize(response.total_rows),
+                page_token: response.page_token,
+            })
+        }
+        ResultsSource::Table(table_reference) => {
+            let request = TableDataListRequest {
+                project_id: table_reference.project_id.clone(),
+                dataset_id: table_reference.dataset_id.clone(),
+                table_id: table_reference.table_id.clone(),
+                max_results: Some(EXPORT_PAGE_SIZE),
+                page_token,
+                start_index: None,
+            };
+            let response = TableData::new(token)
+                .list(request)
+                .await
+                .map_err(|e| (operation(source), e))?;
+
+            Ok(ExportPage {
+                schema: None,
+                rows: response.rows.unwrap_or_default(),
+                rows_total: parse_to_usize(Some(response.total_rows)),
+                page_token: response.page_token,
+            })
+        }
+    }
+}
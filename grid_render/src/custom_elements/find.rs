@@ -0,0 +1,227 @@
+use std::ops::Range;
+
+use website_component_table::{TableRow, TableValue};
+
+use super::{to_table_builder::value_text, value_tree::escape_html};
+
+/*
+Find in the results: the values of the page as the grid renders them (`TableValue`s, the
+elements of ARRAYs and the fields of RECORDs in their inner tables included) are searched
+cell by cell, in the order they appear in the grid. NULLs and the `#` column are left out.
+A match is placed by its row, its top level cell and the leaf cell of the inner tables holding
+it, counted in document order, so the DOM cell can be found without searching the DOM.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FindMatch {
+    /// Data row of the page.
+    pub row: usize,
+    /// Top level cell of the row, the `#` one being 0.
+    pub cell: usize,
+    /// Leaf cell inside the top level one, in document order (0 for a scalar).
+    pub leaf: usize,
+    /// Byte range of the match in the text of the leaf.
+    pub range: Range<usize>,
+}
+
+/// Every match of `find` in the texts of `rows`, `find` giving the byte ranges of the
+/// matches in one text.
+pub(crate) fn find_in_rows(
+    rows: &[TableRow],
+    find: &dyn Fn(&str) -> Vec<Range<usize>>,
+) -> Vec<FindMatch> {
+    let mut matches = Vec::new();
+    for (row_index, row) in rows.iter().enumerate() {
+        // the `#` index cell first
+        for (cell_index, cell) in row.cells.iter().enumerate().skip(1) {
+            let mut texts = Vec::new();
+            leaf_texts(cell, &mut texts);
+            for (leaf, text) in texts.iter().enumerate() {
+                let text = match text {
+                    Some(t) => t,
+                    None => continue,
+                };
+                matches.extend(find(text).into_iter().map(|range| FindMatch {
+                    row: row_index,
+                    cell: cell_index,
+                    leaf,
+                    range,
+                }));
+            }
+        }
+    }
+    matches
+}
+
+/// Text of each leaf cell of `value` in document order, `None` for a NULL.
+pub(crate) fn leaf_texts(value: &TableValue, texts: &mut Vec<Option<String>>) {
+    match value {
+        TableValue::Array(inner) => {
+            for row in &inner.rows {
+                for cell in &row.cells {
+                    leaf_texts(cell, texts);
+                }
+            }
+        }
+        TableValue::Null => texts.push(None),
+        value => texts.push(value_text(value)),
+    }
+}
+
+/// Byte ranges of `needle` in `text`, not overlapping, ignoring the case unless `case_sensitive`.
+pub(crate) fn text_matches(text: &str, needle: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+    if needle.is_empty() {
+        return vec![];
+    }
+    let fold = |c: char| -> Vec<char> {
+        match case_sensitive {
+            true => vec![c],
+            false => c.to_lowercase().collect(),
+        }
+    };
+    let needle: Vec<char> = needle.chars().flat_map(fold).collect();
+
+    let mut matches = Vec::new();
+    let mut search_from = 0;
+    for (start, _) in text.char_indices() {
+        if start < search_from {
+            continue;
+        }
+        let mut folded = text[start..].char_indices().flat_map(|(i, c)| {
+            let end = start + i + c.len_utf8();
+            fold(c).into_iter().map(move |f| (f, end))
+        });
+        let mut end = start;
+        let found = needle.iter().all(|n| match folded.next() {
+            Some((f, e)) if f == *n => {
+                end = e;
+                true
+            }
+            _ => false,
+        });
+        if found {
+            matches.push(start..end);
+            search_from = end;
+        }
+    }
+    matches
+}
+
+/// Byte offset in `text` of the UTF-16 offset `utf16` (as given by a JavaScript `RegExp`).
+pub(crate) fn utf16_to_byte_offset(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= utf16 {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// `text` as html, each of `ranges` in a `<mark>` numbered from `first_index`.
+pub(crate) fn highlighted_html(text: &str, ranges: &[Range<usize>], first_index: usize) -> String {
+    let mut html = String::new();
+    let mut position = 0;
+    for (i, range) in ranges.iter().enumerate() {
+        html.push_str(&escape_html(&text[position..range.start]));
+        html.push_str(&format!(
+            r#"<mark class="find-match" data-find-index="{}">{}</mark>"#,
+            first_index + i,
+            escape_html(&text[range.clone()])
+        ));
+        position = range.end;
+    }
+    html.push_str(&escape_html(&text[position..]));
+    html
+}
+
+/// "3 of 41", the current match counted from 1.
+pub(crate) fn match_status(current: Option<usize>, total: usize) -> String {
+    match (current, total) {
+        (_, 0) => "No results".to_string(),
+        (Some(c), total) => format!("{} of {}", c + 1, total),
+        (None, total) => format!("{} results", total),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use website_component_table::{InnerTableBuilder, TableRow, TableStyle, TableValue};
+
+    use super::{
+        FindMatch, find_in_rows, highlighted_html, match_status, text_matches, utf16_to_byte_offset,
+    };
+
+    #[test]
+    fn plain_text_matches() {
+        assert_eq!(text_matches("Banana", "an", true), vec![1..3, 3..5]);
+        assert_eq!(text_matches("Banana", "AN", true), vec![]);
+        assert_eq!(text_matches("Banana", "AN", false), vec![1..3, 3..5]);
+        assert_eq!(text_matches("aaa", "aa", false), vec![0..2]);
+        assert_eq!(
+            text_matches("Ünïcode ünï", "ÜNÏ", false),
+            vec![0..5, 10..15]
+        );
+        assert_eq!(text_matches("abc", "", false), vec![]);
+    }
+
+    #[test]
+    fn matches_in_nested_tables_in_document_order() {
+        let inner = InnerTableBuilder {
+            style: TableStyle {
+                margin_px: 0,
+                padding_px: 0,
+                css_entries: vec![],
+            },
+            rows: vec![
+                TableRow {
+                    cells: vec![TableValue::String("apple".to_string()), TableValue::Int(7)],
+                },
+                TableRow {
+                    cells: vec![
+                        TableValue::Null,
+                        TableValue::String("pineapple".to_string()),
+                    ],
+                },
+            ],
+            col_span: 2,
+            start_col_index: 1,
+        };
+        let rows = vec![TableRow {
+            cells: vec![
+                TableValue::Index(1),
+                TableValue::Array(inner),
+                TableValue::String("Apple pie".to_string()),
+            ],
+        }];
+
+        let matches = find_in_rows(&rows, &|text| text_matches(text, "apple", false));
+        let placed: Vec<(usize, usize, usize)> =
+            matches.iter().map(|m| (m.row, m.cell, m.leaf)).collect();
+        assert_eq!(placed, vec![(0, 1, 0), (0, 1, 3), (0, 2, 0)]);
+        assert_eq!(
+            matches[1],
+            FindMatch {
+                row: 0,
+                cell: 1,
+                leaf: 3,
+                range: 4..9
+            }
+        );
+        // the row number is not a value
+        assert!(find_in_rows(&rows, &|text| text_matches(text, "1", false)).is_empty());
+    }
+
+    #[test]
+    fn highlights_and_status() {
+        assert_eq!(
+            highlighted_html("a<b>a", &[0..1, 4..5], 3),
+            r#"<mark class="find-match" data-find-index="3">a</mark>&lt;b&gt;<mark class="find-match" data-find-index="4">a</mark>"#
+        );
+        assert_eq!(utf16_to_byte_offset("é😀x", 3), 6);
+        assert_eq!(utf16_to_byte_offset("ab", 5), 2);
+        assert_eq!(match_status(Some(2), 41), "3 of 41");
+        assert_eq!(match_status(None, 0), "No results");
+    }
+}
@@ -0,0 +1,525 @@
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::CellValue,
+    chart::SERIES_COLORS,
+    export::{NestedMode, to_columns, to_records},
+    value_tree::escape_html,
+};
+
+/*
+GEOGRAPHY values, as BigQuery returns them in WKT (`POINT(-122.3 47.6)`, `POLYGON((...))`, the
+MULTI* and GEOMETRYCOLLECTION ones, `... EMPTY`), parsed to draw them: the map of the loaded page
+(every geography of every GEOGRAPHY column, a chart color per column) and the thumbnail of a cell.
+Longitudes and latitudes are drawn as is (plate carrée), the longitudes narrowed by the cosine
+of the latitude at the middle so the shapes keep their proportions away from the equator. There
+is no base map, the editor has no network access to tiles.
+*/
+const MAP_WIDTH: f64 = 640.0;
+const MAP_MAX_HEIGHT: f64 = 480.0;
+const MAP_PADDING: f64 = 12.0;
+const THUMBNAIL_SIZE: f64 = 18.0;
+
+/// A longitude and a latitude.
+pub(crate) type Coord = (f64, f64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Geometry {
+    Point(Coord),
+    LineString(Vec<Coord>),
+    /// The outer ring, then the holes.
+    Polygon(Vec<Vec<Coord>>),
+    /// The parts of a MULTI* or a GEOMETRYCOLLECTION, none for an EMPTY geometry.
+    Collection(Vec<Geometry>),
+}
+
+impl Geometry {
+    fn coords(&self) -> Vec<Coord> {
+        match self {
+            Geometry::Point(c) => vec![*c],
+            Geometry::LineString(line) => line.clone(),
+            Geometry::Polygon(rings) => rings.iter().flatten().copied().collect(),
+            Geometry::Collection(parts) => parts.iter().flat_map(|p| p.coords()).collect(),
+        }
+    }
+}
+
+/// Parses the WKT `text` of a GEOGRAPHY value.
+pub(crate) fn parse_wkt(text: &str) -> Result<Geometry, String> {
+    let mut parser = WktParser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let geometry = parser.geometry()?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(geometry),
+        Some(token) => Err(format!("unexpected {:?} after the geometry", token)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            c if c.is_whitespace() => {}
+            c if c.is_ascii_alphabetic() => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, n)) = chars.peek().copied()
+                    && n.is_ascii_alphabetic()
+                {
+                    end = i + n.len_utf8();
+                    chars.next();
+                }
+                tokens.push(Token::Word(text[start..end].to_ascii_uppercase()));
+            }
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, n)) = chars.peek().copied()
+                    && (n.is_ascii_digit() || matches!(n, '.' | 'e' | 'E' | '-' | '+'))
+                {
+                    end = i + n.len_utf8();
+                    chars.next();
+                }
+                let number = text[start..end]
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number {}", &text[start..end]))?;
+                tokens.push(Token::Number(number));
+            }
+            c => return Err(format!("unexpected character {:?}", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct WktParser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl WktParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            other => Err(format!("expected {:?}, found {:?}", expected, other)),
+        }
+    }
+
+    /// `(` items separated by `,` `)`.
+    fn list<T>(
+        &mut self,
+        item: impl Fn(&mut WktParser) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        self.expect(Token::Open)?;
+        let mut items = vec![item(self)?];
+        while self.peek() == Some(&Token::Comma) {
+            self.next();
+            items.push(item(self)?);
+        }
+        self.expect(Token::Close)?;
+        Ok(items)
+    }
+
+    /// A longitude and a latitude, a Z or M value after them ignored.
+    fn coord(&mut self) -> Result<Coord, String> {
+        let (x, y) = match (self.next(), self.next()) {
+            (Some(Token::Number(x)), Some(Token::Number(y))) => (x, y),
+            other => return Err(format!("expected a coordinate, found {:?}", other)),
+        };
+        while let Some(Token::Number(_)) = self.peek() {
+            self.next();
+        }
+        Ok((x, y))
+    }
+
+    fn line(&mut self) -> Result<Vec<Coord>, String> {
+        self.list(|p| p.coord())
+    }
+
+    fn polygon(&mut self) -> Result<Vec<Vec<Coord>>, String> {
+        self.list(|p| p.line())
+    }
+
+    fn geometry(&mut self) -> Result<Geometry, String> {
+        let kind = match self.next() {
+            Some(Token::Word(w)) => w,
+            other => return Err(format!("expected a geometry type, found {:?}", other)),
+        };
+        if let Some(Token::Word(w)) = self.peek()
+            && matches!(w.as_str(), "Z" | "M" | "ZM")
+        {
+            self.next();
+        }
+        if let Some(Token::Word(w)) = self.peek()
+            && w == "EMPTY"
+        {
+            self.next();
+            return Ok(Geometry::Collection(vec![]));
+        }
+
+        match kind.as_str() {
+            "POINT" => {
+                self.expect(Token::Open)?;
+                let coord = self.coord()?;
+                self.expect(Token::Close)?;
+                Ok(Geometry::Point(coord))
+            }
+            "LINESTRING" => Ok(Geometry::LineString(self.line()?)),
+            "POLYGON" => Ok(Geometry::Polygon(self.polygon()?)),
+            // the points with or without their own parentheses
+            "MULTIPOINT" => Ok(Geometry::Collection(self.list(|p| {
+                if p.peek() == Some(&Token::Open) {
+                    p.next();
+                    let coord = p.coord()?;
+                    p.expect(Token::Close)?;
+                    Ok(Geometry::Point(coord))
+                } else {
+                    p.coord().map(Geometry::Point)
+                }
+            })?)),
+            "MULTILINESTRING" => Ok(Geometry::Collection(
+                self.list(|p| p.line().map(Geometry::LineString))?,
+            )),
+            "MULTIPOLYGON" => Ok(Geometry::Collection(
+                self.list(|p| p.polygon().map(Geometry::Polygon))?,
+            )),
+            "GEOMETRYCOLLECTION" => Ok(Geometry::Collection(self.list(|p| p.geometry())?)),
+            other => Err(format!("unknown geometry type {}", other)),
+        }
+    }
+}
+
+/// The leaf columns of type GEOGRAPHY of `fields`, REPEATED ones included.
+pub(crate) fn geography_columns(fields: &[TableFieldSchema]) -> Vec<String> {
+    to_columns(fields, NestedMode::Flatten)
+        .into_iter()
+        .filter(|(_, field)| field.r#type == "GEOGRAPHY")
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// A geography of the page: its data row, its column (an index into `geography_columns`).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MapFeature {
+    pub row: usize,
+    pub column: usize,
+    pub geometry: Geometry,
+}
+
+/// The geographies of `rows`, and the number of values that are not valid WKT.
+pub(crate) fn page_features(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+) -> (Vec<MapFeature>, usize) {
+    let columns = geography_columns(fields);
+    let (names, records) = to_records(fields, rows, NestedMode::Flatten);
+    let indexes: Vec<Option<usize>> = columns
+        .iter()
+        .map(|c| names.iter().position(|n| n == c))
+        .collect();
+
+    let mut features = vec![];
+    let mut invalid = 0;
+    for (row, record) in records.iter().enumerate() {
+        for (column, index) in indexes.iter().enumerate() {
+            let value = match index.and_then(|i| record.get(i)) {
+                Some(v) => v,
+                None => continue,
+            };
+            let texts: Vec<&str> = match value {
+                CellValue::Text(t) => vec![t.as_str()],
+                CellValue::Array(items) => items
+                    .iter()
+                    .filter_map(|i| match i {
+                        CellValue::Text(t) => Some(t.as_str()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            for text in texts {
+                match parse_wkt(text) {
+                    Ok(geometry) => features.push(MapFeature {
+                        row,
+                        column,
+                        geometry,
+                    }),
+                    Err(_) => invalid += 1,
+                }
+            }
+        }
+    }
+    (features, invalid)
+}
+
+/// Maps longitudes and latitudes into a box of `width` x `height` pixels.
+struct Projection {
+    min_x: f64,
+    max_y: f64,
+    scale: f64,
+    /// How much narrower a degree of longitude is than one of latitude.
+    x_factor: f64,
+    padding_x: f64,
+    padding_y: f64,
+}
+
+impl Projection {
+    /// The projection fitting `coords` in `width` pixels, and the height it needs.
+    fn fit(coords: &[Coord], width: f64, max_height: f64, padding: f64) -> (Projection, f64) {
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = coords.iter().fold(
+            (
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(a, b, c, d), (x, y)| (a.min(*x), b.min(*y), c.max(*x), d.max(*y)),
+        );
+        if coords.is_empty() {
+            (min_x, min_y, max_x, max_y) = (-180.0, -90.0, 180.0, 90.0);
+        }
+        // a single point (or a vertical / horizontal line) gets some room around it
+        if max_x - min_x < 1e-6 {
+            min_x -= 0.01;
+            max_x += 0.01;
+        }
+        if max_y - min_y < 1e-6 {
+            min_y -= 0.01;
+            max_y += 0.01;
+        }
+        let x_factor = ((min_y + max_y) / 2.0).to_radians().cos().max(0.1);
+        let span_x = (max_x - min_x) * x_factor;
+        let span_y = max_y - min_y;
+        let inner = width - 2.0 * padding;
+        let scale = (inner / span_x).min((max_height - 2.0 * padding) / span_y);
+        let height = span_y * scale + 2.0 * padding;
+        let projection = Projection {
+            min_x,
+            max_y,
+            scale,
+            x_factor,
+            // centered horizontally when the shapes are taller than wide
+            padding_x: padding + (inner - span_x * scale) / 2.0,
+            padding_y: padding,
+        };
+        (projection, height)
+    }
+
+    fn point(&self, (x, y): Coord) -> (f64, f64) {
+        (
+            self.padding_x + (x - self.min_x) * self.x_factor * self.scale,
+            self.padding_y + (self.max_y - y) * self.scale,
+        )
+    }
+
+    fn path(&self, line: &[Coord], close: bool) -> String {
+        let mut d: Vec<String> = line
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let (x, y) = self.point(*c);
+                format!("{}{:.1},{:.1}", if i == 0 { "M" } else { "L" }, x, y)
+            })
+            .collect();
+        if close {
+            d.push("Z".to_string());
+        }
+        d.join("")
+    }
+
+    /// The svg elements of `geometry`, points as circles of radius `radius`.
+    fn shapes(&self, geometry: &Geometry, radius: f64) -> String {
+        match geometry {
+            Geometry::Point(c) => {
+                let (x, y) = self.point(*c);
+                format!(
+                    r#"<circle class="geo-point" cx="{:.1}" cy="{:.1}" r="{}"/>"#,
+                    x, y, radius
+                )
+            }
+            Geometry::LineString(line) => {
+                format!(r#"<path class="geo-line" d="{}"/>"#, self.path(line, false))
+            }
+            Geometry::Polygon(rings) => {
+                let d: String = rings.iter().map(|r| self.path(r, true)).collect();
+                format!(
+                    r#"<path class="geo-polygon" fill-rule="evenodd" d="{}"/>"#,
+                    d
+                )
+            }
+            Geometry::Collection(parts) => parts.iter().map(|p| self.shapes(p, radius)).collect(),
+        }
+    }
+}
+
+/// The map of `features` as an svg: a group per feature, focusable, with the row (numbered
+/// from `first_row`) and the column in its title; `columns` are the names of the columns.
+pub(crate) fn map_svg(features: &[MapFeature], columns: &[String], first_row: usize) -> String {
+    let coords: Vec<Coord> = features.iter().flat_map(|f| f.geometry.coords()).collect();
+    let (projection, height) = Projection::fit(&coords, MAP_WIDTH, MAP_MAX_HEIGHT, MAP_PADDING);
+
+    let groups: String = features
+        .iter()
+        .map(|f| {
+            let column = columns.get(f.column).map(String::as_str).unwrap_or("");
+            format!(
+                r#"<g class="geo-feature" style="color: {}" data-map-row="{}" tabindex="0" role="button" aria-label="Row {} · {}"><title>Row {} · {}</title>{}</g>"#,
+                SERIES_COLORS[f.column % SERIES_COLORS.len()].css_var(),
+                f.row,
+                first_row + f.row,
+                escape_html(column),
+                first_row + f.row,
+                escape_html(column),
+                projection.shapes(&f.geometry, 4.0)
+            )
+        })
+        .collect();
+    let (west, north) = coords
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(w, n), (x, y)| {
+            (w.min(*x), n.max(*y))
+        });
+    let (east, south) = coords
+        .iter()
+        .fold((f64::NEG_INFINITY, f64::INFINITY), |(e, s), (x, y)| {
+            (e.max(*x), s.min(*y))
+        });
+    let extent = match coords.is_empty() {
+        true => String::new(),
+        false => format!(
+            r#"<text class="geo-extent" x="4" y="{:.0}">{:.4}, {:.4} – {:.4}, {:.4}</text>"#,
+            height - 4.0,
+            west,
+            south,
+            east,
+            north
+        ),
+    };
+
+    format!(
+        r#"<svg class="geo-map" xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h:.0}" viewBox="0 0 {w} {h:.0}" role="img" aria-label="Map of {n} geograph{y}"><rect class="geo-frame" x="0.5" y="0.5" width="{fw}" height="{fh:.0}"/>{groups}{extent}</svg>"#,
+        w = MAP_WIDTH,
+        h = height,
+        n = features.len(),
+        y = if features.len() == 1 { "y" } else { "ies" },
+        fw = MAP_WIDTH - 1.0,
+        fh = height - 1.0,
+        groups = groups,
+        extent = extent
+    )
+}
+
+/// A small svg of `geometry` alone, for its cell.
+pub(crate) fn thumbnail_svg(geometry: &Geometry) -> String {
+    let (projection, height) =
+        Projection::fit(&geometry.coords(), THUMBNAIL_SIZE, THUMBNAIL_SIZE, 1.5);
+    format!(
+        r#"<svg class="geo-thumbnail" xmlns="http://www.w3.org/2000/svg" width="{s}" height="{s}" viewBox="0 0 {s} {h:.0}" aria-hidden="true" focusable="false">{}</svg>"#,
+        projection.shapes(geometry, 2.0),
+        s = THUMBNAIL_SIZE,
+        h = height.max(1.0)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Geometry, map_svg, page_features, parse_wkt, thumbnail_svg};
+    use crate::bigquery::base::TableFieldSchema;
+
+    #[test]
+    fn parses_the_wkt_of_bigquery() {
+        assert_eq!(
+            parse_wkt("POINT(-50 90)"),
+            Ok(Geometry::Point((-50.0, 90.0)))
+        );
+        assert_eq!(
+            parse_wkt("LINESTRING(1 2, 3.5 -4e1)"),
+            Ok(Geometry::LineString(vec![(1.0, 2.0), (3.5, -40.0)]))
+        );
+        assert_eq!(
+            parse_wkt("POLYGON((0 0, 4 0, 4 4, 0 0), (1 1, 2 1, 2 2, 1 1))"),
+            Ok(Geometry::Polygon(vec![
+                vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 0.0)],
+                vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 1.0)],
+            ]))
+        );
+        assert_eq!(
+            parse_wkt("MULTIPOINT((1 2), 3 4)"),
+            Ok(Geometry::Collection(vec![
+                Geometry::Point((1.0, 2.0)),
+                Geometry::Point((3.0, 4.0))
+            ]))
+        );
+        assert_eq!(
+            parse_wkt("GEOMETRYCOLLECTION(POINT(1 2), MULTILINESTRING((0 0, 1 1)), POLYGON EMPTY)"),
+            Ok(Geometry::Collection(vec![
+                Geometry::Point((1.0, 2.0)),
+                Geometry::Collection(vec![Geometry::LineString(vec![(0.0, 0.0), (1.0, 1.0)])]),
+                Geometry::Collection(vec![]),
+            ]))
+        );
+        assert_eq!(
+            parse_wkt("multipolygon(((0 0, 1 0, 1 1, 0 0)))"),
+            Ok(Geometry::Collection(vec![Geometry::Polygon(vec![vec![
+                (0.0, 0.0),
+                (1.0, 0.0),
+                (1.0, 1.0),
+                (0.0, 0.0)
+            ]])]))
+        );
+
+        assert!(parse_wkt("POINT(1)").is_err());
+        assert!(parse_wkt("POINT(1 2) x").is_err());
+        assert!(parse_wkt("CIRCLE(1 2)").is_err());
+    }
+
+    #[test]
+    fn features_of_the_page() {
+        let fields: Vec<TableFieldSchema> = serde_json::from_str(
+            r#"[{"name": "id", "type": "INTEGER"}, {"name": "place", "type": "GEOGRAPHY"},
+                {"name": "route", "type": "GEOGRAPHY", "mode": "REPEATED"}]"#,
+        )
+        .unwrap();
+        let rows = vec![
+            serde_json::json!({"f": [{"v": "1"}, {"v": "POINT(4.9 52.4)"}, {"v": [{"v": "LINESTRING(4 52, 5 53)"}]}]}),
+            serde_json::json!({"f": [{"v": "2"}, {"v": "not wkt"}, {"v": []}]}),
+            serde_json::json!({"f": [{"v": "3"}, {"v": null}, {"v": [{"v": "POINT(5 52)"}, {"v": "POINT(6 51)"}]}]}),
+        ];
+        let (features, invalid) = page_features(&fields, &rows);
+        assert_eq!(invalid, 1);
+        let placed: Vec<(usize, usize)> = features.iter().map(|f| (f.row, f.column)).collect();
+        assert_eq!(placed, vec![(0, 0), (0, 1), (2, 1), (2, 1)]);
+
+        let svg = map_svg(&features, &["place".to_string(), "route".to_string()], 101);
+        assert_eq!(svg.matches(r#"class="geo-feature"#).count(), 4);
+        assert!(svg.contains(r#"data-map-row="2""#));
+        assert!(svg.contains("<title>Row 103 · route</title>"));
+        // the route from the west edge, half way up, to the top of the map
+        assert!(svg.contains(r#"d="M179.6,240.0L320.0,12.0""#), "{}", svg);
+
+        let thumbnail = thumbnail_svg(&features[0].geometry);
+        assert!(thumbnail.contains(r#"cx="9.0" cy="#));
+    }
+}
@@ -0,0 +1,604 @@
+use std::cell::RefCell;
+
+use serde_json::json;
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use crate::{bigquery::base::TableFieldSchema, get_state, set_state};
+
+use super::{
+    aggregate::Aggregate,
+    bq_common_custom_element::{find_in_shadow, host_of},
+    column_formats, column_widths,
+    conditional_format::FormatRule,
+    grid_layout, grid_map,
+    grid_selection::{column_spans, grid_rows, grid_table},
+    layout::{ColumnLayout, field_path, header_cells, schema_fingerprint},
+    loaded_results::{self, LoadedResults, ResultsSource},
+    schema_tree::field_tooltip,
+    value_tree::escape_html,
+};
+
+/*
+Columns of the grid of `bq-table` and `bq-query` the user hid (column chooser of the controls),
+moved (dragging a top level header onto another), pinned (kept visible with the `#` column on
+a horizontal scroll), resized (`column_widths`), summed up in the footer row (`aggregate`,
+picked from the context menu of a header), formatted (`column_formats`) or drawn as thumbnails
+(`grid_map`). Kept per table reference, or per schema for a query, in the state of the webview
+(`set_state`), so they survive a reload of VS Code; the `MAX_LAYOUTS` saved last only, so the
+state does not grow with every table ever opened.
+*/
+pub(crate) const COLUMN_CHOOSER: &str = "column_chooser";
+const STATE_KEY: &str = "columnLayouts";
+const MAX_LAYOUTS: usize = 200;
+const PINNED_CLASS: &str = "pinned";
+/// The footer row of the aggregates, tagged by `grid_navigation::apply_aria` and left out of the
+/// data rows of `grid_rows`.
+pub(crate) const FOOTER_CLASS: &str = "grid-footer";
+
+thread_local! {
+    // by `layout_key`, the one saved last at the end; read from the webview state on first use
+    static COLUMN_LAYOUTS: RefCell<Option<Vec<(String, ColumnLayout)>>> = const { RefCell::new(None) };
+    // the `be_id` of the element and the top level field of the header being dragged
+    static DRAGGED: RefCell<Option<(String, String)>> = const { RefCell::new(None) };
+}
+
+/// Key of the column layout of results: the table, or the columns of a query.
+pub(crate) fn layout_key(source: Option<&ResultsSource>, fields: &[TableFieldSchema]) -> String {
+    match source {
+        Some(ResultsSource::Table(table)) => format!(
+            "table:{}.{}.{}",
+            table.project_id, table.dataset_id, table.table_id
+        ),
+        _ => format!("query:{}", schema_fingerprint(fields)),
+    }
+}
+
+pub(crate) fn get(key: &str) -> ColumnLayout {
+    with_layouts(|layouts| {
+        layouts
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, l)| l.clone())
+            .unwrap_or_default()
+    })
+}
+
+fn save(key: &str, column_layout: ColumnLayout) {
+    let layouts = with_layouts(|layouts| {
+        layouts.retain(|(k, _)| k != key);
+        if column_layout != ColumnLayout::default() {
+            layouts.push((key.to_string(), column_layout));
+        }
+        if layouts.len() > MAX_LAYOUTS {
+            layouts.drain(..layouts.len() - MAX_LAYOUTS);
+        }
+        layouts.clone()
+    });
+
+    if let Err(e) = set_state(&json!({ STATE_KEY: layouts }).to_string()) {
+        web_sys::console::error_1(&JsValue::from_str(&format!(
+            "grid_columns: failed to save the column layout: {:?}",
+            e
+        )));
+    }
+}
+
+fn with_layouts<T>(f: impl FnOnce(&mut Vec<(String, ColumnLayout)>) -> T) -> T {
+    COLUMN_LAYOUTS.with(|l| {
+        let mut layouts = l.borrow_mut();
+        let layouts = layouts.get_or_insert_with(|| {
+            get_state()
+                .ok()
+                .flatten()
+                .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok())
+                .and_then(|state| state.get(STATE_KEY).cloned())
+                .and_then(|l| serde_json::from_value(l).ok())
+                .unwrap_or_default()
+        });
+        f(layouts)
+    })
+}
+
+/// The loaded rows of the element as its grid shows them: hidden fields left out, top level fields in order.
+pub(crate) fn visible_results(element_id: &str) -> Option<LoadedResults> {
+    let loaded_results = loaded_results::get(element_id)?;
+    let column_layout = get(&layout_key(
+        loaded_results.source.as_ref(),
+        &loaded_results.schema.fields,
+    ));
+    let (fields, rows) = column_layout.project(&loaded_results.schema.fields, &loaded_results.rows);
+
+    Some(LoadedResults {
+        schema: crate::bigquery::base::TableSchema { fields },
+        rows,
+        ..loaded_results
+    })
+}
+
+/// The column layout of the results loaded in the element `element_id`.
+pub(crate) fn column_layout(element_id: &str) -> Option<ColumnLayout> {
+    let loaded_results = loaded_results::get(element_id)?;
+    Some(get(&layout_key(
+        loaded_results.source.as_ref(),
+        &loaded_results.schema.fields,
+    )))
+}
+
+/// The column layout of the results loaded in `host`, with its key.
+fn host_layout(host: &Element) -> Option<(String, ColumnLayout, LoadedResults)> {
+    let element_id = host.get_attribute("be_id")?;
+    let loaded_results = loaded_results::get(&element_id)?;
+    let key = layout_key(
+        loaded_results.source.as_ref(),
+        &loaded_results.schema.fields,
+    );
+    let column_layout = get(&key);
+    Some((key, column_layout, loaded_results))
+}
+
+/// Gives the leaf column at `path` the width `width_px` and renders the grid of `host` again.
+pub(crate) fn set_width(host: &Element, path: &str, width_px: usize) {
+    if let Some((key, mut column_layout, _)) = host_layout(host) {
+        column_layout.widths.insert(path.to_string(), width_px);
+        save(&key, column_layout);
+        grid_layout::rerender(host);
+    }
+}
+
+/// Shows `aggregate` (none: no aggregate) in the footer row under the leaf column at `path`
+/// and renders the grid of `host` again.
+pub(crate) fn set_aggregate(host: &Element, path: &str, aggregate: Option<Aggregate>) {
+    if let Some((key, mut column_layout, _)) = host_layout(host) {
+        column_layout.set_aggregate(path, aggregate);
+        save(&key, column_layout);
+        grid_layout::rerender(host);
+    }
+}
+
+/// Formats the leaf column at `path` by `rules` (none: no formatting) and renders the grid of
+/// `host` again.
+pub(crate) fn set_formats(host: &Element, path: &str, rules: Vec<FormatRule>) {
+    if let Some((key, mut column_layout, _)) = host_layout(host) {
+        match rules.is_empty() {
+            true => column_layout.formats.remove(path),
+            false => column_layout.formats.insert(path.to_string(), rules),
+        };
+        save(&key, column_layout);
+        grid_layout::rerender(host);
+    }
+}
+
+/// Shows (or not) the thumbnails of the GEOGRAPHY values in the leaf column at `path` and
+/// renders the grid of `host` again.
+pub(crate) fn set_thumbnails(host: &Element, path: &str, shown: bool) {
+    if let Some((key, mut column_layout, _)) = host_layout(host) {
+        column_layout.thumbnails.retain(|t| t != path);
+        if shown {
+            column_layout.thumbnails.push(path.to_string());
+        }
+        save(&key, column_layout);
+        grid_layout::rerender(host);
+    }
+}
+
+/// Pins the `#` column and the pinned fields, describes the fields in the tooltips of their
+/// headers, lets the top level headers be dragged and the leaf columns be resized, formats the
+/// cells and draws the thumbnails of the geographies, on a freshly rendered grid.
+pub(crate) fn apply(host: &Element) {
+    let (table, element_id) = match (grid_table(host), host.get_attribute("be_id")) {
+        (Some(t), Some(id)) => (t, id),
+        _ => return,
+    };
+    let visible = match visible_results(&element_id) {
+        Some(v) => v,
+        None => return,
+    };
+    let column_layout = host_layout(host).map(|(_, c, _)| c).unwrap_or_default();
+    let pinned = column_layout.pinned;
+    column_widths::add_handles(host, &visible);
+    let (header_rows, data_rows) = grid_rows(&table);
+
+    column_formats::apply(&visible, &column_layout, &data_rows);
+    grid_map::apply_thumbnails(&visible, &column_layout, &data_rows);
+
+    // the path, type, mode and description of the fields in the tooltips of their headers
+    let layout = grid_layout::get();
+    for (depth, (cells, row)) in header_cells(&visible.schema.fields, &layout)
+        .iter()
+        .zip(header_rows.iter())
+        .enumerate()
+    {
+        let mut header = row.first_element_child();
+        // the `#` header of the index column starts the first row
+        if depth == 0 {
+            header = header.and_then(|h| h.next_element_sibling());
+        }
+        for (path, field) in cells {
+            let h = match header {
+                Some(h) => h,
+                None => break,
+            };
+            let _ = h.set_attribute("title", &field_tooltip(path, field));
+            header = h.next_element_sibling();
+        }
+    }
+
+    // the top level headers, after the `#` one
+    if let Some(first_row) = header_rows.first() {
+        let mut header = first_row
+            .first_element_child()
+            .and_then(|c| c.next_element_sibling());
+        for field in &visible.schema.fields {
+            let h = match header {
+                Some(h) => h,
+                None => break,
+            };
+            let _ = h.set_attribute("draggable", "true");
+            let _ = h.set_attribute("data-column-field", &field.name);
+            let tooltip = h.get_attribute("title").unwrap_or_default();
+            let _ = h.set_attribute(
+                "title",
+                &format!("{}\n\nDrag onto another header to move the column", tooltip),
+            );
+            header = h.next_element_sibling();
+        }
+    }
+
+    // the columns up to the end of the last pinned field
+    let header_spans = column_spans(&header_rows);
+    let pinned_end = header_rows
+        .first()
+        .and_then(|r| {
+            r.children()
+                .item(pinned.min(visible.schema.fields.len()) as u32)
+        })
+        .and_then(|h| {
+            header_spans
+                .iter()
+                .find(|(c, _)| *c == h)
+                .map(|(_, s)| s.end)
+        })
+        .unwrap_or(1);
+
+    // the footer of the aggregates, tagged by `grid_navigation::apply_aria`, is pinned too
+    let footer: Vec<Element> = table
+        .query_selector(&format!("tr.{}", FOOTER_CLASS))
+        .ok()
+        .flatten()
+        .into_iter()
+        .collect();
+    let mut spans = header_spans;
+    spans.extend(column_spans(&data_rows));
+    spans.extend(column_spans(&footer));
+    // offsets first, the page is laid out once
+    let offsets: Vec<(Element, i32)> = spans
+        .into_iter()
+        .filter(|(_, span)| span.end <= pinned_end)
+        .filter_map(|(cell, _)| {
+            let left = cell.dyn_ref::<web_sys::HtmlElement>()?.offset_left();
+            Some((cell, left))
+        })
+        .collect();
+    for (cell, left) in offsets {
+        let _ = cell.class_list().add_1(PINNED_CLASS);
+        let style = cell.get_attribute("style").unwrap_or_default();
+        let separator = if style.is_empty() || style.trim_end().ends_with(';') {
+            ""
+        } else {
+            ";"
+        };
+        let _ = cell.set_attribute(
+            "style",
+            &format!("{}{} left: {}px;", style, separator, left),
+        );
+    }
+}
+
+/// Binds the dragging of the top level headers on a grid element.
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    let listeners = [
+        ("dragstart", on_drag_start as fn(&web_sys::Event)),
+        ("dragover", on_drag_over),
+        ("drop", on_drop),
+    ];
+    for (event_type, listener) in listeners {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(listener) as Box<dyn Fn(&web_sys::Event)>);
+        if let Err(e) = element.add_event_listener_with_callback(
+            event_type,
+            on_event_type_closure.as_ref().unchecked_ref(),
+        ) {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "{} define: failed to add '{}' listener: {:?}",
+                tag_name, event_type, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+fn dragged_header(event: &web_sys::Event) -> Option<(Element, Element, String)> {
+    let host = event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())?;
+    let target = event.composed_path().get(0).dyn_into::<Element>().ok()?;
+    let header = target.closest("th[data-column-field]").ok().flatten()?;
+    let name = header.get_attribute("data-column-field")?;
+    Some((host, header, name))
+}
+
+fn on_drag_start(event: &web_sys::Event) {
+    if let Some((host, _, name)) = dragged_header(event)
+        && let Some(element_id) = host.get_attribute("be_id")
+    {
+        DRAGGED.with(|d| *d.borrow_mut() = Some((element_id, name)));
+    }
+}
+
+fn on_drag_over(event: &web_sys::Event) {
+    let dragging = DRAGGED.with(|d| d.borrow().is_some());
+    if dragging && dragged_header(event).is_some() {
+        // allows the drop
+        event.prevent_default();
+    }
+}
+
+fn on_drop(event: &web_sys::Event) {
+    let dragged = DRAGGED.with(|d| d.borrow_mut().take());
+    let ((element_id, name), (host, _, target)) = match (dragged, dragged_header(event)) {
+        (Some(d), Some(h)) => (d, h),
+        _ => return,
+    };
+    event.prevent_default();
+    if host.get_attribute("be_id").as_deref() != Some(element_id.as_str()) || name == target {
+        return;
+    }
+
+    if let Some((key, mut column_layout, loaded_results)) = host_layout(&host) {
+        column_layout.move_field(&loaded_results.schema.fields, &name, &target);
+        save(&key, column_layout);
+        grid_layout::rerender(&host);
+    }
+}
+
+/// The button of the controls opening the column chooser.
+pub(crate) fn init_button(button: &Element) {
+    if button.get_attribute("bee").is_some() {
+        return;
+    }
+
+    button.set_inner_html(r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M1 2h4v12H1V2zm5 0h4v12H6V2zm5 0h4v12h-4V2z"/></svg> Columns"#);
+    let _ = button.set_attribute("title", "Show, hide and pin columns");
+    let _ = button.set_attribute("aria-label", "Columns");
+    let _ = button.set_attribute("aria-haspopup", "dialog");
+    let _ = button.set_attribute("aria-expanded", "false");
+
+    let on_click_closure = Closure::wrap(Box::new(on_button_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ =
+        button.add_event_listener_with_callback("click", on_click_closure.as_ref().unchecked_ref());
+    on_click_closure.forget();
+    let _ = button.set_attribute("bee", "1");
+}
+
+/// The (hidden) column chooser rendered by the controls.
+pub(crate) fn init_chooser(chooser: &Element) {
+    if chooser.get_attribute("bee").is_some() {
+        return;
+    }
+
+    let _ = chooser.set_attribute("style", "display: none;");
+    let _ = chooser.set_attribute("role", "dialog");
+    let _ = chooser.set_attribute("aria-label", "Columns");
+
+    let on_click_closure =
+        Closure::wrap(Box::new(on_chooser_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = chooser
+        .add_event_listener_with_callback("click", on_click_closure.as_ref().unchecked_ref());
+    on_click_closure.forget();
+    let on_key_down_closure =
+        Closure::wrap(Box::new(on_chooser_key_down) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = chooser
+        .add_event_listener_with_callback("keydown", on_key_down_closure.as_ref().unchecked_ref());
+    on_key_down_closure.forget();
+    let _ = chooser.set_attribute("bee", "1");
+}
+
+fn on_button_click(event: &web_sys::Event) {
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|b| host_of(&b))
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let chooser = match find_in_shadow(&host, COLUMN_CHOOSER) {
+        Some(c) => c,
+        None => return,
+    };
+
+    let open = chooser
+        .get_attribute("style")
+        .is_some_and(|style| style.contains("display: none"));
+    if open {
+        fill_chooser(&host, &chooser);
+        let _ = chooser.remove_attribute("style");
+        if let Some(first) = chooser
+            .query_selector("button")
+            .ok()
+            .flatten()
+            .and_then(|b| b.dyn_into::<web_sys::HtmlElement>().ok())
+        {
+            let _ = first.focus();
+        }
+    } else {
+        let _ = chooser.set_attribute("style", "display: none;");
+    }
+    if let Some(button) = event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        let _ = button.set_attribute("aria-expanded", if open { "true" } else { "false" });
+    }
+}
+
+/// The fields of the results as a tree of checkboxes, top level fields in the order shown.
+fn fill_chooser(host: &Element, chooser: &Element) {
+    let (_, column_layout, loaded_results) = match host_layout(host) {
+        Some(l) => l,
+        None => {
+            chooser.set_inner_html(r#"<p class="column-chooser-empty">No columns loaded.</p>"#);
+            return;
+        }
+    };
+    let fields = &loaded_results.schema.fields;
+
+    let mut visible_index = 0;
+    let items: String = column_layout
+        .ordered_names(fields)
+        .iter()
+        .filter_map(|name| fields.iter().find(|f| f.name == *name))
+        .map(|field| {
+            let pin = if column_layout.is_hidden(&field.name) {
+                String::new()
+            } else {
+                let pinned = visible_index < column_layout.pinned;
+                let button = format!(
+                    r#"<button type="button" class="column-pin" data-column-pin="{}" aria-pressed="{}" title="Keep the columns up to this one visible on a horizontal scroll">Pin</button>"#,
+                    visible_index, pinned
+                );
+                visible_index += 1;
+                button
+            };
+            field_item(field, "", &column_layout, false, &pin)
+        })
+        .collect();
+
+    chooser.set_inner_html(&format!(
+        concat!(
+            r#"<div class="column-chooser-header"><span>Columns</span>"#,
+            r#"<button type="button" data-columns-reset title="Show every column, in schema order, none pinned or resized">Reset</button></div>"#,
+            r#"<ul role="tree" aria-label="Columns">{}</ul>"#
+        ),
+        items
+    ));
+}
+
+fn field_item(
+    field: &TableFieldSchema,
+    parent: &str,
+    column_layout: &ColumnLayout,
+    parent_hidden: bool,
+    pin: &str,
+) -> String {
+    let path = field_path(parent, &field.name);
+    let hidden = column_layout.is_hidden(&path);
+    let sub_items = match &field.fields {
+        Some(sub_fields) => format!(
+            r#"<ul role="group">{}</ul>"#,
+            sub_fields
+                .iter()
+                .map(|f| field_item(f, &path, column_layout, parent_hidden || hidden, ""))
+                .collect::<String>()
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        r#"<li role="treeitem"><button type="button" role="checkbox" aria-checked="{}" data-column-path="{}"{}>{}</button> <span class="type">{}</span>{}{}</li>"#,
+        !hidden,
+        escape_html(&path),
+        if parent_hidden { " disabled" } else { "" },
+        escape_html(&field.name),
+        escape_html(&field.r#type),
+        pin,
+        sub_items
+    )
+}
+
+fn on_chooser_click(event: &web_sys::Event) {
+    let button = match event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("button").ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    let (host, chooser) = match (
+        host_of(&button),
+        event
+            .current_target()
+            .and_then(|t| t.dyn_into::<Element>().ok()),
+    ) {
+        (Some(h), Some(c)) => (h, c),
+        _ => return,
+    };
+    let (key, mut column_layout, _) = match host_layout(&host) {
+        Some(l) => l,
+        None => return,
+    };
+
+    let selector = if let Some(path) = button.get_attribute("data-column-path") {
+        let hidden = !column_layout.is_hidden(&path);
+        column_layout.set_hidden(&path, hidden);
+        format!("[data-column-path='{}']", path.replace('\'', "\\'"))
+    } else if let Some(index) = button
+        .get_attribute("data-column-pin")
+        .and_then(|i| i.parse::<usize>().ok())
+    {
+        column_layout.pinned = if column_layout.pinned == index + 1 {
+            index
+        } else {
+            index + 1
+        };
+        format!("[data-column-pin='{}']", index)
+    } else if button.has_attribute("data-columns-reset") {
+        column_layout = ColumnLayout::default();
+        "[data-columns-reset]".to_string()
+    } else {
+        return;
+    };
+
+    save(&key, column_layout);
+    grid_layout::rerender(&host);
+
+    // the items were rendered again, the focus stays on the one used
+    fill_chooser(&host, &chooser);
+    if let Some(same_button) = chooser
+        .query_selector(&selector)
+        .ok()
+        .flatten()
+        .and_then(|b| b.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = same_button.focus();
+    }
+}
+
+fn on_chooser_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    // the keys of the grid do not apply in the chooser
+    event.stop_propagation();
+
+    if keyboard_event.key() == "Escape"
+        && let Some(chooser) = event
+            .current_target()
+            .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        event.prevent_default();
+        let _ = chooser.set_attribute("style", "display: none;");
+        if let Some(button) = chooser
+            .previous_element_sibling()
+            .filter(|b| b.get_attribute("aria-haspopup").is_some())
+        {
+            let _ = button.set_attribute("aria-expanded", "false");
+            if let Some(button) = button.dyn_ref::<web_sys::HtmlElement>() {
+                let _ = button.focus();
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Serialize;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::Element;
+
+use crate::bigquery::error::BigQueryError;
+
+use super::{
+    bq_common_custom_element::get_opt_num_attribute, cell_value::CellValue,
+    export::to_grid_records, grid_columns, grid_layout,
+};
+
+/*
+Public events dispatched by `bq-table`, `bq-query` and `bq-script` for the host page.
+All of them bubble and are composed, so they cross the shadow root and reach `document`.
+See `grid_events.md` for the `detail` of each one.
+*/
+pub(crate) const EVENT_PAGE_CHANGED: &str = "bq-page-changed";
+pub(crate) const EVENT_LOAD_COMPLETE: &str = "bq-load-complete";
+pub(crate) const EVENT_LOAD_ERROR: &str = "bq-load-error";
+pub(crate) const EVENT_ROW_SELECTED: &str = "bq-row-selected";
+pub(crate) const EVENT_CELL_SELECTED: &str = "bq-cell-selected";
+
+pub(crate) fn get_all() -> Vec<&'static str> {
+    vec![
+        EVENT_PAGE_CHANGED,
+        EVENT_LOAD_COMPLETE,
+        EVENT_LOAD_ERROR,
+        EVENT_ROW_SELECTED,
+        EVENT_CELL_SELECTED,
+    ]
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct PageChangedDetail {
+    pub page_start_index: usize,
+    pub page_size: usize,
+    pub rows_total: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct LoadCompleteDetail {
+    pub page_start_index: usize,
+    pub rows_in_page: usize,
+    pub rows_total: Option<usize>,
+}
+
+/// `bq-load-complete` of `bq-script`, once every child job is done.
+#[derive(Debug, Serialize)]
+pub(crate) struct ScriptLoadCompleteDetail {
+    pub job_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct LoadErrorDetail<'a> {
+    /// The API call that failed, e.g. `tables.get` or `jobs.getQueryResults`.
+    pub operation: &'a str,
+    /// e.g. `notFound` or `accessDenied`, also found in `error.errors`.
+    pub reason: Option<&'a str>,
+    pub error: &'a BigQueryError,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RowSelectedDetail {
+    /// Row number as shown in the index column (1 based, across pages).
+    pub row_number: Option<usize>,
+    pub cells: Vec<CellDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct CellSelectedDetail {
+    pub row_number: Option<usize>,
+    #[serde(flatten)]
+    pub cell: CellDetail,
+}
+
+/// A cell of the loaded page, not its text in the DOM.
+#[derive(Debug, Serialize)]
+pub(crate) struct CellDetail {
+    /// Path of the column, `parent.child` for the fields of a RECORD shown as columns.
+    pub column: String,
+    /// As exported to JSON: INT64 and NUMERIC as strings, ARRAY and STRUCT as values.
+    pub value: serde_json::Value,
+    pub is_null: bool,
+}
+
+pub(crate) fn dispatch_grid_event<T: Serialize>(element: &Element, event_name: &str, detail: &T) {
+    // plain objects for the maps of the JSON values, not `Map`s
+    let detail = match detail.serialize(&serde_wasm_bindgen::Serializer::json_compatible()) {
+        Ok(d) => d,
+        Err(e) => {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "dispatch_grid_event: failed to serialize '{}' detail: {}",
+                event_name, e
+            )));
+            return;
+        }
+    };
+
+    let custom_event_init = web_sys::CustomEventInit::new();
+    custom_event_init.set_bubbles(true);
+    custom_event_init.set_composed(true);
+    custom_event_init.set_detail(&detail);
+
+    if let Ok(event) =
+        web_sys::CustomEvent::new_with_event_init_dict(event_name, &custom_event_init)
+    {
+        let _ = element.dispatch_event(&event);
+    }
+}
+
+/// `bq-page-changed`, read back from the paging attributes the element has just been given.
+pub(crate) fn dispatch_page_changed(element: &Element) {
+    dispatch_grid_event(
+        element,
+        EVENT_PAGE_CHANGED,
+        &PageChangedDetail {
+            page_start_index: get_opt_num_attribute(element, "page_start_index").unwrap_or(0),
+            page_size: get_opt_num_attribute(element, "page_size").unwrap_or(50),
+            rows_total: get_opt_num_attribute(element, "rows_total"),
+        },
+    );
+}
+
+pub(crate) fn dispatch_load_error(element: &Element, operation: &str, error: &BigQueryError) {
+    web_sys::console::error_1(&JsValue::from_str(&format!("{}: {}", operation, error)));
+
+    dispatch_grid_event(
+        element,
+        EVENT_LOAD_ERROR,
+        &LoadErrorDetail {
+            operation,
+            reason: error.reason(),
+            error,
+        },
+    );
+}
+
+/// Click listener of the grid elements: a click on the index column selects the row,
+/// a click on any other data cell selects that cell.
+pub(crate) fn on_grid_click(event: &web_sys::Event) {
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(e) => e,
+        None => return,
+    };
+
+    // the target is retargeted to the host outside the shadow root, the original one is first in the path
+    let cell = match event
+        .composed_path()
+        .get(0)
+        .dyn_into::<Element>()
+        .ok()
+        // the drill-down grid of the overlay panel is not the loaded page
+        .filter(|t| t.closest(".overlay-body").ok().flatten().is_none())
+        .and_then(|t| t.closest("bstruct-table td").ok().flatten())
+    {
+        Some(c) => c,
+        None => return,
+    };
+
+    let row = match cell.parent_element() {
+        Some(r) => r,
+        None => return,
+    };
+    let row_number = row
+        .query_selector(":scope > td.index")
+        .ok()
+        .flatten()
+        .and_then(|index_cell| index_cell.text_content())
+        .and_then(|t| t.trim().parse::<usize>().ok());
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let cells = match row_number.and_then(|n| row_cells(&element_id, n)) {
+        Some(c) => c,
+        None => return,
+    };
+
+    if cell.class_list().contains("index") {
+        dispatch_grid_event(
+            &host,
+            EVENT_ROW_SELECTED,
+            &RowSelectedDetail { row_number, cells },
+        );
+    } else {
+        let mut column = 0;
+        let mut previous = cell.previous_element_sibling();
+        while let Some(p) = previous {
+            if !p.class_list().contains("index") {
+                column += 1;
+            }
+            previous = p.previous_element_sibling();
+        }
+
+        if let Some(cell) = cells.into_iter().nth(column) {
+            dispatch_grid_event(
+                &host,
+                EVENT_CELL_SELECTED,
+                &CellSelectedDetail { row_number, cell },
+            );
+        }
+    }
+}
+
+/// The cells of the row `row_number` (as in the index column) of the page loaded by the
+/// element `element_id`, one per column of the grid.
+fn row_cells(element_id: &str, row_number: usize) -> Option<Vec<CellDetail>> {
+    // hidden and moved columns applied, as the grid shows them
+    let loaded_results = grid_columns::visible_results(element_id)?;
+    let row = loaded_results
+        .rows
+        .get(row_number.checked_sub(loaded_results.page_start_index + 1)?)?;
+    // the columns of the grid: a RECORD shown as JSON or key: value is one column
+    let (columns, records) = to_grid_records(
+        &loaded_results.schema.fields,
+        std::slice::from_ref(row),
+        &grid_layout::get(),
+    );
+
+    let cells = records.into_iter().next()?;
+    Some(
+        columns
+            .into_iter()
+            .zip(cells)
+            .map(|(column, value)| CellDetail {
+                column,
+                is_null: value == CellValue::Null,
+                value: serde_json::from_str(&value.to_json()).unwrap_or_default(),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use wasm_bindgen::{JsCast, prelude::Closure};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn page_changed_bubbles_with_detail() {
+        let parent_node = &crate::createElement("div");
+        let element = &crate::createElement("bq-table");
+        element.set_attribute("page_start_index", "100").unwrap();
+        element.set_attribute("page_size", "50").unwrap();
+        element.set_attribute("rows_total", "1234").unwrap();
+        parent_node.append_child(element).unwrap();
+
+        let received: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+        let received_clone = received.clone();
+        let listener = Closure::wrap(Box::new(move |event: web_sys::CustomEvent| {
+            let detail = js_sys::JSON::stringify(&event.detail()).unwrap();
+            *received_clone.borrow_mut() = detail.as_string();
+        }) as Box<dyn Fn(web_sys::CustomEvent)>);
+        parent_node
+            .add_event_listener_with_callback(
+                super::EVENT_PAGE_CHANGED,
+                listener.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+
+        super::dispatch_page_changed(element);
+
+        assert_eq!(
+            received.borrow().as_deref(),
+            Some(r#"{"page_start_index":100,"page_size":50,"rows_total":1234}"#)
+        );
+    }
+}
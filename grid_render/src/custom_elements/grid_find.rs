@@ -0,0 +1,467 @@
+use std::{cell::RefCell, collections::HashMap, ops::Range};
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    find::{
+        FindMatch, find_in_rows, highlighted_html, match_status, text_matches, utf16_to_byte_offset,
+    },
+    grid_columns, grid_layout,
+    grid_selection::{grid_rows, grid_table},
+    layout::ColumnLayout,
+    to_table_builder::rows_to_table_builder,
+};
+
+/*
+Find bar of the controls of `bq-table` and `bq-query`: the find of the webview does not see
+inside the shadow roots of the grid, so Ctrl+F opens this one instead. The values of the loaded
+page are searched (`find`), the matches highlighted in the grid; Enter and Shift+Enter (or the
+arrows) go to the next and previous one, scrolling to it and expanding the cells holding it.
+The search runs again on every render of the grid (a new page, a column moved...).
+*/
+pub(crate) const FIND_BAR: &str = "find_bar";
+const CURRENT_CLASS: &str = "current";
+/// A cell holding the current match, shown whole.
+const EXPANDED_CLASS: &str = "find-expanded";
+
+#[derive(Debug, Clone, Default)]
+struct FindState {
+    matches: Vec<FindMatch>,
+    current: Option<usize>,
+}
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static FINDS: RefCell<HashMap<String, FindState>> = RefCell::new(HashMap::new());
+}
+
+/// The find bar rendered by the controls.
+pub(crate) fn init_bar(bar: &Element) {
+    if bar.get_attribute("bee").is_some() {
+        return;
+    }
+
+    let _ = bar.set_attribute("role", "search");
+    let _ = bar.set_attribute("aria-label", "Find in results");
+    bar.set_inner_html(concat!(
+        r#"<input type="search" data-find-input placeholder="Find" aria-label="Find in results" title="Find in the values of this page (Ctrl+F)" aria-keyshortcuts="Control+F">"#,
+        r#"<button type="button" data-find-option="case" aria-pressed="false" title="Match case" aria-label="Match case">Aa</button>"#,
+        r#"<button type="button" data-find-option="regex" aria-pressed="false" title="Use regular expression" aria-label="Use regular expression">.*</button>"#,
+        r#"<span data-find-status role="status" aria-live="polite"></span>"#,
+        r#"<button type="button" data-find-step="-1" title="Previous match (Shift+Enter)" aria-label="Previous match">&#8593;</button>"#,
+        r#"<button type="button" data-find-step="1" title="Next match (Enter)" aria-label="Next match">&#8595;</button>"#
+    ));
+
+    let listeners = [
+        ("input", on_input as fn(&web_sys::Event)),
+        ("click", on_click),
+        ("keydown", on_bar_key_down),
+    ];
+    for (event_type, listener) in listeners {
+        let closure = Closure::wrap(Box::new(listener) as Box<dyn Fn(&web_sys::Event)>);
+        let _ = bar.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+    let _ = bar.set_attribute("bee", "1");
+}
+
+/// Binds Ctrl+F (Cmd+F) on a grid element to the find bar.
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    let on_key_down_closure = Closure::wrap(Box::new(on_key_down) as Box<dyn Fn(&web_sys::Event)>);
+    if let Err(e) = element
+        .add_event_listener_with_callback("keydown", on_key_down_closure.as_ref().unchecked_ref())
+    {
+        web_sys::console::error_1(&JsValue::from_str(&format!(
+            "{} define: failed to add 'keydown' listener: {:?}",
+            tag_name, e
+        )));
+    }
+    on_key_down_closure.forget();
+}
+
+fn on_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    if !(keyboard_event.ctrl_key() || keyboard_event.meta_key())
+        || !keyboard_event.key().eq_ignore_ascii_case("f")
+    {
+        return;
+    }
+    let input = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|host| find_input(&host))
+    {
+        Some(i) => i,
+        None => return,
+    };
+    event.prevent_default();
+    let _ = input.focus();
+    input.select();
+}
+
+fn on_input(event: &web_sys::Event) {
+    if let Some(host) = event_host(event) {
+        run(&host, true);
+    }
+}
+
+fn on_click(event: &web_sys::Event) {
+    let (host, target) = match (
+        event_host(event),
+        event.target().and_then(|t| t.dyn_into::<Element>().ok()),
+    ) {
+        (Some(h), Some(t)) => (h, t),
+        _ => return,
+    };
+
+    if let Some(option) = target.closest("button[data-find-option]").ok().flatten() {
+        let pressed = option.get_attribute("aria-pressed").as_deref() == Some("true");
+        let _ = option.set_attribute("aria-pressed", if pressed { "false" } else { "true" });
+        run(&host, true);
+    } else if let Some(step) = target
+        .closest("button[data-find-step]")
+        .ok()
+        .flatten()
+        .and_then(|b| b.get_attribute("data-find-step"))
+        .and_then(|s| s.parse::<i32>().ok())
+    {
+        go_to_next(&host, step);
+    }
+}
+
+fn on_bar_key_down(event: &web_sys::Event) {
+    let (keyboard_event, host) =
+        match (event.dyn_ref::<web_sys::KeyboardEvent>(), event_host(event)) {
+            (Some(k), Some(h)) => (k, h),
+            _ => return,
+        };
+    // the keys of the grid do not apply in the find bar
+    event.stop_propagation();
+
+    match keyboard_event.key().as_str() {
+        "Enter" => {
+            event.prevent_default();
+            go_to_next(&host, if keyboard_event.shift_key() { -1 } else { 1 });
+        }
+        "Escape" => {
+            event.prevent_default();
+            if let Some(input) = find_input(&host) {
+                input.set_value("");
+            }
+            run(&host, true);
+        }
+        _ => {}
+    }
+}
+
+/// Searches the page again after a render of the grid of `host`, when something is searched.
+pub(crate) fn refresh(host: &Element) {
+    if find_input(host).is_some_and(|i| !i.value().is_empty()) {
+        run(host, false);
+    }
+}
+
+/// Searches the loaded page for the text of the find bar and highlights the matches, going to
+/// the first one when `from_start` (the search changed), keeping the current one otherwise.
+fn run(host: &Element, from_start: bool) {
+    let (element_id, table, bar, input) = match (
+        host.get_attribute("be_id"),
+        grid_table(host),
+        find_bar(host),
+        find_input(host),
+    ) {
+        (Some(id), Some(t), Some(b), Some(i)) => (id, t, b, i),
+        _ => return,
+    };
+    clear_highlights(&table);
+    let _ = input.remove_attribute("aria-invalid");
+
+    let query = input.value();
+    let visible = match grid_columns::visible_results(&element_id) {
+        Some(v) if !query.is_empty() => v,
+        _ => {
+            FINDS.with(|f| f.borrow_mut().remove(&element_id));
+            set_status(&bar, "");
+            return;
+        }
+    };
+    let option = |name: &str| {
+        bar.query_selector(&format!("button[data-find-option='{}']", name))
+            .ok()
+            .flatten()
+            .is_some_and(|b| b.get_attribute("aria-pressed").as_deref() == Some("true"))
+    };
+    let (case_sensitive, regex) = (option("case"), option("regex"));
+
+    // the values as rendered, the footer left out
+    let rows: Vec<(usize, serde_json::Value)> = visible
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (visible.page_start_index + 1 + i, row.clone()))
+        .collect();
+    let table_builder = rows_to_table_builder(
+        &visible.schema.fields,
+        &rows,
+        &grid_layout::get(),
+        &ColumnLayout::default(),
+    );
+    let matches = if regex {
+        match regex_find(&query, case_sensitive) {
+            Some(find) => find_in_rows(&table_builder.rows, &find),
+            None => {
+                let _ = input.set_attribute("aria-invalid", "true");
+                FINDS.with(|f| f.borrow_mut().remove(&element_id));
+                set_status(&bar, "Invalid regular expression");
+                return;
+            }
+        }
+    } else {
+        find_in_rows(&table_builder.rows, &|text| {
+            text_matches(text, &query, case_sensitive)
+        })
+    };
+
+    highlight(&table, &matches);
+    let previous = FINDS.with(|f| f.borrow().get(&element_id).and_then(|s| s.current));
+    let current = match (matches.len(), from_start) {
+        (0, _) => None,
+        (_, true) => Some(0),
+        (len, false) => Some(previous.unwrap_or(0).min(len - 1)),
+    };
+    FINDS.with(|f| {
+        f.borrow_mut()
+            .insert(element_id, FindState { matches, current })
+    });
+    show_current(host, from_start);
+}
+
+/// Finder of the matches of the JavaScript regular expression `pattern` in a text, `None`
+/// when `pattern` is not one.
+fn regex_find(pattern: &str, case_sensitive: bool) -> Option<impl Fn(&str) -> Vec<Range<usize>>> {
+    let flags = if case_sensitive { "g" } else { "gi" };
+    let constructor = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("RegExp")).ok()?;
+    let regex = js_sys::Reflect::construct(
+        constructor.dyn_ref::<js_sys::Function>()?,
+        &js_sys::Array::of2(&JsValue::from_str(pattern), &JsValue::from_str(flags)),
+    )
+    .ok()?
+    .dyn_into::<js_sys::RegExp>()
+    .ok()?;
+
+    Some(move |text: &str| {
+        let mut ranges = Vec::new();
+        regex.set_last_index(0);
+        while let Some(found) = regex.exec(text) {
+            let index = js_sys::Reflect::get(&found, &JsValue::from_str("index"))
+                .ok()
+                .and_then(|i| i.as_f64())
+                .unwrap_or(0.0) as usize;
+            let length = found
+                .get(0)
+                .as_string()
+                .unwrap_or_default()
+                .encode_utf16()
+                .count();
+            if length == 0 {
+                // an empty match (e.g. `a*`) would be found again
+                regex.set_last_index(regex.last_index() + 1);
+                continue;
+            }
+            ranges.push(
+                utf16_to_byte_offset(text, index)..utf16_to_byte_offset(text, index + length),
+            );
+        }
+        ranges
+    })
+}
+
+/// Wraps the matches in `<mark>`s, in the leaf cells holding them.
+fn highlight(table: &Element, matches: &[FindMatch]) {
+    let (_, data_rows) = grid_rows(table);
+    let mut first = 0;
+    while first < matches.len() {
+        let m = &matches[first];
+        let same_leaf = matches[first..]
+            .iter()
+            .take_while(|o| (o.row, o.cell, o.leaf) == (m.row, m.cell, m.leaf))
+            .count();
+        let ranges: Vec<Range<usize>> = matches[first..first + same_leaf]
+            .iter()
+            .map(|o| o.range.clone())
+            .collect();
+
+        if let Some(holder) = data_rows
+            .get(m.row)
+            .and_then(|row| leaf_cell(row, m.cell, m.leaf))
+            .map(|leaf| text_holder(&leaf))
+        {
+            let text = holder.text_content().unwrap_or_default();
+            // the text is the rendered value, unless the grid changed under the search
+            let fits = ranges.iter().all(|r| {
+                text.is_char_boundary(r.start)
+                    && text.is_char_boundary(r.end)
+                    && r.end <= text.len()
+            });
+            if fits {
+                let _ = holder.set_attribute("data-find-text", &text);
+                holder.set_inner_html(&highlighted_html(&text, &ranges, first));
+            }
+        }
+        first += same_leaf;
+    }
+}
+
+/// The `leaf`-th cell without an inner table of the top level cell `cell_index` of `row`.
+fn leaf_cell(row: &Element, cell_index: usize, leaf: usize) -> Option<Element> {
+    let cell = row.children().item(cell_index as u32)?;
+    if cell.query_selector("td").ok().flatten().is_none() {
+        return (leaf == 0).then_some(cell);
+    }
+    let cells = cell.query_selector_all("td").ok()?;
+    (0..cells.length())
+        .filter_map(|i| cells.item(i).and_then(|c| c.dyn_into::<Element>().ok()))
+        .filter(|c| c.query_selector("td").ok().flatten().is_none())
+        .nth(leaf)
+}
+
+/// The innermost element of `cell` holding its text.
+fn text_holder(cell: &Element) -> Element {
+    let mut holder = cell.clone();
+    while holder.child_element_count() == 1 {
+        match holder.first_element_child() {
+            Some(child) => holder = child,
+            None => break,
+        }
+    }
+    holder
+}
+
+fn clear_highlights(table: &Element) {
+    if let Ok(holders) = table.query_selector_all("[data-find-text]") {
+        for i in 0..holders.length() {
+            if let Some(holder) = holders.item(i).and_then(|h| h.dyn_into::<Element>().ok()) {
+                let text = holder.get_attribute("data-find-text");
+                holder.set_text_content(text.as_deref());
+                let _ = holder.remove_attribute("data-find-text");
+            }
+        }
+    }
+    if let Ok(expanded) = table.query_selector_all(&format!(".{}", EXPANDED_CLASS)) {
+        for i in 0..expanded.length() {
+            if let Some(cell) = expanded.item(i).and_then(|c| c.dyn_into::<Element>().ok()) {
+                let _ = cell.class_list().remove_1(EXPANDED_CLASS);
+            }
+        }
+    }
+}
+
+/// Moves to the match `step` after the current one, wrapping around.
+fn go_to_next(host: &Element, step: i32) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let moved = FINDS.with(|f| {
+        let mut finds = f.borrow_mut();
+        let state = finds.get_mut(&element_id)?;
+        let len = state.matches.len() as i32;
+        let current = state.current? as i32;
+        state.current = Some((current + step).rem_euclid(len) as usize);
+        Some(())
+    });
+    match moved {
+        Some(_) => show_current(host, true),
+        // nothing searched yet (e.g. the options changed with an empty box)
+        None => run(host, true),
+    }
+}
+
+/// Marks the current match and updates the status, scrolling to the match when `scroll`.
+fn show_current(host: &Element, scroll: bool) {
+    let (element_id, table, bar) = match (
+        host.get_attribute("be_id"),
+        grid_table(host),
+        find_bar(host),
+    ) {
+        (Some(id), Some(t), Some(b)) => (id, t, b),
+        _ => return,
+    };
+    let (current, total) = FINDS.with(|f| {
+        f.borrow()
+            .get(&element_id)
+            .map(|s| (s.current, s.matches.len()))
+            .unwrap_or((None, 0))
+    });
+    set_status(&bar, &match_status(current, total));
+
+    if let Ok(previous) = table.query_selector_all(&format!("mark.{}", CURRENT_CLASS)) {
+        for i in 0..previous.length() {
+            if let Some(mark) = previous.item(i).and_then(|m| m.dyn_into::<Element>().ok()) {
+                let _ = mark.class_list().remove_1(CURRENT_CLASS);
+            }
+        }
+    }
+    let mark = match current.and_then(|c| {
+        table
+            .query_selector(&format!("mark[data-find-index='{}']", c))
+            .ok()
+            .flatten()
+    }) {
+        Some(m) => m,
+        None => return,
+    };
+    let _ = mark.class_list().add_1(CURRENT_CLASS);
+    if !scroll {
+        return;
+    }
+
+    // the cells holding the match, the inner ones of ARRAYs included, are shown whole
+    let mut parent = mark.parent_element();
+    while let Some(p) = parent {
+        if p == table {
+            break;
+        }
+        if p.tag_name().eq_ignore_ascii_case("td") {
+            let _ = p.class_list().add_1(EXPANDED_CLASS);
+        }
+        parent = p.parent_element();
+    }
+    let options = web_sys::ScrollIntoViewOptions::new();
+    options.set_block(web_sys::ScrollLogicalPosition::Center);
+    options.set_inline(web_sys::ScrollLogicalPosition::Nearest);
+    mark.scroll_into_view_with_scroll_into_view_options(&options);
+}
+
+fn set_status(bar: &Element, status: &str) {
+    if let Ok(Some(element)) = bar.query_selector("[data-find-status]") {
+        element.set_text_content(Some(status));
+    }
+}
+
+fn event_host(event: &web_sys::Event) -> Option<Element> {
+    event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())?
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+}
+
+fn find_bar(host: &Element) -> Option<Element> {
+    host.shadow_root()?
+        .query_selector(&format!("[be_id='{}']", FIND_BAR))
+        .ok()
+        .flatten()
+}
+
+fn find_input(host: &Element) -> Option<web_sys::HtmlInputElement> {
+    find_bar(host)?
+        .query_selector("input[data-find-input]")
+        .ok()
+        .flatten()?
+        .dyn_into::<web_sys::HtmlInputElement>()
+        .ok()
+}
@@ -0,0 +1,111 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::JsValue;
+use web_sys::Element;
+use website_component_table::HtmlNodeRender;
+
+use super::{
+    grid_columns, grid_find, grid_navigation,
+    grid_selection::{self, grid_table},
+    layout::{GridLayout, StructPresentation},
+    to_table_builder::{footer_index, rows_to_table_builder},
+};
+
+/*
+The layout of the grid of `bq-table` and `bq-query` chosen by the user, kept in the local storage
+of the webview so it applies to every result with the same field paths.
+*/
+const STORAGE_KEY: &str = "bq-grid-layout";
+
+thread_local! {
+    static LAYOUT: RefCell<Option<GridLayout>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn get() -> GridLayout {
+    if let Some(layout) = LAYOUT.with(|l| l.borrow().clone()) {
+        return layout;
+    }
+
+    let layout = local_storage()
+        .and_then(|s| s.get_item(STORAGE_KEY).ok().flatten())
+        .and_then(|text| serde_json::from_str::<GridLayout>(&text).ok())
+        .unwrap_or_default();
+    LAYOUT.with(|l| *l.borrow_mut() = Some(layout.clone()));
+    layout
+}
+
+/// Shows the RECORD at `path` as `presentation` and renders the grid of `host` again.
+pub(crate) fn set_struct_presentation(
+    host: &Element,
+    path: &str,
+    presentation: StructPresentation,
+) {
+    let mut layout = get();
+    layout.set_struct_presentation(path, presentation);
+    save(&layout);
+
+    rerender(host);
+}
+
+fn save(layout: &GridLayout) {
+    LAYOUT.with(|l| *l.borrow_mut() = Some(layout.clone()));
+
+    let text = match serde_json::to_string(layout) {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+    if let Some(storage) = local_storage()
+        && let Err(e) = storage.set_item(STORAGE_KEY, &text)
+    {
+        web_sys::console::error_1(&JsValue::from_str(&format!(
+            "grid_layout: failed to save the layout: {:?}",
+            e
+        )));
+    }
+}
+
+/// Replaces the grid of `host` with one laid out by the current layouts, from the loaded rows.
+pub(crate) fn rerender(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (loaded_results, shadow_root, table) = match (
+        grid_columns::visible_results(&element_id),
+        host.shadow_root(),
+        grid_table(host),
+    ) {
+        (Some(r), Some(s), Some(t)) => (r, s, t),
+        _ => return,
+    };
+
+    let rows: Vec<(usize, serde_json::Value)> = loaded_results
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| (loaded_results.page_start_index + 1 + i, row.clone()))
+        .collect();
+    let column_layout = grid_columns::column_layout(&element_id).unwrap_or_default();
+    let table_builder =
+        rows_to_table_builder(&loaded_results.schema.fields, &rows, &get(), &column_layout);
+    let nodes = match table_builder.render() {
+        Ok(n) => n,
+        Err(e) => {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "grid_layout: table render failed: {:?}",
+                e
+            )));
+            return;
+        }
+    };
+
+    table.remove();
+    for node in nodes.iter().filter_map(|n| n.to_element_node().ok()) {
+        let _ = shadow_root.append_child(&node);
+    }
+    grid_selection::clear(&element_id);
+    grid_navigation::apply_aria(host, footer_index(&table_builder));
+    grid_columns::apply(host);
+    grid_find::refresh(host);
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
@@ -0,0 +1,190 @@
+use wasm_bindgen::{JsCast, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    cell_value::CellValue,
+    chart::SERIES_COLORS,
+    export::{to_grid_columns, to_grid_records},
+    geography::{geography_columns, map_svg, page_features, parse_wkt, thumbnail_svg},
+    grid_columns, grid_layout,
+    grid_navigation::set_active_cell,
+    grid_selection::{grid_rows, grid_table},
+    layout::ColumnLayout,
+    loaded_results::LoadedResults,
+    overlay_panel,
+    profile::field_at,
+    value_tree::escape_html,
+};
+
+/*
+GEOGRAPHY columns of the grid of `bq-table` and `bq-query`: the map of the page in the overlay
+panel (opened from the context menu of the header of such a column), every geography of the
+rows as the user sees them drawn by `geography`, and the optional thumbnails at the start of
+the cells of a column. Hovering (or focusing) a shape marks its row in the grid, clicking it
+(or `Enter`) closes the panel on the `#` cell of the row.
+*/
+const LINKED_CLASS: &str = "map-linked";
+
+/// Whether the leaf column at `path` of the results loaded in `host` is a GEOGRAPHY one.
+pub(crate) fn is_geography(host: &Element, path: &str) -> bool {
+    host.get_attribute("be_id")
+        .and_then(|id| grid_columns::visible_results(&id))
+        .and_then(|v| field_at(&v.schema.fields, path).map(|(f, _)| f.r#type == "GEOGRAPHY"))
+        .unwrap_or(false)
+}
+
+/// Opens the map of the geographies of the page of `host`.
+pub(crate) fn open(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let visible = match grid_columns::visible_results(&element_id) {
+        Some(v) => v,
+        None => return,
+    };
+    let fields = &visible.schema.fields;
+    let columns = geography_columns(fields);
+    let (features, invalid) = page_features(fields, &visible.rows);
+
+    let mut meta = format!(
+        "{} geograph{} of {} row{}",
+        features.len(),
+        if features.len() == 1 { "y" } else { "ies" },
+        visible.rows.len(),
+        if visible.rows.len() == 1 { "" } else { "s" }
+    );
+    if invalid > 0 {
+        meta.push_str(&format!(" · {} not valid WKT, left out", invalid));
+    }
+    let body = match overlay_panel::open(host, "Map of the page", &meta, vec![]) {
+        Some(b) => b,
+        None => return,
+    };
+
+    let legend: String = match columns.len() {
+        0 | 1 => String::new(),
+        _ => {
+            let items: String = columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    format!(
+                        r#"<li><span class="swatch" style="background-color: {}"></span>{}</li>"#,
+                        SERIES_COLORS[i % SERIES_COLORS.len()].css_var(),
+                        escape_html(c)
+                    )
+                })
+                .collect();
+            format!(r#"<ul class="geo-legend">{}</ul>"#, items)
+        }
+    };
+    let content = match features.is_empty() {
+        true => r#"<p class="geo-empty">No geography on the page.</p>"#.to_string(),
+        false => format!(
+            r#"<div class="geo-area">{}</div>{}"#,
+            map_svg(&features, &columns, visible.page_start_index + 1),
+            legend
+        ),
+    };
+    body.set_inner_html(&content);
+
+    if let Ok(Some(map)) = body.query_selector("svg.geo-map") {
+        for event in ["mouseover", "focusin"] {
+            let closure = Closure::wrap(Box::new(on_hover) as Box<dyn Fn(&web_sys::Event)>);
+            let _ = map.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+            closure.forget();
+        }
+    }
+}
+
+/// A shape of the map clicked, or `Enter` on it: the panel closed on the `#` cell of its row.
+pub(crate) fn on_activate(host: &Element, feature: &Element) {
+    let row = match feature
+        .get_attribute("data-map-row")
+        .and_then(|r| r.parse::<usize>().ok())
+    {
+        Some(r) => r,
+        None => return,
+    };
+    overlay_panel::close(host);
+    set_active_cell(host, row, 0);
+}
+
+fn on_hover(event: &web_sys::Event) {
+    let target = match event.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+        Some(t) => t,
+        None => return,
+    };
+    let host = match target
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let row = target
+        .closest("[data-map-row]")
+        .ok()
+        .flatten()
+        .and_then(|f| f.get_attribute("data-map-row"))
+        .and_then(|r| r.parse::<usize>().ok());
+    mark_row(&host, row);
+}
+
+/// Marks the data row `row` of the grid (none: no row, e.g. the panel closed), scrolled into view.
+pub(crate) fn mark_row(host: &Element, row: Option<usize>) {
+    let table = match grid_table(host) {
+        Some(t) => t,
+        None => return,
+    };
+    let (_, data_rows) = grid_rows(&table);
+    for (i, data_row) in data_rows.iter().enumerate() {
+        if Some(i) == row {
+            let _ = data_row.class_list().add_1(LINKED_CLASS);
+            let options = web_sys::ScrollIntoViewOptions::new();
+            options.set_block(web_sys::ScrollLogicalPosition::Nearest);
+            data_row.scroll_into_view_with_scroll_into_view_options(&options);
+        } else {
+            let _ = data_row.class_list().remove_1(LINKED_CLASS);
+        }
+    }
+}
+
+/// Starts the cells of the GEOGRAPHY columns listed in the column layout with the thumbnail
+/// of their value, on a freshly rendered grid.
+pub(crate) fn apply_thumbnails(
+    visible: &LoadedResults,
+    column_layout: &ColumnLayout,
+    data_rows: &[Element],
+) {
+    if column_layout.thumbnails.is_empty() {
+        return;
+    }
+    let layout = grid_layout::get();
+    let fields = &visible.schema.fields;
+    let columns = to_grid_columns(fields, &layout);
+    let (_, records) = to_grid_records(fields, &visible.rows, &layout);
+
+    for (column, (path, field)) in columns.iter().enumerate() {
+        // a REPEATED column shows its values in a nested table, left as is
+        if field.r#type != "GEOGRAPHY"
+            || field.mode.as_deref() == Some("REPEATED")
+            || !column_layout.thumbnails.contains(path)
+        {
+            continue;
+        }
+        for (row, record) in data_rows.iter().zip(&records) {
+            let geometry = match record.get(column) {
+                Some(CellValue::Text(text)) => match parse_wkt(text) {
+                    Ok(g) => g,
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            // the `#` cell first
+            if let Some(cell) = row.children().item(column as u32 + 1) {
+                let _ = cell.insert_adjacent_html("afterbegin", &thumbnail_svg(&geometry));
+            }
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+};
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    bq_common_custom_element::get_opt_num_attribute,
+    cell_inspector,
+    data_table_controls_element::{
+        EVENT_GO_TO_FIRST_PAGE, EVENT_GO_TO_LAST_PAGE, EVENT_GO_TO_NEXT_PAGE,
+        EVENT_GO_TO_PREVIOUS_PAGE, go_to_page,
+    },
+    drill_down,
+    grid_columns::FOOTER_CLASS,
+    grid_selection::{grid_rows, grid_table, select_cell},
+};
+
+/*
+ARIA grid semantics and keyboard navigation of the grid of `bq-table` and `bq-query`.
+Only the top level cells take part: a nested (ARRAY) table is read as the content of its cell.
+One cell at a time is in the tab order (roving tabindex), the arrow keys move it.
+*/
+
+// rows moved by PageUp / PageDown inside the page of results
+const ROWS_PER_PAGE_KEY: usize = 10;
+
+thread_local! {
+    // keyed by the `be_id` of the element: (data row, cell index), the index cell is 0
+    static ACTIVE_CELLS: RefCell<HashMap<String, (usize, usize)>> = RefCell::new(HashMap::new());
+    // elements paged from the keyboard, the focus goes back to the grid once rendered
+    static REFOCUS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    let on_event_type_closure =
+        Closure::wrap(Box::new(on_key_down) as Box<dyn Fn(&web_sys::Event)>);
+    if let Err(e) = element
+        .add_event_listener_with_callback("keydown", on_event_type_closure.as_ref().unchecked_ref())
+    {
+        web_sys::console::error_1(&JsValue::from_str(&format!(
+            "{} define: failed to add 'keydown' listener: {:?}",
+            tag_name, e
+        )));
+    }
+    on_event_type_closure.forget();
+}
+
+/// Roles, row indexes and the roving tabindex of a freshly rendered grid, its row
+/// `footer_index` the footer of the aggregates (see `to_table_builder::footer_index`).
+pub(crate) fn apply_aria(host: &Element, footer_index: Option<usize>) {
+    let table = match grid_table(host) {
+        Some(t) => t,
+        None => return,
+    };
+    // tagged before the rows are counted, the footer is none of the data rows
+    if let Some(footer) = footer_index.and_then(|i| grid_rows(&table).1.into_iter().nth(i)) {
+        let _ = footer.class_list().add_1(FOOTER_CLASS);
+        let _ = footer.set_attribute("role", "row");
+        let _ = footer.set_attribute("aria-label", "Aggregates of the page");
+        // out of the row indexes of the results and out of the tab order
+        for_each_cell(&footer, |cell, _| {
+            let _ = cell.set_attribute("role", "gridcell");
+        });
+    }
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (header_rows, data_rows) = grid_rows(&table);
+    let page_start_index = get_opt_num_attribute(host, "page_start_index").unwrap_or(0);
+    let rows_total = get_opt_num_attribute(host, "rows_total").unwrap_or(data_rows.len());
+    let (row_count, row_indexes) = aria_rows(
+        header_rows.len(),
+        data_rows.len(),
+        page_start_index,
+        rows_total,
+    );
+
+    let _ = table.set_attribute("role", "grid");
+    let _ = table.set_attribute("aria-label", "Results");
+    let _ = table.set_attribute("aria-multiselectable", "true");
+    let _ = table.set_attribute("aria-rowcount", &row_count.to_string());
+
+    for (i, row) in header_rows.iter().enumerate() {
+        let _ = row.set_attribute("role", "row");
+        let _ = row.set_attribute("aria-rowindex", &(i + 1).to_string());
+        for_each_cell(row, |cell, _| {
+            let _ = cell.set_attribute("role", "columnheader");
+        });
+    }
+
+    let (active_row, active_cell) = ACTIVE_CELLS
+        .with(|a| a.borrow().get(&element_id).copied())
+        .map(|(row, cell)| (row.min(data_rows.len().saturating_sub(1)), cell))
+        .unwrap_or((0, 0));
+
+    for (i, (row, row_index)) in data_rows.iter().zip(row_indexes).enumerate() {
+        let _ = row.set_attribute("role", "row");
+        let _ = row.set_attribute("aria-rowindex", &row_index.to_string());
+        for_each_cell(row, |cell, cell_index| {
+            let role = if cell_index == 0 {
+                "rowheader"
+            } else {
+                "gridcell"
+            };
+            let _ = cell.set_attribute("role", role);
+            let tabindex = if (i, cell_index) == (active_row, active_cell) {
+                "0"
+            } else {
+                "-1"
+            };
+            let _ = cell.set_attribute("tabindex", tabindex);
+        });
+    }
+
+    if REFOCUS.with(|r| r.borrow_mut().remove(&element_id)) {
+        set_active_cell(host, 0, active_cell);
+    }
+}
+
+/// `aria-rowcount` of a grid with `header_rows` header rows over `rows_total` rows of results,
+/// and the `aria-rowindex` of its `data_rows` rows, the page starting after `page_start_index`.
+fn aria_rows(
+    header_rows: usize,
+    data_rows: usize,
+    page_start_index: usize,
+    rows_total: usize,
+) -> (usize, Vec<usize>) {
+    let first = header_rows + page_start_index + 1;
+    (
+        header_rows + rows_total,
+        (first..first + data_rows).collect(),
+    )
+}
+
+/// Makes a top level cell the one in the tab order and focuses it.
+pub(crate) fn set_active_cell(host: &Element, row: usize, cell_index: usize) {
+    let table = match grid_table(host) {
+        Some(t) => t,
+        None => return,
+    };
+    let (_, data_rows) = grid_rows(&table);
+    let cell = match data_rows
+        .get(row)
+        .and_then(|r| r.children().item(cell_index as u32))
+    {
+        Some(c) => c,
+        None => return,
+    };
+
+    if let Ok(previous) = table
+        .query_selector_all("[role='gridcell'][tabindex='0'], [role='rowheader'][tabindex='0']")
+    {
+        for i in 0..previous.length() {
+            if let Some(p) = previous.item(i).and_then(|p| p.dyn_into::<Element>().ok()) {
+                let _ = p.set_attribute("tabindex", "-1");
+            }
+        }
+    }
+    let _ = cell.set_attribute("tabindex", "0");
+    if let Some(html_element) = cell.dyn_ref::<web_sys::HtmlElement>() {
+        let _ = html_element.focus();
+    }
+
+    if let Some(element_id) = host.get_attribute("be_id") {
+        ACTIVE_CELLS.with(|a| a.borrow_mut().insert(element_id, (row, cell_index)));
+    }
+}
+
+/// Focuses the cell in the tab order, e.g. when a menu over the grid closes.
+pub(crate) fn focus_active_cell(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (row, cell_index) = ACTIVE_CELLS
+        .with(|a| a.borrow().get(&element_id).copied())
+        .unwrap_or((0, 0));
+    set_active_cell(host, row, cell_index);
+}
+
+fn on_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    let key = keyboard_event.key();
+
+    // paging, from anywhere in the element
+    if keyboard_event.alt_key() {
+        let event_type = match key.as_str() {
+            "Home" => EVENT_GO_TO_FIRST_PAGE,
+            "PageUp" => EVENT_GO_TO_PREVIOUS_PAGE,
+            "PageDown" => EVENT_GO_TO_NEXT_PAGE,
+            "End" => EVENT_GO_TO_LAST_PAGE,
+            _ => return,
+        };
+        event.prevent_default();
+        if let Some(element_id) = host.get_attribute("be_id") {
+            REFOCUS.with(|r| r.borrow_mut().insert(element_id));
+        }
+        go_to_page(&host, event_type);
+        return;
+    }
+
+    // moving between cells, from a top level cell only
+    let (row, cell_index) = match focused_cell(event, &host) {
+        Some(p) => p,
+        None => return,
+    };
+    let table = match grid_table(&host) {
+        Some(t) => t,
+        None => return,
+    };
+    let (_, data_rows) = grid_rows(&table);
+    let last_row = data_rows.len().saturating_sub(1);
+    let last_cell = data_rows
+        .get(row)
+        .map(|r| (r.child_element_count() as usize).saturating_sub(1))
+        .unwrap_or(0);
+    let ctrl = keyboard_event.ctrl_key() || keyboard_event.meta_key();
+
+    if key == "Enter" {
+        event.prevent_default();
+        if !(keyboard_event.shift_key() && drill_down::open_cell(&host, row, cell_index)) {
+            cell_inspector::open(&host, row, cell_index);
+        }
+        return;
+    }
+
+    let (row, cell_index) = match key.as_str() {
+        "ArrowUp" => (row.saturating_sub(1), cell_index),
+        "ArrowDown" => ((row + 1).min(last_row), cell_index),
+        "ArrowLeft" => (row, cell_index.saturating_sub(1)),
+        "ArrowRight" => (row, (cell_index + 1).min(last_cell)),
+        "PageUp" => (row.saturating_sub(ROWS_PER_PAGE_KEY), cell_index),
+        "PageDown" => ((row + ROWS_PER_PAGE_KEY).min(last_row), cell_index),
+        "Home" if ctrl => (0, 0),
+        "Home" => (row, 0),
+        "End" if ctrl => (last_row, last_cell),
+        "End" => (row, last_cell),
+        _ => return,
+    };
+
+    event.prevent_default();
+    set_active_cell(&host, row, cell_index);
+    select_cell(&host, row, cell_index, keyboard_event.shift_key());
+}
+
+/// (data row, cell index) of the top level cell the key event comes from.
+fn focused_cell(event: &web_sys::Event, host: &Element) -> Option<(usize, usize)> {
+    let target = event.composed_path().get(0).dyn_into::<Element>().ok()?;
+    let role = target.get_attribute("role");
+    if !matches!(role.as_deref(), Some("gridcell") | Some("rowheader")) {
+        return None;
+    }
+
+    let row = target.parent_element()?;
+    let table = grid_table(host)?;
+    let (_, data_rows) = grid_rows(&table);
+    let row_index = data_rows.iter().position(|r| *r == row)?;
+
+    let mut cell_index = 0;
+    let mut previous = target.previous_element_sibling();
+    while let Some(p) = previous {
+        cell_index += 1;
+        previous = p.previous_element_sibling();
+    }
+
+    Some((row_index, cell_index))
+}
+
+fn for_each_cell(row: &Element, f: impl Fn(&Element, usize)) {
+    let mut cell_index = 0;
+    let mut cell = row.first_element_child();
+    while let Some(c) = cell {
+        f(&c, cell_index);
+        cell_index += 1;
+        cell = c.next_element_sibling();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::aria_rows;
+    use crate::{
+        bigquery::base::TableFieldSchema,
+        custom_elements::{
+            aggregate::Aggregate,
+            layout::{ColumnLayout, GridLayout},
+            to_table_builder::{footer_index, rows_to_table_builder},
+        },
+    };
+
+    #[test]
+    fn page_with_an_aggregate_keeps_the_rows_of_the_data() {
+        let fields: Vec<TableFieldSchema> =
+            serde_json::from_str(r#"[{"name": "n", "type": "INTEGER"}]"#).unwrap();
+        let rows: Vec<(usize, serde_json::Value)> = (0..3)
+            .map(|i| (11 + i, json!({"f": [{"v": i.to_string()}]})))
+            .collect();
+        let column_layout = ColumnLayout {
+            aggregates: [("n".to_string(), Aggregate::Sum)].into(),
+            ..ColumnLayout::default()
+        };
+        let table_builder =
+            rows_to_table_builder(&fields, &rows, &GridLayout::default(), &column_layout);
+        assert_eq!(table_builder.rows.len(), 4);
+
+        // the rows before the footer are the data rows of the grid
+        let footer = footer_index(&table_builder);
+        assert_eq!(footer, Some(3));
+        let data_rows = table_builder.rows.len() - footer.iter().count();
+
+        // second page of 10 rows out of 13, one header row
+        let (row_count, row_indexes) = aria_rows(1, data_rows, 10, 13);
+        assert_eq!(row_count, 14);
+        assert_eq!(row_indexes, vec![12, 13, 14]);
+
+        let table_builder = rows_to_table_builder(
+            &fields,
+            &rows,
+            &GridLayout::default(),
+            &ColumnLayout::default(),
+        );
+        assert_eq!(footer_index(&table_builder), None);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use website_component_table::HtmlNodeRender;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    aggregate::Aggregate,
+    grid_columns, overlay_panel,
+    pivot::{PivotConfig, pivot_columns, pivot_description, pivot_table},
+    value_tree::escape_html,
+};
+
+/*
+Pivot mode of the grid of `bq-table` and `bq-query`: the rows as the user sees them (hidden
+columns left out) cross-tabulated by `pivot` in the overlay panel, with the row keys, column
+keys, value and aggregate picked above the table. The choice is kept per element until the
+panel is opened again, the keys the results no longer have dropped.
+*/
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static PIVOTS: RefCell<HashMap<String, PivotConfig>> = RefCell::new(HashMap::new());
+}
+
+/// Opens the pivot of the rows shown by the grid of `host`.
+pub(crate) fn open(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let fields = match grid_columns::visible_results(&element_id) {
+        Some(v) => v.schema.fields,
+        None => return,
+    };
+    let config = PIVOTS
+        .with(|p| p.borrow().get(&element_id).map(|c| c.valid_for(&fields)))
+        .unwrap_or_else(|| PivotConfig::default_for(&fields));
+    PIVOTS.with(|p| p.borrow_mut().insert(element_id, config));
+
+    render(host);
+}
+
+/// A `data-pivot-action` button of the panel: removes a key or swaps rows and columns.
+pub(crate) fn on_click(host: &Element, button: &Element) {
+    let action = button
+        .get_attribute("data-pivot-action")
+        .unwrap_or_default();
+    let side = button.get_attribute("data-pivot-side").unwrap_or_default();
+    let index = button
+        .get_attribute("data-pivot-index")
+        .and_then(|i| i.parse::<usize>().ok());
+
+    update(host, |config| {
+        match (action.as_str(), side.as_str(), index) {
+            ("remove", "rows", Some(i)) if i < config.rows.len() => {
+                config.rows.remove(i);
+            }
+            ("remove", "columns", Some(i)) if i < config.columns.len() => {
+                config.columns.remove(i);
+            }
+            ("swap", _, _) => std::mem::swap(&mut config.rows, &mut config.columns),
+            _ => {}
+        }
+    });
+    render(host);
+    refocus(host, &format!("[data-pivot-action='{}']", action));
+}
+
+/// Change of a `data-pivot-field` select: a key added, the value or the aggregate.
+pub(crate) fn on_change(host: &Element, select: &Element) {
+    let field = match select.get_attribute("data-pivot-field") {
+        Some(f) => f,
+        None => return,
+    };
+    let value = select
+        .dyn_ref::<web_sys::HtmlSelectElement>()
+        .map(|s| s.value())
+        .unwrap_or_default();
+
+    update(host, |config| match field.as_str() {
+        "rows" | "columns" if !value.is_empty() => {
+            // a key is on one side only
+            config.rows.retain(|k| *k != value);
+            config.columns.retain(|k| *k != value);
+            match field.as_str() {
+                "rows" => config.rows.push(value.clone()),
+                _ => config.columns.push(value.clone()),
+            }
+        }
+        "value" => config.value = (!value.is_empty()).then(|| value.clone()),
+        "aggregate" => {
+            if let Ok(aggregate) = value.parse::<Aggregate>() {
+                config.aggregate = aggregate;
+            }
+        }
+        _ => {}
+    });
+    render(host);
+    refocus(host, &format!("[data-pivot-field='{}']", field));
+}
+
+fn update(host: &Element, f: impl FnOnce(&mut PivotConfig)) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    PIVOTS.with(|p| {
+        if let Some(config) = p.borrow_mut().get_mut(&element_id) {
+            f(config);
+        }
+    });
+}
+
+fn render(host: &Element) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (visible, config) = match (
+        grid_columns::visible_results(&element_id),
+        PIVOTS.with(|p| p.borrow().get(&element_id).cloned()),
+    ) {
+        (Some(v), Some(c)) => (v, c),
+        _ => return,
+    };
+    let fields = &visible.schema.fields;
+
+    let meta = format!(
+        "{} · {} row{} of the page",
+        pivot_description(&config),
+        visible.rows.len(),
+        if visible.rows.len() == 1 { "" } else { "s" }
+    );
+    let body = match overlay_panel::open(host, "Pivot", &meta, vec![]) {
+        Some(b) => b,
+        None => return,
+    };
+    if let Some(panel) = body.parent_element() {
+        let _ = panel.class_list().add_1("wide");
+    }
+
+    body.set_inner_html(&format!(
+        r#"{}<div class="pivot-grid"></div>"#,
+        config_html(&pivot_columns(fields), &config)
+    ));
+    let grid = match body.query_selector(".pivot-grid").ok().flatten() {
+        Some(g) => g,
+        None => return,
+    };
+
+    match pivot_table(fields, &visible.rows, &config) {
+        Ok(table_builder) => match table_builder.render() {
+            Ok(nodes) => {
+                for node in nodes.iter().filter_map(|n| n.to_element_node().ok()) {
+                    let _ = grid.append_child(&node);
+                }
+            }
+            Err(e) => {
+                web_sys::console::error_1(&wasm_bindgen::JsValue::from_str(&format!(
+                    "grid_pivot: table render failed: {:?}",
+                    e
+                )));
+            }
+        },
+        Err(message) => grid.set_inner_html(&format!(
+            r#"<p class="pivot-error" role="alert">{}</p>"#,
+            escape_html(&message)
+        )),
+    }
+}
+
+fn config_html(columns: &[(String, &TableFieldSchema)], config: &PivotConfig) -> String {
+    let keys_html = |side: &str, label: &str, keys: &[String]| {
+        let chips: String = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                format!(
+                    r#"<li>{0}<button type="button" data-pivot-action="remove" data-pivot-side="{1}" data-pivot-index="{2}" aria-label="Remove the key {0}" title="Remove">×</button></li>"#,
+                    escape_html(key),
+                    side,
+                    i
+                )
+            })
+            .collect();
+        let options: String = columns
+            .iter()
+            .filter(|(name, _)| !keys.contains(name))
+            .map(|(name, _)| format!(r#"<option value="{0}">{0}</option>"#, escape_html(name)))
+            .collect();
+        format!(
+            concat!(
+                r#"<div class="pivot-keys" role="group" aria-label="{0}"><span>{0}</span>"#,
+                r#"<ol>{1}</ol>"#,
+                r#"<select data-pivot-field="{2}" aria-label="Add a key to the {3}"><option value="">add…</option>{4}</select></div>"#
+            ),
+            label,
+            chips,
+            side,
+            label.to_lowercase(),
+            options
+        )
+    };
+
+    let values: String = columns
+        .iter()
+        .map(|(name, _)| {
+            let selected = if config.value.as_deref() == Some(name.as_str()) {
+                " selected"
+            } else {
+                ""
+            };
+            format!(
+                r#"<option value="{0}"{1}>{0}</option>"#,
+                escape_html(name),
+                selected
+            )
+        })
+        .collect();
+    let aggregates: String = Aggregate::get_all()
+        .iter()
+        .map(|a| {
+            let selected = if *a == config.aggregate {
+                " selected"
+            } else {
+                ""
+            };
+            format!(
+                r#"<option value="{}"{}>{}</option>"#,
+                a.name(),
+                selected,
+                a.label()
+            )
+        })
+        .collect();
+
+    format!(
+        concat!(
+            r#"<div class="pivot-config">{}{}"#,
+            r#"<button type="button" data-pivot-action="swap" title="Swap the row and column keys">⇄ Swap</button>"#,
+            r#"<label>Aggregate <select data-pivot-field="aggregate">{}</select></label>"#,
+            r#"<label>of <select data-pivot-field="value"><option value="">rows</option>{}</select></label>"#,
+            r#"</div>"#
+        ),
+        keys_html("rows", "Rows", &config.rows),
+        keys_html("columns", "Columns", &config.columns),
+        aggregates,
+        values
+    )
+}
+
+fn refocus(host: &Element, selector: &str) {
+    if let Some(element) = host
+        .shadow_root()
+        .and_then(|s| s.query_selector(selector).ok().flatten())
+        .and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = element.focus();
+    }
+}
@@ -0,0 +1,788 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ops::Range,
+    str::FromStr,
+};
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    aggregate::Aggregate,
+    bq_common_custom_element::{find_in_shadow, host_of},
+    column_formats, column_profile,
+    export::{to_grid_columns, to_grid_records},
+    grid_columns, grid_layout, grid_map,
+    grid_navigation::{focus_active_cell, set_active_cell},
+    layout::{StructPresentation, header_cells},
+    profile::field_at,
+    selection::{CellPosition, CopyFormat, Selection, selection_text},
+    value_tree::escape_html,
+};
+use crate::utils::write_clipboard;
+
+/*
+Cell, row and column selection of the grid of `bq-table` and `bq-query`.
+Positions are taken from the top level cells only: a click inside a nested (ARRAY) table
+selects the cell holding it. The copied values come from `loaded_results`, not from the DOM.
+The context menu of the header of a non repeated RECORD also chooses how the RECORD is shown,
+the one of the header of a leaf column acts on the column (e.g. its profile, the aggregate of
+its footer, its conditional formatting).
+*/
+pub(crate) const SELECTION_MENU: &str = "selection_menu";
+const SELECTED_CLASS: &str = "selected";
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static SELECTIONS: RefCell<HashMap<String, Selection>> = RefCell::new(HashMap::new());
+    static DRAGGING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// What a pointer event hit in the grid.
+enum GridHit {
+    Cell(CellPosition),
+    /// The index cell of a row.
+    Row(usize),
+    /// A header, with the data columns under it.
+    Header(Range<usize>),
+}
+
+impl GridHit {
+    fn to_selection(&self) -> Selection {
+        match self {
+            GridHit::Cell(position) => Selection::Cells {
+                anchor: *position,
+                focus: *position,
+            },
+            GridHit::Row(row) => Selection::Rows {
+                anchor: *row,
+                focus: *row,
+            },
+            GridHit::Header(columns) => Selection::Columns {
+                anchor: columns.clone(),
+                focus: columns.clone(),
+            },
+        }
+    }
+
+    fn is_in(&self, selection: &Selection, row_count: usize, column_count: usize) -> bool {
+        let (rows, columns) = selection.bounds(row_count, column_count);
+        match (self, selection) {
+            (GridHit::Cell(position), _) => selection.contains(*position, row_count, column_count),
+            (GridHit::Row(row), Selection::Rows { .. }) => rows.contains(row),
+            (GridHit::Header(hit), Selection::Columns { .. }) => {
+                columns.start <= hit.start && hit.end <= columns.end
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Binds the mouse and keyboard listeners of the selection on a grid element.
+pub(crate) fn add_event_listeners(element: &Element, tag_name: &str) {
+    let listeners = [
+        ("mousedown", on_mouse_down as fn(&web_sys::Event)),
+        ("mouseover", on_mouse_over),
+        ("mouseup", on_mouse_up),
+        ("keydown", on_key_down),
+        ("contextmenu", on_context_menu),
+    ];
+    for (event_type, listener) in listeners {
+        let on_event_type_closure =
+            Closure::wrap(Box::new(listener) as Box<dyn Fn(&web_sys::Event)>);
+        if let Err(e) = element.add_event_listener_with_callback(
+            event_type,
+            on_event_type_closure.as_ref().unchecked_ref(),
+        ) {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "{} define: failed to add '{}' listener: {:?}",
+                tag_name, event_type, e
+            )));
+        }
+        on_event_type_closure.forget();
+    }
+}
+
+/// Forgets the selection of an element, called when it shows other rows.
+pub(crate) fn clear(element_id: &str) {
+    SELECTIONS.with(|s| s.borrow_mut().remove(element_id));
+}
+
+/// Fills the (hidden) context menu rendered by the controls.
+pub(crate) fn init_menu(menu: &Element) {
+    if menu.get_attribute("bee").is_some() {
+        return;
+    }
+
+    let buttons: Vec<String> = CopyFormat::get_all()
+        .iter()
+        .map(|f| {
+            format!(
+                r#"<button type="button" role="menuitem" data-format="{}">{}</button>"#,
+                f.name(),
+                f.label()
+            )
+        })
+        .collect();
+    menu.set_inner_html(&format!(
+        concat!(
+            r#"{}<div class="struct-presentation" role="group" aria-label="Show the RECORD" style="display: none;"></div>"#,
+            r#"<div class="column-actions" role="group" aria-label="Column" style="display: none;"></div>"#
+        ),
+        buttons.join("")
+    ));
+    let _ = menu.set_attribute("style", "display: none;");
+    let _ = menu.set_attribute("role", "menu");
+    let _ = menu.set_attribute("aria-label", "Copy the selection");
+
+    let on_event_type_closure =
+        Closure::wrap(Box::new(on_menu_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = menu
+        .add_event_listener_with_callback("click", on_event_type_closure.as_ref().unchecked_ref());
+    let _ = menu.set_attribute("bee", "1");
+    on_event_type_closure.forget();
+}
+
+fn on_mouse_down(event: &web_sys::Event) {
+    let (host, target) = match host_and_target(event) {
+        Some(h) => h,
+        None => return,
+    };
+    if target
+        .closest(&format!("[be_id='{}']", SELECTION_MENU))
+        .ok()
+        .flatten()
+        .is_some()
+    {
+        return;
+    }
+    hide_menu(&host);
+
+    let mouse_event = match event.dyn_ref::<web_sys::MouseEvent>() {
+        Some(m) => m,
+        None => return,
+    };
+    if mouse_event.button() != 0 {
+        return;
+    }
+    let hit = match hit_test(&host, &target) {
+        Some(h) => h,
+        None => return,
+    };
+
+    select(&host, &hit, mouse_event.shift_key());
+    DRAGGING.with(|d| d.set(true));
+
+    // keeps the browser from selecting the text under the pointer, the cell is focused instead;
+    // not on a header, it can be dragged onto another (`grid_columns`)
+    match hit {
+        GridHit::Cell(position) => {
+            event.prevent_default();
+            set_active_cell(&host, position.row, position.column + 1)
+        }
+        GridHit::Row(row) => {
+            event.prevent_default();
+            set_active_cell(&host, row, 0)
+        }
+        GridHit::Header(_) => {}
+    }
+}
+
+/// Selects the top level cell `cell_index` (0 is the index cell) of the data row `row`,
+/// or extends the selection up to it.
+pub(crate) fn select_cell(host: &Element, row: usize, cell_index: usize, extend: bool) {
+    let hit = match cell_index {
+        0 => GridHit::Row(row),
+        _ => GridHit::Cell(CellPosition {
+            row,
+            column: cell_index - 1,
+        }),
+    };
+    select(host, &hit, extend);
+}
+
+fn select(host: &Element, hit: &GridHit, extend: bool) {
+    let selection = hit.to_selection();
+    let selection = match get_selection(host) {
+        Some(current) if extend => current.extend_to(&selection),
+        _ => selection,
+    };
+    set_selection(host, selection);
+}
+
+fn on_mouse_over(event: &web_sys::Event) {
+    if !DRAGGING.with(|d| d.get()) {
+        return;
+    }
+    let mouse_event = match event.dyn_ref::<web_sys::MouseEvent>() {
+        Some(m) => m,
+        None => return,
+    };
+    // the button was released outside the element
+    if mouse_event.buttons() & 1 == 0 {
+        DRAGGING.with(|d| d.set(false));
+        return;
+    }
+
+    let (host, target) = match host_and_target(event) {
+        Some(h) => h,
+        None => return,
+    };
+    if let (Some(current), Some(hit)) = (get_selection(&host), hit_test(&host, &target)) {
+        set_selection(&host, current.extend_to(&hit.to_selection()));
+    }
+}
+
+fn on_mouse_up(_event: &web_sys::Event) {
+    DRAGGING.with(|d| d.set(false));
+}
+
+fn on_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+    {
+        Some(h) => h,
+        None => return,
+    };
+
+    match keyboard_event.key().as_str() {
+        "c" | "C"
+            if (keyboard_event.ctrl_key() || keyboard_event.meta_key())
+                && get_selection(&host).is_some() =>
+        {
+            event.prevent_default();
+            copy_selection(&host, CopyFormat::Tsv);
+        }
+        "Escape" if is_menu_open(&host) => {
+            hide_menu(&host);
+            focus_active_cell(&host);
+        }
+        "Escape" => set_selection_opt(&host, None),
+        _ => {}
+    }
+}
+
+fn on_context_menu(event: &web_sys::Event) {
+    let (host, target) = match host_and_target(event) {
+        Some(h) => h,
+        None => return,
+    };
+    let mouse_event = match event.dyn_ref::<web_sys::MouseEvent>() {
+        Some(m) => m,
+        None => return,
+    };
+    let hit = match hit_test(&host, &target) {
+        Some(h) => h,
+        None => return,
+    };
+
+    // right click outside of the selection selects what is under the pointer
+    let (row_count, column_count) = grid_size(&host);
+    let inside = get_selection(&host)
+        .map(|current| hit.is_in(&current, row_count, column_count))
+        .unwrap_or(false);
+    if !inside {
+        set_selection(&host, hit.to_selection());
+    }
+
+    let menu = match find_in_shadow(&host, SELECTION_MENU) {
+        Some(m) => m,
+        None => return,
+    };
+    event.prevent_default();
+    if let Ok(Some(section)) = menu.query_selector(".struct-presentation") {
+        fill_struct_presentation(&section, &host, &target);
+    }
+    if let Ok(Some(section)) = menu.query_selector(".column-actions") {
+        fill_column_actions(&section, &host, &target);
+    }
+    let _ = menu.set_attribute(
+        "style",
+        &format!(
+            "left: {}px; top: {}px;",
+            mouse_event.client_x(),
+            mouse_event.client_y()
+        ),
+    );
+    if let Some(first_item) = menu
+        .query_selector("button")
+        .ok()
+        .flatten()
+        .and_then(|b| b.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = first_item.focus();
+    }
+}
+
+/// The items choosing how the RECORD of the header under the pointer is shown, hidden when the
+/// header is not the one of a non repeated RECORD.
+fn fill_struct_presentation(section: &Element, host: &Element, target: &Element) {
+    let (path, presentation) = match header_record(host, target) {
+        Some(p) => (p.clone(), grid_layout::get().struct_presentation(&p)),
+        None => {
+            section.set_inner_html("");
+            let _ = section.set_attribute("style", "display: none;");
+            return;
+        }
+    };
+
+    let buttons: Vec<String> = StructPresentation::get_all()
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<button type="button" role="menuitemradio" aria-checked="{}" data-presentation="{}" data-path="{}">{}</button>"#,
+                *p == presentation,
+                p.name(),
+                escape_html(&path),
+                p.label()
+            )
+        })
+        .collect();
+    section.set_inner_html(&buttons.join(""));
+    let _ = section.remove_attribute("style");
+}
+
+/// The items acting on the leaf column whose header is `target`, hidden on other headers and cells.
+fn fill_column_actions(section: &Element, host: &Element, target: &Element) {
+    let path = match target
+        .closest("th[data-column-path]")
+        .ok()
+        .flatten()
+        .and_then(|h| h.get_attribute("data-column-path"))
+    {
+        Some(p) => p,
+        None => {
+            section.set_inner_html("");
+            let _ = section.set_attribute("style", "display: none;");
+            return;
+        }
+    };
+
+    let mut buttons = vec![format!(
+        r#"<button type="button" role="menuitem" data-profile-path="{}">Profile column</button>"#,
+        escape_html(&path)
+    )];
+    if column_formats::can_format(host, &path) {
+        buttons.push(format!(
+            r#"<button type="button" role="menuitem" data-format-path="{}">Conditional formatting…</button>"#,
+            escape_html(&path)
+        ));
+    }
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    if grid_map::is_geography(host, &path) {
+        let thumbnails = grid_columns::column_layout(&element_id)
+            .map(|c| c.thumbnails.contains(&path))
+            .unwrap_or(false);
+        buttons.push(format!(
+            r#"<button type="button" role="menuitem" data-map-path="{}">Show on map</button>"#,
+            escape_html(&path)
+        ));
+        buttons.push(format!(
+            r#"<button type="button" role="menuitemcheckbox" aria-checked="{}" data-thumbnails-path="{}">Thumbnails in cells</button>"#,
+            thumbnails,
+            escape_html(&path)
+        ));
+    }
+    if let (Some(visible), Some(column_layout)) = (
+        grid_columns::visible_results(&element_id),
+        grid_columns::column_layout(&element_id),
+    ) && let Some((field, _)) = field_at(&visible.schema.fields, &path)
+    {
+        let current = column_layout.aggregates.get(&path).copied();
+        let choices = Aggregate::get_all()
+            .into_iter()
+            .filter(|a| a.applies_to(field))
+            .map(|a| (Some(a), a.name(), format!("Footer: {}", a.label())))
+            .chain([(None, "none", "No footer aggregate".to_string())]);
+        for (aggregate, name, label) in choices {
+            buttons.push(format!(
+                r#"<button type="button" role="menuitemradio" aria-checked="{}" data-aggregate="{}" data-aggregate-path="{}">{}</button>"#,
+                aggregate == current,
+                name,
+                escape_html(&path),
+                label
+            ));
+        }
+    }
+    section.set_inner_html(&buttons.join(""));
+    let _ = section.remove_attribute("style");
+}
+
+/// Path of the non repeated RECORD whose header is `target`.
+fn header_record(host: &Element, target: &Element) -> Option<String> {
+    let table = grid_table(host)?;
+    let header = target.closest("th").ok().flatten()?;
+    let row = header.parent_element()?;
+    let (header_rows, _) = grid_rows(&table);
+    let depth = header_rows.iter().position(|r| *r == row)?;
+
+    let mut position: usize = 0;
+    let mut previous = header.previous_element_sibling();
+    while let Some(p) = previous {
+        position += 1;
+        previous = p.previous_element_sibling();
+    }
+    // the `#` header of the index column starts the first row
+    let position = if depth == 0 {
+        position.checked_sub(1)?
+    } else {
+        position
+    };
+
+    let element_id = host.get_attribute("be_id")?;
+    let loaded_results = grid_columns::visible_results(&element_id)?;
+    let layout = grid_layout::get();
+    let cells = header_cells(&loaded_results.schema.fields, &layout);
+    let (path, field) = cells.get(depth)?.get(position)?;
+    match field.fields {
+        Some(_) if field.mode.as_deref() != Some("REPEATED") => Some(path.clone()),
+        _ => None,
+    }
+}
+
+fn on_menu_click(event: &web_sys::Event) {
+    let target = match event.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+        Some(t) => t,
+        None => return,
+    };
+    if let Some(button) = target.closest("button[data-presentation]").ok().flatten() {
+        on_struct_presentation_click(&button);
+        return;
+    }
+    if let Some(button) = target
+        .closest(concat!(
+            "button[data-profile-path], button[data-format-path], button[data-aggregate], ",
+            "button[data-map-path], button[data-thumbnails-path]"
+        ))
+        .ok()
+        .flatten()
+    {
+        on_column_action_click(&button);
+        return;
+    }
+    let button = match target.closest("button[data-format]").ok().flatten() {
+        Some(b) => b,
+        None => return,
+    };
+    let format = match button
+        .get_attribute("data-format")
+        .and_then(|f| CopyFormat::from_str(&f).ok())
+    {
+        Some(f) => f,
+        None => return,
+    };
+    let host = match host_of(&button) {
+        Some(h) => h,
+        None => return,
+    };
+
+    copy_selection(&host, format);
+    hide_menu(&host);
+    focus_active_cell(&host);
+}
+
+fn on_struct_presentation_click(button: &Element) {
+    let (path, presentation) = match (
+        button.get_attribute("data-path"),
+        button
+            .get_attribute("data-presentation")
+            .and_then(|p| StructPresentation::from_str(&p).ok()),
+    ) {
+        (Some(path), Some(presentation)) => (path, presentation),
+        _ => return,
+    };
+    let host = match host_of(button) {
+        Some(h) => h,
+        None => return,
+    };
+
+    hide_menu(&host);
+    grid_layout::set_struct_presentation(&host, &path, presentation);
+    focus_active_cell(&host);
+}
+
+fn on_column_action_click(button: &Element) {
+    let host = match button
+        .get_root_node()
+        .dyn_into::<web_sys::ShadowRoot>()
+        .ok()
+        .map(|s| s.host())
+    {
+        Some(h) => h,
+        None => return,
+    };
+    hide_menu(&host);
+
+    if let Some(path) = button.get_attribute("data-profile-path") {
+        column_profile::open(&host, &path);
+    }
+    if let Some(path) = button.get_attribute("data-format-path") {
+        column_formats::open(&host, &path);
+    }
+    if button.has_attribute("data-map-path") {
+        grid_map::open(&host);
+    }
+    if let Some(path) = button.get_attribute("data-thumbnails-path") {
+        let shown = button.get_attribute("aria-checked").as_deref() == Some("true");
+        grid_columns::set_thumbnails(&host, &path, !shown);
+    }
+    if let (Some(name), Some(path)) = (
+        button.get_attribute("data-aggregate"),
+        button.get_attribute("data-aggregate-path"),
+    ) {
+        grid_columns::set_aggregate(&host, &path, Aggregate::from_str(&name).ok());
+    }
+}
+
+fn copy_selection(host: &Element, format: CopyFormat) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let (selection, loaded_results) = match (
+        get_selection(host),
+        grid_columns::visible_results(&element_id),
+    ) {
+        (Some(s), Some(r)) => (s, r),
+        _ => return,
+    };
+
+    let fields = &loaded_results.schema.fields;
+    let layout = grid_layout::get();
+    let columns = to_grid_columns(fields, &layout);
+    let (_, records) = to_grid_records(fields, &loaded_results.rows, &layout);
+    let text = selection_text(&selection, &columns, &records, format);
+
+    write_clipboard(text);
+}
+
+fn host_and_target(event: &web_sys::Event) -> Option<(Element, Element)> {
+    let host = event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())?;
+    // the target is retargeted to the host outside the shadow root, the original one is first in the path
+    let target = event.composed_path().get(0).dyn_into::<Element>().ok()?;
+    Some((host, target))
+}
+
+pub(crate) fn grid_table(host: &Element) -> Option<Element> {
+    host.shadow_root()?
+        .query_selector(":host > bstruct-table")
+        .ok()
+        .flatten()
+}
+
+/// Rows of the top level table, header rows (`th`) and data rows (`td`) apart, the footer row
+/// of the aggregates left out.
+pub(crate) fn grid_rows(table: &Element) -> (Vec<Element>, Vec<Element>) {
+    let mut header_rows = Vec::new();
+    let mut data_rows = Vec::new();
+
+    let rows = match table.query_selector_all("tr") {
+        Ok(r) => r,
+        Err(_) => return (header_rows, data_rows),
+    };
+    for i in 0..rows.length() {
+        let row = match rows.item(i).and_then(|r| r.dyn_into::<Element>().ok()) {
+            Some(r) => r,
+            None => continue,
+        };
+        // rows of nested tables live inside a cell
+        let nested = row
+            .parent_element()
+            .and_then(|p| p.closest("td, th").ok().flatten())
+            .is_some();
+        if nested || row.class_list().contains(grid_columns::FOOTER_CLASS) {
+            continue;
+        }
+        match row.first_element_child().map(|c| c.tag_name()) {
+            Some(t) if t.eq_ignore_ascii_case("th") => header_rows.push(row),
+            Some(_) => data_rows.push(row),
+            None => {}
+        }
+    }
+
+    (header_rows, data_rows)
+}
+
+fn grid_size(host: &Element) -> (usize, usize) {
+    let data_rows = match grid_table(host) {
+        Some(t) => grid_rows(&t).1,
+        None => return (0, 0),
+    };
+    let column_count = data_rows
+        .first()
+        .map(|r| (r.child_element_count() as usize).saturating_sub(1))
+        .unwrap_or(0);
+    (data_rows.len(), column_count)
+}
+
+fn hit_test(host: &Element, target: &Element) -> Option<GridHit> {
+    let table = grid_table(host)?;
+
+    // the outermost cell, a nested table selects the cell holding it
+    let mut cell = target.closest("td, th").ok().flatten()?;
+    while let Some(outer) = cell
+        .parent_element()
+        .and_then(|p| p.closest("td, th").ok().flatten())
+    {
+        cell = outer;
+    }
+    if !table.contains(Some(&cell)) {
+        return None;
+    }
+
+    let row = cell.parent_element()?;
+    let (header_rows, data_rows) = grid_rows(&table);
+
+    if cell.tag_name().eq_ignore_ascii_case("th") {
+        let header_span = column_spans(&header_rows)
+            .into_iter()
+            .find(|(c, _)| *c == cell)
+            .map(|(_, span)| span)?;
+        let data_spans = column_spans(&data_rows[..data_rows.len().min(1)]);
+        // the index column (first cell) is not a data column
+        let columns: Vec<usize> = data_spans
+            .iter()
+            .skip(1)
+            .enumerate()
+            .filter(|(_, (_, span))| span.start < header_span.end && header_span.start < span.end)
+            .map(|(column, _)| column)
+            .collect();
+        return match (columns.first(), columns.last()) {
+            (Some(first), Some(last)) => Some(GridHit::Header(*first..*last + 1)),
+            _ => None,
+        };
+    }
+
+    let row_index = data_rows.iter().position(|r| *r == row)?;
+    let mut column = 0;
+    let mut previous = cell.previous_element_sibling();
+    while let Some(p) = previous {
+        column += 1;
+        previous = p.previous_element_sibling();
+    }
+
+    if column == 0 {
+        Some(GridHit::Row(row_index))
+    } else {
+        Some(GridHit::Cell(CellPosition {
+            row: row_index,
+            column: column - 1,
+        }))
+    }
+}
+
+/// Each cell of `rows` with the grid columns it covers, `colspan` and `rowspan` applied.
+pub(crate) fn column_spans(rows: &[Element]) -> Vec<(Element, Range<usize>)> {
+    let span = |cell: &Element, name: &str| {
+        cell.get_attribute(name)
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(1)
+            .max(1)
+    };
+
+    let mut spans = Vec::new();
+    // columns still taken by a `rowspan` of a row above: (first column, end column, rows left)
+    let mut taken: Vec<(usize, usize, usize)> = Vec::new();
+    for row in rows {
+        let mut column = 0;
+        let mut cell = row.first_element_child();
+        while let Some(c) = cell {
+            while let Some(t) = taken.iter().find(|t| t.0 <= column && column < t.1) {
+                column = t.1;
+            }
+            let end = column + span(&c, "colspan");
+            let rowspan = span(&c, "rowspan");
+            if rowspan > 1 {
+                taken.push((column, end, rowspan));
+            }
+            spans.push((c.clone(), column..end));
+            column = end;
+            cell = c.next_element_sibling();
+        }
+
+        for t in taken.iter_mut() {
+            t.2 -= 1;
+        }
+        taken.retain(|t| t.2 > 0);
+    }
+    spans
+}
+
+fn get_selection(host: &Element) -> Option<Selection> {
+    let element_id = host.get_attribute("be_id")?;
+    SELECTIONS.with(|s| s.borrow().get(&element_id).cloned())
+}
+
+fn set_selection(host: &Element, selection: Selection) {
+    set_selection_opt(host, Some(selection));
+}
+
+fn set_selection_opt(host: &Element, selection: Option<Selection>) {
+    let element_id = match host.get_attribute("be_id") {
+        Some(id) => id,
+        None => return,
+    };
+    SELECTIONS.with(|s| match &selection {
+        Some(selection) => {
+            s.borrow_mut().insert(element_id, selection.clone());
+        }
+        None => {
+            s.borrow_mut().remove(&element_id);
+        }
+    });
+
+    paint(host, selection.as_ref());
+}
+
+/// Marks the selected cells (and the index cell of selected rows) with the `selected` class.
+fn paint(host: &Element, selection: Option<&Selection>) {
+    let table = match grid_table(host) {
+        Some(t) => t,
+        None => return,
+    };
+    let (row_count, column_count) = grid_size(host);
+    let (_, data_rows) = grid_rows(&table);
+
+    for (row, data_row) in data_rows.iter().enumerate() {
+        let mut column = 0;
+        let mut cell = data_row.first_element_child();
+        while let Some(c) = cell {
+            let selected = match selection {
+                None => false,
+                Some(Selection::Rows { .. }) if column == 0 => selection
+                    .map(|s| s.bounds(row_count, column_count).0.contains(&row))
+                    .unwrap_or(false),
+                Some(_) if column == 0 => false,
+                Some(s) => s.contains(
+                    CellPosition {
+                        row,
+                        column: column - 1,
+                    },
+                    row_count,
+                    column_count,
+                ),
+            };
+            let _ = c.class_list().toggle_with_force(SELECTED_CLASS, selected);
+            let _ = c.set_attribute("aria-selected", if selected { "true" } else { "false" });
+            column += 1;
+            cell = c.next_element_sibling();
+        }
+    }
+}
+
+fn is_menu_open(host: &Element) -> bool {
+    find_in_shadow(host, SELECTION_MENU)
+        .and_then(|m| m.get_attribute("style"))
+        .is_some_and(|style| !style.contains("display: none"))
+}
+
+fn hide_menu(host: &Element) {
+    if let Some(menu) = find_in_shadow(host, SELECTION_MENU) {
+        let _ = menu.set_attribute("style", "display: none;");
+    }
+}
@@ -0,0 +1,489 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{aggregate::Aggregate, cell_value::CellValue, conditional_format::FormatRule};
+
+/// How a non repeated RECORD is shown in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StructPresentation {
+    /// One column per sub-field, under a grouped header.
+    #[default]
+    Columns,
+    /// A single cell with the RECORD as JSON.
+    Json,
+    /// A single cell with `key: value` pairs.
+    KeyValue,
+}
+
+impl StructPresentation {
+    pub(crate) fn get_all() -> Vec<StructPresentation> {
+        vec![
+            StructPresentation::Columns,
+            StructPresentation::Json,
+            StructPresentation::KeyValue,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            StructPresentation::Columns => "columns",
+            StructPresentation::Json => "json",
+            StructPresentation::KeyValue => "key_value",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            StructPresentation::Columns => "Show as columns",
+            StructPresentation::Json => "Show as JSON",
+            StructPresentation::KeyValue => "Show as key: value list",
+        }
+    }
+}
+
+impl FromStr for StructPresentation {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StructPresentation::get_all()
+            .into_iter()
+            .find(|p| p.name() == s)
+            .ok_or(())
+    }
+}
+
+/// How the columns of the grid are laid out, remembered across results.
+/// RECORDs are keyed by their field path (`a.b`, through ARRAYs too), the ones not listed are columns.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GridLayout {
+    #[serde(default)]
+    pub structs: HashMap<String, StructPresentation>,
+}
+
+impl GridLayout {
+    pub(crate) fn struct_presentation(&self, path: &str) -> StructPresentation {
+        self.structs.get(path).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn set_struct_presentation(&mut self, path: &str, presentation: StructPresentation) {
+        match presentation {
+            StructPresentation::Columns => self.structs.remove(path),
+            _ => self.structs.insert(path.to_string(), presentation),
+        };
+    }
+
+    /// Whether the field at `path` is a non repeated RECORD shown in a single cell.
+    pub(crate) fn is_collapsed(&self, path: &str, field: &TableFieldSchema) -> bool {
+        field.fields.is_some()
+            && field.mode.as_deref() != Some("REPEATED")
+            && self.struct_presentation(path) != StructPresentation::Columns
+    }
+}
+
+/// The columns of one result (a query or a table) the user hid, moved, pinned or resized.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ColumnLayout {
+    /// Paths of the hidden fields, a hidden RECORD hides all its sub-fields.
+    pub hidden: Vec<String>,
+    /// Names of the top level fields in the order shown, the ones not listed follow in schema order.
+    pub order: Vec<String>,
+    /// Number of leading top level fields kept visible on a horizontal scroll, with the `#` column.
+    pub pinned: usize,
+    /// Width in pixels of the leaf columns resized by dragging the border of their header, by path.
+    pub widths: HashMap<String, usize>,
+    /// Aggregate shown in the footer row under the leaf columns, by path; no footer without any.
+    pub aggregates: HashMap<String, Aggregate>,
+    /// Conditional formatting rules of the leaf columns, by path, evaluated in order.
+    pub formats: HashMap<String, Vec<FormatRule>>,
+    /// Paths of the GEOGRAPHY columns whose cells start with a thumbnail of their shape.
+    pub thumbnails: Vec<String>,
+}
+
+/// Which fields of a RECORD (or of the row) are shown, in order.
+struct Kept {
+    /// Index of the field in the schema.
+    index: usize,
+    /// The sub-fields shown, for a RECORD.
+    sub_fields: Option<Vec<Kept>>,
+}
+
+impl ColumnLayout {
+    pub(crate) fn is_hidden(&self, path: &str) -> bool {
+        self.hidden.iter().any(|h| h == path)
+    }
+
+    pub(crate) fn set_aggregate(&mut self, path: &str, aggregate: Option<Aggregate>) {
+        match aggregate {
+            Some(a) => self.aggregates.insert(path.to_string(), a),
+            None => self.aggregates.remove(path),
+        };
+    }
+
+    pub(crate) fn set_hidden(&mut self, path: &str, hidden: bool) {
+        self.hidden.retain(|h| h != path);
+        if hidden {
+            self.hidden.push(path.to_string());
+        }
+    }
+
+    /// Moves the top level field `name` to the place of `target`, shifting `target` right
+    /// when coming from its right and left otherwise.
+    pub(crate) fn move_field(&mut self, fields: &[TableFieldSchema], name: &str, target: &str) {
+        let mut order = self.ordered_names(fields);
+        let (from, to) = match (
+            order.iter().position(|n| n == name),
+            order.iter().position(|n| n == target),
+        ) {
+            (Some(f), Some(t)) => (f, t),
+            _ => return,
+        };
+        let moved = order.remove(from);
+        order.insert(to, moved);
+        self.order = order;
+    }
+
+    /// Names of the top level fields of `fields` in the order shown, hidden ones included.
+    pub(crate) fn ordered_names(&self, fields: &[TableFieldSchema]) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .order
+            .iter()
+            .filter(|n| fields.iter().any(|f| f.name == **n))
+            .cloned()
+            .collect();
+        for field in fields {
+            if !names.contains(&field.name) {
+                names.push(field.name.clone());
+            }
+        }
+        names
+    }
+
+    /// The schema and the REST rows (`{"f": [...]}`) as shown: hidden fields left out of both,
+    /// top level fields in order. A RECORD with all its sub-fields hidden is left out too.
+    pub(crate) fn project(
+        &self,
+        fields: &[TableFieldSchema],
+        rows: &[serde_json::Value],
+    ) -> (Vec<TableFieldSchema>, Vec<serde_json::Value>) {
+        let (mut schema, mut kept) = self.keep(fields, "");
+
+        let names = self.ordered_names(fields);
+        let position = |name: &str| names.iter().position(|n| n == name).unwrap_or(usize::MAX);
+        let mut pairs: Vec<(TableFieldSchema, Kept)> =
+            schema.drain(..).zip(kept.drain(..)).collect();
+        pairs.sort_by_key(|(field, _)| position(&field.name));
+        let (schema, kept): (Vec<TableFieldSchema>, Vec<Kept>) = pairs.into_iter().unzip();
+
+        let rows = rows.iter().map(|row| project_record(row, &kept)).collect();
+        (schema, rows)
+    }
+
+    fn keep(
+        &self,
+        fields: &[TableFieldSchema],
+        parent: &str,
+    ) -> (Vec<TableFieldSchema>, Vec<Kept>) {
+        let mut schema = Vec::new();
+        let mut kept = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            let path = field_path(parent, &field.name);
+            if self.is_hidden(&path) {
+                continue;
+            }
+            match &field.fields {
+                Some(sub_fields) => {
+                    let (sub_schema, sub_kept) = self.keep(sub_fields, &path);
+                    if sub_schema.is_empty() {
+                        continue;
+                    }
+                    let mut field = field.clone();
+                    field.fields = Some(sub_schema);
+                    schema.push(field);
+                    kept.push(Kept {
+                        index,
+                        sub_fields: Some(sub_kept),
+                    });
+                }
+                None => {
+                    schema.push(field.clone());
+                    kept.push(Kept {
+                        index,
+                        sub_fields: None,
+                    });
+                }
+            }
+        }
+        (schema, kept)
+    }
+}
+
+/// `{"f": [...]}` with the cells of `kept` only.
+fn project_record(record: &serde_json::Value, kept: &[Kept]) -> serde_json::Value {
+    let f = record.pointer("/f").and_then(|f| f.as_array());
+    let cells: Vec<serde_json::Value> = kept
+        .iter()
+        .map(|k| {
+            let cell = f
+                .and_then(|f| f.get(k.index))
+                .cloned()
+                .unwrap_or_else(|| json!({ "v": null }));
+            match &k.sub_fields {
+                Some(sub_fields) => project_cell(&cell, sub_fields),
+                None => cell,
+            }
+        })
+        .collect();
+    json!({ "f": cells })
+}
+
+/// `{"v": ...}` of a RECORD, or of an ARRAY of RECORDs, with the sub-fields of `kept` only.
+fn project_cell(cell: &serde_json::Value, kept: &[Kept]) -> serde_json::Value {
+    match cell.pointer("/v") {
+        Some(serde_json::Value::Array(items)) => json!({
+            "v": items.iter().map(|item| project_cell(item, kept)).collect::<Vec<serde_json::Value>>()
+        }),
+        Some(record) if record.is_object() => json!({ "v": project_record(record, kept) }),
+        _ => cell.clone(),
+    }
+}
+
+/// Short hash of the names, types and modes of `fields`: the results of a query run again
+/// (same columns) share their column layout.
+pub(crate) fn schema_fingerprint(fields: &[TableFieldSchema]) -> String {
+    // FNV-1a, stable across runs and builds
+    fn hash_fields(hash: &mut u64, fields: &[TableFieldSchema]) {
+        for field in fields {
+            let text = format!(
+                "{}:{}:{};",
+                field.name,
+                field.r#type,
+                field.mode.as_deref().unwrap_or("")
+            );
+            for byte in text.bytes() {
+                *hash ^= byte as u64;
+                *hash = hash.wrapping_mul(0x100000001b3);
+            }
+            if let Some(sub_fields) = &field.fields {
+                hash_fields(hash, sub_fields);
+            }
+        }
+    }
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    hash_fields(&mut hash, fields);
+    format!("{:016x}", hash)
+}
+
+/// Path of the sub-field `name` of the field at `parent` (`""` at the top level).
+pub(crate) fn field_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
+/// Path and field of the header cells of the grid, one list per header row, left to right
+/// (the `#` header of the index column left out). Sub-fields of the RECORDs spread by `layout`
+/// are on the row below their RECORD.
+pub(crate) fn header_cells<'a>(
+    fields: &'a [TableFieldSchema],
+    layout: &GridLayout,
+) -> Vec<Vec<(String, &'a TableFieldSchema)>> {
+    let mut rows = Vec::new();
+    push_header_cells(&mut rows, 0, "", fields, layout);
+    rows
+}
+
+fn push_header_cells<'a>(
+    rows: &mut Vec<Vec<(String, &'a TableFieldSchema)>>,
+    depth: usize,
+    parent: &str,
+    fields: &'a [TableFieldSchema],
+    layout: &GridLayout,
+) {
+    for field in fields {
+        let path = field_path(parent, &field.name);
+        if rows.len() <= depth {
+            rows.push(Vec::new());
+        }
+        rows[depth].push((path.clone(), field));
+        if let Some(sub_fields) = &field.fields
+            && !layout.is_collapsed(&path, field)
+        {
+            push_header_cells(rows, depth + 1, &path, sub_fields, layout);
+        }
+    }
+}
+
+/// Text of a RECORD shown in a single cell.
+pub(crate) fn struct_text(value: &CellValue, presentation: StructPresentation) -> String {
+    match presentation {
+        StructPresentation::KeyValue => key_value_text(value),
+        _ => value.to_json(),
+    }
+}
+
+/// `key: value, key: value`, nested RECORDs in braces, ARRAYs as JSON.
+fn key_value_text(value: &CellValue) -> String {
+    match value {
+        CellValue::Struct(entries) => entries
+            .iter()
+            .map(|(key, entry)| match entry {
+                CellValue::Struct(_) => format!("{}: {{{}}}", key, key_value_text(entry)),
+                _ => format!("{}: {}", key, key_value_text(entry)),
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+        CellValue::Null => "NULL".to_string(),
+        _ => value.to_text().unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ColumnLayout, GridLayout, StructPresentation, header_cells, schema_fingerprint, struct_text,
+    };
+    use crate::bigquery::base::TableFieldSchema;
+    use crate::custom_elements::cell_value::CellValue;
+
+    #[test]
+    fn columns_is_the_default() {
+        let mut layout = GridLayout::default();
+        layout.set_struct_presentation("a.b", StructPresentation::Json);
+        assert_eq!(layout.struct_presentation("a.b"), StructPresentation::Json);
+        assert_eq!(layout.struct_presentation("a"), StructPresentation::Columns);
+
+        layout.set_struct_presentation("a.b", StructPresentation::Columns);
+        assert_eq!(layout, GridLayout::default());
+
+        let saved = serde_json::to_string(&GridLayout {
+            structs: [("s".to_string(), StructPresentation::KeyValue)].into(),
+        })
+        .unwrap();
+        assert_eq!(saved, r#"{"structs":{"s":"key_value"}}"#);
+        assert_eq!(
+            serde_json::from_str::<GridLayout>("{}").unwrap(),
+            GridLayout::default()
+        );
+    }
+
+    #[test]
+    fn header_rows_by_depth() {
+        let fields: Vec<TableFieldSchema> = serde_json::from_str(
+            r#"[{"name": "a", "type": "RECORD", "fields": [
+                {"name": "b", "type": "RECORD", "fields": [{"name": "c", "type": "STRING"}]},
+                {"name": "r", "type": "RECORD", "mode": "REPEATED", "fields": [{"name": "d", "type": "STRING"}]}
+            ]}, {"name": "e", "type": "STRING"}]"#,
+        )
+        .unwrap();
+        let paths = |layout: &GridLayout| -> Vec<Vec<String>> {
+            header_cells(&fields, layout)
+                .into_iter()
+                .map(|row| row.into_iter().map(|(path, _)| path).collect())
+                .collect()
+        };
+
+        assert_eq!(
+            paths(&GridLayout::default()),
+            vec![vec!["a", "e"], vec!["a.b", "a.r"], vec!["a.b.c", "a.r.d"]]
+        );
+
+        let mut layout = GridLayout::default();
+        layout.set_struct_presentation("a.b", StructPresentation::Json);
+        assert_eq!(
+            paths(&layout),
+            vec![vec!["a", "e"], vec!["a.b", "a.r"], vec!["a.r.d"]]
+        );
+    }
+
+    #[test]
+    fn struct_as_key_value() {
+        let value = CellValue::Struct(vec![
+            ("name".to_string(), CellValue::Text("x".to_string())),
+            ("size".to_string(), CellValue::Null),
+            (
+                "inner".to_string(),
+                CellValue::Struct(vec![("n".to_string(), CellValue::Int(1))]),
+            ),
+        ]);
+
+        assert_eq!(
+            struct_text(&value, StructPresentation::KeyValue),
+            "name: x, size: NULL, inner: {n: 1}"
+        );
+        assert_eq!(
+            struct_text(&value, StructPresentation::Json),
+            r#"{"name":"x","size":null,"inner":{"n":"1"}}"#
+        );
+    }
+
+    #[test]
+    fn hidden_and_moved_columns() {
+        let fields: Vec<TableFieldSchema> = serde_json::from_str(
+            r#"[{"name": "a", "type": "RECORD", "fields": [
+                {"name": "b", "type": "STRING"},
+                {"name": "c", "type": "STRING"}
+            ]}, {"name": "r", "type": "RECORD", "mode": "REPEATED", "fields": [
+                {"name": "d", "type": "STRING"},
+                {"name": "e", "type": "STRING"}
+            ]}, {"name": "x", "type": "STRING"}]"#,
+        )
+        .unwrap();
+        let rows: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[{"f": [{"v": {"f": [{"v": "b1"}, {"v": "c1"}]}},
+                       {"v": [{"v": {"f": [{"v": "d1"}, {"v": "e1"}]}}]},
+                       {"v": "x1"}]}]"#,
+        )
+        .unwrap();
+
+        let mut columns = ColumnLayout::default();
+        columns.set_hidden("a.b", true);
+        columns.set_hidden("r.e", true);
+        columns.move_field(&fields, "x", "a");
+
+        let (schema, projected) = columns.project(&fields, &rows);
+        let names: Vec<&str> = schema.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["x", "a", "r"]);
+        assert_eq!(schema[1].fields.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            projected[0],
+            serde_json::json!({"f": [
+                {"v": "x1"},
+                {"v": {"f": [{"v": "c1"}]}},
+                {"v": [{"v": {"f": [{"v": "d1"}]}}]}
+            ]})
+        );
+
+        // every sub-field hidden: the RECORD is gone
+        columns.set_hidden("a.c", true);
+        let (schema, _) = columns.project(&fields, &rows);
+        assert!(schema.iter().all(|f| f.name != "a"));
+
+        columns.move_field(&fields, "x", "r");
+        assert_eq!(columns.ordered_names(&fields), vec!["a", "r", "x"]);
+    }
+
+    #[test]
+    fn same_columns_same_fingerprint() {
+        let fields = |data_type: &str| -> Vec<TableFieldSchema> {
+            serde_json::from_str(&format!(r#"[{{"name": "a", "type": "{}"}}]"#, data_type)).unwrap()
+        };
+
+        assert_eq!(
+            schema_fingerprint(&fields("STRING")),
+            schema_fingerprint(&fields("STRING"))
+        );
+        assert_ne!(
+            schema_fingerprint(&fields("STRING")),
+            schema_fingerprint(&fields("INTEGER"))
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::bigquery::{
+    base::{TableReference, TableSchema},
+    jobs::JobReference,
+};
+
+/// The page of rows a `bq-table` / `bq-query` element is showing, as received from the API.
+/// Kept so the data can be exported (and reused) without asking BigQuery again.
+#[derive(Debug, Clone)]
+pub(crate) struct LoadedResults {
+    /// Used to name the exported files: the table id or the job id.
+    pub name: String,
+    pub schema: TableSchema,
+    pub rows: Vec<serde_json::Value>,
+    pub page_start_index: usize,
+    /// Where the rows come from, to fetch the other pages (export all).
+    pub source: Option<ResultsSource>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ResultsSource {
+    /// `jobs.getQueryResults`
+    Query(JobReference),
+    /// `tabledata.list`
+    Table(TableReference),
+}
+
+thread_local! {
+    // keyed by the `be_id` of the element
+    static LOADED_RESULTS: RefCell<HashMap<String, LoadedResults>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn store(element_id: &str, loaded_results: LoadedResults) {
+    LOADED_RESULTS.with(|r| {
+        r.borrow_mut()
+            .insert(element_id.to_string(), loaded_results)
+    });
+}
+
+pub(crate) fn get(element_id: &str) -> Option<LoadedResults> {
+    LOADED_RESULTS.with(|r| r.borrow().get(element_id).cloned())
+}
+
+/// The first results loaded by any element that `predicate` accepts.
+pub(crate) fn find(predicate: &dyn Fn(&LoadedResults) -> bool) -> Option<LoadedResults> {
+    LOADED_RESULTS.with(|r| r.borrow().values().find(|l| predicate(l)).cloned())
+}
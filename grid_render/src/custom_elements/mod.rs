@@ -1,11 +1,52 @@
 pub(crate) mod bq_table_custom_element;
 pub(crate) mod bq_script_custom_element;
 pub(crate) mod bq_query_custom_element;
+pub(crate) mod bq_chart_custom_element;
+pub(crate) mod bq_diff_custom_element;
+pub(crate) mod bq_schema_custom_element;
+mod aggregate;
 mod bq_common_custom_element;
 mod bq_to_table;
+mod cell_inspector;
+mod cell_value;
+mod chart;
+mod column_formats;
+mod column_profile;
+mod column_widths;
+mod conditional_format;
 mod custom_element_definition;
+pub(crate) mod custom_element_registry;
 mod data_table_controls_element;
+mod decimal;
+mod diff;
+mod drill_down;
+mod export;
+mod export_all;
+mod find;
+mod geography;
+mod grid_columns;
+pub(crate) mod grid_events;
+mod grid_find;
+mod grid_layout;
+mod grid_map;
+mod grid_navigation;
+mod grid_pivot;
+mod grid_selection;
+mod layout;
+mod loaded_results;
+mod overlay_panel;
+mod page_walk;
+mod pivot;
+mod profile;
+mod range_interval;
+mod record_view;
+mod schema_tree;
+mod selection;
+mod sql_highlight;
+mod table_details;
+mod table_tabs;
 mod to_table_builder;
+mod value_tree;
 // pub(crate) mod data_table_element;
 pub(crate) mod base_element;
 pub(crate) mod base_element_trait;
@@ -23,6 +64,9 @@ pub enum CustomElement {
     BqTable = "bq-table",
     BqQuery = "bq-query",
     BqScript = "bq-script",
+    BqChart = "bq-chart",
+    BqDiff = "bq-diff",
+    BqSchema = "bq-schema",
 }
 
 impl CustomElement {
@@ -30,7 +74,14 @@ impl CustomElement {
     ADD NEW COMPONENT STEP 2: make it available on the full list
     */
     pub fn get_all() -> Vec<CustomElement> {
-        vec![CustomElement::BqTable, CustomElement::BqQuery, CustomElement::BqScript]
+        vec![
+            CustomElement::BqTable,
+            CustomElement::BqQuery,
+            CustomElement::BqScript,
+            CustomElement::BqChart,
+            CustomElement::BqDiff,
+            CustomElement::BqSchema,
+        ]
     }
 
     /**
@@ -53,11 +104,74 @@ impl CustomElement {
             CustomElement::BqScript => {
                 bq_script_custom_element::BigqueryScriptCustomElement::define(document, element)
             }
+            CustomElement::BqChart => {
+                bq_chart_custom_element::BigqueryChartCustomElement::define(document, element)
+            }
+            CustomElement::BqDiff => {
+                bq_diff_custom_element::BigqueryDiffCustomElement::define(document, element)
+            }
+            CustomElement::BqSchema => {
+                bq_schema_custom_element::BigquerySchemaCustomElement::define(document, element)
+            }
             _ => eprintln!("definition for custom element not found"),
         };
 
         Ok(())
     }
+
+    /**
+    ADD NEW COMPONENT STEP 4: list the attributes that re-render the component when it is written directly in the html
+    */
+    pub fn observed_attributes(&self) -> Vec<&'static str> {
+        match self {
+            CustomElement::BqQuery => {
+                bq_query_custom_element::BigqueryQueryCustomElement::observed_attributes()
+            }
+            CustomElement::BqTable => {
+                bq_table_custom_element::BigqueryTableCustomElement::observed_attributes()
+            }
+            CustomElement::BqScript => {
+                bq_script_custom_element::BigqueryScriptCustomElement::observed_attributes()
+            }
+            CustomElement::BqChart => {
+                bq_chart_custom_element::BigqueryChartCustomElement::observed_attributes()
+            }
+            CustomElement::BqDiff => {
+                bq_diff_custom_element::BigqueryDiffCustomElement::observed_attributes()
+            }
+            CustomElement::BqSchema => {
+                bq_schema_custom_element::BigquerySchemaCustomElement::observed_attributes()
+            }
+            _ => vec![],
+        }
+    }
+
+    /**
+    ADD NEW COMPONENT STEP 5: create new match line for the component to render from its own attributes
+    */
+    pub fn render_declarative(&self, element: &web_sys::Element) {
+        match self {
+            CustomElement::BqQuery => {
+                bq_query_custom_element::BigqueryQueryCustomElement::render_declarative(element)
+            }
+            CustomElement::BqTable => {
+                bq_table_custom_element::BigqueryTableCustomElement::render_declarative(element)
+            }
+            CustomElement::BqScript => {
+                bq_script_custom_element::BigqueryScriptCustomElement::render_declarative(element)
+            }
+            CustomElement::BqChart => {
+                bq_chart_custom_element::BigqueryChartCustomElement::render_declarative(element)
+            }
+            CustomElement::BqDiff => {
+                bq_diff_custom_element::BigqueryDiffCustomElement::render_declarative(element)
+            }
+            CustomElement::BqSchema => {
+                bq_schema_custom_element::BigquerySchemaCustomElement::render_declarative(element)
+            }
+            _ => eprintln!("declarative render for custom element not found"),
+        };
+    }
 }
 
 impl fmt::Display for CustomElement {
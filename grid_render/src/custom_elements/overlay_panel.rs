@@ -0,0 +1,236 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use wasm_bindgen::{JsCast, prelude::Closure};
+use web_sys::Element;
+
+use super::{
+    bq_common_custom_element::{find_in_shadow, host_of},
+    column_formats, column_profile, drill_down, grid_map,
+    grid_navigation::focus_active_cell,
+    grid_pivot, record_view, value_tree,
+};
+use crate::utils::write_clipboard;
+
+/*
+Side panel over the grid of `bq-table` and `bq-query`, showing one thing at a time
+(e.g. the whole value of a cell). It lives in the controls, the last child of the shadow root
+stays the table.
+*/
+pub(crate) const OVERLAY_PANEL: &str = "overlay_panel";
+
+thread_local! {
+    // keyed by the `be_id` of the element: the texts of the copy buttons of the open panel
+    static COPY_TEXTS: RefCell<HashMap<String, Vec<String>>> = RefCell::new(HashMap::new());
+}
+
+pub(crate) fn init(panel: &Element) {
+    if panel.get_attribute("bee").is_some() {
+        return;
+    }
+
+    panel.set_inner_html(concat!(
+        r#"<div class="overlay-header">"#,
+        r#"<h2 class="overlay-title"></h2>"#,
+        r#"<span class="overlay-actions"></span>"#,
+        r#"<button type="button" class="overlay-close" title="Close (Escape)" aria-label="Close">"#,
+        r#"<svg aria-hidden="true" focusable="false" xmlns="http://www.w3.org/2000/svg" width="13" height="13" viewBox="0 0 16 16" fill="currentColor"><path d="M3.5 2.5l4.5 4.5 4.5-4.5 1 1-4.5 4.5 4.5 4.5-1 1-4.5-4.5-4.5 4.5-1-1 4.5-4.5-4.5-4.5z"/></svg>"#,
+        r#"</button>"#,
+        r#"</div>"#,
+        r#"<div class="overlay-meta"></div>"#,
+        r#"<div class="overlay-body"></div>"#
+    ));
+    let _ = panel.set_attribute("style", "display: none;");
+    let _ = panel.set_attribute("role", "dialog");
+    let _ = panel.set_attribute("tabindex", "-1");
+
+    let on_click_closure = Closure::wrap(Box::new(on_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ =
+        panel.add_event_listener_with_callback("click", on_click_closure.as_ref().unchecked_ref());
+    on_click_closure.forget();
+    let on_key_down_closure = Closure::wrap(Box::new(on_key_down) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = panel
+        .add_event_listener_with_callback("keydown", on_key_down_closure.as_ref().unchecked_ref());
+    on_key_down_closure.forget();
+    let on_change_closure = Closure::wrap(Box::new(on_change) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = panel
+        .add_event_listener_with_callback("change", on_change_closure.as_ref().unchecked_ref());
+    on_change_closure.forget();
+    let on_double_click_closure =
+        Closure::wrap(Box::new(on_double_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = panel.add_event_listener_with_callback(
+        "dblclick",
+        on_double_click_closure.as_ref().unchecked_ref(),
+    );
+    on_double_click_closure.forget();
+    let _ = panel.set_attribute("bee", "1");
+}
+
+/// Shows the panel of `host` with `title` and `meta` (plain text) and one copy button per
+/// `(label, text)`. Returns the body, for the caller to fill.
+pub(crate) fn open(
+    host: &Element,
+    title: &str,
+    meta: &str,
+    copy_actions: Vec<(&str, String)>,
+) -> Option<Element> {
+    let panel = find_in_shadow(host, OVERLAY_PANEL)?;
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+
+    if let Ok(Some(title_element)) = panel.query_selector(".overlay-title") {
+        title_element.set_text_content(Some(title));
+    }
+    let _ = panel.set_attribute("aria-label", title);
+    if let Ok(Some(meta_element)) = panel.query_selector(".overlay-meta") {
+        meta_element.set_text_content(Some(meta));
+    }
+    if let Ok(Some(actions)) = panel.query_selector(".overlay-actions") {
+        let buttons: Vec<String> = copy_actions
+            .iter()
+            .enumerate()
+            .map(|(i, (label, _))| {
+                format!(
+                    r#"<button type="button" data-copy="{}">{}</button>"#,
+                    i,
+                    value_tree::escape_html(label)
+                )
+            })
+            .collect();
+        actions.set_inner_html(&buttons.join(""));
+    }
+    COPY_TEXTS.with(|c| {
+        c.borrow_mut().insert(
+            element_id,
+            copy_actions.into_iter().map(|(_, text)| text).collect(),
+        )
+    });
+
+    let body = panel.query_selector(".overlay-body").ok().flatten()?;
+    body.set_inner_html("");
+    panel.set_class_name("");
+    let _ = panel.remove_attribute("style");
+    if let Some(html_element) = panel.dyn_ref::<web_sys::HtmlElement>() {
+        let _ = html_element.focus();
+    }
+
+    Some(body)
+}
+
+pub(crate) fn close(host: &Element) {
+    if let Some(panel) = find_in_shadow(host, OVERLAY_PANEL) {
+        let _ = panel.set_attribute("style", "display: none;");
+        if let Ok(Some(body)) = panel.query_selector(".overlay-body") {
+            body.set_inner_html("");
+        }
+    }
+    if let Some(element_id) = host.get_attribute("be_id") {
+        COPY_TEXTS.with(|c| c.borrow_mut().remove(&element_id));
+    }
+    grid_map::mark_row(host, None);
+    focus_active_cell(host);
+}
+
+fn on_click(event: &web_sys::Event) {
+    let target = match event.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+        Some(t) => t,
+        None => return,
+    };
+    if let Ok(Some(feature)) = target.closest("[data-map-row]")
+        && let Some(host) = host_of(&feature)
+    {
+        grid_map::on_activate(&host, &feature);
+        return;
+    }
+    let button = match target.closest("button").ok().flatten() {
+        Some(b) => b,
+        None => return,
+    };
+    let host = match host_of(&button) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if button.class_list().contains("overlay-close") {
+        close(&host);
+    } else if let Some(index) = button
+        .get_attribute("data-copy")
+        .and_then(|i| i.parse::<usize>().ok())
+    {
+        let element_id = host.get_attribute("be_id").unwrap_or_default();
+        let text = COPY_TEXTS.with(|c| {
+            c.borrow()
+                .get(&element_id)
+                .and_then(|texts| texts.get(index).cloned())
+        });
+        if let Some(text) = text {
+            write_clipboard(text);
+        }
+    } else if button.has_attribute("data-array-nav") {
+        value_tree::on_array_nav(&button);
+    } else if button.has_attribute("data-record-nav") {
+        record_view::on_record_nav(&host, &button);
+    } else if button.has_attribute("data-profile-all") {
+        column_profile::on_profile_all(&host, &button);
+    } else if button.has_attribute("data-format-action") {
+        column_formats::on_click(&host, &button);
+    } else if button.has_attribute("data-pivot-action") {
+        grid_pivot::on_click(&host, &button);
+    } else {
+        drill_down::on_click(&host, &button);
+    }
+}
+
+fn on_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    // the keys of the grid (paging, copying the selection) do not apply in the panel
+    event.stop_propagation();
+
+    let host = match event
+        .current_target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|panel| host_of(&panel))
+    {
+        Some(h) => h,
+        None => return,
+    };
+    match keyboard_event.key().as_str() {
+        "Escape" => {
+            event.prevent_default();
+            close(&host);
+        }
+        "Enter" => match event.target().and_then(|t| t.dyn_into::<Element>().ok()) {
+            Some(target) if target.class_list().contains("drillable") => {
+                event.prevent_default();
+                drill_down::on_activate(&host, &target);
+            }
+            Some(target) if target.has_attribute("data-map-row") => {
+                event.prevent_default();
+                grid_map::on_activate(&host, &target);
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+fn on_change(event: &web_sys::Event) {
+    if let Some(target) = event.target().and_then(|t| t.dyn_into::<Element>().ok())
+        && let Some(host) = host_of(&target)
+    {
+        if target.has_attribute("data-pivot-field") {
+            grid_pivot::on_change(&host, &target);
+        } else {
+            drill_down::on_change(&host, &target);
+        }
+    }
+}
+
+fn on_double_click(event: &web_sys::Event) {
+    if let Some(target) = event.target().and_then(|t| t.dyn_into::<Element>().ok())
+        && let Some(host) = host_of(&target)
+    {
+        drill_down::on_activate(&host, &target);
+    }
+}
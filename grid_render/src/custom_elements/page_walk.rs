@@ -0,0 +1,103 @@
+/*
+The walk of `export_all` through every page of the results, from page token to page token,
+apart from the fetching and the writing: after each page, whether to write it and fetch the next
+one, or to stop, and the rows done out of the total for the progress of the controls.
+*/
+
+/// What the export does with a page it fetched.
+#[derive(Debug, PartialEq)]
+pub(crate) enum PageStep {
+    /// Cancelled while the page was fetched: the page is dropped and the export stops.
+    Cancelled,
+    /// The page is written, then the page of the token is fetched; the export ends on `None`.
+    Write(Option<String>),
+    /// The page came with the token it was fetched with: fetching it again would return it again,
+    /// forever, and ending there would export part of the rows as if they were all of them.
+    Stalled,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PageWalk {
+    /// Rows of the pages written so far.
+    pub(crate) rows_done: usize,
+    /// As last given by the API, unknown until a page gives it.
+    pub(crate) rows_total: Option<usize>,
+    /// The token the last page was fetched with.
+    page_token: Option<String>,
+}
+
+impl PageWalk {
+    /// The step after a page of `rows` rows, followed by `page_token`, fetched while the export
+    /// was `cancelled` or not.
+    pub(crate) fn step(
+        &mut self,
+        rows: usize,
+        rows_total: Option<usize>,
+        page_token: Option<String>,
+        cancelled: bool,
+    ) -> PageStep {
+        if cancelled {
+            return PageStep::Cancelled;
+        }
+        // an empty page may still carry a token (the API stops a response at its time or size
+        // limit), but not the token it was fetched with
+        if page_token.is_some() && page_token == self.page_token {
+            return PageStep::Stalled;
+        }
+        self.rows_done += rows;
+        self.rows_total = rows_total.or(self.rows_total);
+        self.page_token = page_token.clone();
+        PageStep::Write(page_token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageStep, PageWalk};
+
+    #[test]
+    fn walks_until_the_page_without_token() {
+        let mut walk = PageWalk::default();
+        assert_eq!(
+            walk.step(10_000, Some(15_000), Some("t1".to_string()), false),
+            PageStep::Write(Some("t1".to_string()))
+        );
+        assert_eq!((walk.rows_done, walk.rows_total), (10_000, Some(15_000)));
+
+        // the last page has no token
+        assert_eq!(walk.step(5_000, None, None, false), PageStep::Write(None));
+        assert_eq!((walk.rows_done, walk.rows_total), (15_000, Some(15_000)));
+    }
+
+    #[test]
+    fn empty_page_with_a_token_goes_on() {
+        let mut walk = PageWalk::default();
+        assert_eq!(
+            walk.step(0, None, Some("t1".to_string()), false),
+            PageStep::Write(Some("t1".to_string()))
+        );
+        assert_eq!(
+            walk.step(100, Some(100), Some("t2".to_string()), false),
+            PageStep::Write(Some("t2".to_string()))
+        );
+        // the token of the page itself: the walk cannot go on, nor end as if complete
+        assert_eq!(
+            walk.step(0, Some(100), Some("t2".to_string()), false),
+            PageStep::Stalled
+        );
+        assert_eq!((walk.rows_done, walk.rows_total), (100, Some(100)));
+    }
+
+    #[test]
+    fn cancelled_between_pages() {
+        let mut walk = PageWalk::default();
+        walk.step(10_000, Some(30_000), Some("t1".to_string()), false);
+
+        // the page fetched while cancelled is not counted
+        assert_eq!(
+            walk.step(10_000, Some(30_000), Some("t2".to_string()), true),
+            PageStep::Cancelled
+        );
+        assert_eq!((walk.rows_done, walk.rows_total), (10_000, Some(30_000)));
+    }
+}
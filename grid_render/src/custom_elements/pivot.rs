@@ -0,0 +1,470 @@
+use website_component_table::{
+    TableBuilder, TableColumn, TableColumnDefinition, TableColumnGroup, TableRow, TableValue,
+};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    aggregate::Aggregate,
+    cell_value::CellValue,
+    export::{NestedMode, to_columns, to_records},
+    profile::is_numeric_type,
+    to_table_builder::{patch_column_widths, vscode_main_style},
+};
+
+/*
+Pivot of the loaded page: the distinct values of the row keys down, the ones of the column keys
+across (one header level per key), and in each cell an aggregate of the value column over the
+records of that combination, computed like the footer row (NUMERIC exactly). NULL is a bucket of
+its own, sorted first like in the grid. The first key of each side gets a subtotal per value when
+there are several keys, and the last row and column are the grand totals; the totals aggregate
+the values themselves, so an average or a max is the one of the records, not of the cells.
+*/
+const MAX_PIVOT_COLUMNS: usize = 200;
+
+/// What the pivot groups by and computes, keys and value as the column names of
+/// `to_columns(.., NestedMode::Flatten)`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PivotConfig {
+    pub rows: Vec<String>,
+    pub columns: Vec<String>,
+    /// `None` only counts the records.
+    pub value: Option<String>,
+    pub aggregate: Aggregate,
+}
+
+impl PivotConfig {
+    /// The records counted by the first column that can be a key.
+    pub(crate) fn default_for(fields: &[TableFieldSchema]) -> PivotConfig {
+        PivotConfig {
+            rows: pivot_columns(fields)
+                .into_iter()
+                .take(1)
+                .map(|(name, _)| name)
+                .collect(),
+            columns: vec![],
+            value: None,
+            aggregate: Aggregate::Count,
+        }
+    }
+
+    /// The config without the columns `fields` does not have (the query edited since).
+    pub(crate) fn valid_for(&self, fields: &[TableFieldSchema]) -> PivotConfig {
+        let names: Vec<String> = pivot_columns(fields)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let keep = |keys: &[String]| -> Vec<String> {
+            keys.iter().filter(|k| names.contains(k)).cloned().collect()
+        };
+        PivotConfig {
+            rows: keep(&self.rows),
+            columns: keep(&self.columns),
+            value: self.value.clone().filter(|v| names.contains(v)),
+            aggregate: self.aggregate,
+        }
+    }
+}
+
+/// The columns a pivot can group by or aggregate: the scalar leaves, REPEATED fields left out.
+pub(crate) fn pivot_columns(fields: &[TableFieldSchema]) -> Vec<(String, &TableFieldSchema)> {
+    to_columns(fields, NestedMode::Flatten)
+        .into_iter()
+        .filter(|(_, field)| field.fields.is_none() && field.mode.as_deref() != Some("REPEATED"))
+        .collect()
+}
+
+/// A row or a column of the pivot: the records of a full key, of a value of the first key
+/// (its subtotal), or all of them (the grand total).
+#[derive(Debug, Clone)]
+enum Slot {
+    Key(Vec<CellValue>),
+    Subtotal(CellValue),
+    Total,
+}
+
+impl Slot {
+    fn contains(&self, key: &[CellValue]) -> bool {
+        match self {
+            Slot::Key(k) => k.as_slice() == key,
+            Slot::Subtotal(first) => key.first() == Some(first),
+            Slot::Total => true,
+        }
+    }
+}
+
+/// The pivot of `rows` of `fields` as a grid, or why it cannot be built.
+pub(crate) fn pivot_table(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    config: &PivotConfig,
+) -> Result<TableBuilder, String> {
+    let (names, records) = to_records(fields, rows, NestedMode::Flatten);
+    let columns = pivot_columns(fields);
+    let index_of = |name: &str| names.iter().position(|n| n == name);
+    let key_indexes = |keys: &[String]| -> Result<Vec<usize>, String> {
+        keys.iter()
+            .map(|k| index_of(k).ok_or_else(|| format!("No column {} to group by.", k)))
+            .collect()
+    };
+    let row_indexes = key_indexes(&config.rows)?;
+    let column_indexes = key_indexes(&config.columns)?;
+
+    let value = match &config.value {
+        Some(name) => {
+            let field = columns
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, f)| *f)
+                .ok_or_else(|| format!("No column {} to aggregate.", name))?;
+            if !config.aggregate.applies_to(field) {
+                return Err(format!(
+                    "{} does not apply to {} ({}).",
+                    config.aggregate.label(),
+                    name,
+                    field.r#type
+                ));
+            }
+            index_of(name).map(|i| (i, field))
+        }
+        None if config.aggregate == Aggregate::Count => None,
+        None => {
+            return Err(format!(
+                "Pick the column to {}.",
+                config.aggregate.label().to_lowercase()
+            ));
+        }
+    };
+
+    let key = |record: &Vec<CellValue>, indexes: &[usize]| -> Vec<CellValue> {
+        indexes
+            .iter()
+            .map(|i| record.get(*i).cloned().unwrap_or(CellValue::Null))
+            .collect()
+    };
+    let row_keys: Vec<Vec<CellValue>> = records.iter().map(|r| key(r, &row_indexes)).collect();
+    let column_keys: Vec<Vec<CellValue>> =
+        records.iter().map(|r| key(r, &column_indexes)).collect();
+
+    let (column_headers, column_slots) =
+        column_headers(&distinct(&column_keys), config.columns.len());
+    if column_slots.len() > MAX_PIVOT_COLUMNS {
+        return Err(format!(
+            "{} columns, at most {} can be shown: pick column keys with fewer values.",
+            column_slots.len(),
+            MAX_PIVOT_COLUMNS
+        ));
+    }
+    let row_slots = slots(&distinct(&row_keys), config.rows.len());
+
+    let mut table_columns: Vec<TableColumnDefinition> = if config.rows.is_empty() {
+        vec![header_column("rows", "")]
+    } else {
+        config
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, name)| header_column(&format!("row_{}", i), name))
+            .collect()
+    };
+    table_columns.extend(column_headers);
+
+    let table_rows: Vec<TableRow> = row_slots
+        .iter()
+        .map(|row_slot| {
+            // the records of the row, then of each of its cells
+            let in_row: Vec<usize> = (0..records.len())
+                .filter(|r| row_slot.contains(&row_keys[*r]))
+                .collect();
+            let mut cells = row_header_cells(row_slot, config.rows.len().max(1));
+            cells.extend(column_slots.iter().map(|column_slot| {
+                let in_cell = in_row
+                    .iter()
+                    .filter(|r| column_slot.contains(&column_keys[**r]));
+                match value {
+                    Some((index, field)) => {
+                        let values: Vec<CellValue> = in_cell
+                            .map(|r| records[*r].get(index).cloned().unwrap_or(CellValue::Null))
+                            .collect();
+                        let numeric =
+                            config.aggregate == Aggregate::Count || is_numeric_type(&field.r#type);
+                        value_cell(config.aggregate.compute(field, &values), numeric)
+                    }
+                    None => TableValue::Int(in_cell.count() as i64),
+                }
+            }));
+            TableRow { cells }
+        })
+        .collect();
+
+    patch_column_widths(&mut table_columns, &table_rows, &Default::default());
+    Ok(TableBuilder {
+        style: vscode_main_style(),
+        dynamic_table_render: false,
+        columns: table_columns,
+        rows: table_rows,
+    })
+}
+
+/// Short description of what the cells are, e.g. `sum of amount` or `count of rows`.
+pub(crate) fn pivot_description(config: &PivotConfig) -> String {
+    format!(
+        "{} of {}",
+        config.aggregate.name(),
+        config.value.as_deref().unwrap_or("rows")
+    )
+}
+
+/// The distinct `keys`, sorted key by key like the columns of the grid.
+fn distinct(keys: &[Vec<CellValue>]) -> Vec<Vec<CellValue>> {
+    let mut keys = keys.to_vec();
+    keys.sort_by(|a, b| {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| x.sort_cmp(y))
+            .find(|o| o.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    keys.dedup();
+    keys
+}
+
+/// The rows (or columns) of the sorted distinct `keys` of `depth` values: each key, the
+/// subtotal after each value of the first one when there are several, and the grand total.
+fn slots(keys: &[Vec<CellValue>], depth: usize) -> Vec<Slot> {
+    if depth == 0 {
+        return vec![Slot::Total];
+    }
+    let mut slots = vec![];
+    for (i, key) in keys.iter().enumerate() {
+        slots.push(Slot::Key(key.clone()));
+        let last_of_first = keys
+            .get(i + 1)
+            .is_none_or(|next| next.first() != key.first());
+        if depth > 1 && last_of_first {
+            slots.push(Slot::Subtotal(key[0].clone()));
+        }
+    }
+    slots.push(Slot::Total);
+    slots
+}
+
+/// The header of the columns of the distinct column `keys`: a level per key, the subtotal of a
+/// value of the first key under it. In the order of the slots of `slots`.
+fn column_headers(
+    keys: &[Vec<CellValue>],
+    depth: usize,
+) -> (Vec<TableColumnDefinition>, Vec<Slot>) {
+    let slots = slots(keys, depth);
+    let mut headers: Vec<TableColumnDefinition> = vec![];
+    for (i, slot) in slots.iter().enumerate() {
+        let name = format!("c{}", i);
+        match slot {
+            Slot::Key(key) => {
+                let mut column = header_column(&name, &key_text(&key[depth - 1]));
+                // wrapped in the groups of the other levels, innermost first
+                for level in (0..depth - 1).rev() {
+                    column = TableColumnDefinition::Group(TableColumnGroup {
+                        name: format!("g{}_{}", i, level),
+                        text: key_text(&key[level]),
+                        columns: vec![column],
+                    });
+                }
+                push_merged(&mut headers, column);
+            }
+            Slot::Subtotal(first) => {
+                let column = TableColumnDefinition::Group(TableColumnGroup {
+                    name: format!("g{}_0", i),
+                    text: key_text(first),
+                    columns: vec![header_column(&name, "Subtotal")],
+                });
+                push_merged(&mut headers, column);
+            }
+            Slot::Total => headers.push(header_column(&name, "Total")),
+        }
+    }
+    (headers, slots)
+}
+
+/// Appends `column`, in the last group of `headers` when both are groups of the same value.
+fn push_merged(headers: &mut Vec<TableColumnDefinition>, column: TableColumnDefinition) {
+    match (headers.last_mut(), column) {
+        (Some(TableColumnDefinition::Group(last)), TableColumnDefinition::Group(group))
+            if last.text == group.text =>
+        {
+            for inner in group.columns {
+                push_merged(&mut last.columns, inner);
+            }
+        }
+        (_, column) => headers.push(column),
+    }
+}
+
+fn header_column(name: &str, text: &str) -> TableColumnDefinition {
+    TableColumnDefinition::Column(TableColumn {
+        name: name.to_string(),
+        text: text.to_string(),
+        width_px: 0,
+    })
+}
+
+/// The `width` key cells of a row: the values of its key, `Subtotal` after the first one of a
+/// subtotal, `Total` first for the grand total.
+fn row_header_cells(slot: &Slot, width: usize) -> Vec<TableValue> {
+    let mut cells: Vec<TableValue> = match slot {
+        Slot::Key(key) => key.iter().map(key_cell).collect(),
+        Slot::Subtotal(first) => vec![key_cell(first), TableValue::String("Subtotal".to_string())],
+        Slot::Total => vec![TableValue::String("Total".to_string())],
+    };
+    cells.resize(width, TableValue::String(String::new()));
+    cells
+}
+
+fn key_text(value: &CellValue) -> String {
+    value.to_text().unwrap_or_else(|| "NULL".to_string())
+}
+
+fn key_cell(value: &CellValue) -> TableValue {
+    match value {
+        CellValue::Null => TableValue::Null,
+        _ => TableValue::String(key_text(value)),
+    }
+}
+
+/// The cell of an aggregate, `numeric` when it is a number: an integer as one, a decimal kept
+/// as its text so NUMERIC stays exact.
+fn value_cell(text: Option<String>, numeric: bool) -> TableValue {
+    match text {
+        None => TableValue::Null,
+        Some(t) => match t.parse::<i64>() {
+            Ok(i) if numeric => TableValue::Int(i),
+            _ => TableValue::String(t),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use website_component_table::{TableColumnDefinition, TableValue};
+
+    use super::{PivotConfig, pivot_table};
+    use crate::bigquery::base::TableFieldSchema;
+    use crate::custom_elements::aggregate::Aggregate;
+
+    fn fields() -> Vec<TableFieldSchema> {
+        serde_json::from_str(
+            r#"[{"name": "region", "type": "STRING"}, {"name": "year", "type": "INTEGER"},
+                {"name": "quarter", "type": "STRING"}, {"name": "amount", "type": "NUMERIC"}]"#,
+        )
+        .unwrap()
+    }
+
+    fn rows() -> Vec<serde_json::Value> {
+        [
+            (Some("eu"), "2023", "Q1", "0.1"),
+            (Some("eu"), "2023", "Q2", "0.2"),
+            (Some("eu"), "2024", "Q1", "1"),
+            (Some("us"), "2023", "Q1", "5"),
+            (None, "2024", "Q2", "7"),
+        ]
+        .iter()
+        .map(|(region, year, quarter, amount)| {
+            serde_json::json!({"f": [{"v": region}, {"v": year}, {"v": quarter}, {"v": amount}]})
+        })
+        .collect()
+    }
+
+    fn text(cell: &TableValue) -> String {
+        match cell {
+            TableValue::String(s) => s.clone(),
+            TableValue::Int(i) => i.to_string(),
+            TableValue::Float(f) => f.to_string(),
+            TableValue::Null => "NULL".to_string(),
+            other => panic!("cell {:?}", other),
+        }
+    }
+
+    fn table(config: &PivotConfig) -> Vec<Vec<String>> {
+        pivot_table(&fields(), &rows(), config)
+            .unwrap()
+            .rows
+            .iter()
+            .map(|r| r.cells.iter().map(text).collect())
+            .collect()
+    }
+
+    #[test]
+    fn sums_by_row_and_column_keys_with_totals() {
+        let config = PivotConfig {
+            rows: vec!["region".to_string()],
+            columns: vec!["year".to_string()],
+            value: Some("amount".to_string()),
+            aggregate: Aggregate::Sum,
+        };
+        assert_eq!(
+            table(&config),
+            vec![
+                vec!["NULL", "NULL", "7", "7"],
+                vec!["eu", "0.3", "1", "1.3"],
+                vec!["us", "5", "NULL", "5"],
+                vec!["Total", "5.3", "8", "13.3"],
+            ]
+        );
+    }
+
+    #[test]
+    fn subtotals_of_the_first_key() {
+        let config = PivotConfig {
+            rows: vec!["region".to_string(), "quarter".to_string()],
+            columns: vec!["year".to_string(), "quarter".to_string()],
+            value: None,
+            aggregate: Aggregate::Count,
+        };
+        let pivot = pivot_table(&fields(), &rows(), &config).unwrap();
+        let rows: Vec<Vec<String>> = pivot
+            .rows
+            .iter()
+            .map(|r| r.cells.iter().map(text).collect())
+            .collect();
+        // 2023: Q1, Q2, subtotal; 2024: Q1, Q2, subtotal; total
+        assert_eq!(rows[2], vec!["eu", "Q1", "1", "0", "1", "1", "0", "1", "2"]);
+        assert_eq!(
+            rows[4],
+            vec!["eu", "Subtotal", "1", "1", "2", "1", "0", "1", "3"]
+        );
+        assert_eq!(
+            rows.last().unwrap(),
+            &vec!["Total", "", "2", "1", "3", "1", "1", "2", "5"]
+        );
+
+        // two groups of the years over the quarters and their subtotal, then the total
+        let headers: Vec<String> = pivot.columns[2..]
+            .iter()
+            .map(|c| match c {
+                TableColumnDefinition::Group(g) => format!("{}:{}", g.text, g.columns.len()),
+                TableColumnDefinition::Column(c) => c.text.clone(),
+            })
+            .collect();
+        assert_eq!(headers, vec!["2023:3", "2024:3", "Total"]);
+    }
+
+    #[test]
+    fn aggregate_must_apply_to_the_value() {
+        let config = PivotConfig {
+            rows: vec![],
+            columns: vec!["region".to_string()],
+            value: Some("quarter".to_string()),
+            aggregate: Aggregate::Avg,
+        };
+        assert!(pivot_table(&fields(), &rows(), &config).is_err());
+
+        let config = PivotConfig {
+            value: Some("amount".to_string()),
+            ..config
+        };
+        assert_eq!(
+            table(&config),
+            vec![vec!["Total", "7", "0.433333333", "5", "2.66"]]
+        );
+    }
+}
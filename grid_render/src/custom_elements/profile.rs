@@ -0,0 +1,537 @@
+use std::collections::HashMap;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{cell_value::CellValue, value_tree::escape_html};
+
+/*
+Profile of one column of the results: nulls, distinct values, min / max, mean and a histogram
+for the numeric types, the most frequent values for the others. Built page by page so it can be
+computed over the loaded page or over every page of the results; what it keeps is bounded
+whatever the number of rows: the counts of `MAX_DISTINCT` distinct values at most, and the first
+`MAX_HISTOGRAM_VALUES` numbers for the histogram (the mean, min and max cover all of them).
+The values of a REPEATED column (or of a field under one) are its elements, an empty ARRAY
+counts as a NULL.
+*/
+const HISTOGRAM_BINS: usize = 10;
+const MAX_DISTINCT: usize = 10_000;
+const MAX_HISTOGRAM_VALUES: usize = 100_000;
+const TOP_VALUES: usize = 5;
+const HISTOGRAM_WIDTH: f64 = 240.0;
+const HISTOGRAM_HEIGHT: f64 = 60.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ColumnProfile {
+    pub path: String,
+    /// `TableFieldSchema.r#type` of the field.
+    pub r#type: String,
+    /// The field, or one of the RECORDs holding it, is REPEATED.
+    pub repeated: bool,
+    pub values: usize,
+    pub nulls: usize,
+    pub distinct: usize,
+    /// More than `distinct` distinct values: the ones past `MAX_DISTINCT` were not counted.
+    pub distinct_capped: bool,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Numeric types only.
+    pub mean: Option<f64>,
+    /// Value and count, most frequent first; not for the numeric types.
+    pub top_values: Vec<(String, usize)>,
+    /// Numeric types only.
+    pub histogram: Vec<HistogramBin>,
+    /// The histogram covers the first numbers only, that many.
+    pub histogram_first: Option<usize>,
+}
+
+/// Counts of a column profiled so far.
+#[derive(Debug, Clone)]
+pub(crate) struct ProfileBuilder {
+    path: String,
+    r#type: String,
+    repeated: bool,
+    values: usize,
+    nulls: usize,
+    counts: HashMap<String, usize>,
+    distinct_capped: bool,
+    /// The first `MAX_HISTOGRAM_VALUES` numbers.
+    numbers: Vec<f64>,
+    number_count: usize,
+    number_sum: f64,
+    min: Option<CellValue>,
+    max: Option<CellValue>,
+}
+
+pub(crate) fn is_numeric_type(field_type: &str) -> bool {
+    matches!(
+        field_type,
+        "INTEGER" | "INT64" | "FLOAT" | "FLOAT64" | "NUMERIC" | "BIGNUMERIC"
+    )
+}
+
+impl ProfileBuilder {
+    /// `None` when `path` is not a field of `fields`.
+    pub(crate) fn new(fields: &[TableFieldSchema], path: &str) -> Option<ProfileBuilder> {
+        let (field, repeated) = field_at(fields, path)?;
+
+        Some(ProfileBuilder {
+            path: path.to_string(),
+            r#type: field.r#type.clone(),
+            repeated,
+            values: 0,
+            nulls: 0,
+            counts: HashMap::new(),
+            distinct_capped: false,
+            numbers: Vec::new(),
+            number_count: 0,
+            number_sum: 0.0,
+            min: None,
+            max: None,
+        })
+    }
+
+    /// Adds the REST rows (`{"f": [...]}`) of a page, `fields` being the schema of the results.
+    pub(crate) fn add_rows(&mut self, fields: &[TableFieldSchema], rows: &[serde_json::Value]) {
+        for value in column_values(fields, rows, &self.path) {
+            self.add(value);
+        }
+    }
+
+    fn add(&mut self, value: CellValue) {
+        self.values += 1;
+        let text = match value.to_text() {
+            Some(t) => t,
+            None => {
+                self.nulls += 1;
+                return;
+            }
+        };
+        if let Some(count) = self.counts.get_mut(&text) {
+            *count += 1;
+        } else if self.counts.len() < MAX_DISTINCT {
+            self.counts.insert(text, 1);
+        } else {
+            self.distinct_capped = true;
+        }
+
+        if is_numeric_type(&self.r#type) {
+            let number = match &value {
+                CellValue::Int(i) => Some(*i as f64),
+                CellValue::Float(f) => Some(*f),
+                CellValue::Numeric(s) => s.parse::<f64>().ok(),
+                _ => None,
+            };
+            if let Some(n) = number.filter(|n| n.is_finite()) {
+                self.number_count += 1;
+                self.number_sum += n;
+                if self.numbers.len() < MAX_HISTOGRAM_VALUES {
+                    self.numbers.push(n);
+                }
+            }
+        }
+        if self.min.as_ref().is_none_or(|m| value.sort_cmp(m).is_lt()) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|m| value.sort_cmp(m).is_gt()) {
+            self.max = Some(value);
+        }
+    }
+
+    pub(crate) fn build(&self) -> ColumnProfile {
+        let numeric = is_numeric_type(&self.r#type);
+        let mean = if numeric && self.number_count > 0 {
+            Some(self.number_sum / self.number_count as f64)
+        } else {
+            None
+        };
+
+        let mut top_values: Vec<(String, usize)> = if numeric {
+            vec![]
+        } else {
+            self.counts.iter().map(|(v, c)| (v.clone(), *c)).collect()
+        };
+        top_values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_values.truncate(TOP_VALUES);
+
+        ColumnProfile {
+            path: self.path.clone(),
+            r#type: self.r#type.clone(),
+            repeated: self.repeated,
+            values: self.values,
+            nulls: self.nulls,
+            distinct: self.counts.len(),
+            distinct_capped: self.distinct_capped,
+            min: self.min.as_ref().and_then(|m| m.to_text()),
+            max: self.max.as_ref().and_then(|m| m.to_text()),
+            mean,
+            top_values,
+            histogram: histogram(&self.numbers),
+            histogram_first: Some(self.numbers.len()).filter(|n| *n < self.number_count),
+        }
+    }
+}
+
+/// The field at `path` (`a.b`) of `fields`, and whether it or one of the RECORDs holding it is REPEATED.
+pub(crate) fn field_at<'a>(
+    fields: &'a [TableFieldSchema],
+    path: &str,
+) -> Option<(&'a TableFieldSchema, bool)> {
+    let mut current = fields;
+    let mut repeated = false;
+    let mut field = None;
+    for segment in path.split('.') {
+        let f = current.iter().find(|f| f.name == segment)?;
+        repeated |= f.mode.as_deref() == Some("REPEATED");
+        current = f.fields.as_deref().unwrap_or(&[]);
+        field = Some(f);
+    }
+    Some((field?, repeated))
+}
+
+/// The values of the field at `path` in the REST rows (`{"f": [...]}`) of `fields`, the
+/// elements of the ARRAYs on the way one by one.
+pub(crate) fn column_values(
+    fields: &[TableFieldSchema],
+    rows: &[serde_json::Value],
+    path: &str,
+) -> Vec<CellValue> {
+    let segments: Vec<String> = path.split('.').map(|s| s.to_string()).collect();
+    let index = match fields.iter().position(|f| f.name == segments[0]) {
+        Some(i) => i,
+        None => return vec![],
+    };
+    let mut values = Vec::new();
+    for row in rows {
+        let v = row
+            .pointer("/f")
+            .and_then(|f| f.get(index))
+            .and_then(|c| c.pointer("/v"))
+            .unwrap_or(&serde_json::Value::Null);
+        values_at(
+            CellValue::from_bq(v, &fields[index]),
+            &segments[1..],
+            &mut values,
+        );
+    }
+    values
+}
+
+/// The leaf values of `value` at the sub-field path `segments`, the elements of ARRAYs on the way.
+fn values_at(value: CellValue, segments: &[String], values: &mut Vec<CellValue>) {
+    match value {
+        CellValue::Array(items) if items.is_empty() => values.push(CellValue::Null),
+        CellValue::Array(items) => {
+            for item in items {
+                values_at(item, segments, values);
+            }
+        }
+        CellValue::Struct(entries) if !segments.is_empty() => {
+            let value = entries
+                .into_iter()
+                .find(|(name, _)| *name == segments[0])
+                .map(|(_, v)| v)
+                .unwrap_or(CellValue::Null);
+            values_at(value, &segments[1..], values);
+        }
+        value => values.push(value),
+    }
+}
+
+/// Equal width bins from the smallest to the largest number, a single bin when they are all equal.
+fn histogram(numbers: &[f64]) -> Vec<HistogramBin> {
+    let (min, max) = match numbers.iter().fold(None, |range: Option<(f64, f64)>, n| {
+        Some(range.map_or((*n, *n), |(min, max)| (min.min(*n), max.max(*n))))
+    }) {
+        Some(r) => r,
+        None => return vec![],
+    };
+    if min == max {
+        return vec![HistogramBin {
+            start: min,
+            end: max,
+            count: numbers.len(),
+        }];
+    }
+
+    let width = (max - min) / HISTOGRAM_BINS as f64;
+    let mut bins: Vec<HistogramBin> = (0..HISTOGRAM_BINS)
+        .map(|i| HistogramBin {
+            start: min + width * i as f64,
+            end: if i + 1 == HISTOGRAM_BINS {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for n in numbers {
+        let i = (((n - min) / width) as usize).min(HISTOGRAM_BINS - 1);
+        bins[i].count += 1;
+    }
+    bins
+}
+
+/// Short text of a number: integers without decimals, the others with up to 4.
+pub(crate) fn number_text(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+    let text = format!("{:.4}", n);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn percent_text(count: usize, total: usize) -> String {
+    match total {
+        0 => "0%".to_string(),
+        _ => format!("{:.1}%", count as f64 * 100.0 / total as f64),
+    }
+}
+
+/// The profile as html, for the overlay panel.
+pub(crate) fn profile_html(profile: &ColumnProfile) -> String {
+    let mut stats = vec![
+        (
+            if profile.repeated { "Values" } else { "Rows" },
+            profile.values.to_string(),
+        ),
+        (
+            "Nulls",
+            format!(
+                "{} ({})",
+                profile.nulls,
+                percent_text(profile.nulls, profile.values)
+            ),
+        ),
+        (
+            "Distinct",
+            if profile.distinct_capped {
+                format!("≥ {}", profile.distinct)
+            } else {
+                profile.distinct.to_string()
+            },
+        ),
+    ];
+    if let Some(min) = &profile.min {
+        stats.push(("Min", min.clone()));
+    }
+    if let Some(max) = &profile.max {
+        stats.push(("Max", max.clone()));
+    }
+    if let Some(mean) = profile.mean {
+        stats.push(("Mean", number_text(mean)));
+    }
+
+    let mut html = format!(
+        r#"<dl class="profile-stats">{}</dl>"#,
+        stats
+            .iter()
+            .map(|(name, value)| format!("<dt>{}</dt><dd>{}</dd>", name, escape_html(value)))
+            .collect::<String>()
+    );
+
+    if !profile.top_values.is_empty() {
+        html.push_str(&format!(
+            r#"<h3>Most frequent{}</h3><ol class="profile-top">{}</ol>"#,
+            if profile.distinct_capped {
+                format!(" of the first {} distinct values", profile.distinct)
+            } else {
+                String::new()
+            },
+            profile
+                .top_values
+                .iter()
+                .map(|(value, count)| format!(
+                    r#"<li><span class="value">{}</span> <span class="count">{} ({})</span></li>"#,
+                    escape_html(value),
+                    count,
+                    percent_text(*count, profile.values)
+                ))
+                .collect::<String>()
+        ));
+    }
+
+    if !profile.histogram.is_empty() {
+        html.push_str(&histogram_svg(
+            &profile.path,
+            &profile.histogram,
+            profile.histogram_first,
+        ));
+    }
+    html
+}
+
+fn histogram_svg(path: &str, bins: &[HistogramBin], first: Option<usize>) -> String {
+    let highest = bins.iter().map(|b| b.count).max().unwrap_or(0).max(1) as f64;
+    let bar_width = HISTOGRAM_WIDTH / bins.len() as f64;
+    let bars: String = bins
+        .iter()
+        .enumerate()
+        .map(|(i, bin)| {
+            let height = (bin.count as f64 / highest * HISTOGRAM_HEIGHT).max(if bin.count > 0 { 1.0 } else { 0.0 });
+            format!(
+                r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"><title>{} – {}: {}</title></rect>"#,
+                i as f64 * bar_width + 1.0,
+                HISTOGRAM_HEIGHT - height,
+                (bar_width - 2.0).max(1.0),
+                height,
+                number_text(bin.start),
+                number_text(bin.end),
+                bin.count
+            )
+        })
+        .collect();
+
+    format!(
+        concat!(
+            r#"<h3>Histogram{}</h3>"#,
+            r#"<svg class="profile-histogram" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" role="img" aria-label="Histogram of {}">{}</svg>"#,
+            r#"<div class="profile-axis"><span>{}</span><span>{}</span></div>"#
+        ),
+        match first {
+            Some(n) => format!(" of the first {} values", n),
+            None => String::new(),
+        },
+        HISTOGRAM_WIDTH,
+        HISTOGRAM_HEIGHT,
+        escape_html(path),
+        bars,
+        number_text(bins[0].start),
+        number_text(bins[bins.len() - 1].end)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        HistogramBin, MAX_DISTINCT, MAX_HISTOGRAM_VALUES, ProfileBuilder, number_text, profile_html,
+    };
+    use crate::bigquery::base::TableFieldSchema;
+
+    fn fields() -> Vec<TableFieldSchema> {
+        serde_json::from_str(
+            r#"[{"name": "n", "type": "INTEGER"}, {"name": "s", "type": "RECORD", "fields": [
+                {"name": "name", "type": "STRING"}
+            ]}, {"name": "r", "type": "RECORD", "mode": "REPEATED", "fields": [
+                {"name": "f", "type": "FLOAT"}
+            ]}]"#,
+        )
+        .unwrap()
+    }
+
+    fn rows() -> Vec<serde_json::Value> {
+        serde_json::from_str(
+            r#"[
+                {"f": [{"v": "1"}, {"v": {"f": [{"v": "b"}]}}, {"v": [{"v": {"f": [{"v": "0.5"}]}}, {"v": {"f": [{"v": "1.5"}]}}]}]},
+                {"f": [{"v": "11"}, {"v": {"f": [{"v": "a"}]}}, {"v": []}]},
+                {"f": [{"v": null}, {"v": {"f": [{"v": "b"}]}}, {"v": [{"v": {"f": [{"v": null}]}}]}]},
+                {"f": [{"v": "3"}, {"v": null}, {"v": []}]}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn numeric_column() {
+        let mut builder = ProfileBuilder::new(&fields(), "n").unwrap();
+        builder.add_rows(&fields(), &rows());
+        let profile = builder.build();
+
+        assert_eq!((profile.values, profile.nulls, profile.distinct), (4, 1, 3));
+        assert_eq!(profile.min.as_deref(), Some("1"));
+        assert_eq!(profile.max.as_deref(), Some("11"));
+        assert_eq!(profile.mean, Some(5.0));
+        assert!(profile.top_values.is_empty());
+        assert_eq!(profile.histogram.len(), 10);
+        assert_eq!(
+            profile.histogram[0],
+            HistogramBin {
+                start: 1.0,
+                end: 2.0,
+                count: 1
+            }
+        );
+        assert_eq!(profile.histogram[9].count, 1);
+        assert_eq!(profile.histogram.iter().map(|b| b.count).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn strings_under_a_record_and_pages_added_up() {
+        let mut builder = ProfileBuilder::new(&fields(), "s.name").unwrap();
+        builder.add_rows(&fields(), &rows()[..2]);
+        builder.add_rows(&fields(), &rows()[2..]);
+        let profile = builder.build();
+
+        assert_eq!((profile.values, profile.nulls, profile.distinct), (4, 1, 2));
+        assert_eq!(
+            profile.top_values,
+            vec![("b".to_string(), 2), ("a".to_string(), 1)]
+        );
+        assert_eq!(profile.mean, None);
+        assert!(profile.histogram.is_empty());
+        assert!(!profile.repeated);
+
+        let html = profile_html(&profile);
+        assert!(html.contains("<dt>Nulls</dt><dd>1 (25.0%)</dd>"));
+        assert!(!html.contains("<svg"));
+    }
+
+    #[test]
+    fn elements_of_a_repeated_record() {
+        let mut builder = ProfileBuilder::new(&fields(), "r.f").unwrap();
+        builder.add_rows(&fields(), &rows());
+        let profile = builder.build();
+
+        // 0.5, 1.5, NULL and the two empty arrays
+        assert!(profile.repeated);
+        assert_eq!((profile.values, profile.nulls, profile.distinct), (5, 3, 2));
+        assert_eq!(profile.mean, Some(1.0));
+        assert!(profile_html(&profile).contains(r#"aria-label="Histogram of r.f""#));
+
+        assert!(ProfileBuilder::new(&fields(), "r.missing").is_none());
+    }
+
+    #[test]
+    fn counts_are_bounded() {
+        let fields: Vec<TableFieldSchema> =
+            serde_json::from_str(r#"[{"name": "n", "type": "INTEGER"}]"#).unwrap();
+        let rows: Vec<serde_json::Value> = (0..MAX_HISTOGRAM_VALUES + 10)
+            .map(|i| serde_json::json!({"f": [{"v": i.to_string()}]}))
+            .collect();
+        let mut builder = ProfileBuilder::new(&fields, "n").unwrap();
+        builder.add_rows(&fields, &rows);
+        let profile = builder.build();
+
+        assert_eq!(profile.values, MAX_HISTOGRAM_VALUES + 10);
+        assert_eq!(
+            (profile.distinct, profile.distinct_capped),
+            (MAX_DISTINCT, true)
+        );
+        assert_eq!(profile.histogram_first, Some(MAX_HISTOGRAM_VALUES));
+        assert_eq!(
+            profile.histogram.iter().map(|b| b.count).sum::<usize>(),
+            MAX_HISTOGRAM_VALUES
+        );
+        // over every number, not only the first ones
+        assert_eq!(profile.mean, Some((MAX_HISTOGRAM_VALUES + 9) as f64 / 2.0));
+        assert_eq!(profile.max.as_deref(), Some("100009"));
+
+        let html = profile_html(&profile);
+        assert!(html.contains("<dt>Distinct</dt><dd>≥ 10000</dd>"));
+        assert!(html.contains("<h3>Histogram of the first 100000 values</h3>"));
+    }
+
+    #[test]
+    fn number_texts() {
+        assert_eq!(number_text(3.0), "3");
+        assert_eq!(number_text(-0.125), "-0.125");
+        assert_eq!(number_text(1.0 / 3.0), "0.3333");
+    }
+}
@@ -0,0 +1,321 @@
+use std::cmp::Ordering;
+
+use super::cell_value::timestamp_text;
+
+/*
+RANGE<DATE | DATETIME | TIMESTAMP> and INTERVAL values, as BigQuery returns them: the range as a
+bracketed text (`[2024-01-01, 2024-02-01)`, `UNBOUNDED` for an open bound, the bounds of a
+RANGE<TIMESTAMP> possibly in seconds like a TIMESTAMP) and the interval in its canonical
+`Y-M D H:M:S[.F]` format (`1-2 3 4:5:6.5`, each part signed). Parsed to be shown readably in the
+grid and sorted by value; the cells keep the text BigQuery returned, for the copies and exports.
+*/
+
+/// An INTERVAL: the months of its year-month part, its days and the rest in microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Interval {
+    pub months: i64,
+    pub days: i64,
+    pub micros: i64,
+}
+
+impl Interval {
+    /// Parses the canonical `Y-M D H:M:S[.F]` text.
+    pub(crate) fn parse(text: &str) -> Option<Interval> {
+        let mut parts = text.split_whitespace();
+        let (year_month, days, time) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let (negative, year_month) = unsigned(year_month);
+        let (years, months) = year_month.split_once('-')?;
+        let months = years.parse::<i64>().ok()? * 12 + months.parse::<i64>().ok()?;
+
+        let (negative_time, time) = unsigned(time);
+        let mut hms = time.splitn(3, ':');
+        let (hours, minutes, seconds) = (hms.next()?, hms.next()?, hms.next()?);
+        let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+        if fraction.len() > 6 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let micros = ((hours.parse::<i64>().ok()? * 60 + minutes.parse::<i64>().ok()?) * 60
+            + seconds.parse::<i64>().ok()?)
+            * 1_000_000
+            + format!("{:0<6}", fraction).parse::<i64>().ok()?;
+
+        Some(Interval {
+            months: if negative { -months } else { months },
+            days: days.parse().ok()?,
+            micros: if negative_time { -micros } else { micros },
+        })
+    }
+
+    /// The length, a month counted as 30 days and a day as 24 hours, as BigQuery compares them.
+    fn total_micros(&self) -> i128 {
+        const DAY: i128 = 24 * 3600 * 1_000_000;
+        (self.months as i128 * 30 + self.days as i128) * DAY + self.micros as i128
+    }
+
+    /// E.g. `1 year 2 days 03:00:00`, `-3 months`; the time left out when zero.
+    pub(crate) fn text(&self) -> String {
+        let mut parts = vec![];
+        let (years, months) = (self.months / 12, self.months % 12);
+        for (count, unit) in [(years, "year"), (months, "month"), (self.days, "day")] {
+            if count != 0 {
+                parts.push(format!(
+                    "{} {}{}",
+                    count,
+                    unit,
+                    if count.abs() == 1 { "" } else { "s" }
+                ));
+            }
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let micros = self.micros.unsigned_abs();
+            let seconds = micros / 1_000_000;
+            let mut time = format!(
+                "{}{:02}:{:02}:{:02}",
+                if self.micros < 0 { "-" } else { "" },
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            );
+            let fraction = micros % 1_000_000;
+            if fraction != 0 {
+                time.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+            }
+            parts.push(time);
+        }
+        parts.join(" ")
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Interval) -> Ordering {
+        self.total_micros()
+            .cmp(&other.total_micros())
+            .then_with(|| (self.months, self.days).cmp(&(other.months, other.days)))
+    }
+}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Interval) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// `-` in front of `text`, and the rest.
+fn unsigned(text: &str) -> (bool, &str) {
+    match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    }
+}
+
+/// A RANGE: its bounds as returned, `None` when unbounded; the end is excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Range {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl Range {
+    /// Parses the `[start, end)` text.
+    pub(crate) fn parse(text: &str) -> Option<Range> {
+        let inner = text.trim().strip_prefix('[')?.strip_suffix(')')?;
+        let (start, end) = inner.split_once(',')?;
+        let bound = |b: &str| {
+            let b = b.trim();
+            match b.eq_ignore_ascii_case("UNBOUNDED") || b.eq_ignore_ascii_case("NULL") {
+                true => None,
+                false => Some(b.to_string()),
+            }
+        };
+        Some(Range {
+            start: bound(start),
+            end: bound(end),
+        })
+    }
+
+    /// E.g. `[2024-01-01, 2024-02-01)`, `(−∞, 2024-02-01)`; bounds in seconds as timestamps.
+    pub(crate) fn text(&self) -> String {
+        format!(
+            "{}, {})",
+            match &self.start {
+                Some(s) => format!("[{}", bound_text(s)),
+                None => "(−∞".to_string(),
+            },
+            match &self.end {
+                Some(e) => bound_text(e),
+                None => "∞".to_string(),
+            }
+        )
+    }
+
+    /// The text of the range in a `RANGE<...>` literal: `UNBOUNDED` for an open bound and the
+    /// bounds in seconds as timestamps, e.g. `[2024-01-01, UNBOUNDED)`.
+    pub(crate) fn sql_text(&self) -> String {
+        let bound = |b: &Option<String>| match b {
+            Some(b) => bound_text(b),
+            None => "UNBOUNDED".to_string(),
+        };
+        format!("[{}, {})", bound(&self.start), bound(&self.end))
+    }
+
+    /// By start, an unbounded one first, then by end, an unbounded one last.
+    fn sort_cmp(&self, other: &Range) -> Ordering {
+        let start = match (&self.start, &other.start) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => bound_cmp(a, b),
+        };
+        start.then_with(|| match (&self.end, &other.end) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => bound_cmp(a, b),
+        })
+    }
+}
+
+/// A bound as shown: a timestamp in seconds in ISO 8601, to the microsecond, the others as is.
+fn bound_text(bound: &str) -> String {
+    match bound.parse::<f64>() {
+        Ok(_) => timestamp_text(bound).unwrap_or_else(|| bound.to_string()),
+        Err(_) => bound.to_string(),
+    }
+}
+
+/// Dates and datetimes sort as text, timestamps in seconds by value.
+fn bound_cmp(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.total_cmp(&y),
+        _ => a.cmp(b),
+    }
+}
+
+/// The text shown in the grid for the value `text` of a column of type `field_type`, `None`
+/// when it is not a RANGE or INTERVAL (or does not parse as one).
+pub(crate) fn display_text(field_type: &str, text: &str) -> Option<String> {
+    match field_type {
+        "INTERVAL" => Interval::parse(text).map(|i| i.text()),
+        "RANGE" => Range::parse(text).map(|r| r.text()),
+        _ => None,
+    }
+}
+
+/// Order of two texts that are both intervals or both ranges, `None` otherwise.
+pub(crate) fn sort_cmp(a: &str, b: &str) -> Option<Ordering> {
+    if let (Some(x), Some(y)) = (Interval::parse(a), Interval::parse(b)) {
+        return Some(x.cmp(&y));
+    }
+    if let (Some(x), Some(y)) = (Range::parse(a), Range::parse(b)) {
+        return Some(x.sort_cmp(&y));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::{Interval, Range, display_text, sort_cmp};
+
+    #[test]
+    fn intervals_are_shown_readably() {
+        assert_eq!(
+            display_text("INTERVAL", "1-0 2 3:0:0").as_deref(),
+            Some("1 year 2 days 03:00:00")
+        );
+        assert_eq!(
+            display_text("INTERVAL", "1-6 15 0:0:0").as_deref(),
+            Some("1 year 6 months 15 days")
+        );
+        assert_eq!(
+            display_text("INTERVAL", "-0-3 0 0:0:0").as_deref(),
+            Some("-3 months")
+        );
+        assert_eq!(
+            display_text("INTERVAL", "0-0 -1 -100:5:6.25").as_deref(),
+            Some("-1 day -100:05:06.25")
+        );
+        assert_eq!(
+            display_text("INTERVAL", "0-0 0 0:0:0").as_deref(),
+            Some("00:00:00")
+        );
+        assert_eq!(
+            Interval::parse("-2-11 3 -0:0:0.000001"),
+            Some(Interval {
+                months: -35,
+                days: 3,
+                micros: -1
+            })
+        );
+        assert_eq!(display_text("INTERVAL", "1 year"), None);
+        assert_eq!(display_text("STRING", "1-0 2 3:0:0"), None);
+    }
+
+    #[test]
+    fn ranges_mark_their_open_bounds() {
+        assert_eq!(
+            display_text("RANGE", "[2024-01-01, 2024-02-01)").as_deref(),
+            Some("[2024-01-01, 2024-02-01)")
+        );
+        assert_eq!(
+            display_text("RANGE", "[UNBOUNDED, 2024-02-01T10:00:00)").as_deref(),
+            Some("(−∞, 2024-02-01T10:00:00)")
+        );
+        assert_eq!(
+            display_text("RANGE", "[2024-01-01, UNBOUNDED)").as_deref(),
+            Some("[2024-01-01, ∞)")
+        );
+        assert_eq!(
+            Range::parse("[UNBOUNDED, UNBOUNDED)"),
+            Some(Range {
+                start: None,
+                end: None
+            })
+        );
+        assert_eq!(display_text("RANGE", "2024-01-01"), None);
+    }
+
+    #[test]
+    fn sorts_by_value() {
+        // 1 month is 30 days, whatever the text says
+        assert_eq!(
+            sort_cmp("0-1 0 0:0:0", "0-0 29 23:0:0"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            sort_cmp("0-0 0 10:0:0", "0-0 0 9:0:0"),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            sort_cmp("-1-0 0 0:0:0", "0-0 0 0:0:1"),
+            Some(Ordering::Less)
+        );
+
+        let mut ranges = vec![
+            "[2024-01-01, UNBOUNDED)",
+            "[2024-01-01, 2024-02-01)",
+            "[UNBOUNDED, 2023-01-01)",
+            "[2023-06-01, 2023-07-01)",
+        ];
+        ranges.sort_by(|a, b| sort_cmp(a, b).unwrap());
+        assert_eq!(
+            ranges,
+            vec![
+                "[UNBOUNDED, 2023-01-01)",
+                "[2023-06-01, 2023-07-01)",
+                "[2024-01-01, 2024-02-01)",
+                "[2024-01-01, UNBOUNDED)",
+            ]
+        );
+        assert_eq!(
+            sort_cmp("[900, 1000)", "[1000, 1100)"),
+            Some(Ordering::Less)
+        );
+        assert_eq!(sort_cmp("abc", "0-0 0 0:0:0"), None);
+    }
+}
@@ -0,0 +1,174 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    bq_common_custom_element::get_opt_num_attribute,
+    cell_value::CellValue,
+    export::{NestedMode, to_columns, to_records},
+    loaded_results, overlay_panel,
+    value_tree::{escape_html, value_html},
+};
+
+/*
+Record (vertical) view of one row of the grid of `bq-table` and `bq-query`, in the overlay panel:
+one line per leaf field with its path, type, mode and value, in the order of the schema.
+Fields and values are laid out by `to_columns(.., NestedMode::Flatten)`: every leaf field, whatever
+the grid layout shows in a single cell.
+*/
+
+thread_local! {
+    // keyed by the `be_id` of the element: the data row shown
+    static RECORD_ROWS: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+}
+
+/// Opens the record view on the data row `row` of the page.
+pub(crate) fn open(host: &Element, row: usize) {
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let loaded_results = match loaded_results::get(&element_id) {
+        Some(r) => r,
+        None => return,
+    };
+    let row_values = match loaded_results.rows.get(row) {
+        Some(r) => std::slice::from_ref(r),
+        None => return,
+    };
+
+    let fields = &loaded_results.schema.fields;
+    let columns = to_columns(fields, NestedMode::Flatten);
+    let (_, records) = to_records(fields, row_values, NestedMode::Flatten);
+    let (_, nested_records) = to_records(fields, row_values, NestedMode::Json);
+    let (record, nested_record) = match (
+        records.into_iter().next(),
+        nested_records.into_iter().next(),
+    ) {
+        (Some(r), Some(n)) => (r, n),
+        _ => return,
+    };
+
+    // the copy keeps the RECORDs nested
+    let record_json = CellValue::Struct(
+        fields
+            .iter()
+            .map(|f| f.name.clone())
+            .zip(nested_record)
+            .collect(),
+    )
+    .to_json_pretty();
+
+    let row_number = get_opt_num_attribute(host, "page_start_index").unwrap_or(0) + row + 1;
+    let title = format!("Row {}", row_number);
+    let meta = format!("{} fields", columns.len());
+    let body = match overlay_panel::open(host, &title, &meta, vec![("Copy as JSON", record_json)]) {
+        Some(b) => b,
+        None => return,
+    };
+
+    let previous_disabled = if row == 0 { " disabled" } else { "" };
+    let next_disabled = if row + 1 >= loaded_results.rows.len() {
+        " disabled"
+    } else {
+        ""
+    };
+    body.set_inner_html(&format!(
+        concat!(
+            r#"<div class="record-nav">"#,
+            r#"<button type="button" data-record-nav="-1" aria-label="Previous row" title="Previous row"{}>‹ Previous</button>"#,
+            r#"<button type="button" data-record-nav="1" aria-label="Next row" title="Next row"{}>Next ›</button>"#,
+            r#"</div>{}"#
+        ),
+        previous_disabled,
+        next_disabled,
+        record_html(&columns, &record)
+    ));
+
+    RECORD_ROWS.with(|r| r.borrow_mut().insert(element_id, row));
+}
+
+/// Click on a `data-record-nav` button: shows the previous or next row of the page.
+pub(crate) fn on_record_nav(host: &Element, button: &Element) {
+    let step = match button.get_attribute("data-record-nav") {
+        Some(s) => s,
+        None => return,
+    };
+    let element_id = host.get_attribute("be_id").unwrap_or_default();
+    let row = match RECORD_ROWS.with(|r| r.borrow().get(&element_id).copied()) {
+        Some(r) => r,
+        None => return,
+    };
+    let row = match step.as_str() {
+        "-1" => row.saturating_sub(1),
+        _ => row + 1,
+    };
+
+    open(host, row);
+
+    // the buttons were rendered again, the focus stays on the one used
+    if let Some(same_button) = host
+        .shadow_root()
+        .and_then(|s| {
+            s.query_selector(&format!("[data-record-nav='{}']:not([disabled])", step))
+                .ok()
+                .flatten()
+        })
+        .and_then(|b| b.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = same_button.focus();
+    }
+}
+
+/// One line per leaf field: path, type, mode and value.
+pub(crate) fn record_html(columns: &[(String, &TableFieldSchema)], record: &[CellValue]) -> String {
+    let lines: String = columns
+        .iter()
+        .zip(record)
+        .map(|((path, field), value)| {
+            format!(
+                r#"<tr><th scope="row">{}</th><td class="type">{}</td><td class="mode">{}</td><td>{}</td></tr>"#,
+                escape_html(path),
+                escape_html(&field.r#type),
+                field.mode.as_deref().unwrap_or("NULLABLE"),
+                value_html(value, Some(field))
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<table class="record"><thead><tr><th scope="col">Field</th><th scope="col">Type</th><th scope="col">Mode</th><th scope="col">Value</th></tr></thead><tbody>{}</tbody></table>"#,
+        lines
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::record_html;
+    use crate::bigquery::{base::TableFieldSchema, jobs::GetQueryResultsResponse};
+    use crate::custom_elements::export::{NestedMode, to_columns, to_records};
+
+    fn load_query_results(contents: &str) -> (Vec<TableFieldSchema>, Vec<serde_json::Value>) {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(contents).unwrap();
+        (
+            response.schema.unwrap().fields,
+            response.rows.unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn one_line_per_leaf_field() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let columns = to_columns(&fields, NestedMode::Flatten);
+        let (_, records) = to_records(&fields, &rows, NestedMode::Flatten);
+
+        let html = record_html(&columns, &records[0]);
+
+        assert_eq!(html.matches(r#"<th scope="row">"#).count(), columns.len());
+        assert!(html.contains(concat!(
+            r#"<tr><th scope="row">attributes.row_number</th><td class="type">STRING</td>"#,
+            r#"<td class="mode">NULLABLE</td><td><pre class="value-text">733704</pre></td></tr>"#
+        )));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{layout::field_path, value_tree::escape_html};
+
+/*
+A schema as html, for `bq-schema` and the headers of the grid: the fields in a tree (RECORDs
+expandable), each one with its type (`STRING(50)`, `NUMERIC(10, 2)`, `RANGE<DATE>`), mode,
+description, policy tags and the other attributes BigQuery keeps for it. Filtered by name, the
+RECORDs on the way to a matching field kept.
+*/
+
+/// The type of `field` with its parameters, e.g. `STRING(50)`, `NUMERIC(10, 2)`, `RANGE<DATE>`.
+pub(crate) fn type_text(field: &TableFieldSchema) -> String {
+    let parameters: Vec<&str> = [&field.max_length, &field.precision, &field.scale]
+        .into_iter()
+        .filter_map(|p| p.as_deref())
+        .collect();
+    match (&field.range_element_type, parameters.is_empty()) {
+        (Some(element), _) => format!("{}<{}>", field.r#type, element.r#type),
+        (None, false) => format!("{}({})", field.r#type, parameters.join(", ")),
+        (None, true) => field.r#type.clone(),
+    }
+}
+
+/// Tooltip of the header of the field at `path`: its path, type and mode, and description.
+pub(crate) fn field_tooltip(path: &str, field: &TableFieldSchema) -> String {
+    let mut lines = vec![
+        path.to_string(),
+        format!(
+            "{} {}",
+            type_text(field),
+            field.mode.as_deref().unwrap_or("NULLABLE")
+        ),
+    ];
+    if let Some(description) = field.description.as_deref().filter(|d| !d.is_empty()) {
+        lines.push(description.to_string());
+    }
+    lines.join("\n")
+}
+
+/// The tree of `fields` whose name contains `filter` (any case; every field when empty), and
+/// the number of fields listed, as html.
+pub(crate) fn schema_html(fields: &[TableFieldSchema], filter: &str) -> (String, usize) {
+    let filter = filter.trim().to_lowercase();
+    let mut shown = 0;
+    let items = fields_html(fields, "", &filter, &mut shown);
+    let html = match items.is_empty() {
+        true => r#"<p class="schema-empty">No field matches.</p>"#.to_string(),
+        false => format!(r#"<ul class="schema-tree">{}</ul>"#, items),
+    };
+    (html, shown)
+}
+
+/// Number of fields of `fields`, the sub-fields of the RECORDs included.
+pub(crate) fn field_count(fields: &[TableFieldSchema]) -> usize {
+    fields
+        .iter()
+        .map(|f| 1 + f.fields.as_deref().map(field_count).unwrap_or(0))
+        .sum()
+}
+
+fn matches(field: &TableFieldSchema, filter: &str) -> bool {
+    filter.is_empty() || field.name.to_lowercase().contains(filter)
+}
+
+/// Whether `field` or one of its sub-fields matches.
+fn any_matches(field: &TableFieldSchema, filter: &str) -> bool {
+    matches(field, filter)
+        || field
+            .fields
+            .as_deref()
+            .is_some_and(|sub_fields| sub_fields.iter().any(|f| any_matches(f, filter)))
+}
+
+fn fields_html(
+    fields: &[TableFieldSchema],
+    parent: &str,
+    filter: &str,
+    shown: &mut usize,
+) -> String {
+    fields
+        .iter()
+        .filter(|f| any_matches(f, filter))
+        .map(|field| {
+            *shown += 1;
+            let path = field_path(parent, &field.name);
+            let row = field_html(field, &path, filter);
+            match &field.fields {
+                Some(sub_fields) => {
+                    // all the fields of a matching RECORD, the matching ones of the others
+                    let sub_filter = if matches(field, filter) { "" } else { filter };
+                    format!(
+                        r#"<li><details open><summary>{}</summary><ul>{}</ul></details></li>"#,
+                        row,
+                        fields_html(sub_fields, &path, sub_filter, shown)
+                    )
+                }
+                None => format!("<li>{}</li>", row),
+            }
+        })
+        .collect()
+}
+
+fn field_html(field: &TableFieldSchema, path: &str, filter: &str) -> String {
+    let mut attributes = vec![];
+    if let Some(names) = field.policy_tags.as_ref().map(|p| &p.names) {
+        for name in names {
+            // the tag id, its taxonomy in the tooltip
+            let short = name.rsplit('/').next().unwrap_or(name);
+            attributes.push(format!(
+                r#"<span class="schema-attribute policy-tag" title="{}">policy tag {}</span>"#,
+                escape_html(name),
+                escape_html(short)
+            ));
+        }
+    }
+    for (label, value) in [
+        ("collation", &field.collation),
+        ("rounding", &field.rounding_mode),
+        ("default", &field.default_value_expression),
+    ] {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            attributes.push(format!(
+                r#"<span class="schema-attribute">{} <code>{}</code></span>"#,
+                label,
+                escape_html(value)
+            ));
+        }
+    }
+    let description = field
+        .description
+        .as_deref()
+        .filter(|d| !d.is_empty())
+        .map(|d| {
+            format!(
+                r#"<div class="schema-description">{}</div>"#,
+                escape_html(d)
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        concat!(
+            r#"<div class="schema-field" data-schema-path="{}"><span class="name">{}</span> "#,
+            r#"<span class="type">{}</span> <span class="mode">{}</span>{}</div>{}"#
+        ),
+        escape_html(path),
+        highlighted(&field.name, filter),
+        escape_html(&type_text(field)),
+        field.mode.as_deref().unwrap_or("NULLABLE"),
+        attributes.join(""),
+        description
+    )
+}
+
+/// `name` with the first match of `filter` in a `<mark>`.
+fn highlighted(name: &str, filter: &str) -> String {
+    let start = match filter.is_empty() {
+        true => None,
+        false => name.to_lowercase().find(filter),
+    };
+    // the lowercase of a few characters is longer, their name is left as is
+    match start {
+        Some(start) if name.to_lowercase().len() == name.len() => {
+            let end = start + filter.len();
+            format!(
+                "{}<mark>{}</mark>{}",
+                escape_html(&name[..start]),
+                escape_html(&name[start..end]),
+                escape_html(&name[end..])
+            )
+        }
+        _ => escape_html(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field_count, field_tooltip, schema_html, type_text};
+    use crate::bigquery::base::TableFieldSchema;
+
+    fn fields() -> Vec<TableFieldSchema> {
+        serde_json::from_str(
+            r#"[
+                {"name": "id", "type": "INTEGER", "mode": "REQUIRED", "description": "Row id"},
+                {"name": "price", "type": "NUMERIC", "precision": "10", "scale": "2",
+                 "roundingMode": "ROUND_HALF_EVEN"},
+                {"name": "email", "type": "STRING", "maxLength": "50", "collation": "und:ci",
+                 "policyTags": {"names": ["projects/p/locations/us/taxonomies/1/policyTags/42"]}},
+                {"name": "valid", "type": "RANGE", "rangeElementType": {"type": "DATE"}},
+                {"name": "address", "type": "RECORD", "mode": "REPEATED", "fields": [
+                    {"name": "city", "type": "STRING", "defaultValueExpression": "'Lisbon'"},
+                    {"name": "zip", "type": "STRING"}
+                ]}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn types_with_their_parameters() {
+        let fields = fields();
+        let types: Vec<String> = fields.iter().map(type_text).collect();
+        assert_eq!(
+            types,
+            vec![
+                "INTEGER",
+                "NUMERIC(10, 2)",
+                "STRING(50)",
+                "RANGE<DATE>",
+                "RECORD"
+            ]
+        );
+        assert_eq!(
+            field_tooltip("id", &fields[0]),
+            "id\nINTEGER REQUIRED\nRow id"
+        );
+        assert_eq!(
+            field_tooltip("valid", &fields[3]),
+            "valid\nRANGE<DATE> NULLABLE"
+        );
+        assert_eq!(field_count(&fields), 7);
+    }
+
+    #[test]
+    fn tree_with_every_attribute() {
+        let (html, shown) = schema_html(&fields(), "");
+        assert_eq!(shown, 7);
+        assert!(html.contains(r#"<div class="schema-description">Row id</div>"#));
+        assert!(html.contains(
+            r#"title="projects/p/locations/us/taxonomies/1/policyTags/42">policy tag 42</span>"#
+        ));
+        assert!(html.contains("collation <code>und:ci</code>"));
+        assert!(html.contains("rounding <code>ROUND_HALF_EVEN</code>"));
+        assert!(html.contains("default <code>'Lisbon'</code>"));
+        assert!(html.contains(r#"data-schema-path="address.zip""#));
+    }
+
+    #[test]
+    fn filtered_by_name() {
+        // the RECORD on the way to a match is kept, its other fields are not
+        let (html, shown) = schema_html(&fields(), "CIT");
+        assert_eq!(shown, 2);
+        assert!(html.contains(r#"<span class="name">address</span>"#));
+        assert!(html.contains("<mark>cit</mark>y"));
+        assert!(!html.contains("zip"));
+
+        // every field of a matching RECORD
+        let (_, shown) = schema_html(&fields(), "addr");
+        assert_eq!(shown, 3);
+
+        let (html, shown) = schema_html(&fields(), "nothing");
+        assert_eq!(shown, 0);
+        assert!(html.contains("No field matches."));
+    }
+}
@@ -0,0 +1,338 @@
+use std::{ops::Range, str::FromStr};
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::{
+    cell_value::{CellValue, sql_identifier},
+    export::{ExportFormat, write_header, write_record, write_records},
+};
+
+/// Position of a data cell in the page: `row` from the first row of the page,
+/// `column` from the first data column (the index column is not counted),
+/// columns as laid out by `to_grid_columns`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CellPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// What is selected in a grid, always a rectangle between `anchor` and `focus`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Selection {
+    Cells {
+        anchor: CellPosition,
+        focus: CellPosition,
+    },
+    /// Whole rows, picked on the index column.
+    Rows { anchor: usize, focus: usize },
+    /// Whole columns, picked on the headers. A RECORD header picks all its columns.
+    Columns {
+        anchor: Range<usize>,
+        focus: Range<usize>,
+    },
+}
+
+impl Selection {
+    /// Shift-click or drag: same kind of selection, same anchor, up to `other`.
+    pub(crate) fn extend_to(&self, other: &Selection) -> Selection {
+        match (self, other) {
+            (Selection::Cells { anchor, .. }, Selection::Cells { focus, .. }) => Selection::Cells {
+                anchor: *anchor,
+                focus: *focus,
+            },
+            (Selection::Rows { anchor, .. }, Selection::Rows { focus, .. }) => Selection::Rows {
+                anchor: *anchor,
+                focus: *focus,
+            },
+            (Selection::Columns { anchor, .. }, Selection::Columns { focus, .. }) => {
+                Selection::Columns {
+                    anchor: anchor.clone(),
+                    focus: focus.clone(),
+                }
+            }
+            _ => other.clone(),
+        }
+    }
+
+    /// Rows and columns covered, clamped to a page of `row_count` x `column_count`.
+    pub(crate) fn bounds(
+        &self,
+        row_count: usize,
+        column_count: usize,
+    ) -> (Range<usize>, Range<usize>) {
+        let (rows, columns) = match self {
+            Selection::Cells { anchor, focus } => (
+                anchor.row.min(focus.row)..anchor.row.max(focus.row) + 1,
+                anchor.column.min(focus.column)..anchor.column.max(focus.column) + 1,
+            ),
+            Selection::Rows { anchor, focus } => {
+                (*anchor.min(focus)..*anchor.max(focus) + 1, 0..column_count)
+            }
+            Selection::Columns { anchor, focus } => (
+                0..row_count,
+                anchor.start.min(focus.start)..anchor.end.max(focus.end),
+            ),
+        };
+
+        (
+            rows.start.min(row_count)..rows.end.min(row_count),
+            columns.start.min(column_count)..columns.end.min(column_count),
+        )
+    }
+
+    pub(crate) fn contains(
+        &self,
+        position: CellPosition,
+        row_count: usize,
+        column_count: usize,
+    ) -> bool {
+        let (rows, columns) = self.bounds(row_count, column_count);
+        rows.contains(&position.row) && columns.contains(&position.column)
+    }
+
+    /// Whether the column names are worth copying along with the values.
+    fn has_header(&self) -> bool {
+        !matches!(self, Selection::Cells { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CopyFormat {
+    Tsv,
+    Markdown,
+    Json,
+    Sql,
+}
+
+impl CopyFormat {
+    pub(crate) fn get_all() -> Vec<CopyFormat> {
+        vec![
+            CopyFormat::Tsv,
+            CopyFormat::Markdown,
+            CopyFormat::Json,
+            CopyFormat::Sql,
+        ]
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            CopyFormat::Tsv => "tsv",
+            CopyFormat::Markdown => "md",
+            CopyFormat::Json => "json",
+            CopyFormat::Sql => "sql",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            CopyFormat::Tsv => "Copy",
+            CopyFormat::Markdown => "Copy as Markdown",
+            CopyFormat::Json => "Copy as JSON",
+            CopyFormat::Sql => "Copy as SQL literals",
+        }
+    }
+}
+
+impl FromStr for CopyFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CopyFormat::get_all()
+            .into_iter()
+            .find(|f| f.name() == s)
+            .ok_or(())
+    }
+}
+
+/// Text put on the clipboard for `selection`.
+/// `columns` and `records` are the whole page laid out as the grid (flattened RECORDs).
+pub(crate) fn selection_text(
+    selection: &Selection,
+    columns: &[(String, &TableFieldSchema)],
+    records: &[Vec<CellValue>],
+    format: CopyFormat,
+) -> String {
+    let (rows, column_range) = selection.bounds(records.len(), columns.len());
+    let selected_columns = &columns[column_range.clone()];
+    let names: Vec<String> = selected_columns.iter().map(|(n, _)| n.clone()).collect();
+    let selected_records: Vec<Vec<CellValue>> = records[rows]
+        .iter()
+        .map(|r| r[column_range.start.min(r.len())..column_range.end.min(r.len())].to_vec())
+        .collect();
+    if selected_records.is_empty() || names.is_empty() {
+        return String::new();
+    }
+
+    let single_cell = !selection.has_header() && selected_records.len() == 1 && names.len() == 1;
+
+    match format {
+        CopyFormat::Tsv => {
+            if single_cell {
+                // as is, a lone value is pasted as typed
+                return selected_records[0][0].to_text().unwrap_or_default();
+            }
+            let mut output = String::new();
+            if selection.has_header() {
+                write_header(&mut output, &names, ExportFormat::Tsv);
+            }
+            for (record_index, record) in selected_records.iter().enumerate() {
+                write_record(&mut output, &names, record, ExportFormat::Tsv, record_index);
+            }
+            output
+        }
+        CopyFormat::Markdown => write_records(&names, &selected_records, ExportFormat::Markdown),
+        CopyFormat::Json if single_cell => selected_records[0][0].to_json(),
+        CopyFormat::Json => write_records(&names, &selected_records, ExportFormat::Json),
+        CopyFormat::Sql if single_cell => {
+            selected_records[0][0].to_sql_literal(selected_columns[0].1)
+        }
+        CopyFormat::Sql => {
+            let structs: Vec<String> = selected_records
+                .iter()
+                .map(|record| {
+                    let values: Vec<String> = record
+                        .iter()
+                        .zip(selected_columns)
+                        .map(|(value, (name, field))| {
+                            format!(
+                                "{} AS {}",
+                                value.to_sql_literal(field),
+                                sql_identifier(&name.replace('.', "_"))
+                            )
+                        })
+                        .collect();
+                    format!("  STRUCT({})", values.join(", "))
+                })
+                .collect();
+            format!("SELECT * FROM UNNEST([\n{}\n])", structs.join(",\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellPosition, CopyFormat, Selection, selection_text};
+    use crate::bigquery::{base::TableFieldSchema, jobs::GetQueryResultsResponse};
+    use crate::custom_elements::{
+        cell_value::CellValue,
+        export::{NestedMode, to_columns, to_records},
+    };
+
+    fn load_query_results(contents: &str) -> (Vec<TableFieldSchema>, Vec<serde_json::Value>) {
+        let response = serde_json::from_str::<GetQueryResultsResponse>(contents).unwrap();
+        (
+            response.schema.unwrap().fields,
+            response.rows.unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn bounds_are_ordered_and_clamped() {
+        let selection = Selection::Cells {
+            anchor: CellPosition { row: 4, column: 1 },
+            focus: CellPosition { row: 2, column: 3 },
+        };
+        assert_eq!(selection.bounds(10, 10), (2..5, 1..4));
+        assert_eq!(selection.bounds(3, 2), (2..3, 1..2));
+
+        let rows = Selection::Rows {
+            anchor: 3,
+            focus: 1,
+        };
+        assert_eq!(rows.bounds(10, 4), (1..4, 0..4));
+        assert!(rows.contains(CellPosition { row: 2, column: 3 }, 10, 4));
+        assert!(!rows.contains(CellPosition { row: 4, column: 0 }, 10, 4));
+
+        let columns = Selection::Columns {
+            anchor: 2..4,
+            focus: 0..1,
+        };
+        assert_eq!(columns.bounds(5, 10), (0..5, 0..4));
+    }
+
+    #[test]
+    fn extend_keeps_the_anchor() {
+        let start = Selection::Rows {
+            anchor: 5,
+            focus: 5,
+        };
+        let extended = start.extend_to(&Selection::Rows {
+            anchor: 2,
+            focus: 2,
+        });
+        assert_eq!(
+            extended,
+            Selection::Rows {
+                anchor: 5,
+                focus: 2
+            }
+        );
+
+        // a different kind starts over
+        let cells = Selection::Cells {
+            anchor: CellPosition { row: 0, column: 0 },
+            focus: CellPosition { row: 0, column: 0 },
+        };
+        assert_eq!(start.extend_to(&cells), cells);
+    }
+
+    #[test]
+    fn copies_from_the_typed_cells() {
+        let (fields, rows) =
+            load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let columns = to_columns(&fields, NestedMode::Flatten);
+        let (_, records) = to_records(&fields, &rows, NestedMode::Flatten);
+        assert_eq!(columns[0].0, "attributes.row_number");
+
+        let first_cell = Selection::Cells {
+            anchor: CellPosition { row: 0, column: 0 },
+            focus: CellPosition { row: 0, column: 0 },
+        };
+        assert_eq!(
+            selection_text(&first_cell, &columns, &records, CopyFormat::Tsv),
+            "733704"
+        );
+        assert_eq!(
+            selection_text(&first_cell, &columns, &records, CopyFormat::Sql),
+            "'733704'"
+        );
+
+        let two_cells = Selection::Cells {
+            anchor: CellPosition { row: 0, column: 0 },
+            focus: CellPosition { row: 0, column: 1 },
+        };
+        assert_eq!(
+            selection_text(&two_cells, &columns, &records, CopyFormat::Tsv),
+            "733704\tnjiaukk\n"
+        );
+        assert_eq!(
+            selection_text(&two_cells, &columns, &records, CopyFormat::Sql),
+            "SELECT * FROM UNNEST([\n  STRUCT('733704' AS attributes_row_number, 'njiaukk' AS attributes_data_type)\n])"
+        );
+        assert_eq!(
+            selection_text(&two_cells, &columns, &records, CopyFormat::Markdown),
+            "| attributes.row_number | attributes.data_type |\n| --- | --- |\n| 733704 | njiaukk |\n"
+        );
+    }
+
+    #[test]
+    fn null_cell_copies_as_empty_text() {
+        let field: TableFieldSchema =
+            serde_json::from_str(r#"{"name": "a", "type": "STRING"}"#).unwrap();
+        let columns = vec![("a".to_string(), &field)];
+        let records = vec![vec![CellValue::Null]];
+        let selection = Selection::Rows {
+            anchor: 0,
+            focus: 0,
+        };
+
+        assert_eq!(
+            selection_text(&selection, &columns, &records, CopyFormat::Tsv),
+            "a\n\n"
+        );
+        assert_eq!(
+            selection_text(&selection, &columns, &records, CopyFormat::Json),
+            "[\n  {\"a\":null}\n]\n"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use super::value_tree::escape_html;
+
+/*
+GoogleSQL as html for reading, e.g. the definition of a view in the Details tab of `bq-table`:
+the keywords, strings (quoted, triple quoted, raw and bytes), numbers, comments, quoted
+identifiers and function names in spans of their own (`sql-keyword`, `sql-string`, ...), the
+rest escaped as is. A lexer good enough to color a query, not to parse one.
+*/
+
+// separated by spaces
+const KEYWORDS: &str = concat!(
+    "ALL AND ANY ARRAY AS ASC AT BETWEEN BY CASE CAST COLLATE CREATE CROSS CUBE CURRENT ",
+    "DATE DATETIME DECLARE DEFAULT DELETE DESC DISTINCT ELSE END ESCAPE EXCEPT EXISTS ",
+    "EXTRACT FALSE FIRST FOLLOWING FOR FROM FULL GROUP GROUPING HAVING IF IGNORE IN INNER ",
+    "INSERT INTERSECT INTERVAL INTO IS JOIN LAST LEFT LIKE LIMIT MERGE NOT NULL NULLS OF ",
+    "OFFSET ON OR ORDER OUTER OVER PARTITION PIVOT PRECEDING QUALIFY RANGE RECURSIVE ",
+    "REPLACE RESPECT RIGHT ROLLUP ROW ROWS SAFE_CAST SELECT SET SETS STRUCT SYSTEM_TIME ",
+    "TABLE TABLESAMPLE THEN TIME TIMESTAMP TRUE UNBOUNDED UNION UNNEST UNPIVOT UPDATE USING ",
+    "VALUES VIEW WHEN WHERE WINDOW WITH",
+);
+
+/// `sql` as html, highlighted.
+pub(crate) fn sql_html(sql: &str) -> String {
+    let bytes = sql.as_bytes();
+    let mut html = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &sql[i..];
+        let (class, end) = match bytes[i] {
+            b'-' if rest.starts_with("--") => ("sql-comment", line_end(sql, i)),
+            b'#' => ("sql-comment", line_end(sql, i)),
+            b'/' if rest.starts_with("/*") => (
+                "sql-comment",
+                rest[2..].find("*/").map_or(sql.len(), |e| i + 2 + e + 2),
+            ),
+            b'\'' | b'"' => ("sql-string", string_end(sql, i)),
+            b'`' => (
+                "sql-identifier",
+                rest[1..].find('`').map_or(sql.len(), |e| i + 1 + e + 1),
+            ),
+            b'0'..=b'9' => ("sql-number", word_end(sql, i)),
+            b'.' if bytes.get(i + 1).is_some_and(u8::is_ascii_digit) => {
+                ("sql-number", word_end(sql, i + 1))
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let end = word_end(sql, i);
+                let word = &sql[i..end];
+                match bytes.get(end) {
+                    // r'...', b"...", rb'''...'''
+                    Some(b'\'' | b'"')
+                        if ["r", "b", "rb", "br"].contains(&word.to_lowercase().as_str()) =>
+                    {
+                        ("sql-string", string_end(sql, end))
+                    }
+                    _ if is_keyword(word) => ("sql-keyword", end),
+                    _ if sql[end..].trim_start().starts_with('(') => ("sql-function", end),
+                    _ => ("", end),
+                }
+            }
+            _ => ("", i + rest.chars().next().map_or(1, char::len_utf8)),
+        };
+        match class {
+            "" => html.push_str(&escape_html(&sql[i..end])),
+            _ => html.push_str(&format!(
+                r#"<span class="{}">{}</span>"#,
+                class,
+                escape_html(&sql[i..end])
+            )),
+        }
+        i = end;
+    }
+    html
+}
+
+fn is_keyword(word: &str) -> bool {
+    let word = word.to_uppercase();
+    KEYWORDS.split(' ').any(|k| k == word)
+}
+
+/// End of the line starting at or after `start`, its line break excluded.
+fn line_end(sql: &str, start: usize) -> usize {
+    sql[start..].find('\n').map_or(sql.len(), |e| start + e)
+}
+
+/// End of the letters, digits, `_` and `.` (of a number) starting at `start`.
+fn word_end(sql: &str, start: usize) -> usize {
+    let is_number = sql.as_bytes()[start].is_ascii_digit();
+    sql[start..]
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || (is_number && c == '.')))
+        .map_or(sql.len(), |e| start + e)
+}
+
+/// End of the string whose (possibly triple) quote starts at `start`, a backslash escaping
+/// the character after it.
+fn string_end(sql: &str, start: usize) -> usize {
+    let quote = &sql[start..start + 1];
+    let triple = quote.repeat(3);
+    let delimiter = match sql[start..].starts_with(&triple) {
+        true => triple.as_str(),
+        false => quote,
+    };
+    let mut chars = sql[start + delimiter.len()..].char_indices();
+    while let Some((offset, c)) = chars.next() {
+        let position = start + delimiter.len() + offset;
+        if c == '\\' {
+            chars.next();
+        } else if sql[position..].starts_with(delimiter) {
+            return position + delimiter.len();
+        }
+    }
+    sql.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sql_html;
+
+    #[test]
+    fn query_is_highlighted() {
+        assert_eq!(
+            sql_html("SELECT name, COUNT(*) AS n FROM `p.d.t` WHERE x > 1.5"),
+            concat!(
+                r#"<span class="sql-keyword">SELECT</span> name, "#,
+                r#"<span class="sql-function">COUNT</span>(*) <span class="sql-keyword">AS</span> n "#,
+                r#"<span class="sql-keyword">FROM</span> <span class="sql-identifier">`p.d.t`</span> "#,
+                r#"<span class="sql-keyword">WHERE</span> x &gt; <span class="sql-number">1.5</span>"#
+            )
+        );
+    }
+
+    #[test]
+    fn strings_and_comments() {
+        assert_eq!(
+            sql_html("-- all of them\nselect 'it\\'s', r\"a\\d\", '''a 'b' c''' # end"),
+            concat!(
+                r#"<span class="sql-comment">-- all of them</span>"#,
+                "\n",
+                r#"<span class="sql-keyword">select</span> "#,
+                r#"<span class="sql-string">'it\'s'</span>, "#,
+                r#"<span class="sql-string">r&quot;a\d&quot;</span>, "#,
+                r#"<span class="sql-string">'''a 'b' c'''</span> "#,
+                r#"<span class="sql-comment"># end</span>"#
+            )
+        );
+        assert_eq!(
+            sql_html("/* x < y */ 1 /* open"),
+            concat!(
+                r#"<span class="sql-comment">/* x &lt; y */</span> "#,
+                r#"<span class="sql-number">1</span> "#,
+                r#"<span class="sql-comment">/* open</span>"#
+            )
+        );
+        // unquoted names, non-ASCII text left as is
+        assert_eq!(sql_html("préço"), "préço");
+    }
+}
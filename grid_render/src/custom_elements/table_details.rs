@@ -0,0 +1,903 @@
+use crate::bigquery::{base::TableReference, tables::Table};
+
+use super::{range_interval::Interval, sql_highlight::sql_html, value_tree::escape_html};
+
+/*
+The metadata of a table as html, for the Details tab of `bq-table`: its storage (rows, logical
+and long-term bytes, streaming buffer), lifecycle (creation, last modification, expiration),
+partitioning, clustering, labels and encryption; the definition of a view, the refresh of a
+materialized view, the source of an external table, and the base table of a snapshot or a clone
+(a button opening it, `data-open-table`). Sizes in binary units (`1.5 GiB`), times in UTC (`2024-05-01 10:20:30 UTC`);
+BigQuery returns both as strings, the times in epoch milliseconds.
+*/
+
+const HOUR_MS: u64 = 3600 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+
+/// E.g. `512 B`, `1.5 KiB`, `10 GiB`.
+pub(crate) fn byte_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!(
+            "{} {}",
+            format!("{:.1}", size).trim_end_matches(".0"),
+            UNITS[unit]
+        ),
+    }
+}
+
+/// The time `epoch_ms` (milliseconds since 1970-01-01 UTC, as text) as `2024-05-01 10:20:30 UTC`.
+pub(crate) fn date_time(epoch_ms: &str) -> Option<String> {
+    let ms = epoch_ms.trim().parse::<i64>().ok()?;
+    let seconds = ms.div_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let time = seconds.rem_euclid(86400);
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    ))
+}
+
+/// Year, month and day of the `days`th day since 1970-01-01 (proleptic Gregorian calendar).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months counted from March, so that February (and its leap day) comes last
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// A length in milliseconds in the largest unit it is a whole number of: `60 days`, `12 hours`.
+fn duration(ms: u64) -> String {
+    let (count, unit) = match ms {
+        0 => (0, "millisecond"),
+        _ if ms.is_multiple_of(DAY_MS) => (ms / DAY_MS, "day"),
+        _ if ms.is_multiple_of(HOUR_MS) => (ms / HOUR_MS, "hour"),
+        _ if ms.is_multiple_of(60_000) => (ms / 60_000, "minute"),
+        _ if ms.is_multiple_of(1000) => (ms / 1000, "second"),
+        _ => (ms, "millisecond"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// `1234567` as `1,234,567`.
+fn grouped(count: u64) -> String {
+    let digits = count.to_string();
+    let mut text = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+/// Whether queries on `table` must filter on its partitioning column, set on the table or,
+/// on older tables, on its time partitioning, or on the Hive partitioning of an external table.
+pub(crate) fn requires_partition_filter(table: &Table) -> bool {
+    table.require_partition_filter == Some(true)
+        || table
+            .time_partitioning
+            .as_ref()
+            .is_some_and(|t| t.require_partition_filter == Some(true))
+        || table
+            .external_data_configuration
+            .as_ref()
+            .and_then(|e| e.hive_partitioning_options.as_ref())
+            .is_some_and(|h| h.require_partition_filter == Some(true))
+}
+
+/// Whether the rows of `table` can be listed (`tabledata.list`): those of a view or an external
+/// table are not stored in BigQuery.
+pub(crate) fn rows_listable(table: &Table) -> bool {
+    table.view.is_none() && table.external_data_configuration.is_none()
+}
+
+/// The column the partition filter of `table` applies to, `_PARTITIONTIME` when partitioned by
+/// ingestion time.
+fn partition_column(table: &Table) -> Option<&str> {
+    let hive = table
+        .external_data_configuration
+        .as_ref()
+        .and_then(|e| e.hive_partitioning_options.as_ref());
+    match (&table.time_partitioning, &table.range_partitioning, hive) {
+        (Some(time), _, _) => Some(time.field.as_deref().unwrap_or("_PARTITIONTIME")),
+        (None, Some(range), _) => Some(&range.field),
+        // the key of the paths of a Hive partitioned source, when there is only one
+        (None, None, Some(hive)) if hive.fields.len() == 1 => Some(&hive.fields[0]),
+        _ => None,
+    }
+}
+
+/// The warning shown for a table whose queries must choose partitions, as text.
+pub(crate) fn partition_filter_warning(table: &Table) -> Option<String> {
+    if !requires_partition_filter(table) {
+        return None;
+    }
+    Some(match partition_column(table) {
+        Some(column) => format!(
+            "This table requires a partition filter: a query on it, a preview by query included, must filter on {} in its WHERE clause.",
+            column
+        ),
+        None => "This table requires a partition filter: a query on it, a preview by query included, must filter on its partitioning column.".to_string(),
+    })
+}
+
+/// Why the rows of `table` are previewed by a query rather than listed.
+pub(crate) fn unlisted_reason(table: &Table) -> &'static str {
+    let external = table.external_data_configuration.as_ref();
+    match (
+        &table.view,
+        external.and_then(|e| e.source_format.as_deref()),
+    ) {
+        (Some(_), _) => {
+            "A view stores no rows to list: its preview runs it as a query, billed like any query by the bytes it reads."
+        }
+        (None, Some("GOOGLE_SHEETS")) => {
+            "An external table reads its rows from its source, a Google Sheets spreadsheet here: its preview runs a query, which fails unless the credentials of the extension are allowed to read the spreadsheet on Google Drive."
+        }
+        (None, _) if external.is_some() => {
+            "An external table stores no rows in BigQuery, they are read from its source when it is queried: its preview runs a query, billed by the bytes it reads from the source."
+        }
+        (None, _) => {
+            "The rows of this table could not be listed: its preview can run a query instead, billed like any query by the bytes it reads."
+        }
+    }
+}
+
+/// The query previewing the first `rows` of `table`, and whether it is in legacy SQL (a view
+/// defined in legacy SQL can only be queried in legacy SQL).
+pub(crate) fn preview_query(table: &Table, rows: usize) -> Option<(String, bool)> {
+    let reference = table.table_reference.as_ref()?;
+    let legacy = table.view.as_ref().and_then(|v| v.use_legacy_sql) == Some(true);
+    let from = match legacy {
+        true => format!(
+            "[{}:{}.{}]",
+            reference.project_id, reference.dataset_id, reference.table_id
+        ),
+        false => format!(
+            "`{}.{}.{}`",
+            reference.project_id, reference.dataset_id, reference.table_id
+        ),
+    };
+    Some((format!("SELECT * FROM {} LIMIT {}", from, rows), legacy))
+}
+
+/// The Details tab of `table`.
+pub(crate) fn details_html(table: &Table) -> String {
+    let mut html = String::new();
+    if let Some(warning) = partition_filter_warning(table) {
+        html.push_str(&format!(
+            r#"<p class="details-warning" role="note">{}</p>"#,
+            escape_html(&warning)
+        ));
+    }
+    let mut sections = vec![("Table", overview(table))];
+    match (&table.view, &table.materialized_view) {
+        // a logical view stores no rows, nothing is partitioned nor clustered
+        (Some(_), _) => sections.push(("View", view(table))),
+        (None, Some(_)) => sections.push(("Materialized view", materialized_view(table))),
+        (None, None) => {}
+    }
+    if let Some(snapshot) = &table.snapshot_definition {
+        sections.push((
+            "Snapshot",
+            based_on(
+                &snapshot.base_table_reference,
+                "Taken",
+                &snapshot.snapshot_time,
+            ),
+        ));
+    }
+    if let Some(clone) = &table.clone_definition {
+        sections.push((
+            "Clone",
+            based_on(&clone.base_table_reference, "Cloned", &clone.clone_time),
+        ));
+    }
+    if table.external_data_configuration.is_some() {
+        sections.push(("External data", external_data(table)));
+    }
+    // the rows of a view or an external table are not stored in BigQuery
+    let stored = rows_listable(table);
+    if stored {
+        sections.push(("Storage", storage(table)));
+    }
+    sections.push(("Lifecycle", lifecycle(table)));
+    if stored {
+        sections.push(("Partitioning", partitioning(table)));
+        sections.push(("Clustering", clustering(table)));
+    }
+    sections.push(("Labels", labels(table)));
+    sections.push(("Encryption", encryption(table)));
+
+    for (title, items) in sections {
+        let items: String = items
+            .iter()
+            .map(|(term, description)| format!("<dt>{}</dt><dd>{}</dd>", term, description))
+            .collect();
+        html.push_str(&format!(
+            r#"<section class="details-section"><h3>{}</h3><dl>{}</dl></section>"#,
+            title, items
+        ));
+    }
+    html
+}
+
+fn code(text: &str) -> String {
+    format!("<code>{}</code>", escape_html(text))
+}
+
+/// `text` as a count with its digits grouped, or as is when not a count.
+fn count_text(text: &str) -> String {
+    match text.parse::<u64>() {
+        Ok(count) => grouped(count),
+        Err(_) => escape_html(text),
+    }
+}
+
+fn size_text(text: &str) -> String {
+    match text.parse::<u64>() {
+        Ok(bytes) => byte_size(bytes),
+        Err(_) => escape_html(text),
+    }
+}
+
+fn time_text(text: &str) -> String {
+    date_time(text).unwrap_or_else(|| escape_html(text))
+}
+
+/// An RFC 3339 timestamp in UTC (`2024-05-01T10:20:30.123Z`) as `2024-05-01 10:20:30 UTC`.
+fn rfc3339_text(text: &str) -> String {
+    match text
+        .split_once('T')
+        .and_then(|(date, time)| Some((date, time.strip_suffix('Z')?)))
+    {
+        Some((date, time)) => escape_html(&format!(
+            "{} {} UTC",
+            date,
+            time.split('.').next().unwrap_or(time)
+        )),
+        None => escape_html(text),
+    }
+}
+
+/// The `max_staleness` of a table, the base64 of an INTERVAL (`0-0 0 0:30:0`), readably.
+pub(crate) fn staleness_text(text: &str) -> String {
+    let decoded = base64_decode(text).and_then(|b| String::from_utf8(b).ok());
+    let interval = decoded.as_deref().unwrap_or(text);
+    match Interval::parse(interval) {
+        Some(i) => i.text(),
+        None => escape_html(interval),
+    }
+}
+
+/// The bytes of the standard base64 `text`, its padding optional.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.trim().trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+fn sql_block(sql: &str) -> String {
+    format!(r#"<pre class="sql">{}</pre>"#, sql_html(sql))
+}
+
+fn overview(table: &Table) -> Vec<(&'static str, String)> {
+    let mut items = vec![];
+    if let Some(reference) = &table.table_reference {
+        items.push((
+            "ID",
+            code(&format!(
+                "{}.{}.{}",
+                reference.project_id, reference.dataset_id, reference.table_id
+            )),
+        ));
+    }
+    for (term, value) in [
+        ("Type", &table.r#type),
+        ("Location", &table.location),
+        ("Friendly name", &table.friendly_name),
+        ("Description", &table.description),
+    ] {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            items.push((term, escape_html(value)));
+        }
+    }
+    // a materialized view lists it with its refresh
+    if table.materialized_view.is_none()
+        && let Some(staleness) = &table.max_staleness
+    {
+        items.push(("Max staleness", staleness_text(staleness)));
+    }
+    items
+}
+
+fn view(table: &Table) -> Vec<(&'static str, String)> {
+    let view = match &table.view {
+        Some(v) => v,
+        None => return vec![],
+    };
+    vec![
+        (
+            "Language",
+            match view.use_legacy_sql {
+                Some(true) => "Legacy SQL".to_string(),
+                _ => "GoogleSQL".to_string(),
+            },
+        ),
+        ("Definition", sql_block(&view.query)),
+    ]
+}
+
+fn materialized_view(table: &Table) -> Vec<(&'static str, String)> {
+    let definition = match &table.materialized_view {
+        Some(d) => d,
+        None => return vec![],
+    };
+    let mut items = vec![];
+    // refreshed automatically unless disabled, every 30 minutes at most by default
+    items.push((
+        "Automatic refresh",
+        match definition.enable_refresh {
+            Some(false) => "Off".to_string(),
+            _ => format!(
+                "On, at most every {}",
+                duration(
+                    definition
+                        .refresh_interval_ms
+                        .as_deref()
+                        .and_then(|i| i.parse().ok())
+                        .unwrap_or(30 * 60_000)
+                )
+            ),
+        },
+    ));
+    items.push((
+        "Last refresh",
+        match &definition.last_refresh_time {
+            Some(time) => time_text(time),
+            None => "Never".to_string(),
+        },
+    ));
+    if let Some(status) = &table.materialized_view_status {
+        if let Some(watermark) = &status.refresh_watermark {
+            items.push(("Data up to", rfc3339_text(watermark)));
+        }
+        if let Some(error) = &status.last_refresh_status {
+            let message = error
+                .message
+                .as_deref()
+                .or(error.reason.as_deref())
+                .unwrap_or("failed");
+            items.push((
+                "Last refresh error",
+                format!(
+                    r#"<span class="details-error">{}</span>"#,
+                    escape_html(message)
+                ),
+            ));
+        }
+    }
+    items.push((
+        "Max staleness",
+        match &table.max_staleness {
+            Some(staleness) => staleness_text(staleness),
+            None => "None, queries read the base tables for what is not refreshed yet".to_string(),
+        },
+    ));
+    if definition.allow_non_incremental_definition == Some(true) {
+        items.push(("Non-incremental", "Allowed".to_string()));
+    }
+    if let Some(query) = &definition.query {
+        items.push(("Definition", sql_block(query)));
+    }
+    items
+}
+
+/// The base table of a snapshot or a clone, a button opening it, and when it was taken.
+fn based_on(
+    base: &TableReference,
+    time_term: &'static str,
+    time: &Option<String>,
+) -> Vec<(&'static str, String)> {
+    let mut items = vec![(
+        "Base table",
+        format!(
+            concat!(
+                r#"<button type="button" class="details-link" data-open-table "#,
+                r#"data-project-id="{}" data-dataset-id="{}" data-table-id="{}" "#,
+                r#"title="Preview the base table">{}.{}.{}</button>"#
+            ),
+            escape_html(&base.project_id),
+            escape_html(&base.dataset_id),
+            escape_html(&base.table_id),
+            escape_html(&base.project_id),
+            escape_html(&base.dataset_id),
+            escape_html(&base.table_id)
+        ),
+    )];
+    if let Some(time) = time {
+        items.push((time_term, rfc3339_text(time)));
+    }
+    items
+}
+
+fn external_data(table: &Table) -> Vec<(&'static str, String)> {
+    let external = match &table.external_data_configuration {
+        Some(e) => e,
+        None => return vec![],
+    };
+    let mut items = vec![];
+    if let Some(format) = &external.source_format {
+        items.push(("Format", escape_html(format)));
+    }
+    if !external.source_uris.is_empty() {
+        let uris: String = external
+            .source_uris
+            .iter()
+            .map(|u| format!("<li>{}</li>", code(u)))
+            .collect();
+        items.push((
+            "Source URIs",
+            format!(r#"<ul class="details-list">{}</ul>"#, uris),
+        ));
+    }
+    if let Some(connection) = &external.connection_id {
+        items.push(("Connection", code(connection)));
+    }
+    items.push((
+        "Schema",
+        match external.autodetect {
+            Some(true) => "Detected from the source".to_string(),
+            _ => "Defined on the table".to_string(),
+        },
+    ));
+    if let Some(compression) = external.compression.as_deref().filter(|c| *c != "NONE") {
+        items.push(("Compression", escape_html(compression)));
+    }
+    if external.ignore_unknown_values == Some(true) {
+        items.push(("Unknown values", "Ignored".to_string()));
+    }
+    if let Some(max) = external.max_bad_records.filter(|m| *m > 0) {
+        items.push(("Bad records allowed", grouped(max as u64)));
+    }
+    match &external.hive_partitioning_options {
+        Some(hive) => {
+            let mut parts = vec![format!(
+                "Mode {}",
+                escape_html(hive.mode.as_deref().unwrap_or("AUTO"))
+            )];
+            if !hive.fields.is_empty() {
+                let keys: Vec<String> = hive.fields.iter().map(|f| code(f)).collect();
+                parts.push(format!("keys {}", keys.join(", ")));
+            }
+            if let Some(prefix) = &hive.source_uri_prefix {
+                parts.push(format!("under {}", code(prefix)));
+            }
+            items.push(("Hive partitioning", parts.join(", ")));
+            items.push((
+                "Partition filter",
+                match requires_partition_filter(table) {
+                    true => "Required".to_string(),
+                    false => "Not required".to_string(),
+                },
+            ));
+        }
+        None => items.push(("Hive partitioning", "No".to_string())),
+    }
+    items
+}
+
+fn storage(table: &Table) -> Vec<(&'static str, String)> {
+    let mut items = vec![];
+    if let Some(rows) = &table.num_rows {
+        items.push(("Rows", count_text(rows)));
+    }
+    if let Some(bytes) = &table.num_bytes {
+        items.push(("Logical size", size_text(bytes)));
+    }
+    if let Some(bytes) = &table.num_long_term_bytes {
+        items.push(("Long-term storage", size_text(bytes)));
+    }
+    match &table.streaming_buffer {
+        Some(buffer) => {
+            let mut parts = vec![];
+            if let Some(rows) = &buffer.estimated_rows {
+                parts.push(format!("about {} rows", count_text(rows)));
+            }
+            if let Some(bytes) = &buffer.estimated_bytes {
+                parts.push(format!("about {}", size_text(bytes)));
+            }
+            if let Some(time) = &buffer.oldest_entry_time {
+                parts.push(format!("oldest entry {}", time_text(time)));
+            }
+            items.push(("Streaming buffer", parts.join(", ")));
+        }
+        None => items.push(("Streaming buffer", "Empty".to_string())),
+    }
+    items
+}
+
+fn lifecycle(table: &Table) -> Vec<(&'static str, String)> {
+    let mut items = vec![];
+    if let Some(time) = &table.creation_time {
+        items.push(("Created", time_text(time)));
+    }
+    if let Some(time) = &table.last_modified_time {
+        items.push(("Last modified", time_text(time)));
+    }
+    items.push((
+        "Expires",
+        match &table.expiration_time {
+            Some(time) => time_text(time),
+            None => "Never".to_string(),
+        },
+    ));
+    items
+}
+
+fn partitioning(table: &Table) -> Vec<(&'static str, String)> {
+    let mut items = vec![];
+    match (&table.time_partitioning, &table.range_partitioning) {
+        (Some(time), _) => {
+            items.push((
+                "Partitioned",
+                match &time.field {
+                    Some(field) => format!("By {} on {}", escape_html(&time.r#type), code(field)),
+                    None => format!(
+                        "By {} on ingestion time ({})",
+                        escape_html(&time.r#type),
+                        code("_PARTITIONTIME")
+                    ),
+                },
+            ));
+            if let Some(expiration) = time.expiration_ms.as_deref().and_then(|e| e.parse().ok()) {
+                items.push(("Partition expiration", duration(expiration)));
+            }
+        }
+        (None, Some(range)) => items.push((
+            "Partitioned",
+            format!(
+                "By range of {}, from {} to {} every {}",
+                code(&range.field),
+                escape_html(&range.range.start),
+                escape_html(&range.range.end),
+                escape_html(&range.range.interval)
+            ),
+        )),
+        (None, None) => items.push(("Partitioned", "No".to_string())),
+    }
+    if partition_column(table).is_some() {
+        items.push((
+            "Partition filter",
+            match requires_partition_filter(table) {
+                true => "Required".to_string(),
+                false => "Not required".to_string(),
+            },
+        ));
+    }
+    items
+}
+
+fn clustering(table: &Table) -> Vec<(&'static str, String)> {
+    let fields: Vec<String> = table
+        .clustering
+        .as_ref()
+        .map(|c| c.fields.iter().map(|f| code(f)).collect())
+        .unwrap_or_default();
+    vec![(
+        "Clustered by",
+        match fields.is_empty() {
+            true => "Not clustered".to_string(),
+            false => fields.join(", "),
+        },
+    )]
+}
+
+fn labels(table: &Table) -> Vec<(&'static str, String)> {
+    let labels: String = table
+        .labels
+        .iter()
+        .flatten()
+        .map(|(key, value)| {
+            let text = match value.is_empty() {
+                true => key.to_string(),
+                false => format!("{}: {}", key, value),
+            };
+            format!(
+                r#"<span class="details-label">{}</span>"#,
+                escape_html(&text)
+            )
+        })
+        .collect();
+    vec![(
+        "Labels",
+        match labels.is_empty() {
+            true => "None".to_string(),
+            false => labels,
+        },
+    )]
+}
+
+fn encryption(table: &Table) -> Vec<(&'static str, String)> {
+    let key = table
+        .encryption_configuration
+        .as_ref()
+        .and_then(|e| e.kms_key_name.as_deref());
+    vec![(
+        "Key",
+        match key {
+            Some(key) => format!("Customer-managed, {}", code(key)),
+            None => "Google-managed".to_string(),
+        },
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        byte_size, date_time, details_html, duration, grouped, partition_filter_warning,
+        preview_query, rows_listable, staleness_text, unlisted_reason,
+    };
+    use crate::bigquery::tables::Table;
+
+    #[test]
+    fn sizes_and_times_are_readable() {
+        assert_eq!(byte_size(0), "0 B");
+        assert_eq!(byte_size(512), "512 B");
+        assert_eq!(byte_size(1536), "1.5 KiB");
+        assert_eq!(byte_size(10 * 1024 * 1024 * 1024), "10 GiB");
+        assert_eq!(byte_size(3 * 1024u64.pow(5) / 2), "1.5 PiB");
+
+        assert_eq!(
+            date_time("1714558830000").as_deref(),
+            Some("2024-05-01 10:20:30 UTC")
+        );
+        assert_eq!(
+            date_time("1709251199000").as_deref(),
+            Some("2024-02-29 23:59:59 UTC")
+        );
+        assert_eq!(
+            date_time("-86400000").as_deref(),
+            Some("1969-12-31 00:00:00 UTC")
+        );
+        assert_eq!(date_time("soon"), None);
+
+        assert_eq!(duration(60 * 24 * 3600 * 1000), "60 days");
+        assert_eq!(duration(3600 * 1000), "1 hour");
+        assert_eq!(duration(1500), "1500 milliseconds");
+        assert_eq!(grouped(1234567), "1,234,567");
+        assert_eq!(grouped(123), "123");
+    }
+
+    #[test]
+    fn partitioned_and_clustered_table() {
+        let table: Table = serde_json::from_str(
+            r#"{
+                "tableReference": {"projectId": "p", "datasetId": "d", "tableId": "events"},
+                "type": "TABLE",
+                "location": "EU",
+                "numRows": "1234567",
+                "numBytes": "1610612736",
+                "numLongTermBytes": "0",
+                "creationTime": "1714558830000",
+                "timePartitioning": {"type": "DAY", "field": "event_date", "expirationMs": "5184000000"},
+                "requirePartitionFilter": true,
+                "clustering": {"fields": ["customer_id", "country"]},
+                "labels": {"team": "growth", "pii": ""},
+                "streamingBuffer": {"estimatedRows": "20", "estimatedBytes": "2048", "oldestEntryTime": "1714558830000"},
+                "encryptionConfiguration": {"kmsKeyName": "projects/p/locations/eu/keyRings/r/cryptoKeys/k"}
+            }"#,
+        )
+        .unwrap();
+        let html = details_html(&table);
+        assert!(html.starts_with(r#"<p class="details-warning" role="note">"#));
+        assert!(
+            partition_filter_warning(&table)
+                .unwrap()
+                .contains("event_date")
+        );
+        assert!(html.contains("<dt>Rows</dt><dd>1,234,567</dd>"));
+        assert!(html.contains("<dt>Logical size</dt><dd>1.5 GiB</dd>"));
+        assert!(html.contains("<dt>Long-term storage</dt><dd>0 B</dd>"));
+        assert!(html.contains("about 20 rows, about 2 KiB, oldest entry 2024-05-01 10:20:30 UTC"));
+        assert!(html.contains("<dt>Expires</dt><dd>Never</dd>"));
+        assert!(html.contains("By DAY on <code>event_date</code>"));
+        assert!(html.contains("<dt>Partition expiration</dt><dd>60 days</dd>"));
+        assert!(html.contains("<dt>Partition filter</dt><dd>Required</dd>"));
+        assert!(html.contains("<code>customer_id</code>, <code>country</code>"));
+        // labels by key
+        assert!(html.contains(
+            r#"<span class="details-label">pii</span><span class="details-label">team: growth</span>"#
+        ));
+        assert!(html.contains(
+            "Customer-managed, <code>projects/p/locations/eu/keyRings/r/cryptoKeys/k</code>"
+        ));
+    }
+
+    #[test]
+    fn plain_table() {
+        let table: Table = serde_json::from_str(
+            r#"{
+                "numRows": "3",
+                "expirationTime": "1709251199000",
+                "rangePartitioning": {"field": "customer_id", "range": {"start": "0", "end": "100", "interval": "10"}}
+            }"#,
+        )
+        .unwrap();
+        let html = details_html(&table);
+        assert_eq!(partition_filter_warning(&table), None);
+        assert!(!html.contains("details-warning"));
+        assert!(html.contains("<dt>Expires</dt><dd>2024-02-29 23:59:59 UTC</dd>"));
+        assert!(html.contains("By range of <code>customer_id</code>, from 0 to 100 every 10"));
+        assert!(html.contains("<dt>Partition filter</dt><dd>Not required</dd>"));
+        assert!(html.contains("<dt>Streaming buffer</dt><dd>Empty</dd>"));
+        assert!(html.contains("Not clustered"));
+        assert!(html.contains("<dt>Labels</dt><dd>None</dd>"));
+        assert!(html.contains("<dt>Key</dt><dd>Google-managed</dd>"));
+    }
+
+    #[test]
+    fn views_and_materialized_views() {
+        let view: Table = serde_json::from_str(
+            r#"{
+                "type": "VIEW",
+                "numRows": "0",
+                "view": {"query": "SELECT id FROM `p.d.t` WHERE x = 'a'", "useLegacySql": false}
+            }"#,
+        )
+        .unwrap();
+        let html = details_html(&view);
+        assert!(html.contains("<dt>Language</dt><dd>GoogleSQL</dd>"));
+        assert!(html.contains(
+            r#"<pre class="sql"><span class="sql-keyword">SELECT</span> id <span class="sql-keyword">FROM</span>"#
+        ));
+        assert!(!html.contains("<h3>Storage</h3>"));
+        assert!(!html.contains("<h3>Partitioning</h3>"));
+        // no reference, no query
+        assert_eq!(preview_query(&view, 50), None);
+
+        let legacy: Table = serde_json::from_str(
+            r#"{
+                "tableReference": {"projectId": "my-project", "datasetId": "d", "tableId": "v"},
+                "view": {"query": "SELECT 1", "useLegacySql": true}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            preview_query(&legacy, 50),
+            Some(("SELECT * FROM [my-project:d.v] LIMIT 50".to_string(), true))
+        );
+
+        let materialized: Table = serde_json::from_str(
+            r#"{
+                "type": "MATERIALIZED_VIEW",
+                "maxStaleness": "MC0wIDAgNDowOjA=",
+                "materializedView": {
+                    "query": "SELECT 1",
+                    "lastRefreshTime": "1714558830000",
+                    "refreshIntervalMs": "3600000"
+                },
+                "materializedViewStatus": {
+                    "refreshWatermark": "2024-05-01T10:15:00.123Z",
+                    "lastRefreshStatus": {"reason": "invalidQuery", "message": "Column x not found"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let html = details_html(&materialized);
+        assert!(html.contains("<dt>Automatic refresh</dt><dd>On, at most every 1 hour</dd>"));
+        assert!(html.contains("<dt>Last refresh</dt><dd>2024-05-01 10:20:30 UTC</dd>"));
+        assert!(html.contains("<dt>Data up to</dt><dd>2024-05-01 10:15:00 UTC</dd>"));
+        assert!(html.contains(r#"<span class="details-error">Column x not found</span>"#));
+        assert!(html.contains("<dt>Max staleness</dt><dd>04:00:00</dd>"));
+        assert!(html.contains("<h3>Storage</h3>"));
+
+        assert_eq!(staleness_text("MC0wIDIgMDowOjA="), "2 days");
+        assert_eq!(staleness_text("not an interval"), "not an interval");
+    }
+
+    #[test]
+    fn external_tables_snapshots_and_clones() {
+        let external: Table = serde_json::from_str(
+            r#"{
+                "type": "EXTERNAL",
+                "externalDataConfiguration": {
+                    "sourceUris": ["gs://bucket/sales/*"],
+                    "sourceFormat": "PARQUET",
+                    "autodetect": true,
+                    "hivePartitioningOptions": {
+                        "mode": "AUTO",
+                        "sourceUriPrefix": "gs://bucket/sales/",
+                        "requirePartitionFilter": true,
+                        "fields": ["dt"]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(!rows_listable(&external));
+        assert!(unlisted_reason(&external).starts_with("An external table stores no rows"));
+        let html = details_html(&external);
+        assert!(html.contains("<dt>Format</dt><dd>PARQUET</dd>"));
+        assert!(html.contains(r#"<li><code>gs://bucket/sales/*</code></li>"#));
+        assert!(html.contains("<dt>Schema</dt><dd>Detected from the source</dd>"));
+        assert!(html.contains(
+            "<dd>Mode AUTO, keys <code>dt</code>, under <code>gs://bucket/sales/</code></dd>"
+        ));
+        assert!(html.contains("must filter on dt in its WHERE clause"));
+        assert!(!html.contains("<h3>Storage</h3>"));
+
+        let snapshot: Table = serde_json::from_str(
+            r#"{
+                "type": "SNAPSHOT",
+                "snapshotDefinition": {
+                    "baseTableReference": {"projectId": "p", "datasetId": "d", "tableId": "t"},
+                    "snapshotTime": "2024-05-01T10:20:30Z"
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(rows_listable(&snapshot));
+        let html = details_html(&snapshot);
+        assert!(html.contains(r#"data-project-id="p" data-dataset-id="d" data-table-id="t""#));
+        assert!(html.contains(">p.d.t</button>"));
+        assert!(html.contains("<dt>Taken</dt><dd>2024-05-01 10:20:30 UTC</dd>"));
+        assert!(html.contains("<h3>Storage</h3>"));
+
+        let clone: Table = serde_json::from_str(
+            r#"{
+                "cloneDefinition": {
+                    "baseTableReference": {"projectId": "p", "datasetId": "d", "tableId": "t"},
+                    "cloneTime": "2024-05-01T10:20:30.5Z"
+                }
+            }"#,
+        )
+        .unwrap();
+        let html = details_html(&clone);
+        assert!(html.contains("<h3>Clone</h3>"));
+        assert!(html.contains("<dt>Cloned</dt><dd>2024-05-01 10:20:30 UTC</dd>"));
+    }
+}
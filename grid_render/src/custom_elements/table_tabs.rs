@@ -0,0 +1,382 @@
+use wasm_bindgen::{JsCast, prelude::Closure};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::Element;
+
+use super::{
+    base_element::BaseElement,
+    bq_common_custom_element::{get_opt_num_attribute, host_of, set_attribute},
+    bq_table_custom_element::BigqueryTableCustomElement,
+    grid_events::dispatch_load_error,
+    sql_highlight::sql_html,
+    table_details::{details_html, partition_filter_warning, preview_query, unlisted_reason},
+    value_tree::escape_html,
+};
+use crate::bigquery::{
+    jobs::{Jobs, QueryRequest},
+    tables::Table,
+};
+
+/*
+The Preview and Details tabs of `bq-table`: the preview is the controls and the grid, the details
+the metadata of the table drawn by `table_details`. The tab shown is the `tab` attribute of the
+host, which the styles follow, so it survives the renders of the pages.
+
+The rows of a view or an external table cannot be listed: its preview is then a notice with the
+query previewing it, run on demand (`jobs.query`) into a `bq-query` of its own, the
+`preview_by_query` attribute of the host hiding the empty controls and grid. The base table of a
+snapshot or a clone opens in a `bq-table` of its own, after the host.
+*/
+pub(crate) const TABLE_TABS: &str = "table_tabs";
+pub(crate) const TABLE_DETAILS: &str = "table_details";
+pub(crate) const TABLE_NOTICE: &str = "table_notice";
+const TAB_ATT: &str = "tab";
+const PREVIEW_BY_QUERY_ATT: &str = "preview_by_query";
+const PREVIEW: &str = "preview";
+const DETAILS: &str = "details";
+
+pub(crate) fn init_tabs(base_element: &BaseElement, _bq_table: &BigqueryTableCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    element.set_inner_html(concat!(
+        r#"<button type="button" role="tab" data-table-tab="preview" aria-selected="true">Preview</button>"#,
+        r#"<button type="button" role="tab" data-table-tab="details" aria-selected="false" aria-controls="table_details" tabindex="-1">Details</button>"#,
+    ));
+    let _ = element.set_attribute("role", "tablist");
+    let _ = element.set_attribute("aria-label", "Table");
+
+    let on_click = Closure::wrap(Box::new(on_tab_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref());
+    on_click.forget();
+    let on_key_down = Closure::wrap(Box::new(on_tab_key_down) as Box<dyn Fn(&web_sys::Event)>);
+    let _ =
+        element.add_event_listener_with_callback("keydown", on_key_down.as_ref().unchecked_ref());
+    on_key_down.forget();
+
+    let _ = element.set_attribute("bee", "1");
+}
+
+pub(crate) fn init_details(base_element: &BaseElement, _bq_table: &BigqueryTableCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    element.set_id(TABLE_DETAILS);
+    let _ = element.set_attribute("role", "tabpanel");
+    let _ = element.set_attribute("aria-label", "Details");
+    let _ = element.set_attribute("tabindex", "0");
+
+    let on_click = Closure::wrap(Box::new(on_open_table_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref());
+    on_click.forget();
+
+    let _ = element.set_attribute("bee", "1");
+}
+
+pub(crate) fn init_notice(base_element: &BaseElement, _bq_table: &BigqueryTableCustomElement) {
+    let element = &base_element.element();
+    if element.get_attribute("bee").is_some() {
+        return;
+    }
+    let on_click = Closure::wrap(Box::new(on_preview_click) as Box<dyn Fn(&web_sys::Event)>);
+    let _ = element.add_event_listener_with_callback("click", on_click.as_ref().unchecked_ref());
+    on_click.forget();
+    // the events of the `bq-query` of the preview are its own, not the ones of the grid of the host
+    let stop = Closure::wrap(
+        Box::new(|e: &web_sys::Event| e.stop_propagation()) as Box<dyn Fn(&web_sys::Event)>
+    );
+    for event_type in [
+        "click",
+        "dblclick",
+        "keydown",
+        "mousedown",
+        "mouseover",
+        "mouseup",
+        "mousemove",
+        "contextmenu",
+        "dragstart",
+        "dragover",
+        "drop",
+    ] {
+        let _ = element.add_event_listener_with_callback(event_type, stop.as_ref().unchecked_ref());
+    }
+    stop.forget();
+    let _ = element.set_attribute("bee", "1");
+}
+
+/// Shows, when the rows of `table` were not listed, the notice previewing it by a query instead
+/// of the controls and the grid of `host`.
+pub(crate) fn show_notice(host: &Element, table: &Table, rows_listed: bool) {
+    let notice = match host.shadow_root().and_then(|s| {
+        s.query_selector(&format!("[be_id='{}']", TABLE_NOTICE))
+            .ok()
+            .flatten()
+    }) {
+        Some(n) => n,
+        None => return,
+    };
+    let page_size = get_opt_num_attribute(host, "page_size").unwrap_or(50);
+    let (query, legacy) = match (rows_listed, preview_query(table, page_size)) {
+        (false, Some(q)) => q,
+        _ => {
+            let _ = host.remove_attribute(PREVIEW_BY_QUERY_ATT);
+            let _ = notice.remove_attribute("data-query");
+            notice.set_inner_html("");
+            return;
+        }
+    };
+    set_attribute(host, PREVIEW_BY_QUERY_ATT, "");
+    // a preview already run is kept through the renders of the same table
+    if notice.get_attribute("data-query").as_deref() == Some(query.as_str()) {
+        return;
+    }
+    let warning = partition_filter_warning(table)
+        .map(|w| {
+            format!(
+                r#"<p class="details-warning" role="note">{}</p>"#,
+                escape_html(&w)
+            )
+        })
+        .unwrap_or_default();
+    notice.set_inner_html(&format!(
+        concat!(
+            r#"<p>{}</p>{}<pre class="sql">{}</pre>"#,
+            r#"<button type="button" data-preview-query>Preview by query</button>"#,
+            r#"<span class="notice-status" role="status" aria-live="polite"></span>"#,
+            r#"<div class="query-preview"></div>"#
+        ),
+        unlisted_reason(table),
+        warning,
+        sql_html(&query)
+    ));
+    set_attribute(&notice, "data-query", &query);
+    set_attribute(
+        &notice,
+        "data-legacy-sql",
+        if legacy { "true" } else { "false" },
+    );
+    match &table.location {
+        Some(location) => set_attribute(&notice, "data-location", location),
+        None => {
+            let _ = notice.remove_attribute("data-location");
+        }
+    }
+}
+
+/// Runs the query of the notice, its results shown in a `bq-query`.
+fn on_preview_click(event: &web_sys::Event) {
+    let button = match event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("button[data-preview-query]").ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    let (notice, host) = match (button.parent_element(), host_of(&button)) {
+        (Some(n), Some(h)) => (n, h),
+        _ => return,
+    };
+    let (token, project_id, query) = match (
+        host.get_attribute("token"),
+        host.get_attribute("project_id"),
+        notice.get_attribute("data-query"),
+    ) {
+        (Some(t), Some(p), Some(q)) => (t, p, q),
+        _ => return,
+    };
+    let request = QueryRequest {
+        query,
+        use_legacy_sql: notice.get_attribute("data-legacy-sql").as_deref() == Some("true"),
+        // the rows are read by the `bq-query`
+        max_results: Some(0),
+        timeout_ms: Some(30_000),
+        location: notice.get_attribute("data-location"),
+    };
+    let page_size = get_opt_num_attribute(&host, "page_size").unwrap_or(50);
+    let find = |selector: &str| notice.query_selector(selector).ok().flatten();
+    let (status, preview) = match (find(".notice-status"), find(".query-preview")) {
+        (Some(s), Some(p)) => (s, p),
+        _ => return,
+    };
+    let _ = button.set_attribute("disabled", "");
+    status.set_text_content(Some("Running the query…"));
+
+    spawn_local(async move {
+        match Jobs::new(&token).query(&project_id, request).await {
+            Ok(response) => {
+                status.set_text_content(None);
+                let job = response.job_reference;
+                let bq_query = match web_sys::window()
+                    .and_then(|w| w.document())
+                    .and_then(|d| d.create_element("bq-query").ok())
+                {
+                    Some(q) => q,
+                    None => return,
+                };
+                set_attribute(&bq_query, "token", &token);
+                set_attribute(&bq_query, "project_id", &job.project_id);
+                set_attribute(&bq_query, "job_id", &job.job_id);
+                set_attribute(&bq_query, "location", &job.location);
+                set_attribute(&bq_query, "page_size", &page_size.to_string());
+                preview.set_inner_html("");
+                let _ = preview.append_child(&bq_query);
+            }
+            Err(e) => {
+                let _ = button.remove_attribute("disabled");
+                status.set_text_content(Some(&format!("The query failed: {}", e)));
+                dispatch_load_error(&host, "jobs.query", &e);
+            }
+        }
+    });
+}
+
+/// Opens the base table of the button in a `bq-table` after the host, or scrolls to the one
+/// already open.
+fn on_open_table_click(event: &web_sys::Event) {
+    let button = match event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("button[data-open-table]").ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    let host = match host_of(&button) {
+        Some(h) => h,
+        None => return,
+    };
+    let ids: Vec<(&str, String)> = ["project_id", "dataset_id", "table_id"]
+        .into_iter()
+        .filter_map(|name| {
+            let value = button.get_attribute(&format!("data-{}", name.replace('_', "-")))?;
+            Some((name, value))
+        })
+        .collect();
+    if ids.len() != 3 {
+        return;
+    }
+    let is_base_table = |e: &Element| {
+        e.tag_name().eq_ignore_ascii_case("bq-table")
+            && ids
+                .iter()
+                .all(|(name, value)| e.get_attribute(name).as_deref() == Some(value.as_str()))
+    };
+
+    let mut sibling = host.next_element_sibling();
+    while let Some(s) = sibling {
+        if is_base_table(&s) {
+            s.scroll_into_view();
+            return;
+        }
+        sibling = s.next_element_sibling();
+    }
+    let base_table = match web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.create_element("bq-table").ok())
+    {
+        Some(t) => t,
+        None => return,
+    };
+    if let Some(token) = host.get_attribute("token") {
+        set_attribute(&base_table, "token", &token);
+    }
+    for (name, value) in &ids {
+        set_attribute(&base_table, name, value);
+    }
+    let page_size = get_opt_num_attribute(&host, "page_size").unwrap_or(50);
+    set_attribute(&base_table, "page_size", &page_size.to_string());
+    if host.after_with_node_1(&base_table).is_ok() {
+        base_table.scroll_into_view();
+    }
+}
+
+/// Fills the Details tab of `host` with the metadata of `table`, the tab marked when the table
+/// needs a partition filter.
+pub(crate) fn show_details(host: &Element, table: &Table) {
+    let shadow_root = match host.shadow_root() {
+        Some(s) => s,
+        None => return,
+    };
+    let find = |selector: &str| shadow_root.query_selector(selector).ok().flatten();
+    if let Some(details) = find(&format!("[be_id='{}']", TABLE_DETAILS)) {
+        details.set_inner_html(&details_html(table));
+    }
+    if let Some(tab) = find("button[data-table-tab='details']") {
+        match partition_filter_warning(table) {
+            Some(warning) => {
+                let _ = tab.class_list().add_1("has-warning");
+                let _ = tab.set_attribute("title", &warning);
+            }
+            None => {
+                let _ = tab.class_list().remove_1("has-warning");
+                let _ = tab.remove_attribute("title");
+            }
+        }
+    }
+}
+
+/// Shows the tab `name` of `host`, its button focused when `focus`.
+fn select(host: &Element, name: &str, focus: bool) {
+    let _ = host.set_attribute(TAB_ATT, name);
+    let buttons = match host
+        .shadow_root()
+        .and_then(|s| s.query_selector_all("button[data-table-tab]").ok())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    for i in 0..buttons.length() {
+        let button = match buttons
+            .item(i)
+            .and_then(|b| b.dyn_into::<web_sys::HtmlElement>().ok())
+        {
+            Some(b) => b,
+            None => continue,
+        };
+        let selected = button.get_attribute("data-table-tab").as_deref() == Some(name);
+        let _ = button.set_attribute("aria-selected", if selected { "true" } else { "false" });
+        let _ = button.set_attribute("tabindex", if selected { "0" } else { "-1" });
+        if selected && focus {
+            let _ = button.focus();
+        }
+    }
+}
+
+fn on_tab_click(event: &web_sys::Event) {
+    let button = match event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("button[data-table-tab]").ok().flatten())
+    {
+        Some(b) => b,
+        None => return,
+    };
+    if let (Some(host), Some(name)) = (host_of(&button), button.get_attribute("data-table-tab")) {
+        select(&host, &name, false);
+    }
+}
+
+/// Left and right arrows (and Home, End) move between the tabs.
+fn on_tab_key_down(event: &web_sys::Event) {
+    let keyboard_event = match event.dyn_ref::<web_sys::KeyboardEvent>() {
+        Some(k) => k,
+        None => return,
+    };
+    let name = match keyboard_event.key().as_str() {
+        "ArrowLeft" | "Home" => PREVIEW,
+        "ArrowRight" | "End" => DETAILS,
+        _ => return,
+    };
+    // the arrows of the grid do not apply on the tabs
+    event.prevent_default();
+    event.stop_propagation();
+    if let Some(host) = event
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| host_of(&t))
+    {
+        select(&host, name, true);
+    }
+}
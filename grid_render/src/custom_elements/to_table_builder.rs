@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use website_component_table::{
     InnerTableBuilder, TableBuilder, TableColumn, TableColumnDefinition, TableColumnGroup,
     TableRow, TableStyle, TableValue,
@@ -5,9 +7,17 @@ use website_component_table::{
 
 use crate::bigquery::{base::{TableFieldSchema, TableSchema}, jobs::GetQueryResultsResponse};
 
+use super::{
+    aggregate::{Aggregate, footer_text},
+    cell_value::CellValue,
+    layout::{ColumnLayout, GridLayout, StructPresentation, field_path, struct_text},
+    profile::{column_values, field_at},
+    range_interval::display_text,
+};
+
 // ── VS Code theme-aware table styles ─────────────────────────────────────────────
 /// Main result table: uses VS Code editor colours so it adapts to dark/light themes.
-fn vscode_main_style() -> TableStyle {
+pub(crate) fn vscode_main_style() -> TableStyle {
     TableStyle {
         margin_px: 1,
         padding_px: 6,
@@ -86,99 +96,258 @@ fn vscode_nested_style() -> TableStyle {
 }
 
 // ── Dynamic column-width constants ────────────────────────────────────────────────
-/// Estimated pixels per character (~13 px UI font).
+/// Estimated pixels per character (~13 px UI font), when the text cannot be measured.
 const CHAR_WIDTH_PX: usize = 8;
 /// Horizontal cell padding: `padding: 5px 15px` → 30 px (15 × 2).
 const CELL_PADDING_PX: usize = 30;
 const MIN_COL_WIDTH_PX: usize = 80;
 const MAX_COL_WIDTH_PX: usize = 500;
+/// Widest column a double click on the border of its header fits to.
+const MAX_FIT_WIDTH_PX: usize = 1000;
+/// Characters measured of a value, beyond them the column is at its widest anyway.
+const MEASURED_CHARS: usize = 200;
 
 fn clamp_width(px: usize) -> usize {
-    px.max(MIN_COL_WIDTH_PX).min(MAX_COL_WIDTH_PX)
+    px.clamp(MIN_COL_WIDTH_PX, MAX_COL_WIDTH_PX)
+}
+
+/// Width in pixels of `text` in the font of the grid (`bold` for the headers), measured with
+/// the `measureText` of a canvas so CJK, emoji and proportional fonts are sized right.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn text_width_px(text: &str, bold: bool) -> usize {
+    use wasm_bindgen::JsCast;
+
+    thread_local! {
+        // the canvas context and the fonts of the cells and of the headers, read once
+        static TEXT_CONTEXT: Option<(web_sys::CanvasRenderingContext2d, String, String)> = {
+            let document = web_sys::window().and_then(|w| w.document());
+            let context = document
+                .as_ref()
+                .and_then(|d| d.create_element("canvas").ok())
+                .and_then(|c| c.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+                .and_then(|c| c.get_context("2d").ok().flatten())
+                .and_then(|c| c.dyn_into::<web_sys::CanvasRenderingContext2d>().ok());
+            let style = document
+                .and_then(|d| d.body())
+                .and_then(|b| web_sys::window()?.get_computed_style(&b).ok().flatten());
+            context.map(|context| {
+                let property = |name: &str| {
+                    style
+                        .as_ref()
+                        .and_then(|s| s.get_property_value(name).ok())
+                        .filter(|v| !v.is_empty())
+                };
+                let size = property("font-size").unwrap_or_else(|| "13px".to_string());
+                let family = property("font-family").unwrap_or_else(|| "sans-serif".to_string());
+                (
+                    context,
+                    format!("400 {} {}", size, family),
+                    format!("600 {} {}", size, family),
+                )
+            })
+        };
+    }
+
+    let text: String = text.chars().take(MEASURED_CHARS).collect();
+    TEXT_CONTEXT.with(|c| match c {
+        Some((context, font, bold_font)) => {
+            context.set_font(if bold { bold_font } else { font });
+            context
+                .measure_text(&text)
+                .map(|m| m.width().ceil() as usize)
+                .unwrap_or_else(|_| estimated_width_px(&text))
+        }
+        None => estimated_width_px(&text),
+    })
+}
+
+/// Non-wasm fallback: the width estimated from the number of characters.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn text_width_px(text: &str, _bold: bool) -> usize {
+    let text: String = text.chars().take(MEASURED_CHARS).collect();
+    estimated_width_px(&text)
+}
+
+fn estimated_width_px(text: &str) -> usize {
+    text.chars().count() * CHAR_WIDTH_PX
 }
 
-/// Approximate rendered width of a cell value in characters.
-fn value_char_len(v: &TableValue) -> usize {
+/// Rendered text of a cell value, `None` for an inner table.
+pub(crate) fn value_text(v: &TableValue) -> Option<String> {
     match v {
-        TableValue::String(s) => s.chars().count(),
-        TableValue::Boolean(_) => 5, // "false"
-        TableValue::Null => 4,        // "null"
-        TableValue::Index(n) => n.to_string().len(),
-        TableValue::Int(n) => n.to_string().len(),
-        TableValue::Float(f) => f.to_string().len(),
-        TableValue::Array(_) => 0,    // rendered as inner table; skip
+        TableValue::String(s) => Some(s.clone()),
+        TableValue::Boolean(b) => Some(b.to_string()),
+        TableValue::Null => Some("null".to_string()),
+        TableValue::Index(n) => Some(n.to_string()),
+        TableValue::Int(n) => Some(n.to_string()),
+        TableValue::Float(f) => Some(f.to_string()),
+        TableValue::Array(_) => None, // rendered as inner table, its cells are measured
+    }
+}
+
+/// Path and header text of each leaf (non-group) column of `columns`, left to right.
+fn leaf_headers(columns: &[TableColumnDefinition], parent: &str) -> Vec<(String, String)> {
+    columns
+        .iter()
+        .flat_map(|col_def| match col_def {
+            TableColumnDefinition::Column(col) => {
+                vec![(field_path(parent, &col.name), col.text.clone())]
+            }
+            TableColumnDefinition::Group(group) => {
+                leaf_headers(&group.columns, &field_path(parent, &group.name))
+            }
+        })
+        .collect()
+}
+
+/// Widens `widths` (one per leaf column) to the values of `rows`, placed from the leaf
+/// column `start`. The cells of an inner table are measured against the leaves it spans.
+fn measure_rows(rows: &[TableRow], start: usize, widths: &mut [usize]) {
+    for row in rows {
+        let mut leaf = start;
+        for cell in &row.cells {
+            match cell {
+                TableValue::Array(inner) => {
+                    measure_rows(&inner.rows, leaf, widths);
+                    leaf += inner.col_span.max(1);
+                }
+                _ => {
+                    if let (Some(width), Some(text)) = (widths.get_mut(leaf), value_text(cell)) {
+                        *width = (*width).max(text_width_px(&text, false) + CELL_PADDING_PX);
+                    }
+                    leaf += 1;
+                }
+            }
+        }
     }
 }
 
-/// Set `width_px` on every column starting from `start_col` using the wider of
-/// header vs. content.  Columns before `start_col` are left untouched.
+/// Width fitting the header and the values of each leaf column, unclamped.
+fn fitted_leaf_widths(columns: &[TableColumnDefinition], rows: &[TableRow]) -> Vec<(String, usize)> {
+    let headers = leaf_headers(columns, "");
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|(_, text)| text_width_px(text, true) + CELL_PADDING_PX)
+        .collect();
+    measure_rows(rows, 0, &mut widths);
+    headers.into_iter().map(|(path, _)| path).zip(widths).collect()
+}
+
+/// Set `width_px` on every leaf column starting from the top level column `start_col`, fitting
+/// the wider of header vs. content (the leaves of a group sized from the values under them).
+/// Columns before `start_col` are left untouched.
 fn patch_column_widths_from(
-    columns: &mut Vec<TableColumnDefinition>,
+    columns: &mut [TableColumnDefinition],
     rows: &[TableRow],
     start_col: usize,
 ) {
+    let widths: Vec<usize> = fitted_leaf_widths(columns, rows)
+        .into_iter()
+        .map(|(_, width)| clamp_width(width))
+        .collect();
+    let mut leaf = 0;
     for (col_idx, col_def) in columns.iter_mut().enumerate() {
-        if col_idx < start_col {
-            continue;
+        set_leaf_widths(col_def, &widths, &mut leaf, col_idx >= start_col);
+    }
+}
+
+fn set_leaf_widths(
+    col_def: &mut TableColumnDefinition,
+    widths: &[usize],
+    leaf: &mut usize,
+    patch: bool,
+) {
+    match col_def {
+        TableColumnDefinition::Column(col) => {
+            if patch && let Some(width) = widths.get(*leaf) {
+                col.width_px = *width;
+            }
+            *leaf += 1;
         }
+        TableColumnDefinition::Group(group) => {
+            for col_def in group.columns.iter_mut() {
+                set_leaf_widths(col_def, widths, leaf, patch);
+            }
+        }
+    }
+}
+
+/// Resize every column including the leading row-index column (position 0), then give the
+/// data columns resized by the user (by path) their `widths`.
+pub(crate) fn patch_column_widths(
+    columns: &mut [TableColumnDefinition],
+    rows: &[TableRow],
+    widths: &HashMap<String, usize>,
+) {
+    patch_column_widths_from(columns, rows, 0);
+    if !widths.is_empty() {
+        set_resized_widths(columns.get_mut(1..).unwrap_or_default(), "", widths);
+    }
+}
+
+fn set_resized_widths(
+    columns: &mut [TableColumnDefinition],
+    parent: &str,
+    widths: &HashMap<String, usize>,
+) {
+    for col_def in columns.iter_mut() {
         match col_def {
             TableColumnDefinition::Column(col) => {
-                let header_chars = col.text.chars().count();
-                let max_content_chars = rows
-                    .iter()
-                    .filter_map(|r| r.cells.get(col_idx))
-                    .map(value_char_len)
-                    .max()
-                    .unwrap_or(0);
-                let desired =
-                    header_chars.max(max_content_chars) * CHAR_WIDTH_PX + CELL_PADDING_PX;
-                col.width_px = clamp_width(desired);
+                if let Some(width) = widths.get(&field_path(parent, &col.name)) {
+                    col.width_px = *width;
+                }
             }
             TableColumnDefinition::Group(group) => {
-                // RECORD fields: the row cell is a TableValue::Array (inner table).
-                // Size sub-columns from their header text since there is no flat content.
-                patch_group_header_widths(&mut group.columns);
+                let path = field_path(parent, &group.name);
+                set_resized_widths(&mut group.columns, &path, widths);
             }
         }
     }
 }
 
-/// Resize every column including the leading row-index column (position 0).
-fn patch_column_widths(columns: &mut Vec<TableColumnDefinition>, rows: &[TableRow]) {
-    patch_column_widths_from(columns, rows, 0);
-}
-
 /// Resize every column starting from position 0 — use for tables that have no
 /// leading index column (e.g. error and DML result tables).
 pub(crate) fn patch_all_column_widths(
-    columns: &mut Vec<TableColumnDefinition>,
+    columns: &mut [TableColumnDefinition],
     rows: &[TableRow],
 ) {
     patch_column_widths_from(columns, rows, 0);
 }
 
-fn patch_group_header_widths(cols: &mut Vec<TableColumnDefinition>) {
-    for col_def in cols.iter_mut() {
-        match col_def {
-            TableColumnDefinition::Column(col) => {
-                let desired = col.text.chars().count() * CHAR_WIDTH_PX + CELL_PADDING_PX;
-                col.width_px = clamp_width(desired);
-            }
-            TableColumnDefinition::Group(g) => patch_group_header_widths(&mut g.columns),
-        }
-    }
+/// Width fitting the header and the values on the page of the leaf column at `path`, for a
+/// double click on the border of its header.
+pub(crate) fn fit_column_width(
+    fields: &[TableFieldSchema],
+    rows: &[(usize, serde_json::Value)],
+    layout: &GridLayout,
+    path: &str,
+) -> Option<usize> {
+    let table_builder = rows_to_table_builder(fields, rows, layout, &ColumnLayout::default());
+    fitted_leaf_widths(&table_builder.columns, &table_builder.rows)
+        .into_iter()
+        // the `#` index column first
+        .skip(1)
+        .find(|(leaf_path, _)| leaf_path == path)
+        .map(|(_, width)| width.clamp(MIN_COL_WIDTH_PX, MAX_FIT_WIDTH_PX))
 }
 
 impl GetQueryResultsResponse {
-    pub(crate) fn to_table_builder(&self, row_index: usize) -> TableBuilder {
-        let mut columns = get_columns(&self.schema);
-        let rows = get_rows(&self.rows, &self.schema, row_index);
-        patch_column_widths(&mut columns, &rows);
+    pub(crate) fn to_table_builder(
+        &self,
+        row_index: usize,
+        layout: &GridLayout,
+        column_layout: &ColumnLayout,
+    ) -> TableBuilder {
+        let (schema, rows) = visible(&self.schema, &self.rows, column_layout);
+        let mut columns = get_columns(&schema, layout);
+        let mut built_rows = get_rows(&rows, &schema, row_index, layout);
+        built_rows.extend(footer_row(&columns, &schema, &rows, &column_layout.aggregates));
+        patch_column_widths(&mut columns, &built_rows, &column_layout.widths);
         TableBuilder {
             style: vscode_main_style(),
             dynamic_table_render: false,
             columns,
-            rows,
+            rows: built_rows,
         }
     }
 }
@@ -188,10 +357,14 @@ impl crate::bigquery::tables::Table {
         &self,
         rows: &Option<Vec<serde_json::Value>>,
         row_index: usize,
+        layout: &GridLayout,
+        column_layout: &ColumnLayout,
     ) -> TableBuilder {
-        let mut columns = get_columns(&self.schema);
-        let built_rows = get_rows(rows, &self.schema, row_index);
-        patch_column_widths(&mut columns, &built_rows);
+        let (schema, rows) = visible(&self.schema, rows, column_layout);
+        let mut columns = get_columns(&schema, layout);
+        let mut built_rows = get_rows(&rows, &schema, row_index, layout);
+        built_rows.extend(footer_row(&columns, &schema, &rows, &column_layout.aggregates));
+        patch_column_widths(&mut columns, &built_rows, &column_layout.widths);
         TableBuilder {
             style: vscode_main_style(),
             dynamic_table_render: false,
@@ -201,7 +374,95 @@ impl crate::bigquery::tables::Table {
     }
 }
 
-fn get_columns(schema: &Option<crate::bigquery::base::TableSchema>) -> Vec<TableColumnDefinition> {
+/// Schema and rows of a page of results as `column_layout` shows them.
+fn visible(
+    schema: &Option<TableSchema>,
+    rows: &Option<Vec<serde_json::Value>>,
+    column_layout: &ColumnLayout,
+) -> (Option<TableSchema>, Option<Vec<serde_json::Value>>) {
+    match schema {
+        Some(schema) => {
+            let (fields, visible_rows) =
+                column_layout.project(&schema.fields, rows.as_deref().unwrap_or(&[]));
+            (Some(TableSchema { fields }), rows.as_ref().map(|_| visible_rows))
+        }
+        None => (None, rows.clone()),
+    }
+}
+
+/// Grid of rows of `fields` that are not a page of results (e.g. the elements of an array),
+/// each row `(index, {"f": [...]})` showing its own index. The rows are already projected, only
+/// the widths and the footer aggregates of `column_layout` are used.
+pub(crate) fn rows_to_table_builder(
+    fields: &[TableFieldSchema],
+    rows: &[(usize, serde_json::Value)],
+    layout: &GridLayout,
+    column_layout: &ColumnLayout,
+) -> TableBuilder {
+    let schema = Some(TableSchema {
+        fields: fields.to_vec(),
+    });
+    let mut columns = get_columns(&schema, layout);
+    let mut built_rows: Vec<TableRow> = rows
+        .iter()
+        .map(|(index, row)| json_value_to_row(row, fields, *index, layout))
+        .collect();
+    let rows = Some(rows.iter().map(|(_, row)| row.clone()).collect());
+    built_rows.extend(footer_row(&columns, &schema, &rows, &column_layout.aggregates));
+    patch_column_widths(&mut columns, &built_rows, &column_layout.widths);
+    TableBuilder {
+        style: vscode_main_style(),
+        dynamic_table_render: false,
+        columns,
+        rows: built_rows,
+    }
+}
+
+/// First cell of the footer row of the aggregates, under the `#` column.
+const FOOTER_MARK: &str = "Σ";
+
+/// Position of the footer row of the aggregates among the rows of `table_builder`, after the
+/// rows of the page; `None` without one.
+pub(crate) fn footer_index(table_builder: &TableBuilder) -> Option<usize> {
+    // the rows of the page start with their index, the footer with its mark
+    table_builder.rows.iter().rposition(
+        |row| matches!(row.cells.first(), Some(TableValue::String(s)) if s == FOOTER_MARK),
+    )
+}
+
+/// Footer row with the `aggregates` of the leaf columns of `columns` over `rows`, a blank cell
+/// under the others; `None` without any aggregate or row.
+fn footer_row(
+    columns: &[TableColumnDefinition],
+    schema: &Option<TableSchema>,
+    rows: &Option<Vec<serde_json::Value>>,
+    aggregates: &HashMap<String, Aggregate>,
+) -> Option<TableRow> {
+    let (fields, rows) = match (schema, rows) {
+        (Some(s), Some(r)) if !r.is_empty() && !aggregates.is_empty() => (&s.fields, r),
+        _ => return None,
+    };
+
+    let mut cells = vec![TableValue::String(FOOTER_MARK.to_string())];
+    cells.extend(
+        // the `#` index column first
+        leaf_headers(columns.get(1..).unwrap_or_default(), "")
+            .into_iter()
+            .map(|(path, _)| match (aggregates.get(&path), field_at(fields, &path)) {
+                (Some(aggregate), Some((field, _))) if aggregate.applies_to(field) => {
+                    let value = aggregate.compute(field, &column_values(fields, rows, &path));
+                    TableValue::String(footer_text(*aggregate, value))
+                }
+                _ => TableValue::String(String::new()),
+            }),
+    );
+    Some(TableRow { cells })
+}
+
+fn get_columns(
+    schema: &Option<crate::bigquery::base::TableSchema>,
+    layout: &GridLayout,
+) -> Vec<TableColumnDefinition> {
     let column_row = TableColumnDefinition::Column(TableColumn {
         text: "#".to_string(),
         name: "index".to_string(),
@@ -214,7 +475,7 @@ fn get_columns(schema: &Option<crate::bigquery::base::TableSchema>) -> Vec<Table
             schema
                 .fields
                 .iter()
-                .map(|field| field.to_table_column_definition()),
+                .map(|field| field.to_table_column_definition(&field.name, layout)),
         );
         columns
     } else {
@@ -226,6 +487,7 @@ fn get_rows(
     rows: &Option<Vec<serde_json::Value>>,
     schema: &Option<TableSchema>,
     row_index: usize,
+    layout: &GridLayout,
 ) -> Vec<TableRow> {
     let fields: &[TableFieldSchema] = schema
         .as_ref()
@@ -235,7 +497,7 @@ fn get_rows(
     if let Some(rows) = rows {
         rows.iter()
             .enumerate()
-            .map(|(index, row)| json_value_to_row(row, fields, row_index + index))
+            .map(|(index, row)| json_value_to_row(row, fields, row_index + index, layout))
             .collect()
     } else {
         vec![]
@@ -246,6 +508,7 @@ fn json_value_to_row(
     value: &serde_json::Value,
     fields: &[TableFieldSchema],
     row_index: usize,
+    layout: &GridLayout,
 ) -> TableRow {
     let f = if let Some(obj) = value.as_object() {
         if let Some(f) = obj.get("f") {
@@ -270,12 +533,17 @@ fn json_value_to_row(
             let field_type = field.map(|f| f.r#type.as_str()).unwrap_or("");
             let nested_fields = field.and_then(|f| f.fields.as_deref()).unwrap_or(&[]);
             let mode = field.and_then(|f| f.mode.as_deref()).unwrap_or("");
-            let leaf_count = if nested_fields.is_empty() {
-                1
-            } else {
-                count_leaf_fields(nested_fields)
-            };
-            cells.extend(flatten_value_to_cells(cell, field_type, nested_fields, leaf_start, mode));
+            let path = field.map(|f| f.name.as_str()).unwrap_or("");
+            let leaf_count = field.map(|f| leaf_count(f, path, layout)).unwrap_or(1);
+            cells.extend(flatten_value_to_cells(
+                cell,
+                field_type,
+                nested_fields,
+                leaf_start,
+                mode,
+                path,
+                layout,
+            ));
             leaf_start += leaf_count;
         }
         cells
@@ -289,7 +557,7 @@ fn json_value_to_row(
 /// Format a BigQuery TIMESTAMP (seconds since Unix epoch, possibly in scientific notation)
 /// as an ISO 8601 string using the JS Date API.
 #[cfg(target_arch = "wasm32")]
-fn format_timestamp(s: &str) -> String {
+pub(crate) fn format_timestamp(s: &str) -> String {
     if let Ok(seconds) = s.parse::<f64>() {
         let ms = seconds * 1000.0;
         let date = js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(ms));
@@ -302,28 +570,55 @@ fn format_timestamp(s: &str) -> String {
 
 /// Non-wasm fallback: return the raw timestamp string as-is.
 #[cfg(not(target_arch = "wasm32"))]
-fn format_timestamp(s: &str) -> String {
+pub(crate) fn format_timestamp(s: &str) -> String {
     s.to_string()
 }
 
+/// Text shown for the string value `s` of a scalar of type `field_type`: TIMESTAMPs in ISO 8601,
+/// RANGEs and INTERVALs readably (`range_interval`), the others as is.
+fn scalar_text(field_type: &str, s: &str) -> String {
+    match field_type {
+        "TIMESTAMP" => format_timestamp(s),
+        _ => display_text(field_type, s).unwrap_or_else(|| s.to_string()),
+    }
+}
+
 /// Flatten a cell value into one or more `TableValue` cells.
 ///
 /// Non-repeated STRUCT fields (mode != "REPEATED") are recursively expanded so
 /// their sub-field values become individual cells aligned with the leaf columns
 /// produced by the matching `TableColumnDefinition::Group`.
 ///
-/// REPEATED fields (arrays) and scalar fields produce a single cell as before.
+/// REPEATED fields (arrays) and scalar fields produce a single cell as before,
+/// so do the STRUCTs that `layout` shows as JSON or `key: value` at `path`.
 fn flatten_value_to_cells(
     value: &serde_json::Value,
     field_type: &str,
     nested_fields: &[TableFieldSchema],
     start_col_index: usize,
     mode: &str,
+    path: &str,
+    layout: &GridLayout,
 ) -> Vec<TableValue> {
     let v = value.pointer("/v").unwrap_or_default();
 
     // Non-repeated STRUCT with sub-fields: flatten into individual cells
     if mode != "REPEATED" && !nested_fields.is_empty() {
+        let presentation = layout.struct_presentation(path);
+        if presentation != StructPresentation::Columns {
+            // Collapsed STRUCT: a single text cell
+            if v.is_null() {
+                return vec![TableValue::Null];
+            }
+            let record = CellValue::Struct(
+                nested_fields
+                    .iter()
+                    .map(|f| f.name.clone())
+                    .zip(CellValue::row_from_bq(v, nested_fields))
+                    .collect(),
+            );
+            return vec![TableValue::String(struct_text(&record, presentation))];
+        }
         if let serde_json::Value::Object(_) = v {
             if let Some(f_array) = v.pointer("/f").and_then(|f| f.as_array()) {
                 let mut cells = Vec::new();
@@ -333,13 +628,18 @@ fn flatten_value_to_cells(
                     let nf_type = nf.map(|f| f.r#type.as_str()).unwrap_or("");
                     let nf_nested = nf.and_then(|f| f.fields.as_deref()).unwrap_or(&[]);
                     let nf_mode = nf.and_then(|f| f.mode.as_deref()).unwrap_or("");
-                    let leaf_count = if nf_nested.is_empty() {
-                        1
-                    } else {
-                        count_leaf_fields(nf_nested)
-                    };
+                    let nf_path = field_path(path, nf.map(|f| f.name.as_str()).unwrap_or(""));
+                    let leaf_count = nf.map(|f| leaf_count(f, &nf_path, layout)).unwrap_or(1);
                     // Recursively flatten nested structs
-                    cells.extend(flatten_value_to_cells(cell, nf_type, nf_nested, sub_start, nf_mode));
+                    cells.extend(flatten_value_to_cells(
+                        cell,
+                        nf_type,
+                        nf_nested,
+                        sub_start,
+                        nf_mode,
+                        &nf_path,
+                        layout,
+                    ));
                     sub_start += leaf_count;
                 }
                 return cells;
@@ -347,13 +647,20 @@ fn flatten_value_to_cells(
         }
         // NULL struct: emit Null for each leaf column
         if v.is_null() {
-            let leaf_count = count_leaf_fields(nested_fields);
+            let leaf_count = count_leaf_fields(nested_fields, path, layout);
             return vec![TableValue::Null; leaf_count];
         }
     }
 
     // Default: single cell via the normal conversion
-    vec![json_value_to_table_value(value, field_type, nested_fields, start_col_index)]
+    vec![json_value_to_table_value(
+        value,
+        field_type,
+        nested_fields,
+        start_col_index,
+        path,
+        layout,
+    )]
 }
 
 fn json_value_to_table_value(
@@ -361,6 +668,8 @@ fn json_value_to_table_value(
     field_type: &str,
     nested_fields: &[TableFieldSchema],
     start_col_index: usize,
+    path: &str,
+    layout: &GridLayout,
 ) -> TableValue {
     let v = value.pointer("/v").unwrap_or_default();
 
@@ -374,17 +683,11 @@ fn json_value_to_table_value(
                 TableValue::String(n.to_string())
             }
         }
-        serde_json::Value::String(s) => {
-            if field_type == "TIMESTAMP" {
-                TableValue::String(format_timestamp(s))
-            } else {
-                TableValue::String(s.clone())
-            }
-        }
+        serde_json::Value::String(s) => TableValue::String(scalar_text(field_type, s)),
         serde_json::Value::Array(arr) => {
             // col_span must equal the number of leaf columns so the <td colspan="N">
             // spans exactly the N sub-column headers produced by the Group definition.
-            let col_span = count_leaf_fields(nested_fields).max(1);
+            let col_span = count_leaf_fields(nested_fields, path, layout).max(1);
 
             let rows = if nested_fields.is_empty() {
                 // Simple repeated field: ARRAY<FLOAT64>, ARRAY<STRING>, etc.
@@ -393,13 +696,7 @@ fn json_value_to_table_value(
                     .filter_map(|item| {
                         let v = item.pointer("/v")?;
                         let cell = match v {
-                            serde_json::Value::String(s) => {
-                                if field_type == "TIMESTAMP" {
-                                    TableValue::String(format_timestamp(s))
-                                } else {
-                                    TableValue::String(s.clone())
-                                }
-                            }
+                            serde_json::Value::String(s) => TableValue::String(scalar_text(field_type, s)),
                             serde_json::Value::Number(n) => {
                                 if field_type == "TIMESTAMP" {
                                     TableValue::String(format_timestamp(&n.to_string()))
@@ -428,8 +725,11 @@ fn json_value_to_table_value(
                             let nf_type = nf.map(|f| f.r#type.as_str()).unwrap_or("");
                             let nf_nested = nf.and_then(|f| f.fields.as_deref()).unwrap_or(&[]);
                             let nf_mode = nf.and_then(|f| f.mode.as_deref()).unwrap_or("");
-                            let leaf_count = if nf_nested.is_empty() { 1 } else { count_leaf_fields(nf_nested) };
-                            cells.extend(flatten_value_to_cells(cell, nf_type, nf_nested, sub_start, nf_mode));
+                            let nf_path = field_path(path, nf.map(|f| f.name.as_str()).unwrap_or(""));
+                            let leaf_count = nf.map(|f| leaf_count(f, &nf_path, layout)).unwrap_or(1);
+                            cells.extend(flatten_value_to_cells(
+                                cell, nf_type, nf_nested, sub_start, nf_mode, &nf_path, layout,
+                            ));
                             sub_start += leaf_count;
                         }
                         TableRow { cells }
@@ -455,8 +755,13 @@ fn json_value_to_table_value(
 }
 
 impl TableFieldSchema {
-    pub(crate) fn to_table_column_definition(&self) -> TableColumnDefinition {
-        if let Some(fields) = &self.fields {
+    /// Header of the field at `path`, a RECORD shown in a single cell by `layout` is a plain column.
+    pub(crate) fn to_table_column_definition(
+        &self,
+        path: &str,
+        layout: &GridLayout,
+    ) -> TableColumnDefinition {
+        if let Some(fields) = self.fields.as_ref().filter(|_| !layout.is_collapsed(path, self)) {
             // RECORD (STRUCT) field: emit a Group so sub-field names appear as
             // sub-column headers. The matching row cell is a TableValue::Array
             // with col_span = number of leaf fields, so <td colspan="N"> aligns
@@ -466,7 +771,9 @@ impl TableFieldSchema {
                 name: self.name.clone(),
                 columns: fields
                     .iter()
-                    .map(|field| field.to_table_column_definition())
+                    .map(|field| {
+                        field.to_table_column_definition(&field_path(path, &field.name), layout)
+                    })
                     .collect(),
             })
         } else {
@@ -479,22 +786,32 @@ impl TableFieldSchema {
     }
 }
 
-/// Count the total number of leaf (non-group) columns a field produces,
-/// recursing into nested RECORD sub-fields.
-fn count_leaf_fields(fields: &[TableFieldSchema]) -> usize {
+/// Count the total number of leaf (non-group) columns the sub-fields of the RECORD at
+/// `parent_path` produce, recursing into the nested RECORD sub-fields `layout` spreads.
+fn count_leaf_fields(fields: &[TableFieldSchema], parent_path: &str, layout: &GridLayout) -> usize {
     fields
         .iter()
-        .map(|f| match &f.fields {
-            Some(sub) => count_leaf_fields(sub),
-            None => 1,
-        })
+        .map(|f| leaf_count(f, &field_path(parent_path, &f.name), layout))
         .sum()
 }
 
+/// Leaf columns of the field at `path`: one, unless it is a RECORD spread over its sub-fields.
+fn leaf_count(field: &TableFieldSchema, path: &str, layout: &GridLayout) -> usize {
+    match &field.fields {
+        Some(sub) if !sub.is_empty() && !layout.is_collapsed(path, field) => {
+            count_leaf_fields(sub, path, layout)
+        }
+        _ => 1,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::{fit_column_width, rows_to_table_builder};
     use crate::bigquery::base::TableFieldSchema;
     use crate::bigquery::jobs::GetQueryResultsResponse;
+    use crate::custom_elements::aggregate::Aggregate;
+    use crate::custom_elements::layout::{ColumnLayout, GridLayout, StructPresentation};
     use serde_json::Value;
     use website_component_table::{TableColumnDefinition, TableValue};
 
@@ -529,7 +846,7 @@ mod tests {
             }
             // NULL struct: each leaf should be Null
             if expected_value.is_null() {
-                let leaf_count = super::count_leaf_fields(nested);
+                let leaf_count = super::count_leaf_fields(nested, "", &GridLayout::default());
                 for j in 0..leaf_count {
                     assert!(
                         matches!(actual_cells[offset + j], TableValue::Null),
@@ -558,6 +875,8 @@ mod tests {
                 _ => panic!("expected numeric string at offset {}", offset),
             },
             Value::String(expected) => match actual {
+                // RANGEs and INTERVALs are shown readably, checked by `ranges_and_intervals_are_readable`
+                TableValue::String(_) if matches!(field.r#type.as_str(), "RANGE" | "INTERVAL") => {}
                 TableValue::String(s) => assert_eq!(s, expected),
                 _ => panic!("expected string at offset {}", offset),
             },
@@ -621,7 +940,7 @@ mod tests {
         response: &GetQueryResultsResponse,
         row_index: usize,
     ) {
-        let table_builder = response.to_table_builder(row_index);
+        let table_builder = response.to_table_builder(row_index, &GridLayout::default(), &ColumnLayout::default());
 
         if let Some(schema) = &response.schema {
             // +1 for the leading "#" index column
@@ -731,6 +1050,46 @@ mod tests {
         assert_table_builder_matches_response(&response, 1);
     }
 
+    #[test]
+    fn ranges_and_intervals_are_readable() {
+        let response = load_query_results(include_str!("test_resources/all_types_test.json"));
+        let table_builder = response.to_table_builder(1, &GridLayout::default(), &ColumnLayout::default());
+        let fields = &response.schema.as_ref().unwrap().fields;
+        let column = |name: &str| 1 + fields.iter().position(|f| f.name == name).unwrap();
+        let texts = |name: &str| -> Vec<Option<String>> {
+            table_builder
+                .rows
+                .iter()
+                .map(|r| match &r.cells[column(name)] {
+                    TableValue::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            fields[column("r_date") - 1].range_element_type.as_ref().map(|t| t.r#type.as_str()),
+            Some("DATE")
+        );
+        assert_eq!(
+            texts("i2"),
+            vec![
+                Some("2 days 03:00:00".to_string()),
+                Some("-3 months -01:30:00.5".to_string()),
+                Some("00:00:00".to_string()),
+            ]
+        );
+        assert_eq!(
+            texts("r_date"),
+            vec![
+                Some("[2024-01-01, 2024-02-01)".to_string()),
+                Some("(−∞, 2024-02-01)".to_string()),
+                None,
+            ]
+        );
+        assert_eq!(texts("r_datetime")[2].as_deref(), Some("(−∞, ∞)"));
+    }
+
     #[test]
     fn place_bq_table_rows_test_complex_object_array() {
         let response = load_query_results(include_str!(
@@ -771,4 +1130,180 @@ mod tests {
             load_query_results(include_str!("test_resources/complex_nested_test.json"));
         assert_table_builder_matches_response(&response, 1);
     }
+
+    #[test]
+    fn place_bq_table_rows_test_struct_as_single_cell() {
+        let response = load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let fields = &response.schema.as_ref().unwrap().fields;
+        let mut layout = GridLayout::default();
+        layout.set_struct_presentation("attributes", StructPresentation::KeyValue);
+        let table_builder = response.to_table_builder(1, &layout, &ColumnLayout::default());
+
+        let attributes = fields.iter().position(|f| f.name == "attributes").unwrap();
+        assert!(matches!(
+            &table_builder.columns[attributes + 1],
+            TableColumnDefinition::Column(column) if column.text == "attributes"
+        ));
+        for row in &table_builder.rows {
+            assert_eq!(row.cells.len(), fields.len() + 1);
+        }
+        assert!(matches!(
+            &table_builder.rows[0].cells[attributes + 1],
+            TableValue::String(s) if s.contains("row_number: 733704")
+        ));
+    }
+
+    fn leaf_columns(columns: &[TableColumnDefinition]) -> usize {
+        columns
+            .iter()
+            .map(|c| match c {
+                TableColumnDefinition::Column(_) => 1,
+                TableColumnDefinition::Group(group) => leaf_columns(&group.columns),
+            })
+            .sum()
+    }
+
+    #[test]
+    fn place_bq_table_rows_test_nested_struct_as_single_cell() {
+        let response =
+            load_query_results(include_str!("test_resources/complex_nested_test.json"));
+        let mut layout = GridLayout::default();
+        layout.set_struct_presentation("nested_struct_3_levels.headquarters", StructPresentation::Json);
+        let default_builder = response.to_table_builder(1, &GridLayout::default(), &ColumnLayout::default());
+        let table_builder = response.to_table_builder(1, &layout, &ColumnLayout::default());
+
+        // office_name, latitude, longitude and country_code become one cell
+        assert_eq!(
+            leaf_columns(&table_builder.columns),
+            leaf_columns(&default_builder.columns) - 3
+        );
+        // an ARRAY<STRUCT> cell spans the leaf columns of its group
+        for row in &table_builder.rows {
+            let width: usize = row
+                .cells
+                .iter()
+                .map(|cell| match cell {
+                    TableValue::Array(inner) => inner.col_span,
+                    _ => 1,
+                })
+                .sum();
+            assert_eq!(width, leaf_columns(&table_builder.columns));
+        }
+    }
+
+    #[test]
+    fn place_bq_table_rows_test_hidden_and_moved_columns() {
+        let response = load_query_results(include_str!("test_resources/struct_json_test.json"));
+        let mut column_layout = ColumnLayout::default();
+        column_layout.set_hidden("attributes.data_type", true);
+        column_layout.order = vec!["data".to_string()];
+        let table_builder = response.to_table_builder(1, &GridLayout::default(), &column_layout);
+
+        assert!(matches!(
+            &table_builder.columns[1],
+            TableColumnDefinition::Column(column) if column.text == "data"
+        ));
+        assert!(matches!(
+            &table_builder.columns[2],
+            TableColumnDefinition::Group(group) if group.columns.len() == 1
+        ));
+        for row in &table_builder.rows {
+            assert_eq!(row.cells.len(), 3);
+        }
+        assert!(matches!(
+            &table_builder.rows[0].cells[2],
+            TableValue::String(s) if s == "733704"
+        ));
+    }
+
+    fn column_width(columns: &[TableColumnDefinition], path: &[&str]) -> usize {
+        let column = columns.iter().find(|c| match c {
+            TableColumnDefinition::Column(column) => column.name == path[0],
+            TableColumnDefinition::Group(group) => group.name == path[0],
+        });
+        match column {
+            Some(TableColumnDefinition::Column(column)) => column.width_px,
+            Some(TableColumnDefinition::Group(group)) => column_width(&group.columns, &path[1..]),
+            None => panic!("no column {:?}", path),
+        }
+    }
+
+    #[test]
+    fn group_leaves_sized_from_their_values() {
+        let fields: Vec<TableFieldSchema> = serde_json::from_str(
+            r#"[{"name": "s", "type": "RECORD", "fields": [
+                {"name": "id", "type": "STRING"}, {"name": "label", "type": "STRING"}
+            ]}, {"name": "r", "type": "RECORD", "mode": "REPEATED", "fields": [{"name": "v", "type": "STRING"}]}]"#,
+        )
+        .unwrap();
+        let long = "x".repeat(40);
+        let very_long = "y".repeat(200);
+        let row: Value = serde_json::from_str(&format!(
+            r#"{{"f": [{{"v": {{"f": [{{"v": "1"}}, {{"v": "{}"}}]}}}}, {{"v": [{{"v": {{"f": [{{"v": "{}"}}]}}}}]}}]}}"#,
+            long, very_long
+        ))
+        .unwrap();
+        let rows = vec![(1, row)];
+
+        let table_builder = rows_to_table_builder(&fields, &rows, &GridLayout::default(), &ColumnLayout::default());
+        // the estimate of the character count outside of a browser: 8 px each, 30 px of padding
+        assert_eq!(column_width(&table_builder.columns, &["s", "id"]), 80);
+        assert_eq!(column_width(&table_builder.columns, &["s", "label"]), 40 * 8 + 30);
+        // the values of an ARRAY of RECORDs size its leaves too, up to the widest column
+        assert_eq!(column_width(&table_builder.columns, &["r", "v"]), 500);
+
+        let column_layout = ColumnLayout {
+            widths: [("s.label".to_string(), 120)].into(),
+            ..ColumnLayout::default()
+        };
+        let resized = rows_to_table_builder(&fields, &rows, &GridLayout::default(), &column_layout);
+        assert_eq!(column_width(&resized.columns, &["s", "label"]), 120);
+        assert_eq!(column_width(&resized.columns, &["s", "id"]), 80);
+
+        assert_eq!(fit_column_width(&fields, &rows, &GridLayout::default(), "s.label"), Some(350));
+        assert_eq!(fit_column_width(&fields, &rows, &GridLayout::default(), "r.v"), Some(1000));
+        assert_eq!(fit_column_width(&fields, &rows, &GridLayout::default(), "s"), None);
+    }
+
+    #[test]
+    fn footer_row_of_the_aggregates() {
+        let fields: Vec<TableFieldSchema> = serde_json::from_str(
+            r#"[{"name": "n", "type": "NUMERIC"}, {"name": "s", "type": "RECORD", "fields": [
+                {"name": "name", "type": "STRING"}, {"name": "i", "type": "INTEGER"}
+            ]}]"#,
+        )
+        .unwrap();
+        let rows: Vec<(usize, Value)> = [("0.1", "b", "1"), ("0.2", "a", "2")]
+            .iter()
+            .enumerate()
+            .map(|(index, (n, name, i))| {
+                let row = serde_json::json!({"f": [{"v": n}, {"v": {"f": [{"v": name}, {"v": i}]}}]});
+                (index + 1, row)
+            })
+            .collect();
+
+        let table_builder = rows_to_table_builder(&fields, &rows, &GridLayout::default(), &ColumnLayout::default());
+        assert_eq!(table_builder.rows.len(), 2);
+
+        let column_layout = ColumnLayout {
+            aggregates: [
+                ("n".to_string(), Aggregate::Sum),
+                ("s.name".to_string(), Aggregate::Min),
+                ("s.i".to_string(), Aggregate::Avg),
+            ]
+            .into(),
+            ..ColumnLayout::default()
+        };
+        let table_builder = rows_to_table_builder(&fields, &rows, &GridLayout::default(), &column_layout);
+        assert_eq!(table_builder.rows.len(), 3);
+        let footer: Vec<String> = table_builder.rows[2]
+            .cells
+            .iter()
+            .map(|c| match c {
+                TableValue::String(s) => s.clone(),
+                other => panic!("footer cell {:?}", other),
+            })
+            .collect();
+        assert_eq!(footer, vec!["Σ", "sum: 0.3", "min: a", "avg: 1.5"]);
+    }
 }
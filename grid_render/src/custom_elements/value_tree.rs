@@ -0,0 +1,263 @@
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+use crate::bigquery::base::TableFieldSchema;
+
+use super::cell_value::CellValue;
+
+/*
+Collapsible tree of a value (STRUCT, ARRAY, JSON) as html, for the panels shown over the grid.
+Long arrays are cut in pages; the page buttons are handled by `on_array_nav`.
+*/
+const ARRAY_PAGE_SIZE: usize = 100;
+
+/// The whole value with wrapping: a `<pre>` for scalars, a tree for STRUCT, ARRAY and JSON.
+/// `field` gives the types (JSON columns are parsed), `None` inside parsed JSON.
+pub(crate) fn value_html(value: &CellValue, field: Option<&TableFieldSchema>) -> String {
+    match json_column_value(value, field) {
+        Some(parsed) => node_html(None, &parsed, None),
+        None => match value {
+            CellValue::Array(_) | CellValue::Struct(_) => node_html(None, value, field),
+            CellValue::Null => r#"<pre class="value-text null">NULL</pre>"#.to_string(),
+            _ => format!(
+                r#"<pre class="value-text">{}</pre>"#,
+                escape_html(&value.to_text().unwrap_or_default())
+            ),
+        },
+    }
+}
+
+/// Short description of the value: its type and size.
+pub(crate) fn value_summary(value: &CellValue, field: Option<&TableFieldSchema>) -> String {
+    let type_name = field
+        .map(|f| match f.mode.as_deref() {
+            Some("REPEATED") => format!("ARRAY<{}>", f.r#type),
+            _ => f.r#type.clone(),
+        })
+        .unwrap_or_default();
+    let size = match value {
+        CellValue::Null => "NULL".to_string(),
+        CellValue::Array(items) => count(items.len(), "element"),
+        CellValue::Struct(entries) => count(entries.len(), "field"),
+        CellValue::Text(s) => count(s.chars().count(), "character"),
+        _ => String::new(),
+    };
+
+    [type_name, size]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<String>>()
+        .join(" · ")
+}
+
+/// Indented JSON of the value, the text of a JSON column is parsed first.
+pub(crate) fn pretty_text(value: &CellValue, field: Option<&TableFieldSchema>) -> String {
+    match json_column_value(value, field) {
+        Some(parsed) => parsed.to_json_document_pretty(),
+        None => value.to_json_pretty(),
+    }
+}
+
+/// A value of a JSON column, parsed.
+fn json_column_value(value: &CellValue, field: Option<&TableFieldSchema>) -> Option<CellValue> {
+    match (value, field) {
+        (CellValue::Text(text), Some(f)) if f.r#type == "JSON" => CellValue::from_json_text(text),
+        _ => None,
+    }
+}
+
+fn node_html(name: Option<&str>, value: &CellValue, field: Option<&TableFieldSchema>) -> String {
+    let key = name
+        .map(|n| format!(r#"<span class="key">{}</span> "#, escape_html(n)))
+        .unwrap_or_default();
+
+    if let Some(parsed) = json_column_value(value, field) {
+        return node_html(name, &parsed, None);
+    }
+
+    match value {
+        CellValue::Struct(entries) => {
+            let sub_fields = field.and_then(|f| f.fields.as_deref()).unwrap_or(&[]);
+            let children: String = entries
+                .iter()
+                .enumerate()
+                .map(|(i, (entry_name, entry))| {
+                    node_html(Some(entry_name), entry, sub_fields.get(i))
+                })
+                .collect();
+            format!(
+                r#"<details open><summary>{}<span class="kind">{{{}}}</span></summary><div class="children">{}</div></details>"#,
+                key,
+                entries.len(),
+                children
+            )
+        }
+        CellValue::Array(items) => {
+            // the items of a REPEATED field have the same field, but are not repeated
+            let item_field = field.map(|f| TableFieldSchema {
+                mode: None,
+                ..f.clone()
+            });
+            let pages: Vec<String> = items
+                .chunks(ARRAY_PAGE_SIZE)
+                .enumerate()
+                .map(|(page, chunk)| {
+                    let children: String = chunk
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| {
+                            let index = (page * ARRAY_PAGE_SIZE + i).to_string();
+                            node_html(Some(&index), item, item_field.as_ref())
+                        })
+                        .collect();
+                    let hidden = if page == 0 { "" } else { " hidden" };
+                    format!(
+                        r#"<div class="array-page" data-page="{}"{}>{}</div>"#,
+                        page, hidden, children
+                    )
+                })
+                .collect();
+            let pager = if pages.len() > 1 {
+                format!(
+                    r#"<div class="array-pager"><button type="button" data-array-nav="-1" aria-label="Previous elements">‹</button><span class="array-range">{}</span><button type="button" data-array-nav="1" aria-label="Next elements">›</button></div>"#,
+                    page_range(0, items.len())
+                )
+            } else {
+                String::new()
+            };
+            format!(
+                r#"<details open data-length="{}"><summary>{}<span class="kind">[{}]</span></summary>{}<div class="children">{}</div></details>"#,
+                items.len(),
+                key,
+                items.len(),
+                pager,
+                pages.join("")
+            )
+        }
+        _ => {
+            let class = match value {
+                CellValue::Null => "null",
+                CellValue::Bool(_) => "boolean",
+                CellValue::Int(_) | CellValue::Float(_) | CellValue::Numeric(_) => "number",
+                _ => "string",
+            };
+            let text = match value {
+                CellValue::Null => "NULL".to_string(),
+                CellValue::Text(s) if field.is_none() => s.clone(),
+                _ => value.to_text().unwrap_or_default(),
+            };
+            format!(
+                r#"<div class="leaf">{}<span class="value {}">{}</span></div>"#,
+                key,
+                class,
+                escape_html(&text)
+            )
+        }
+    }
+}
+
+fn page_range(page: usize, length: usize) -> String {
+    let start = page * ARRAY_PAGE_SIZE;
+    format!(
+        "{}–{} of {}",
+        start + 1,
+        (start + ARRAY_PAGE_SIZE).min(length),
+        length
+    )
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Click on a `data-array-nav` button: shows the previous or next page of its array.
+pub(crate) fn on_array_nav(button: &Element) {
+    let step = match button
+        .get_attribute("data-array-nav")
+        .and_then(|s| s.parse::<isize>().ok())
+    {
+        Some(s) => s,
+        None => return,
+    };
+    let array = match button.closest("details[data-length]").ok().flatten() {
+        Some(a) => a,
+        None => return,
+    };
+    let length = array
+        .get_attribute("data-length")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+    let pages = match array.query_selector_all(":scope > .children > .array-page") {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+
+    let mut current = 0;
+    for i in 0..pages.length() {
+        if let Some(page) = pages.item(i).and_then(|p| p.dyn_into::<Element>().ok())
+            && !page.has_attribute("hidden")
+        {
+            current = i as isize;
+        }
+    }
+    let next = (current + step).clamp(0, pages.length() as isize - 1);
+    for i in 0..pages.length() {
+        if let Some(page) = pages.item(i).and_then(|p| p.dyn_into::<Element>().ok()) {
+            let _ = page.toggle_attribute_with_force("hidden", i as isize != next);
+        }
+    }
+
+    if let Ok(Some(range)) = array.query_selector(":scope > .array-pager > .array-range") {
+        range.set_text_content(Some(&page_range(next as usize, length)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{value_html, value_summary};
+    use crate::bigquery::base::TableFieldSchema;
+    use crate::custom_elements::cell_value::CellValue;
+
+    #[test]
+    fn json_column_is_a_tree() {
+        let field: TableFieldSchema =
+            serde_json::from_str(r#"{"name": "j", "type": "JSON"}"#).unwrap();
+        let value = CellValue::Text(r#"{"a": "<b>", "n": null}"#.to_string());
+
+        assert_eq!(
+            value_html(&value, Some(&field)),
+            concat!(
+                r#"<details open><summary><span class="kind">{2}</span></summary><div class="children">"#,
+                r#"<div class="leaf"><span class="key">a</span> <span class="value string">&lt;b&gt;</span></div>"#,
+                r#"<div class="leaf"><span class="key">n</span> <span class="value null">NULL</span></div>"#,
+                r#"</div></details>"#
+            )
+        );
+        assert_eq!(value_summary(&value, Some(&field)), "JSON · 23 characters");
+    }
+
+    #[test]
+    fn long_arrays_are_paged() {
+        let field: TableFieldSchema =
+            serde_json::from_str(r#"{"name": "a", "type": "INTEGER", "mode": "REPEATED"}"#)
+                .unwrap();
+        let value = CellValue::Array((0..250).map(CellValue::Int).collect());
+
+        let html = value_html(&value, Some(&field));
+
+        assert_eq!(html.matches(r#"class="array-page""#).count(), 3);
+        assert_eq!(html.matches(" hidden>").count(), 2);
+        assert!(html.contains("1–100 of 250"));
+        assert_eq!(
+            value_summary(&value, Some(&field)),
+            "ARRAY<INTEGER> · 250 elements"
+        );
+    }
+}
@@ -30,6 +30,8 @@ extern "C" {
     // fn observe_element(element: &Element);
     #[wasm_bindgen(js_namespace = document, js_name = "setState", catch)]
     fn set_state(state_json: &str) -> Result<(), JsValue>;
+    #[wasm_bindgen(js_namespace = document, js_name = "getState", catch)]
+    fn get_state() -> Result<Option<String>, JsValue>;
 }
 
 #[wasm_bindgen]
@@ -40,6 +42,48 @@ pub fn get_web_components_list() -> Vec<JsValue> {
         .collect()
 }
 
+/// Names of the events the grid elements dispatch for the host page (see `grid_events.md`).
+#[wasm_bindgen]
+pub fn get_grid_event_names() -> Vec<JsValue> {
+    custom_elements::grid_events::get_all()
+        .iter()
+        .map(|f| JsValue::from_str(f))
+        .collect()
+}
+
+/// The observed attributes of the custom element `tag_name` (one of `get_web_components_list`),
+/// for the class the host page defines for it (see `custom_element_registry`).
+#[wasm_bindgen]
+pub fn get_observed_attributes(tag_name: &str) -> Vec<JsValue> {
+    custom_elements::custom_element_registry::observed_attributes(tag_name)
+        .unwrap_or_default()
+        .iter()
+        .map(|a| JsValue::from_str(a))
+        .collect()
+}
+
+/// `connectedCallback` of `bq-table`, `bq-query`, `bq-script`, `bq-chart`, `bq-diff` and
+/// `bq-schema`: written directly in the html, they render from their own attributes.
+#[wasm_bindgen]
+pub fn on_custom_element_connected(tag_name: &str, element: web_sys::Element) {
+    custom_elements::custom_element_registry::connected(tag_name, &element);
+}
+
+/// `attributeChangedCallback` of the custom elements: they render again when an observed
+/// attribute changes.
+#[wasm_bindgen]
+pub fn on_custom_element_attribute_changed(
+    tag_name: &str,
+    element: web_sys::Element,
+    name: &str,
+    old_value: Option<String>,
+    new_value: Option<String>,
+) {
+    custom_elements::custom_element_registry::attribute_changed(
+        tag_name, &element, name, old_value, new_value,
+    );
+}
+
 #[wasm_bindgen]
 pub fn register_custom_element(custom_component_name: &JsValue, element: web_sys::Element) {
     let custom_component_string = match custom_component_name.as_string() {
@@ -1,4 +1,6 @@
 use crate::custom_elements::base_element::BaseElement;
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
 use web_sys::Element;
 use website_component_table::{HtmlNodeRender, TableBuilder};
 
@@ -28,3 +30,59 @@ pub(crate) fn render_standalone(table_builder: &TableBuilder, parent_node: &Elem
         }
     }
 }
+
+/// Offers `content` as a file download through a temporary object URL.
+pub(crate) fn download_text(file_name: &str, content: &str, mime_type: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(content));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+
+    download_blob(file_name, &blob)
+}
+
+pub(crate) fn download_blob(file_name: &str, blob: &web_sys::Blob) -> Result<(), JsValue> {
+    let url = web_sys::Url::create_object_url_with_blob(blob)?;
+
+    let anchor: web_sys::HtmlAnchorElement = crate::createElement("a").dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // revoking right away can cancel the download in some browsers
+    let revoke = Closure::once_into_js(move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    });
+    if let Some(window) = web_sys::window() {
+        window.set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), 1000)?;
+    }
+
+    Ok(())
+}
+
+/// Puts `text` on the clipboard, failures are only logged.
+pub(crate) fn write_clipboard(text: String) {
+    // undefined outside of a secure context
+    let clipboard = match web_sys::window()
+        .map(|w| w.navigator().clipboard())
+        .filter(|c| !c.is_undefined())
+    {
+        Some(c) => c,
+        None => {
+            web_sys::console::error_1(&JsValue::from_str(
+                "write_clipboard: clipboard not available",
+            ));
+            return;
+        }
+    };
+    let promise = clipboard.write_text(&text);
+
+    spawn_local(async move {
+        if let Err(e) = JsFuture::from(promise).await {
+            web_sys::console::error_1(&JsValue::from_str(&format!(
+                "write_clipboard: copy failed: {:?}",
+                e
+            )));
+        }
+    });
+}
@@ -1,19 +1,38 @@
 // https://developer.mozilla.org/en-US/docs/WebAssembly/Rust_to_wasm#using_the_package_on_the_web
 
-import init, { get_web_components_list, register_custom_element, on_window_message_received } from "../dist/grid_render.js";
+import init, {
+    get_web_components_list, get_observed_attributes, on_custom_element_connected,
+    on_custom_element_attribute_changed, get_grid_event_names, on_window_message_received
+} from "../dist/grid_render.js";
+
+// bq-table, bq-query, ... written directly in the html: their classes are declared here,
+// the callbacks are the ones of grid_render (no `new Function`, so no 'unsafe-eval' needed)
+function defineCustomElements() {
+    for (const tag of get_web_components_list()) {
+        if (customElements.get(tag)) { continue; }
+        const observedAttributes = get_observed_attributes(tag);
+        customElements.define(tag, class extends HTMLElement {
+            static get observedAttributes() { return observedAttributes; }
+            connectedCallback() { on_custom_element_connected(tag, this); }
+            attributeChangedCallback(name, oldValue, newValue) {
+                on_custom_element_attribute_changed(tag, this, name, oldValue, newValue);
+            }
+        });
+    }
+}
 
 const p = init().then(() => {
 
-    let webComponentsList = get_web_components_list();
+    defineCustomElements();
 
-    for (let index = 0; index < webComponentsList.length; index++) {
-        const elementName = webComponentsList[index];
+    // forward the grid events (bq-page-changed, bq-load-error, ...) to the extension
+    let gridEventNames = get_grid_event_names();
 
-        window.customElements.define(elementName, class extends HTMLElement {
-            connectedCallback() {
-                // console.log(elementName);
-                register_custom_element(elementName, this);
-            }
+    for (let index = 0; index < gridEventNames.length; index++) {
+        const eventName = gridEventNames[index];
+
+        document.addEventListener(eventName, event => {
+            vscode.postMessage({ command: 'grid_event', event: eventName, detail: event.detail });
         });
     }
 
@@ -34,10 +53,16 @@ window.addEventListener('message', async event => {
 
 // set state
 if (!vscode) { vscode = acquireVsCodeApi(); }
+// the keys set are merged into the saved state, the column layouts stay next to the job
 function setState(str){
     console.log('state to set: ' + str);
     let obj = JSON.parse(str);
-    vscode.setState(obj);
+    vscode.setState(Object.assign({}, vscode.getState(), obj));
+}
+
+function getState(){
+    return JSON.stringify(vscode.getState() || {});
 }
 
-document.setState = setState;
\ No newline at end of file
+document.setState = setState;
+document.getState = getState;
\ No newline at end of file
@@ -31,26 +31,34 @@ async function ensureInitialized(): Promise<void> {
             await gridRenderModule.default(wasmUrl);
 
             // Register all custom web components from grid_render
-            const webComponentsList = gridRenderModule.get_web_components_list();
-            for (let index = 0; index < webComponentsList.length; index++) {
-                const elementName = webComponentsList[index];
-                if (!customElements.get(elementName)) {
-                    const gridRender = gridRenderModule; // capture in closure
-                    window.customElements.define(elementName, class extends HTMLElement {
-                        connectedCallback() {
-                            gridRender.register_custom_element(elementName, this);
-                        }
+            // (already defined tags are skipped, so a second load of the renderer is harmless)
+            defineCustomElements(gridRenderModule);
+
+            // Forward the grid events (bq-load-error, ...) to the extension, as grid.js does
+            for (const eventName of gridRenderModule.get_grid_event_names() as string[]) {
+                document.addEventListener(eventName, (event: Event) => {
+                    (window as any).vscode?.postMessage({
+                        command: 'grid_event', event: eventName, detail: (event as CustomEvent).detail,
                     });
-                }
+                });
             }
 
             // Wire up the external_message listener (same pattern as grid.js)
             window.addEventListener('external_message', gridRenderModule.on_window_message_received);
 
-            // Provide dummy setState if not present – Rust code calls document.setState(...)
+            // The Rust code keeps its state (e.g. the column layouts) through document.setState /
+            // document.getState, as in grid.js. The renderer context has no state of its own, so it
+            // is kept in localStorage, the keys set merged into the saved state.
             if (!(document as any).setState) {
+                const stateKey = 'bqnb-grid-state';
+                const readState = (): any => {
+                    try { return JSON.parse(localStorage.getItem(stateKey) || '{}'); } catch { return {}; }
+                };
                 (document as any).setState = function (str: string) {
-                    console.log('[bqnb-renderer] setState:', str);
+                    localStorage.setItem(stateKey, JSON.stringify(Object.assign(readState(), JSON.parse(str))));
+                };
+                (document as any).getState = function (): string {
+                    return JSON.stringify(readState());
                 };
             }
 
@@ -129,6 +137,21 @@ async function ensureInitialized(): Promise<void> {
     return initializedPromise;
 }
 
+// Same classes as grid.js: declared here, their callbacks forwarded to grid_render.
+function defineCustomElements(grid: any): void {
+    for (const tag of grid.get_web_components_list() as string[]) {
+        if (customElements.get(tag)) { continue; }
+        const observedAttributes: string[] = grid.get_observed_attributes(tag);
+        customElements.define(tag, class extends HTMLElement {
+            static get observedAttributes() { return observedAttributes; }
+            connectedCallback() { grid.on_custom_element_connected(tag, this); }
+            attributeChangedCallback(name: string, oldValue: string | null, newValue: string | null) {
+                grid.on_custom_element_attribute_changed(tag, this, name, oldValue, newValue);
+            }
+        });
+    }
+}
+
 function esc(s: string): string {
     return String(s).replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;').replace(/"/g, '&quot;');
 }
@@ -33,6 +33,10 @@ export class ResultsGridRender {
     public static executeCommand(c: any) {
         if ((c as any).command) {
             const command = (c as any).command;
+            if (command === "grid_event") {
+                ResultsGridRender.onGridEvent(c);
+                return;
+            }
             const data = {
                 tableReference: (c as any).table_reference,
                 jobReference: (c as any).job_reference,
@@ -47,6 +51,16 @@ export class ResultsGridRender {
         }
     }
 
+    // events of the grid forwarded by grid.js and the notebook renderer,
+    // see grid_render/src/custom_elements/grid_events.md
+    public static onGridEvent(c: any) {
+        if (c.event === "bq-load-error") {
+            const detail = c.detail || {};
+            const message = detail.error?.message || detail.reason || "unknown error";
+            vscode.window.showErrorMessage(`BigQuery ${detail.operation || "request"} failed: ${message}`);
+        }
+    }
+
     private buildHtml(gridJs: vscode.Uri, gridCss: vscode.Uri, chartGlobalJs: vscode.Uri): string {
         return `<!DOCTYPE html>
 <html lang="en">